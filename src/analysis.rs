@@ -0,0 +1,528 @@
+//! The `analysis` module contains the `AnalysisAggregator`, which folds the `info` messages of a (MultiPV) search
+//! into the best line for each `multipv` index and depth, and the lighter `EvalTracker`, which only keeps what the
+//! engine currently thinks of the position.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "chess")]
+use chess::{Board, ChessMove};
+use chrono::Duration;
+
+#[cfg(feature = "chess")]
+use crate::board::{position_to_board, sanitize_pv};
+use crate::score::{Score, ScoreBound};
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+use crate::uci::{UciInfoAttribute, UciMessage};
+
+/// A principal variation reported by the engine, along with the search statistics reported with it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PvLine {
+    /// The `multipv` index of the line, starting at 1.
+    pub multipv: u16,
+
+    /// The depth the line was found at.
+    pub depth: u8,
+
+    /// The selective depth, if reported.
+    pub sel_depth: Option<u8>,
+
+    /// The score of the line, if reported.
+    pub score: Option<Score>,
+
+    /// Whether the score is exact or a bound.
+    pub bound: ScoreBound,
+
+    /// The number of nodes searched, if reported.
+    pub nodes: Option<u64>,
+
+    /// The time searched, if reported.
+    pub time: Option<Duration>,
+
+    /// The moves of the line.
+    #[cfg(not(feature = "chess"))]
+    pub pv: Vec<UciMove>,
+
+    /// The moves of the line.
+    #[cfg(feature = "chess")]
+    pub pv: Vec<ChessMove>,
+}
+
+impl PvLine {
+    /// Creates a `PvLine` from the attributes of an `info` message. Returns `None` if the attributes do not contain a
+    /// `pv`. A missing `multipv` index defaults to 1 and a missing depth to `depth`.
+    pub fn from_attributes(attributes: &[UciInfoAttribute], depth: u8) -> Option<PvLine> {
+        let mut line = PvLine {
+            multipv: 1,
+            depth,
+            sel_depth: None,
+            score: None,
+            bound: ScoreBound::Exact,
+            nodes: None,
+            time: None,
+            pv: Vec::new(),
+        };
+        let mut has_pv = false;
+
+        for a in attributes {
+            match a {
+                UciInfoAttribute::MultiPv(n) => line.multipv = *n,
+                UciInfoAttribute::Depth(d) => line.depth = *d,
+                UciInfoAttribute::SelDepth(d) => line.sel_depth = Some(*d),
+                UciInfoAttribute::Nodes(n) => line.nodes = Some(*n),
+                UciInfoAttribute::Time(t) => line.time = Some(*t),
+                UciInfoAttribute::Pv(moves) => {
+                    line.pv = moves.clone();
+                    has_pv = true;
+                }
+                UciInfoAttribute::Score { .. } => {
+                    if let Some((score, bound)) = Score::from_attribute(a) {
+                        line.score = Some(score);
+                        line.bound = bound;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if has_pv {
+            Some(line)
+        } else {
+            None
+        }
+    }
+}
+
+/// Consumes the messages of a search and keeps the latest line for each `multipv` index and depth.
+///
+/// Within the same index and depth, a later line replaces an earlier one, except that a line with a bound score
+/// (`lowerbound` or `upperbound`) does not replace one with an exact score. `position`, `ucinewgame` and `go` start a
+/// new search and clear the lines.
+///
+/// With the `chess` feature, the aggregator can also truncate each principal variation at its first illegal move (see
+/// `sanitize_pvs(..)`), since engines occasionally report corrupt lines due to hash collisions.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::analysis::AnalysisAggregator;
+/// use vampirc_uci::score::Score;
+///
+/// let mut aggregator = AnalysisAggregator::new();
+/// for m in parse("info depth 10 multipv 2 score cp 12 pv d2d4\ninfo depth 10 multipv 1 score cp 30 pv e2e4\n\
+///                 info depth 11 multipv 1 score cp 28 pv e2e4 e7e5\n") {
+///     aggregator.consume(&m);
+/// }
+///
+/// let lines = aggregator.snapshot();
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!((lines[0].depth, lines[0].score), (11, Some(Score::Centipawns(28))));
+/// assert_eq!((lines[1].depth, lines[1].score), (10, Some(Score::Centipawns(12))));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct AnalysisAggregator {
+    lines: BTreeMap<u16, BTreeMap<u8, PvLine>>,
+    depth: u8,
+    #[cfg(feature = "chess")]
+    sanitize: bool,
+    #[cfg(feature = "chess")]
+    board: Option<Board>,
+}
+
+impl AnalysisAggregator {
+    /// Creates an empty `AnalysisAggregator`.
+    pub fn new() -> AnalysisAggregator {
+        AnalysisAggregator::default()
+    }
+
+    /// Sets whether the principal variations are truncated at their first illegal move, which is off by default.
+    ///
+    /// The legality is checked against the position of the last `position` message consumed, or the one set with
+    /// `set_board(..)`. Until a position is known, the lines are kept as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse;
+    /// use vampirc_uci::analysis::AnalysisAggregator;
+    ///
+    /// let mut aggregator = AnalysisAggregator::new().sanitize_pvs(true);
+    /// for m in parse("position startpos moves e2e4\ninfo depth 3 score cp -20 pv e7e5 g1f3 g1f3\n") {
+    ///     aggregator.consume(&m);
+    /// }
+    ///
+    /// assert_eq!(aggregator.best_line(1).unwrap().pv.len(), 2);
+    /// ```
+    #[cfg(feature = "chess")]
+    pub fn sanitize_pvs(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Returns whether the principal variations are truncated at their first illegal move.
+    #[cfg(feature = "chess")]
+    pub fn get_sanitize_pvs(&self) -> bool {
+        self.sanitize
+    }
+
+    /// Sets the position the searched lines start from, for when the aggregator does not see the `position` message.
+    #[cfg(feature = "chess")]
+    pub fn set_board(&mut self, board: Board) {
+        self.board = Some(board);
+    }
+
+    /// Consumes the next message. If the message changed a line, the changed line is returned.
+    pub fn consume(&mut self, message: &UciMessage) -> Option<&PvLine> {
+        match message {
+            UciMessage::Info(attributes) => self.consume_info(attributes),
+            UciMessage::Position { .. } | UciMessage::UciNewGame | UciMessage::Go { .. } => {
+                #[cfg(feature = "chess")]
+                {
+                    if let UciMessage::Position { .. } = message {
+                        self.board = position_to_board(message).ok();
+                    }
+                }
+                self.clear();
+                None
+            }
+            _ => None
+        }
+    }
+
+    /// Consumes the attributes of an `info` message. If they changed a line, the changed line is returned.
+    pub fn consume_info(&mut self, attributes: &[UciInfoAttribute]) -> Option<&PvLine> {
+        for a in attributes {
+            if let UciInfoAttribute::Depth(d) = a {
+                self.depth = *d;
+            }
+        }
+
+        #[allow(unused_mut)]
+        let mut line = PvLine::from_attributes(attributes, self.depth)?;
+        #[cfg(feature = "chess")]
+        {
+            if let (true, Some(board)) = (self.sanitize, &self.board) {
+                line.pv = sanitize_pv(board, &line.pv);
+            }
+        }
+        let by_depth = self.lines.entry(line.multipv).or_default();
+        let depth = line.depth;
+
+        if let Some(existing) = by_depth.get(&depth) {
+            if *existing == line || (existing.bound == ScoreBound::Exact && line.bound != ScoreBound::Exact) {
+                return None;
+            }
+        }
+
+        by_depth.insert(depth, line);
+        by_depth.get(&depth)
+    }
+
+    /// Returns the deepest line of each `multipv` index, sorted by the index.
+    pub fn snapshot(&self) -> Vec<PvLine> {
+        self.lines
+            .values()
+            .filter_map(|by_depth| by_depth.values().next_back())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the deepest line of the `multipv` index.
+    pub fn best_line(&self, multipv: u16) -> Option<&PvLine> {
+        self.lines.get(&multipv).and_then(|by_depth| by_depth.values().next_back())
+    }
+
+    /// Returns the line of the `multipv` index at the `depth`.
+    pub fn line_at(&self, multipv: u16, depth: u8) -> Option<&PvLine> {
+        self.lines.get(&multipv).and_then(|by_depth| by_depth.get(&depth))
+    }
+
+    /// Returns the greatest depth of any line.
+    pub fn max_depth(&self) -> Option<u8> {
+        self.lines.values().filter_map(|by_depth| by_depth.keys().next_back()).max().copied()
+    }
+
+    /// Removes all of the lines.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.depth = 0;
+    }
+}
+
+/// Folds the `info` messages of a search into the engine's current opinion: the depth, score and principal variation
+/// of the main line, along with the latest node count and speed.
+///
+/// The depth only ever increases: a main line reported at a lower depth than the current one (by a helper thread,
+/// for example) is ignored. Lines with a `multipv` index other than 1 are ignored, too. A bound score
+/// (`lowerbound` or `upperbound`) does not replace an exact score at the same depth, and the latest exact score is
+/// always available through `exact_score()`. `position`, `ucinewgame` and `go` start a new search and reset the
+/// tracker.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::analysis::EvalTracker;
+/// use vampirc_uci::score::{Score, ScoreBound};
+///
+/// let mut tracker = EvalTracker::new();
+/// for m in parse("info depth 12 score cp 35 nodes 120000 nps 900000 pv e2e4 e7e5\n\
+///                 info depth 13 score cp 60 lowerbound pv e2e4\n") {
+///     tracker.consume(&m);
+/// }
+///
+/// assert_eq!(tracker.depth(), Some(13));
+/// assert_eq!(tracker.score(), Some((Score::Centipawns(60), ScoreBound::Lower)));
+/// assert_eq!(tracker.exact_score(), Some(Score::Centipawns(35)));
+/// assert_eq!(tracker.nodes(), Some(120000));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct EvalTracker {
+    depth: Option<u8>,
+    sel_depth: Option<u8>,
+    score: Option<(Score, ScoreBound)>,
+    exact_score: Option<Score>,
+    nodes: Option<u64>,
+    nps: Option<u64>,
+    time: Option<Duration>,
+    #[cfg(not(feature = "chess"))]
+    pv: Vec<UciMove>,
+    #[cfg(feature = "chess")]
+    pv: Vec<ChessMove>,
+}
+
+impl EvalTracker {
+    /// Creates an `EvalTracker` at the start of a search.
+    pub fn new() -> EvalTracker {
+        EvalTracker::default()
+    }
+
+    /// Consumes the next message. Returns `true` if the message changed the tracked state.
+    pub fn consume(&mut self, message: &UciMessage) -> bool {
+        match message {
+            UciMessage::Info(attributes) => self.consume_info(attributes),
+            UciMessage::Position { .. } | UciMessage::UciNewGame | UciMessage::Go { .. } => {
+                let changed = *self != EvalTracker::default();
+                self.reset();
+                changed
+            }
+            _ => false
+        }
+    }
+
+    /// Consumes the attributes of an `info` message. Returns `true` if they changed the tracked state.
+    pub fn consume_info(&mut self, attributes: &[UciInfoAttribute]) -> bool {
+        let before = self.clone();
+
+        for a in attributes {
+            match a {
+                UciInfoAttribute::Nodes(n) => self.nodes = Some(*n),
+                UciInfoAttribute::Nps(n) => self.nps = Some(*n),
+                UciInfoAttribute::Time(t) => self.time = Some(*t),
+                _ => {}
+            }
+        }
+
+        let mut multipv = 1;
+        let mut depth = None;
+        let mut sel_depth = None;
+        let mut score = None;
+        let mut pv = None;
+        for a in attributes {
+            match a {
+                UciInfoAttribute::MultiPv(n) => multipv = *n,
+                UciInfoAttribute::Depth(d) => depth = Some(*d),
+                UciInfoAttribute::SelDepth(d) => sel_depth = Some(*d),
+                UciInfoAttribute::Pv(moves) => pv = Some(moves),
+                UciInfoAttribute::Score { .. } => score = Score::from_attribute(a),
+                _ => {}
+            }
+        }
+
+        if multipv != 1 {
+            return *self != before;
+        }
+
+        let line_depth = depth.or(self.depth);
+        if line_depth < self.depth {
+            return *self != before;
+        }
+        let deeper = line_depth > self.depth;
+
+        if depth.is_some() {
+            self.depth = depth;
+            if sel_depth.is_some() || deeper {
+                self.sel_depth = sel_depth;
+            }
+        }
+
+        if let Some((s, bound)) = score {
+            let keeps_exact = !deeper && bound != ScoreBound::Exact && matches!(self.score, Some((_, ScoreBound::Exact)));
+            if !keeps_exact {
+                self.score = Some((s, bound));
+                if let Some(moves) = pv {
+                    self.pv = moves.clone();
+                }
+            }
+            if bound == ScoreBound::Exact {
+                self.exact_score = Some(s);
+            }
+        } else if let Some(moves) = pv {
+            self.pv = moves.clone();
+        }
+
+        *self != before
+    }
+
+    /// The depth of the main line, if reported.
+    pub fn depth(&self) -> Option<u8> {
+        self.depth
+    }
+
+    /// The selective depth of the main line, if reported.
+    pub fn sel_depth(&self) -> Option<u8> {
+        self.sel_depth
+    }
+
+    /// The score of the main line and whether it is exact or a bound, if reported.
+    pub fn score(&self) -> Option<(Score, ScoreBound)> {
+        self.score
+    }
+
+    /// The latest exact score of the main line, if any.
+    pub fn exact_score(&self) -> Option<Score> {
+        self.exact_score
+    }
+
+    /// The principal variation of the main line (empty if not reported).
+    #[cfg(not(feature = "chess"))]
+    pub fn pv(&self) -> &[UciMove] {
+        &self.pv
+    }
+
+    /// The principal variation of the main line (empty if not reported).
+    #[cfg(feature = "chess")]
+    pub fn pv(&self) -> &[ChessMove] {
+        &self.pv
+    }
+
+    /// The latest node count, if reported.
+    pub fn nodes(&self) -> Option<u64> {
+        self.nodes
+    }
+
+    /// The latest search speed in nodes per second, if reported.
+    pub fn nps(&self) -> Option<u64> {
+        self.nps
+    }
+
+    /// The latest search time, if reported.
+    pub fn time(&self) -> Option<Duration> {
+        self.time
+    }
+
+    /// Resets the tracker for a new search.
+    pub fn reset(&mut self) {
+        *self = EvalTracker::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn feed(aggregator: &mut AnalysisAggregator, s: &str) -> Vec<bool> {
+        parse(s).iter().map(|m| aggregator.consume(m).is_some()).collect()
+    }
+
+    #[test]
+    fn test_lines_per_multipv_and_depth() {
+        let mut a = AnalysisAggregator::new();
+        let changed = feed(&mut a, "info depth 5\ninfo multipv 1 score cp 20 pv e2e4\ninfo multipv 2 score cp 10 pv d2d4\n\
+                                    info depth 6 multipv 2 score cp 15 pv c2c4\ninfo currmove g1f3 currmovenum 3\n\
+                                    info depth 6 multipv 2 score cp 15 pv c2c4\n");
+        assert_eq!(changed, vec![false, true, true, true, false, false]);
+
+        assert_eq!(a.max_depth(), Some(6));
+        assert_eq!(a.line_at(2, 5).unwrap().score, Some(Score::Centipawns(10)));
+        assert_eq!(a.best_line(2).unwrap().depth, 6);
+
+        let snapshot = a.snapshot();
+        assert_eq!(snapshot.iter().map(|l| (l.multipv, l.depth)).collect::<Vec<_>>(), vec![(1, 5), (2, 6)]);
+    }
+
+    #[test]
+    fn test_bounds_and_new_search() {
+        let mut a = AnalysisAggregator::new();
+        let changed = feed(&mut a, "info depth 8 score cp 50 lowerbound pv e2e4\ninfo depth 8 score cp 60 pv e2e4 e7e5\n\
+                                    info depth 8 score cp 70 lowerbound pv e2e4\n");
+        assert_eq!(changed, vec![true, true, false]);
+        assert_eq!(a.best_line(1).unwrap().bound, ScoreBound::Exact);
+
+        feed(&mut a, "go infinite\n");
+        assert!(a.snapshot().is_empty());
+        assert_eq!(a.max_depth(), None);
+    }
+
+    #[test]
+    fn test_eval_tracker_monotonic_depth() {
+        let mut t = EvalTracker::new();
+        let changed: Vec<bool> = parse("info depth 10 seldepth 14 score cp 20 pv e2e4\ninfo depth 9 score cp -300 pv a2a3\n\
+                                        info depth 10 multipv 2 score cp 5 pv d2d4\ninfo nodes 5000 nps 100000\n\
+                                        info depth 11 score mate 5 pv g1f3\n")
+            .iter()
+            .map(|m| t.consume(m))
+            .collect();
+
+        assert_eq!(changed, vec![true, false, false, true, true]);
+        assert_eq!(t.depth(), Some(11));
+        assert_eq!(t.sel_depth(), None);
+        assert_eq!(t.score(), Some((Score::Mate(5), ScoreBound::Exact)));
+        assert_eq!(t.pv().len(), 1);
+        assert_eq!(t.nps(), Some(100000));
+    }
+
+    #[test]
+    fn test_eval_tracker_bounds() {
+        let mut t = EvalTracker::new();
+        for m in parse("info depth 10 score cp 20 pv e2e4 e7e5\ninfo depth 10 score cp 80 lowerbound pv e2e4\n") {
+            t.consume(&m);
+        }
+        assert_eq!(t.score(), Some((Score::Centipawns(20), ScoreBound::Exact)));
+        assert_eq!(t.pv().len(), 2);
+
+        for m in parse("info depth 11 score cp 80 lowerbound pv e2e4\n") {
+            t.consume(&m);
+        }
+        assert_eq!(t.score(), Some((Score::Centipawns(80), ScoreBound::Lower)));
+        assert_eq!(t.exact_score(), Some(Score::Centipawns(20)));
+
+        assert!(t.consume(&UciMessage::UciNewGame));
+        assert_eq!(t, EvalTracker::new());
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_sanitize_pvs() {
+        let mut a = AnalysisAggregator::new();
+        feed(&mut a, "position startpos\ninfo depth 2 score cp 20 pv e2e4 e2e4\n");
+        assert!(!a.get_sanitize_pvs());
+        assert_eq!(a.best_line(1).unwrap().pv.len(), 2);
+
+        let mut a = AnalysisAggregator::new().sanitize_pvs(true);
+        feed(&mut a, "info depth 2 score cp 20 pv e2e4 e2e4\n");
+        assert_eq!(a.best_line(1).unwrap().pv.len(), 2);
+
+        feed(&mut a, "position startpos moves e2e4 e7e5\ninfo depth 4 multipv 1 score cp 30 pv g1f3 b8c6 f1b5 e1e2\n\
+                      info depth 4 multipv 2 score cp 10 pv e7e5\n");
+        assert_eq!(a.best_line(1).unwrap().pv.len(), 3);
+        assert!(a.best_line(2).unwrap().pv.is_empty());
+
+        a.set_board(chess::Board::default());
+        feed(&mut a, "info depth 5 score cp 20 pv e2e4 e7e5\n");
+        assert_eq!(a.best_line(1).unwrap().pv.len(), 2);
+    }
+}
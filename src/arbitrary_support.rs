@@ -0,0 +1,363 @@
+//! `Arbitrary` implementations for the `arbitrary` feature, for structure-aware fuzzing.
+//!
+//! The generated messages are well-formed: serializing one and parsing it back yields the same message, so that a fuzz
+//! target can compare the two. Names and other free text are made of words that cannot be mistaken for protocol
+//! keywords, an `info string` or other unknown `info` attribute only comes last, and values stay within the ranges the
+//! grammar accepts. Neither `UciMessage::Unknown` nor the `cpunr` of an `info currline` are generated, as they do not
+//! survive the round trip.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+#[cfg(feature = "chess")]
+use chess::{ChessMove, Piece, ALL_SQUARES};
+use chrono::Duration;
+
+use crate::uci::{CommunicationDirection, ProtectionState, UciFen, UciFloat, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+#[cfg(not(feature = "chess"))]
+use crate::uci::{UciMove, UciPiece, UciSquare};
+
+/// No protocol keyword starts with one of these letters.
+const WORD_START: &[u8] = b"fgjxyz";
+const WORD_REST: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// The largest number the grammar accepts where it expects up to 12 digits.
+const MAX_DIGITS12: u64 = 999_999_999_999;
+
+fn word(u: &mut Unstructured) -> Result<String> {
+    let mut s = String::new();
+    s.push(*u.choose(WORD_START)? as char);
+    for _ in 0..u.int_in_range(0..=7)? {
+        s.push(*u.choose(WORD_REST)? as char);
+    }
+
+    Ok(s)
+}
+
+fn words(u: &mut Unstructured) -> Result<String> {
+    let mut s = word(u)?;
+    for _ in 0..u.int_in_range(0..=2)? {
+        s.push(' ');
+        s.push_str(&word(u)?);
+    }
+
+    Ok(s)
+}
+
+fn millis(u: &mut Unstructured, min: i64) -> Result<Duration> {
+    Ok(Duration::milliseconds(u.int_in_range(min..=MAX_DIGITS12 as i64)?))
+}
+
+fn digits12(u: &mut Unstructured) -> Result<u64> {
+    u.int_in_range(0..=MAX_DIGITS12)
+}
+
+fn signed_digits12(u: &mut Unstructured) -> Result<i64> {
+    u.int_in_range(-(MAX_DIGITS12 as i64)..=MAX_DIGITS12 as i64)
+}
+
+/// A decimal with three digits after the point, which survives serialization.
+fn decimal(u: &mut Unstructured) -> Result<UciFloat> {
+    Ok(UciFloat(signed_digits12(u)? as f64 / 1000.0))
+}
+
+fn option<T, F: FnOnce(&mut Unstructured) -> Result<T>>(u: &mut Unstructured, f: F) -> Result<Option<T>> {
+    Ok(if u.arbitrary()? { Some(f(u)?) } else { None })
+}
+
+#[cfg(not(feature = "chess"))]
+fn a_move(u: &mut Unstructured) -> Result<UciMove> {
+    UciMove::arbitrary(u)
+}
+
+#[cfg(feature = "chess")]
+fn a_move(u: &mut Unstructured) -> Result<ChessMove> {
+    let promotion = *u.choose(&[None, Some(Piece::Knight), Some(Piece::Bishop), Some(Piece::Rook), Some(Piece::Queen)])?;
+    Ok(ChessMove::new(*u.choose(&ALL_SQUARES)?, *u.choose(&ALL_SQUARES)?, promotion))
+}
+
+#[cfg(not(feature = "chess"))]
+type Move = UciMove;
+
+#[cfg(feature = "chess")]
+type Move = ChessMove;
+
+fn moves(u: &mut Unstructured, min: usize) -> Result<Vec<Move>> {
+    (0..u.int_in_range(min..=min + 7)?).map(|_| a_move(u)).collect()
+}
+
+#[cfg(not(feature = "chess"))]
+impl<'a> Arbitrary<'a> for UciPiece {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[UciPiece::Pawn, UciPiece::Knight, UciPiece::Bishop, UciPiece::Rook, UciPiece::Queen, UciPiece::King])?)
+    }
+}
+
+#[cfg(not(feature = "chess"))]
+impl<'a> Arbitrary<'a> for UciSquare {
+    /// Generates a square on the board.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(UciSquare::from(*u.choose(b"abcdefgh")? as char, u.int_in_range(1..=8)?))
+    }
+}
+
+#[cfg(not(feature = "chess"))]
+impl<'a> Arbitrary<'a> for UciMove {
+    /// Generates a move between two squares on the board, possibly promoting to a knight, bishop, rook or queen.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let promotion = *u.choose(&[None, Some(UciPiece::Knight), Some(UciPiece::Bishop), Some(UciPiece::Rook), Some(UciPiece::Queen)])?;
+        Ok(UciMove {
+            from: UciSquare::arbitrary(u)?,
+            to: UciSquare::arbitrary(u)?,
+            promotion,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for UciFen {
+    /// Generates a syntactically valid FEN. The position itself need not be legal.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut ranks = Vec::with_capacity(8);
+        for _ in 0..8 {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for _ in 0..8 {
+                if u.ratio(1, 2)? {
+                    empty += 1;
+                } else {
+                    if empty > 0 {
+                        rank += &empty.to_string();
+                        empty = 0;
+                    }
+                    rank.push(*u.choose(b"PNBRQKpnbrqk")? as char);
+                }
+            }
+            if empty > 0 {
+                rank += &empty.to_string();
+            }
+            ranks.push(rank);
+        }
+
+        let mut castling = String::new();
+        for c in "KQkq".chars() {
+            if u.arbitrary()? {
+                castling.push(c);
+            }
+        }
+        let en_passant = if u.arbitrary()? {
+            format!("{}{}", *u.choose(b"abcdefgh")? as char, *u.choose(b"36")? as char)
+        } else {
+            String::from("-")
+        };
+
+        Ok(UciFen(format!("{} {} {} {} {} {}", ranks.join("/"), *u.choose(&["w", "b"])?,
+                          if castling.is_empty() { "-" } else { castling.as_str() }, en_passant,
+                          u.int_in_range(0..=99)?, u.int_in_range(1..=999)?)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for CommunicationDirection {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[CommunicationDirection::GuiToEngine, CommunicationDirection::EngineToGui])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ProtectionState {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[ProtectionState::Checking, ProtectionState::Ok, ProtectionState::Error])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UciTimeControl {
+    /// Generates a time control; a `TimeLeft` always has at least one of its fields set.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let time_control = match u.int_in_range(0..=3)? {
+            0 => UciTimeControl::Ponder,
+            1 => UciTimeControl::Infinite,
+            2 => UciTimeControl::MoveTime(millis(u, 0)?),
+            _ => {
+                let white_time = option(u, |u| millis(u, -(MAX_DIGITS12 as i64)))?;
+                UciTimeControl::TimeLeft {
+                    white_time: if white_time.is_none() { Some(millis(u, 0)?) } else { white_time },
+                    black_time: option(u, |u| millis(u, -(MAX_DIGITS12 as i64)))?,
+                    white_increment: option(u, |u| millis(u, 0))?,
+                    black_increment: option(u, |u| millis(u, 0))?,
+                    moves_to_go: option(u, |u| u.arbitrary())?,
+                }
+            }
+        };
+
+        Ok(time_control)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UciSearchControl {
+    /// Generates a search control that is never empty.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut search_control = UciSearchControl {
+            search_moves: moves(u, 0)?,
+            mate: option(u, |u| u.arbitrary())?,
+            depth: option(u, |u| u.arbitrary())?,
+            nodes: option(u, digits12)?,
+        };
+        if search_control.is_empty() {
+            search_control.depth = Some(u.arbitrary()?);
+        }
+
+        Ok(search_control)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UciOptionConfig {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let name = words(u)?;
+        let config = match u.int_in_range(0..=5)? {
+            0 => UciOptionConfig::Check { name, default: u.arbitrary()? },
+            1 => UciOptionConfig::Spin {
+                name,
+                default: option(u, signed_digits12)?,
+                min: option(u, signed_digits12)?,
+                max: option(u, signed_digits12)?,
+            },
+            2 => UciOptionConfig::Combo {
+                name,
+                default: option(u, words)?,
+                var: (0..u.int_in_range(0..=4)?).map(|_| words(u)).collect::<Result<_>>()?,
+            },
+            3 => UciOptionConfig::Button { name },
+            4 => UciOptionConfig::Float {
+                name,
+                // Without a default, a float option with integral bounds would be parsed back as a `spin` one
+                default: Some(decimal(u)?),
+                min: option(u, decimal)?,
+                max: option(u, decimal)?,
+            },
+            _ => UciOptionConfig::String { name, default: option(u, words)? },
+        };
+
+        Ok(config)
+    }
+}
+
+/// Generates an `info` attribute with a fixed number of values, which can be followed by more attributes.
+fn delimited_attribute(u: &mut Unstructured) -> Result<UciInfoAttribute> {
+    let attribute = match u.int_in_range(0..=14)? {
+        0 => UciInfoAttribute::Depth(u.arbitrary()?),
+        1 => UciInfoAttribute::SelDepth(u.arbitrary()?),
+        2 => UciInfoAttribute::Time(millis(u, 0)?),
+        3 => UciInfoAttribute::Nodes(digits12(u)?),
+        4 => UciInfoAttribute::Pv(moves(u, 1)?),
+        5 => UciInfoAttribute::MultiPv(u.arbitrary()?),
+        6 => {
+            let (cp, mate) = if u.arbitrary()? { (Some(u.arbitrary()?), None) } else { (None, Some(u.arbitrary()?)) };
+            let (lower_bound, upper_bound) = *u.choose(&[(None, None), (Some(true), None), (None, Some(true))])?;
+            UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound }
+        }
+        7 => UciInfoAttribute::CurrMove(a_move(u)?),
+        8 => UciInfoAttribute::CurrMoveNum(u.arbitrary()?),
+        9 => UciInfoAttribute::HashFull(u.arbitrary()?),
+        10 => UciInfoAttribute::Nps(digits12(u)?),
+        11 => UciInfoAttribute::TbHits(digits12(u)?),
+        12 => UciInfoAttribute::SbHits(digits12(u)?),
+        13 => UciInfoAttribute::CpuLoad(u.arbitrary()?),
+        _ => {
+            let line = moves(u, 1)?;
+            if u.arbitrary()? { UciInfoAttribute::Refutation(line) } else { UciInfoAttribute::CurrLine { cpu_nr: None, line } }
+        }
+    };
+
+    Ok(attribute)
+}
+
+/// Generates an `info` attribute that takes the rest of the line.
+fn trailing_attribute(u: &mut Unstructured) -> Result<UciInfoAttribute> {
+    Ok(if u.arbitrary()? { UciInfoAttribute::String(words(u)?) } else { UciInfoAttribute::Any(word(u)?, words(u)?) })
+}
+
+impl<'a> Arbitrary<'a> for UciInfoAttribute {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.ratio(1, 8)? { trailing_attribute(u) } else { delimited_attribute(u) }
+    }
+}
+
+impl<'a> Arbitrary<'a> for UciMessage {
+    /// Generates a well-formed message, which is parsed back unchanged from its serialization.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let message = match u.int_in_range(0..=18)? {
+            0 => UciMessage::Uci,
+            1 => UciMessage::Debug(u.arbitrary()?),
+            2 => UciMessage::IsReady,
+            3 => match u.int_in_range(0..=3)? {
+                0 => UciMessage::register_later(),
+                1 => UciMessage::register_code(&words(u)?, &words(u)?),
+                2 => UciMessage::Register { later: false, name: Some(words(u)?), code: None },
+                _ => UciMessage::Register { later: false, name: None, code: Some(words(u)?) },
+            },
+            4 => {
+                let fen: Option<UciFen> = u.arbitrary()?;
+                UciMessage::Position { startpos: fen.is_none(), fen, moves: moves(u, 0)? }
+            }
+            5 => UciMessage::SetOption { name: words(u)?, value: Some(words(u)?) },
+            6 => UciMessage::UciNewGame,
+            7 => UciMessage::Stop,
+            8 => UciMessage::PonderHit,
+            9 => UciMessage::Quit,
+            10 => UciMessage::Go { time_control: u.arbitrary()?, search_control: u.arbitrary()? },
+            11 => if u.arbitrary()? {
+                UciMessage::Id { name: Some(words(u)?), author: None }
+            } else {
+                UciMessage::Id { name: None, author: Some(words(u)?) }
+            },
+            12 => UciMessage::UciOk,
+            13 => UciMessage::ReadyOk,
+            14 => UciMessage::BestMove {
+                best_move: a_move(u)?,
+                ponder: option(u, a_move)?,
+                #[cfg(feature = "extensions")]
+                offer: None,
+            },
+            15 => UciMessage::CopyProtection(u.arbitrary()?),
+            16 => UciMessage::Registration(u.arbitrary()?),
+            17 => UciMessage::Option(u.arbitrary()?),
+            _ => {
+                let mut attributes = (0..u.int_in_range(1..=6)?).map(|_| delimited_attribute(u)).collect::<Result<Vec<_>>>()?;
+                if u.ratio(1, 4)? {
+                    attributes.push(trailing_attribute(u)?);
+                }
+                UciMessage::Info(attributes)
+            }
+        };
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::parser::parse_one;
+    use crate::uci::{Serializable, UciMessage};
+
+    /// Fills a buffer with pseudo-random bytes.
+    fn noise(seed: u64, length: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (0..length).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        }).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for seed in 0..2000 {
+            let bytes = noise(seed, 256);
+            let message = UciMessage::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert_eq!(parse_one(&message.serialize()), message, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_exhausted_input() {
+        let message = UciMessage::arbitrary(&mut Unstructured::new(&[])).unwrap();
+        assert_eq!(parse_one(&message.serialize()), message);
+    }
+}
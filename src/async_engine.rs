@@ -0,0 +1,548 @@
+//! The `async_engine` module contains the `EngineHandle`, an asynchronous, [tokio](https://tokio.rs)-based client
+//! for talking to a UCI engine running as a child process, or over any other pair of streams (such as a TCP
+//! connection, see the `tcp` module). It is available with the `tokio` feature.
+//!
+//! The `EngineHandle` performs the `uci` handshake on startup and offers typed methods for the rest of the
+//! protocol. A search started with `go(..)` yields the engine's `info` lines as a stream and resolves to the best
+//! move when awaited.
+
+use std::ffi::OsStr;
+use std::future::{poll_fn, Future};
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::timeout as with_timeout;
+
+use crate::async_io::{TokioCompat, UciReader, UciWriter};
+use crate::async_process::spawn;
+use crate::discovery::EngineInfo;
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+#[cfg(feature = "extensions")]
+use crate::uci::Offer;
+use crate::uci::{UciFen, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+
+/// The attributes of a single `info` line sent by the engine.
+pub type UciInfoLine = Vec<UciInfoAttribute>;
+
+/// The result of a search, as reported by the engine's `bestmove` message.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct BestMove {
+    /// The best move found.
+    #[cfg(not(feature = "chess"))]
+    pub best_move: UciMove,
+
+    /// The best move found.
+    #[cfg(feature = "chess")]
+    pub best_move: ChessMove,
+
+    /// The move the engine would like to ponder on, if any.
+    #[cfg(not(feature = "chess"))]
+    pub ponder: Option<UciMove>,
+
+    /// The move the engine would like to ponder on, if any.
+    #[cfg(feature = "chess")]
+    pub ponder: Option<ChessMove>,
+
+    /// The draw offer or resignation that came with the move, if any.
+    #[cfg(feature = "extensions")]
+    pub offer: Option<Offer>,
+}
+
+/// An asynchronous handle to a UCI engine running as a child process, or reached over a pair of streams.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::{UciSearchControl, UciTimeControl};
+/// use vampirc_uci::async_engine::EngineHandle;
+///
+/// # async fn analyse() -> std::io::Result<()> {
+/// let mut engine = EngineHandle::spawn("stockfish", &[] as &[&str]).await?;
+/// engine.set_option("Threads", Some("4")).await?;
+/// engine.new_game().await?;
+/// engine.position(None, &[]).await?;
+///
+/// let mut search = engine.go(None, Some(UciSearchControl::depth(20))).await?;
+/// while let Some(info) = search.next_info().await {
+///     println!("{:?}", info);
+/// }
+/// let best = search.await?;
+/// println!("Best move: {}", best.best_move);
+/// # Ok(())
+/// # }
+/// ```
+pub struct EngineHandle {
+    child: Option<Child>,
+    writer: UciWriter<Box<dyn futures_io::AsyncWrite + Send + Unpin>>,
+    reader: UciReader<Box<dyn futures_io::AsyncRead + Send + Unpin>>,
+    name: Option<String>,
+    author: Option<String>,
+    options: Vec<UciOptionConfig>,
+    searching: bool,
+}
+
+impl EngineHandle {
+    /// Spawns the engine `program` with the specified `args` and performs the `uci` handshake, waiting until the
+    /// engine replies with `uciok`.
+    pub async fn spawn<P: AsRef<OsStr>, A: AsRef<OsStr>>(program: P, args: &[A]) -> IoResult<EngineHandle> {
+        let mut command = Command::new(program);
+        command.args(args);
+        EngineHandle::spawn_command(command).await
+    }
+
+    /// Spawns the engine with the prepared `command` and performs the `uci` handshake. The command's standard input
+    /// and output are replaced with pipes.
+    pub async fn spawn_command(command: Command) -> IoResult<EngineHandle> {
+        let (child, writer, reader) = spawn(command)?;
+        EngineHandle::from_parts(child, writer, reader).await
+    }
+
+    /// Creates the handle from an already spawned engine (see `async_process::spawn(..)`) and performs the `uci`
+    /// handshake.
+    pub async fn from_parts(child: Child, writer: UciWriter<TokioCompat<ChildStdin>>,
+                            reader: UciReader<TokioCompat<ChildStdout>>) -> IoResult<EngineHandle> {
+        let writer: Box<dyn futures_io::AsyncWrite + Send + Unpin> = Box::new(writer.into_inner());
+        EngineHandle::handshake(Some(child), UciWriter::new(writer), reader.boxed()).await
+    }
+
+    /// Creates the handle for an engine that is not a child process, but is reached over the given streams: the
+    /// messages are written to `input` and read from `output`. Performs the `uci` handshake.
+    pub async fn from_streams<W, R>(input: W, output: R) -> IoResult<EngineHandle>
+        where W: AsyncWrite + Send + Unpin + 'static, R: AsyncRead + Send + Unpin + 'static {
+        let writer: Box<dyn futures_io::AsyncWrite + Send + Unpin> = Box::new(TokioCompat::new(input));
+        let reader: Box<dyn futures_io::AsyncRead + Send + Unpin> = Box::new(TokioCompat::new(output));
+        EngineHandle::handshake(None, UciWriter::new(writer), UciReader::new(reader)).await
+    }
+
+    async fn handshake(child: Option<Child>, writer: UciWriter<Box<dyn futures_io::AsyncWrite + Send + Unpin>>,
+                       reader: UciReader<Box<dyn futures_io::AsyncRead + Send + Unpin>>) -> IoResult<EngineHandle> {
+        let mut handle = EngineHandle {
+            child,
+            writer,
+            reader,
+            name: None,
+            author: None,
+            options: Vec::new(),
+            searching: false,
+        };
+
+        handle.send(&UciMessage::Uci).await?;
+        loop {
+            match handle.recv_required().await? {
+                UciMessage::Id { name, author } => {
+                    handle.name = name.or(handle.name);
+                    handle.author = author.or(handle.author);
+                }
+                UciMessage::Option(config) => handle.options.push(config),
+                UciMessage::UciOk => break,
+                _ => {}
+            }
+        }
+
+        Ok(handle)
+    }
+
+    /// The name of the engine, as reported by `id name`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The author of the engine, as reported by `id author`.
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// The options the engine declared during the handshake.
+    pub fn options(&self) -> &[UciOptionConfig] {
+        &self.options
+    }
+
+    /// The information the engine reported about itself during the handshake.
+    pub fn info(&self) -> EngineInfo {
+        EngineInfo {
+            name: self.name.clone(),
+            author: self.author.clone(),
+            options: self.options.clone(),
+        }
+    }
+
+    /// Sends a raw message to the engine.
+    pub async fn send(&mut self, message: &UciMessage) -> IoResult<()> {
+        self.writer.send(message).await?;
+        if let UciMessage::Go { .. } = message {
+            self.searching = true;
+        }
+
+        Ok(())
+    }
+
+    /// Receives the next message from the engine, or `None` if the engine closed its output. Blank lines are
+    /// skipped.
+    pub async fn recv(&mut self) -> IoResult<Option<UciMessage>> {
+        let message = self.reader.recv().await?;
+        if let Some(m) = &message {
+            if m.ends_search() {
+                self.searching = false;
+            }
+        }
+
+        Ok(message)
+    }
+
+    /// Returns `true` if a `go` was sent and its `bestmove` has not been received yet.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    async fn recv_required(&mut self) -> IoResult<UciMessage> {
+        self.recv().await?.ok_or_else(closed)
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    pub async fn is_ready(&mut self) -> IoResult<()> {
+        self.send(&UciMessage::IsReady).await?;
+        while self.recv_required().await? != UciMessage::ReadyOk {}
+
+        Ok(())
+    }
+
+    /// Sets the option `name` to the `value` (`None` for `button` options).
+    pub async fn set_option(&mut self, name: &str, value: Option<&str>) -> IoResult<()> {
+        self.send(&UciMessage::set_option(name, value.map(String::from))).await
+    }
+
+    /// Sends `ucinewgame` and waits for the engine to be ready.
+    pub async fn new_game(&mut self) -> IoResult<()> {
+        self.send(&UciMessage::UciNewGame).await?;
+        self.is_ready().await
+    }
+
+    /// Sets up the position from the `fen` (or the starting position, if `None`) and the `moves` played from it.
+    #[cfg(not(feature = "chess"))]
+    pub async fn position(&mut self, fen: Option<&UciFen>, moves: &[UciMove]) -> IoResult<()> {
+        self.send(&UciMessage::Position {
+            startpos: fen.is_none(),
+            fen: fen.cloned(),
+            moves: moves.to_vec(),
+        }).await
+    }
+
+    /// Sets up the position from the `fen` (or the starting position, if `None`) and the `moves` played from it.
+    #[cfg(feature = "chess")]
+    pub async fn position(&mut self, fen: Option<&UciFen>, moves: &[ChessMove]) -> IoResult<()> {
+        self.send(&UciMessage::Position {
+            startpos: fen.is_none(),
+            fen: fen.cloned(),
+            moves: moves.to_vec(),
+        }).await
+    }
+
+    /// Starts a search with the specified limits. The returned `Search` yields the engine's `info` lines and
+    /// resolves to the best move.
+    ///
+    /// If an earlier search is still in progress (its `Search` was dropped before the `bestmove` arrived), it is
+    /// stopped first and its `bestmove` discarded, so that it is not taken for the result of the new search.
+    pub async fn go(&mut self, time_control: Option<UciTimeControl>, search_control: Option<UciSearchControl>) -> IoResult<Search<'_>> {
+        if self.searching {
+            self.end_search().await?;
+        }
+        self.send(&UciMessage::Go { time_control, search_control }).await?;
+
+        Ok(Search {
+            handle: self,
+            result: None,
+            done: false,
+        })
+    }
+
+    /// Sends `stop` and skips the engine's output up to the `bestmove` that ends the search in progress.
+    async fn end_search(&mut self) -> IoResult<()> {
+        self.send(&UciMessage::Stop).await?;
+        while self.searching {
+            self.recv_required().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends `quit` and waits for the engine process to exit. An engine that is not a child process is waited for
+    /// until it closes its output.
+    pub async fn quit(mut self) -> IoResult<()> {
+        self.send(&UciMessage::Quit).await?;
+        match self.child.as_mut() {
+            Some(child) => {
+                child.wait().await?;
+            }
+            None => {
+                let _ = self.writer.close().await;
+                while self.recv().await?.is_some() {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shuts the engine down gracefully. If a search is in progress, `stop` is sent and its `bestmove` awaited for at
+    /// most the `timeout`. Then `quit` is sent and the process is given another `timeout` to exit, after which it is
+    /// killed. Errors writing to an engine that already exited are ignored.
+    ///
+    /// An engine that is not a child process is instead given the `timeout` to close its output, and the returned
+    /// status is always a success.
+    pub async fn shutdown(mut self, timeout: Duration) -> IoResult<ExitStatus> {
+        if self.searching {
+            let _ = with_timeout(timeout, self.end_search()).await;
+        }
+
+        if self.send(&UciMessage::Quit).await.is_ok() {
+            let _ = self.writer.close().await;
+        }
+
+        let mut child = match self.child.take() {
+            Some(child) => child,
+            None => {
+                let _ = with_timeout(timeout, async {
+                    while let Ok(Some(_)) = self.recv().await {}
+                }).await;
+                return Ok(ExitStatus::default());
+            }
+        };
+
+        match with_timeout(timeout, child.wait()).await {
+            Ok(status) => status,
+            Err(_) => {
+                child.kill().await?;
+                child.wait().await
+            }
+        }
+    }
+}
+
+/// A search in progress, started by `EngineHandle::go(..)`.
+///
+/// As a `Stream`, the search yields the attributes of each `info` line the engine sends. As a `Future`, it resolves
+/// to the `bestmove` that ends the search, skipping any `info` lines that have not been consumed.
+pub struct Search<'a> {
+    handle: &'a mut EngineHandle,
+    result: Option<IoResult<BestMove>>,
+    done: bool,
+}
+
+impl<'a> Search<'a> {
+    /// Returns the next `info` line, or `None` once the search has ended.
+    pub async fn next_info(&mut self) -> Option<UciInfoLine> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Sends `stop`, asking the engine to end the search as soon as possible.
+    pub async fn stop(&mut self) -> IoResult<()> {
+        self.handle.send(&UciMessage::Stop).await
+    }
+
+    /// Sends `ponderhit`, telling the engine the expected move was played.
+    pub async fn ponder_hit(&mut self) -> IoResult<()> {
+        self.handle.send(&UciMessage::PonderHit).await
+    }
+}
+
+impl<'a> Stream for Search<'a> {
+    type Item = UciInfoLine;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<UciInfoLine>> {
+        let this = self.get_mut();
+        if this.done || this.result.is_some() {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            let message = match Pin::new(&mut this.handle.reader).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Err(e))) => {
+                    this.result = Some(Err(e));
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) => {
+                    this.result = Some(Err(closed()));
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(Ok(message))) => message,
+            };
+
+            match message {
+                UciMessage::Info(attributes) => return Poll::Ready(Some(attributes)),
+                #[cfg(not(feature = "extensions"))]
+                UciMessage::BestMove { best_move, ponder } => {
+                    this.handle.searching = false;
+                    this.result = Some(Ok(BestMove { best_move, ponder }));
+                    return Poll::Ready(None);
+                }
+                #[cfg(feature = "extensions")]
+                UciMessage::BestMove { best_move, ponder, offer } => {
+                    this.handle.searching = false;
+                    this.result = Some(Ok(BestMove { best_move, ponder, offer }));
+                    return Poll::Ready(None);
+                }
+                UciMessage::Unknown(text, _) if text.trim_start().starts_with("bestmove") => {
+                    this.handle.searching = false;
+                    this.result = Some(Err(IoError::new(ErrorKind::InvalidData, format!("invalid bestmove: {}", text))));
+                    return Poll::Ready(None);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a> Future for Search<'a> {
+    type Output = IoResult<BestMove>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<BestMove>> {
+        // Once the result has been taken, the engine's output belongs to the next command
+        if self.done {
+            return Poll::Ready(Err(IoError::other("the search was already awaited")));
+        }
+
+        loop {
+            match self.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => {
+                    self.done = true;
+                    return Poll::Ready(self.result.take().unwrap_or_else(|| Err(IoError::other("the search was already awaited"))));
+                }
+            }
+        }
+    }
+}
+
+fn closed() -> IoError {
+    IoError::new(ErrorKind::UnexpectedEof, "the engine closed its output")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use tokio::net::TcpStream;
+
+    use crate::parser::parse_one;
+    use crate::testing::{MockEngine, MockScript};
+    use crate::uci::{Serializable, UciMessageKind};
+
+    use super::*;
+
+    fn mock_engine() -> MockEngine {
+        MockEngine::new("Fake", "Tester")
+            .option(UciOptionConfig::Spin { name: String::from("Hash"), default: Some(16), min: Some(1), max: Some(64) })
+    }
+
+    fn search_script() -> MockScript {
+        MockScript::new()
+            .send(parse_one("info depth 1 score cp 12 pv e2e4"))
+            .send_line("")
+            .send_all(["info depth 2 score cp 20 pv e2e4 e7e5", "bestmove e2e4 ponder e7e5"].iter().map(|m| parse_one(m)))
+    }
+
+    /// Serves the `mock` engine on a local port and connects a handle to it.
+    async fn connect(mock: MockEngine) -> IoResult<EngineHandle> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        mock.serve(listener);
+
+        let (output, input) = TcpStream::connect(addr).await?.into_split();
+        EngineHandle::from_streams(input, output).await
+    }
+
+    #[tokio::test]
+    async fn test_handshake() {
+        let engine = connect(mock_engine()).await.unwrap();
+        assert_eq!(engine.name(), Some("Fake"));
+        assert_eq!(engine.author(), Some("Tester"));
+        assert_eq!(engine.options().len(), 1);
+        engine.quit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_and_result() {
+        let mut engine = connect(mock_engine().on(UciMessageKind::Go, search_script())).await.unwrap();
+        engine.set_option("Hash", Some("32")).await.unwrap();
+        engine.new_game().await.unwrap();
+        engine.position(None, &[]).await.unwrap();
+
+        let mut search = engine.go(None, Some(UciSearchControl::depth(2))).await.unwrap();
+        let mut depths = Vec::new();
+        while let Some(info) = search.next_info().await {
+            depths.push(info[0].clone());
+        }
+        assert_eq!(depths, vec![UciInfoAttribute::Depth(1), UciInfoAttribute::Depth(2)]);
+
+        let best = search.await.unwrap();
+        assert_eq!(UciMessage::best_move_with_ponder(best.best_move, best.ponder.unwrap()).serialize(), "bestmove e2e4 ponder e7e5");
+
+        // Awaiting directly skips the info lines
+        let best = engine.go(None, None).await.unwrap().await.unwrap();
+        assert!(best.ponder.is_some());
+
+        // A completed search does not read the replies to the next commands
+        let mut search = engine.go(None, None).await.unwrap();
+        assert!((&mut search).await.is_ok());
+        search.handle.send(&UciMessage::IsReady).await.unwrap();
+        assert_eq!((&mut search).await.unwrap_err().kind(), ErrorKind::Other);
+        assert!(search.next_info().await.is_none());
+        assert_eq!(engine.recv().await.unwrap(), Some(UciMessage::ReadyOk));
+
+        engine.quit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_search() {
+        let mock = mock_engine()
+            .on(UciMessageKind::Go, MockScript::new().send_all(["info depth 1", "bestmove a2a3"].iter().map(|m| parse_one(m))))
+            .on(UciMessageKind::Go, MockScript::new().send(parse_one("bestmove e2e4")))
+            .on(UciMessageKind::Go, MockScript::new().send_line("bestmove e2"));
+        let mut engine = connect(mock).await.unwrap();
+
+        drop(engine.go(Some(UciTimeControl::Infinite), None).await.unwrap());
+        assert!(engine.is_searching());
+
+        // The bestmove of the dropped search is not taken for the result of the next one
+        let best = engine.go(None, Some(UciSearchControl::depth(1))).await.unwrap().await.unwrap();
+        assert_eq!(UciMessage::best_move(best.best_move).serialize(), "bestmove e2e4");
+        assert!(!engine.is_searching());
+
+        // Nor does a malformed bestmove leave the search in progress
+        assert!(engine.go(None, Some(UciSearchControl::depth(2))).await.unwrap().await.is_err());
+        assert!(!engine.is_searching());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        // Answers stop with a bestmove, then exits on quit
+        let mock = mock_engine().on(UciMessageKind::Stop, MockScript::new().send(parse_one("bestmove e2e4")));
+        let log = mock.log();
+        let mut engine = connect(mock).await.unwrap();
+        engine.send(&UciMessage::go_infinite()).await.unwrap();
+        assert!(engine.is_searching());
+        assert!(engine.shutdown(Duration::from_secs(5)).await.unwrap().success());
+        assert_eq!(log.messages(), vec![UciMessage::Uci, UciMessage::go_infinite(), UciMessage::Stop, UciMessage::Quit]);
+
+        // Never answers stop, so the wait for its bestmove is cut short
+        let mut engine = connect(mock_engine()).await.unwrap();
+        engine.send(&UciMessage::go_infinite()).await.unwrap();
+        assert!(engine.shutdown(Duration::from_millis(100)).await.unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_closed_engine() {
+        let mock = mock_engine().on(UciMessageKind::Uci, MockScript::new().send(UciMessage::id_name("Quitter")).exit());
+        assert!(connect(mock).await.is_err());
+    }
+}
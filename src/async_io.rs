@@ -0,0 +1,523 @@
+//! The `async_io` module contains the runtime-agnostic halves of an asynchronous connection to an engine: the
+//! `UciWriter` and the `UciReader` wrap any writer and reader implementing the `AsyncWrite` and `AsyncRead` traits of
+//! the [futures](https://docs.rs/futures) crates, and so work on any executor. It is available with the `futures`
+//! feature, which the `tokio` feature enables. The `UciSink` is the send half in the form of a `Sink` of messages.
+//!
+//! Thin adapters connect the halves to the runtimes:
+//!
+//! * with the `tokio` feature, `TokioCompat` wraps the readers and writers of [tokio](https://tokio.rs), and
+//!   `async_process::spawn(..)` starts an engine as a tokio child process. The `EngineHandle` in the `async_engine`
+//!   module is built on top of them;
+//! * with the `async-std` feature, whose readers and writers implement the futures traits directly,
+//!   `spawn_async_std(..)` starts an engine as an [async-std](https://docs.rs/async-std) child process.
+
+use std::future::poll_fn;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use crate::parser::parse_one;
+use crate::uci::{Serializable, UciMessage};
+
+/// The send half: serializes messages and writes them, a line at a time, flushing after each.
+#[derive(Debug)]
+pub struct UciWriter<W> {
+    writer: W,
+}
+
+impl<W: AsyncWrite + Unpin> UciWriter<W> {
+    /// Wraps the `writer`.
+    pub fn new(writer: W) -> UciWriter<W> {
+        UciWriter {
+            writer
+        }
+    }
+
+    /// Sends the `message`.
+    pub async fn send(&mut self, message: &UciMessage) -> IoResult<()> {
+        let mut line = message.serialize();
+        line.push('\n');
+
+        let mut written = 0;
+        poll_fn(|cx| poll_write_all(&mut self.writer, cx, line.as_bytes(), &mut written)).await?;
+        poll_fn(|cx| Pin::new(&mut self.writer).poll_flush(cx)).await
+    }
+
+    /// Closes the underlying writer, which closes the pipe to a child process.
+    pub async fn close(&mut self) -> IoResult<()> {
+        poll_fn(|cx| Pin::new(&mut self.writer).poll_close(cx)).await
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// The send half as a `Sink` of messages: each message is serialized and terminated by a newline, and is written out
+/// when the sink is flushed (or when it is readied for the next message). Closing the sink flushes it and closes the
+/// underlying writer.
+///
+/// Together with the `UciReader`, which is a `Stream`, it makes the connection to an engine a plain `Stream` + `Sink`
+/// pair, ready for the combinators of the futures crates.
+///
+/// # Examples
+///
+/// ```
+/// use futures::executor::block_on;
+/// use futures::{SinkExt, StreamExt};
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::async_io::{UciReader, UciSink};
+///
+/// let engine_output: &[u8] = b"id name Fake\nuciok\n";
+/// let mut sink = UciSink::new(Vec::new());
+/// let stream = UciReader::new(engine_output);
+///
+/// block_on(async {
+///     sink.send(UciMessage::Uci).await.unwrap();
+///     let replies: Vec<UciMessage> = stream.map(Result::unwrap).collect().await;
+///     assert_eq!(replies, vec![UciMessage::id_name("Fake"), UciMessage::UciOk]);
+/// });
+///
+/// assert_eq!(sink.get_ref(), b"uci\n");
+/// ```
+#[derive(Debug)]
+pub struct UciSink<W> {
+    writer: W,
+    buffer: Vec<u8>,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> UciSink<W> {
+    /// Wraps the `writer`.
+    pub fn new(writer: W) -> UciSink<W> {
+        UciSink {
+            writer,
+            buffer: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the wrapped writer. Messages that were sent but not yet flushed are lost.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        match poll_write_all(&mut self.writer, cx, &self.buffer, &mut self.written) {
+            Poll::Ready(Ok(())) => {
+                self.buffer.clear();
+                self.written = 0;
+                Poll::Ready(Ok(()))
+            }
+            other => other,
+        }
+    }
+}
+
+/// Writes the `buf` from the `written` offset on, advancing the offset, until all of it is written. Interrupted writes
+/// are retried.
+fn poll_write_all<W: AsyncWrite + Unpin>(writer: &mut W, cx: &mut Context<'_>, buf: &[u8], written: &mut usize)
+                                         -> Poll<IoResult<()>> {
+    while *written < buf.len() {
+        match Pin::new(&mut *writer).poll_write(cx, &buf[*written..]) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(0)) => return Poll::Ready(Err(IoError::from(ErrorKind::WriteZero))),
+            Poll::Ready(Ok(n)) => *written += n,
+        }
+    }
+
+    Poll::Ready(Ok(()))
+}
+
+impl<W: AsyncWrite + Unpin> Sink<UciMessage> for UciSink<W> {
+    type Error = IoError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().poll_write_buffer(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: UciMessage) -> IoResult<()> {
+        let this = self.get_mut();
+        this.buffer.extend_from_slice(item.serialize().as_bytes());
+        this.buffer.push(b'\n');
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buffer(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut *this).poll_flush(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+/// The receive half: reads lines and parses each into a message. Blank lines are skipped, and a final line that is
+/// not terminated by a newline is still parsed once the pipe is closed.
+///
+/// As a `Stream`, it yields the messages until the pipe is closed.
+#[derive(Debug)]
+pub struct UciReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+impl<R: AsyncRead + Unpin> UciReader<R> {
+    /// Wraps the `reader`.
+    pub fn new(reader: R) -> UciReader<R> {
+        UciReader {
+            reader,
+            buffer: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// Receives the next message, or `None` if the pipe was closed.
+    pub async fn recv(&mut self) -> IoResult<Option<UciMessage>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await.transpose()
+    }
+
+    /// Returns the wrapped reader. Input that was read from it but not yet received is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Boxes the wrapped reader, keeping the input that was read from it but not yet received.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn boxed(self) -> UciReader<Box<dyn AsyncRead + Send + Unpin>> where R: Send + 'static {
+        UciReader {
+            reader: Box::new(self.reader),
+            buffer: self.buffer,
+            closed: self.closed,
+        }
+    }
+
+    fn poll_next_line(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<Option<String>>> {
+        loop {
+            let end = match self.buffer.iter().position(|b| *b == b'\n') {
+                Some(i) => Some(i + 1),
+                None if self.closed && !self.buffer.is_empty() => Some(self.buffer.len()),
+                None if self.closed => return Poll::Ready(Ok(None)),
+                None => None,
+            };
+
+            if let Some(end) = end {
+                let line: Vec<u8> = self.buffer.drain(..end).collect();
+                let line = String::from_utf8(line).map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+                return Poll::Ready(Ok(Some(line.trim_end_matches(['\r', '\n']).to_string())));
+            }
+
+            let mut chunk = [0u8; 1024];
+            match Pin::new(&mut self.reader).poll_read(cx, &mut chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(0)) => self.closed = true,
+                Poll::Ready(Ok(n)) => self.buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for UciReader<R> {
+    type Item = IoResult<UciMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<IoResult<UciMessage>>> {
+        let this = self.get_mut();
+        loop {
+            match this.poll_next_line(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Ok(None)) => return Poll::Ready(None),
+                Poll::Ready(Ok(Some(line))) => {
+                    if !line.trim().is_empty() {
+                        return Poll::Ready(Some(Ok(parse_one(&line))));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wraps a tokio reader or writer so that it implements the futures `AsyncRead` or `AsyncWrite` trait. Closing the
+/// wrapper shuts the tokio writer down. Available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioCompat<T> {
+    inner: T,
+}
+
+#[cfg(feature = "tokio")]
+impl<T> TokioCompat<T> {
+    /// Wraps the tokio reader or writer.
+    pub fn new(inner: T) -> TokioCompat<T> {
+        TokioCompat {
+            inner
+        }
+    }
+
+    /// Returns the wrapped reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin> AsyncRead for TokioCompat<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<IoResult<usize>> {
+        let mut read_buf = tokio::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.get_mut().inner).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(read_buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin> AsyncWrite for TokioCompat<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Spawns the engine with the prepared async-std `command`, replacing its standard input and output with pipes, and
+/// returns the child process along with the send and receive halves. The child is killed when it is dropped.
+/// Available with the `async-std` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use async_std::process::Command;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::async_io::spawn_async_std;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let (_child, mut writer, mut reader) = spawn_async_std(Command::new("stockfish"))?;
+/// writer.send(&UciMessage::Uci).await?;
+/// while let Some(m) = reader.recv().await? {
+///     if m == UciMessage::UciOk {
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "async-std")]
+#[allow(clippy::type_complexity)]
+pub fn spawn_async_std(mut command: async_std::process::Command)
+                       -> IoResult<(async_std::process::Child,
+                                    UciWriter<async_std::process::ChildStdin>,
+                                    UciReader<async_std::process::ChildStdout>)> {
+    use async_std::process::Stdio;
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take().ok_or_else(|| IoError::other("the engine's stdin is not piped"))?;
+    let stdout = child.stdout.take().ok_or_else(|| IoError::other("the engine's stdout is not piped"))?;
+
+    Ok((child, UciWriter::new(stdin), UciReader::new(stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    // The halves need no runtime: with in-memory readers and writers, every poll completes at once.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(noop_waker_ref())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future is pending"),
+        }
+    }
+
+    #[test]
+    fn test_reader_partial_lines() {
+        let input: &[u8] = b"id name Fake\r\n\n  \nreadyok\nbestmove e2e4";
+        let mut reader = UciReader::new(input);
+
+        block_on(async {
+            assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::id_name("Fake")));
+            assert_eq!(poll_fn(|cx| Pin::new(&mut reader).poll_next(cx)).await.unwrap().unwrap(), UciMessage::ReadyOk);
+            assert!(matches!(reader.recv().await.unwrap(), Some(UciMessage::BestMove { .. })));
+            assert_eq!(reader.recv().await.unwrap(), None);
+            assert!(poll_fn(|cx| Pin::new(&mut reader).poll_next(cx)).await.is_none());
+        });
+
+        let input: &[u8] = b"uciok\n\xff\n";
+        let mut reader = UciReader::new(input);
+        block_on(async {
+            assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::UciOk));
+            assert_eq!(reader.recv().await.unwrap_err().kind(), ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut writer = UciWriter::new(Vec::new());
+        block_on(async {
+            writer.send(&UciMessage::Uci).await.unwrap();
+            writer.send(&UciMessage::IsReady).await.unwrap();
+            writer.close().await.unwrap();
+        });
+        assert_eq!(writer.into_inner(), b"uci\nisready\n");
+
+        let mut writer = UciWriter::new(InterruptedWriter::default());
+        block_on(writer.send(&UciMessage::Uci)).unwrap();
+        assert_eq!(writer.into_inner().written, b"uci\n");
+    }
+
+    // Fails the first write as interrupted.
+    #[derive(Default)]
+    struct InterruptedWriter {
+        written: Vec<u8>,
+        interrupted: bool,
+    }
+
+    impl AsyncWrite for InterruptedWriter {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+            let this = self.get_mut();
+            if !this.interrupted {
+                this.interrupted = true;
+                return Poll::Ready(Err(IoError::from(ErrorKind::Interrupted)));
+            }
+
+            this.written.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    // Accepts at most three bytes per write, and every other poll is pending.
+    #[derive(Default)]
+    struct SlowWriter {
+        written: Vec<u8>,
+        ready: bool,
+        flushes: usize,
+        closed: bool,
+    }
+
+    impl AsyncWrite for SlowWriter {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+            let this = self.get_mut();
+            this.ready = !this.ready;
+            if !this.ready {
+                return Poll::Pending;
+            }
+
+            let n = buf.len().min(3);
+            this.written.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            self.get_mut().flushes += 1;
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            self.get_mut().closed = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll_until_ready<T>(mut poll: impl FnMut(&mut Context<'_>) -> Poll<T>) -> T {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        loop {
+            if let Poll::Ready(output) = poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_sink() {
+        let mut sink = UciSink::new(SlowWriter::default());
+
+        poll_until_ready(|cx| Pin::new(&mut sink).poll_ready(cx)).unwrap();
+        Pin::new(&mut sink).start_send(UciMessage::Uci).unwrap();
+        Pin::new(&mut sink).start_send(UciMessage::IsReady).unwrap();
+        assert!(sink.get_ref().written.is_empty());
+
+        poll_until_ready(|cx| Pin::new(&mut sink).poll_flush(cx)).unwrap();
+        assert_eq!(sink.get_ref().written, b"uci\nisready\n");
+        assert_eq!(sink.get_ref().flushes, 1);
+
+        poll_until_ready(|cx| Pin::new(&mut sink).poll_ready(cx)).unwrap();
+        Pin::new(&mut sink).start_send(UciMessage::Quit).unwrap();
+        poll_until_ready(|cx| Pin::new(&mut sink).poll_close(cx)).unwrap();
+
+        let writer = sink.into_inner();
+        assert_eq!(writer.written, b"uci\nisready\nquit\n");
+        assert_eq!(writer.flushes, 2);
+        assert!(writer.closed);
+
+        let mut full = [0u8; 2];
+        let mut sink = UciSink::new(futures::io::Cursor::new(&mut full[..]));
+        Pin::new(&mut sink).start_send(UciMessage::Uci).unwrap();
+        let err = poll_until_ready(|cx| Pin::new(&mut sink).poll_flush(cx)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+    }
+
+    #[cfg(all(unix, feature = "async-std"))]
+    #[test]
+    fn test_spawn_async_std() {
+        let mut command = async_std::process::Command::new("sh");
+        command.args(["-c", "read -r line; printf 'id name Echo\\nuciok'"]);
+
+        async_std::task::block_on(async {
+            let (mut child, mut writer, mut reader) = spawn_async_std(command).unwrap();
+            writer.send(&UciMessage::Uci).await.unwrap();
+            assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::id_name("Echo")));
+            assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::UciOk));
+            assert_eq!(reader.recv().await.unwrap(), None);
+            assert!(child.status().await.unwrap().success());
+        });
+    }
+}
@@ -0,0 +1,70 @@
+//! The `async_process` module wires the standard input and output of a [tokio](https://tokio.rs) child process to
+//! UCI messages. It is available with the `tokio` feature.
+//!
+//! `spawn(..)` starts an engine and returns its send half, the `UciWriter`, and its receive half, the `UciReader`,
+//! which is a `Stream` of the parsed messages. The halves are the runtime-agnostic ones of the `async_io` module,
+//! wrapping the pipes of the child process with `TokioCompat`. The `EngineHandle` in the `async_engine` module is
+//! built on top of them.
+
+use std::io::Result as IoResult;
+use std::process::Stdio;
+
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use crate::async_io::{TokioCompat, UciReader, UciWriter};
+
+/// Spawns the engine with the prepared `command`, replacing its standard input and output with pipes, and returns
+/// the child process along with the send and receive halves. The child is killed when it is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio::process::Command;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::async_process::spawn;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let (_child, mut writer, mut reader) = spawn(Command::new("stockfish"))?;
+/// writer.send(&UciMessage::Uci).await?;
+/// while let Some(m) = reader.recv().await? {
+///     if m == UciMessage::UciOk {
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn spawn(mut command: Command) -> IoResult<(Child, UciWriter<TokioCompat<ChildStdin>>, UciReader<TokioCompat<ChildStdout>>)> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take().ok_or_else(|| std::io::Error::other("the engine's stdin is not piped"))?;
+    let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("the engine's stdout is not piped"))?;
+
+    Ok((child, UciWriter::new(TokioCompat::new(stdin)), UciReader::new(TokioCompat::new(stdout))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uci::UciMessage;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "read -r line; printf 'id name Echo\\nuciok'"]);
+
+        let (mut child, mut writer, mut reader) = spawn(command).unwrap();
+        writer.send(&UciMessage::Uci).await.unwrap();
+        assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::id_name("Echo")));
+        assert_eq!(reader.recv().await.unwrap(), Some(UciMessage::UciOk));
+        assert_eq!(reader.recv().await.unwrap(), None);
+        assert!(child.wait().await.unwrap().success());
+    }
+}
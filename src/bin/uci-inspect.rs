@@ -0,0 +1,219 @@
+//! `uci-inspect` reads UCI text from a file or the standard input and prints each parsed message with its line number,
+//! its kind and its fields, or the error if the line could not be parsed. Available with the `cli` feature.
+//!
+//! ```text
+//! uci-inspect [--strict] [--json] [FILE]
+//! ```
+//!
+//! By default the parsing is lenient: a line that cannot be parsed is printed as an `Unknown` message, along with the
+//! parse error. With `--strict`, it is reported as an error instead, and the exit status is 1 if any line failed.
+//! With `--json`, a JSON object is printed per line instead of the human-readable text.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+
+use serde_json::json;
+use vampirc_uci::{parse_strict, parse_one, UciMessage};
+
+const USAGE: &str = "usage: uci-inspect [--strict] [--json] [FILE]
+
+Prints each message of the UCI text in FILE (or the standard input) with its line number, kind and fields.
+
+  --strict  report the lines that cannot be parsed as errors, and exit with status 1 if there are any
+  --json    print a JSON object per line
+  --help    print this help";
+
+/// The command line arguments.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+struct Args {
+    strict: bool,
+    json: bool,
+    path: Option<String>,
+}
+
+fn parse_args<I: IntoIterator<Item=String>>(args: I) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => parsed.strict = true,
+            "--json" => parsed.json = true,
+            "-h" | "--help" => return Ok(None),
+            a if a.starts_with('-') && a != "-" => return Err(format!("unknown flag `{}`", a)),
+            _ if parsed.path.is_some() => return Err(String::from("only one file can be inspected at a time")),
+            _ => parsed.path = Some(arg),
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+/// The outcome of parsing a line.
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Entry {
+    Message(UciMessage),
+    Error(String),
+}
+
+fn inspect(line: &str, strict: bool) -> Entry {
+    if strict {
+        match parse_strict(line) {
+            Ok(mut messages) if !messages.is_empty() => Entry::Message(messages.remove(0)),
+            Ok(_) => Entry::Error(String::from("no message")),
+            Err(e) => Entry::Error(e.message().into_owned()),
+        }
+    } else {
+        Entry::Message(parse_one(line))
+    }
+}
+
+fn format_text(line_number: usize, entry: &Entry) -> String {
+    match entry {
+        Entry::Message(UciMessage::Unknown(text, error)) => {
+            let reason = error.as_ref().map(|e| format!(" ({})", e.variant.message())).unwrap_or_default();
+            format!("{:>5}  {:<14} {:?}{}", line_number, "Unknown", text, reason)
+        }
+        Entry::Message(m) => format!("{:>5}  {:<14} {:?}", line_number, format!("{:?}", m.kind()), m),
+        Entry::Error(e) => format!("{:>5}  {:<14} {}", line_number, "error", e),
+    }
+}
+
+fn format_json(line_number: usize, entry: &Entry) -> String {
+    let value = match entry {
+        Entry::Message(UciMessage::Unknown(text, error)) => json!({
+            "line": line_number,
+            "kind": "Unknown",
+            "text": text,
+            "error": error.as_ref().map(|e| e.variant.message().into_owned()),
+        }),
+        Entry::Message(m) => json!({
+            "line": line_number,
+            "kind": m.kind(),
+            "message": m,
+        }),
+        Entry::Error(e) => json!({
+            "line": line_number,
+            "error": e,
+        }),
+    };
+
+    value.to_string()
+}
+
+fn run(args: &Args, input: &mut dyn BufRead, output: &mut dyn Write) -> io::Result<bool> {
+    let mut failed = false;
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry = inspect(&line, args.strict);
+        failed |= matches!(entry, Entry::Error(_));
+
+        if args.json {
+            writeln!(output, "{}", format_json(i + 1, &entry))?;
+        } else {
+            writeln!(output, "{}", format_text(i + 1, &entry))?;
+        }
+    }
+
+    Ok(!failed)
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("uci-inspect: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut input: Box<dyn BufRead> = match args.path.as_deref() {
+        None | Some("-") => Box::new(BufReader::new(io::stdin())),
+        Some(path) => match File::open(path) {
+            Ok(f) => Box::new(BufReader::new(f)),
+            Err(e) => {
+                eprintln!("uci-inspect: cannot open {}: {}", path, e);
+                process::exit(2);
+            }
+        },
+    };
+
+    let stdout = io::stdout();
+    match run(&args, &mut input, &mut stdout.lock()) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("uci-inspect: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "uci\n\nid name Vampirc\nposiiton startpos\n";
+
+    fn run_to_string(args: &Args, input: &str) -> (bool, String) {
+        let mut output = Vec::new();
+        let ok = run(args, &mut input.as_bytes(), &mut output).unwrap();
+        (ok, String::from_utf8(output).unwrap())
+    }
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(args(&[]), Ok(Some(Args::default())));
+        assert_eq!(args(&["--json", "--strict", "log.txt"]),
+                   Ok(Some(Args { strict: true, json: true, path: Some(String::from("log.txt")) })));
+        assert_eq!(args(&["--help"]), Ok(None));
+        assert!(args(&["--verbose"]).is_err());
+        assert!(args(&["a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_lenient_text() {
+        let (ok, text) = run_to_string(&Args::default(), INPUT);
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(ok);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "    1  Uci            Uci");
+        assert!(lines[1].starts_with("    3  Id             Id {"));
+        assert!(lines[2].starts_with("    4  Unknown        \"posiiton startpos\" (unknown keyword `posiiton`"));
+    }
+
+    #[test]
+    fn test_strict_text() {
+        let (ok, text) = run_to_string(&Args { strict: true, ..Args::default() }, INPUT);
+
+        assert!(!ok);
+        assert_eq!(text.lines().last().unwrap(),
+                   "    4  error          unknown keyword `posiiton`, did you mean `position`?");
+    }
+
+    #[test]
+    fn test_json() {
+        let (_, text) = run_to_string(&Args { strict: true, json: true, ..Args::default() }, INPUT);
+        let values: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(values[0], json!({ "line": 1, "kind": "Uci", "message": "Uci" }));
+        assert_eq!(values[1]["message"]["Id"]["name"], "Vampirc");
+        assert_eq!(values[2]["line"], 4);
+        assert!(values[2]["error"].as_str().unwrap().starts_with("unknown keyword"));
+    }
+}
@@ -0,0 +1,791 @@
+//! The `binary` module contains a compact binary encoding of the `UciMessage`s, for shipping parsed messages between
+//! processes or over the network without serializing them to text and parsing them again.
+//!
+//! An encoded message starts with a version byte (currently `1`), followed by a byte identifying the kind of message
+//! and the message's fields. Unsigned integers are encoded as LEB128 varints, signed integers (and durations, in
+//! milliseconds) as zigzag varints, decimals as their 8 little-endian IEEE 754 bytes, strings as their length followed by their UTF-8 bytes, and options and lists with
+//! a presence byte or a length prefix. A move takes three bytes: the source and destination square indexes (`a1` is
+//! `0`, `h8` is `63`) and the promotion piece. The parse error of a `UciMessage::Unknown` is not encoded. A `bestmove`
+//! with a draw offer or a resignation (with the `extensions` feature) is a message kind of its own, which builds without
+//! the feature fail to decode.
+
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[cfg(feature = "chess")]
+use chess::{ChessMove, Piece, ALL_PIECES, ALL_SQUARES};
+use chrono::Duration;
+
+#[cfg(feature = "extensions")]
+use crate::uci::Offer;
+use crate::uci::{
+    ProtectionState, UciFen, UciFloat, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl,
+};
+#[cfg(not(feature = "chess"))]
+use crate::uci::{UciMove, UciPiece, UciSquare};
+
+/// The version of the binary encoding written by `UciMessage::encode_into(..)`.
+pub const ENCODING_VERSION: u8 = 1;
+
+/// The square byte announcing a square outside of the board, followed by its file and rank.
+#[cfg(not(feature = "chess"))]
+const OFF_BOARD_SQUARE: u8 = 0xff;
+
+/// An error describing why a byte slice could not be decoded into a `UciMessage`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum DecodeError {
+    /// The message was encoded with an unsupported version of the encoding. The version is included.
+    UnsupportedVersion(u8),
+
+    /// The input ended in the middle of a message.
+    UnexpectedEnd,
+
+    /// The input is not a valid encoding. The part of the message that could not be decoded is included.
+    InvalidData(&'static str),
+
+    /// The message was decoded, but this many bytes followed it.
+    TrailingBytes(usize),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported encoding version {}", version),
+            DecodeError::UnexpectedEnd => write!(f, "the input ended in the middle of a message"),
+            DecodeError::InvalidData(what) => write!(f, "invalid {}", what),
+            DecodeError::TrailingBytes(count) => write!(f, "{} bytes follow the message", count),
+        }
+    }
+}
+
+impl StdError for DecodeError {}
+
+impl UciMessage {
+    /// Appends the binary encoding of this message to the `buffer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{parse_one, UciMessage};
+    ///
+    /// let message = parse_one("info depth 20 score cp 35 pv e2e4 e7e5 g1f3");
+    /// let mut buffer = Vec::new();
+    /// message.encode_into(&mut buffer);
+    ///
+    /// assert!(buffer.len() < message.to_string().len());
+    /// assert_eq!(UciMessage::decode(&buffer).unwrap(), message);
+    /// ```
+    pub fn encode_into(&self, buffer: &mut Vec<u8>) {
+        let mut encoder = Encoder { buffer };
+        encoder.byte(ENCODING_VERSION);
+        encoder.message(self);
+    }
+
+    /// Decodes a message encoded by `encode_into(..)`. The `bytes` must contain exactly one message.
+    pub fn decode(bytes: &[u8]) -> Result<UciMessage, DecodeError> {
+        let mut decoder = Decoder { bytes, position: 0 };
+        let version = decoder.byte()?;
+        if version != ENCODING_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let message = decoder.message()?;
+        match bytes.len() - decoder.position {
+            0 => Ok(message),
+            trailing => Err(DecodeError::TrailingBytes(trailing)),
+        }
+    }
+}
+
+struct Encoder<'a> {
+    buffer: &'a mut Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    fn byte(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.byte(value as u8);
+    }
+
+    fn unsigned(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.byte((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.byte(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.unsigned(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn float(&mut self, value: UciFloat) {
+        self.buffer.extend_from_slice(&value.0.to_le_bytes());
+    }
+
+    fn duration(&mut self, value: &Duration) {
+        self.signed(value.num_milliseconds());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.unsigned(value.len() as u64);
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    fn option<T, F: FnOnce(&mut Self, &T)>(&mut self, value: &Option<T>, encode: F) {
+        match value {
+            Some(v) => {
+                self.byte(1);
+                encode(self, v);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    fn list<T, F: FnMut(&mut Self, &T)>(&mut self, values: &[T], mut encode: F) {
+        self.unsigned(values.len() as u64);
+        for v in values {
+            encode(self, v);
+        }
+    }
+
+    #[cfg(not(feature = "chess"))]
+    fn square(&mut self, square: &UciSquare) {
+        if ('a'..='h').contains(&square.file) && (1..=8).contains(&square.rank) {
+            self.byte((square.rank - 1) * 8 + (square.file as u8 - b'a'));
+        } else {
+            self.byte(OFF_BOARD_SQUARE);
+            self.unsigned(square.file as u64);
+            self.byte(square.rank);
+        }
+    }
+
+    #[cfg(not(feature = "chess"))]
+    fn a_move(&mut self, m: &UciMove) {
+        self.square(&m.from);
+        self.square(&m.to);
+        self.byte(match m.promotion {
+            None => 0,
+            Some(UciPiece::Pawn) => 1,
+            Some(UciPiece::Knight) => 2,
+            Some(UciPiece::Bishop) => 3,
+            Some(UciPiece::Rook) => 4,
+            Some(UciPiece::Queen) => 5,
+            Some(UciPiece::King) => 6,
+        });
+    }
+
+    #[cfg(feature = "chess")]
+    fn a_move(&mut self, m: &ChessMove) {
+        self.byte(m.get_source().to_int());
+        self.byte(m.get_dest().to_int());
+        self.byte(m.get_promotion().map_or(0, |p| p.to_index() as u8 + 1));
+    }
+
+    fn protection_state(&mut self, state: &ProtectionState) {
+        self.byte(match state {
+            ProtectionState::Checking => 0,
+            ProtectionState::Ok => 1,
+            ProtectionState::Error => 2,
+        });
+    }
+
+    fn message(&mut self, message: &UciMessage) {
+        match message {
+            UciMessage::Uci => self.byte(0),
+            UciMessage::Debug(on) => {
+                self.byte(1);
+                self.bool(*on);
+            }
+            UciMessage::IsReady => self.byte(2),
+            UciMessage::Register { later, name, code } => {
+                self.byte(3);
+                self.bool(*later);
+                self.option(name, |e, s| e.string(s));
+                self.option(code, |e, s| e.string(s));
+            }
+            UciMessage::Position { startpos, fen, moves } => {
+                self.byte(4);
+                self.bool(*startpos);
+                self.option(fen, |e, f| e.string(f.as_str()));
+                self.list(moves, |e, m| e.a_move(m));
+            }
+            UciMessage::SetOption { name, value } => {
+                self.byte(5);
+                self.string(name);
+                self.option(value, |e, s| e.string(s));
+            }
+            UciMessage::UciNewGame => self.byte(6),
+            UciMessage::Stop => self.byte(7),
+            UciMessage::PonderHit => self.byte(8),
+            UciMessage::Quit => self.byte(9),
+            UciMessage::Go { time_control, search_control } => {
+                self.byte(10);
+                self.option(time_control, |e, tc| e.time_control(tc));
+                self.option(search_control, |e, sc| e.search_control(sc));
+            }
+            UciMessage::Id { name, author } => {
+                self.byte(11);
+                self.option(name, |e, s| e.string(s));
+                self.option(author, |e, s| e.string(s));
+            }
+            UciMessage::UciOk => self.byte(12),
+            UciMessage::ReadyOk => self.byte(13),
+            #[cfg(feature = "extensions")]
+            UciMessage::BestMove { best_move, ponder, offer: Some(offer) } => {
+                self.byte(20);
+                self.a_move(best_move);
+                self.option(ponder, |e, m| e.a_move(m));
+                self.byte(match offer {
+                    Offer::Draw => 0,
+                    Offer::Resign => 1,
+                });
+            }
+            UciMessage::BestMove { best_move, ponder, .. } => {
+                self.byte(14);
+                self.a_move(best_move);
+                self.option(ponder, |e, m| e.a_move(m));
+            }
+            UciMessage::CopyProtection(state) => {
+                self.byte(15);
+                self.protection_state(state);
+            }
+            UciMessage::Registration(state) => {
+                self.byte(16);
+                self.protection_state(state);
+            }
+            UciMessage::Option(config) => {
+                self.byte(17);
+                self.option_config(config);
+            }
+            UciMessage::Info(attributes) => {
+                self.byte(18);
+                self.list(attributes, |e, a| e.info_attribute(a));
+            }
+            UciMessage::Unknown(text, _) => {
+                self.byte(19);
+                self.string(text);
+            }
+        }
+    }
+
+    fn time_control(&mut self, time_control: &UciTimeControl) {
+        match time_control {
+            UciTimeControl::Ponder => self.byte(0),
+            UciTimeControl::Infinite => self.byte(1),
+            UciTimeControl::TimeLeft { white_time, black_time, white_increment, black_increment, moves_to_go } => {
+                self.byte(2);
+                self.option(white_time, |e, d| e.duration(d));
+                self.option(black_time, |e, d| e.duration(d));
+                self.option(white_increment, |e, d| e.duration(d));
+                self.option(black_increment, |e, d| e.duration(d));
+                self.option(moves_to_go, |e, n| e.byte(*n));
+            }
+            UciTimeControl::MoveTime(duration) => {
+                self.byte(3);
+                self.duration(duration);
+            }
+        }
+    }
+
+    fn search_control(&mut self, search_control: &UciSearchControl) {
+        self.list(&search_control.search_moves, |e, m| e.a_move(m));
+        self.option(&search_control.mate, |e, n| e.byte(*n));
+        self.option(&search_control.depth, |e, n| e.byte(*n));
+        self.option(&search_control.nodes, |e, n| e.unsigned(*n));
+    }
+
+    fn option_config(&mut self, config: &UciOptionConfig) {
+        match config {
+            UciOptionConfig::Check { name, default } => {
+                self.byte(0);
+                self.string(name);
+                self.option(default, |e, b| e.bool(*b));
+            }
+            UciOptionConfig::Spin { name, default, min, max } => {
+                self.byte(1);
+                self.string(name);
+                self.option(default, |e, n| e.signed(*n));
+                self.option(min, |e, n| e.signed(*n));
+                self.option(max, |e, n| e.signed(*n));
+            }
+            UciOptionConfig::Float { name, default, min, max } => {
+                self.byte(5);
+                self.string(name);
+                self.option(default, |e, n| e.float(*n));
+                self.option(min, |e, n| e.float(*n));
+                self.option(max, |e, n| e.float(*n));
+            }
+            UciOptionConfig::Combo { name, default, var } => {
+                self.byte(2);
+                self.string(name);
+                self.option(default, |e, s| e.string(s));
+                self.list(var, |e, s| e.string(s));
+            }
+            UciOptionConfig::Button { name } => {
+                self.byte(3);
+                self.string(name);
+            }
+            UciOptionConfig::String { name, default } => {
+                self.byte(4);
+                self.string(name);
+                self.option(default, |e, s| e.string(s));
+            }
+        }
+    }
+
+    fn info_attribute(&mut self, attribute: &UciInfoAttribute) {
+        match attribute {
+            UciInfoAttribute::Depth(n) => {
+                self.byte(0);
+                self.byte(*n);
+            }
+            UciInfoAttribute::SelDepth(n) => {
+                self.byte(1);
+                self.byte(*n);
+            }
+            UciInfoAttribute::Time(duration) => {
+                self.byte(2);
+                self.duration(duration);
+            }
+            UciInfoAttribute::Nodes(n) => {
+                self.byte(3);
+                self.unsigned(*n);
+            }
+            UciInfoAttribute::Pv(moves) => {
+                self.byte(4);
+                self.list(moves, |e, m| e.a_move(m));
+            }
+            UciInfoAttribute::MultiPv(n) => {
+                self.byte(5);
+                self.unsigned(*n as u64);
+            }
+            UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound } => {
+                self.byte(6);
+                self.option(cp, |e, n| e.signed(*n as i64));
+                self.option(mate, |e, n| e.signed(*n as i64));
+                self.option(lower_bound, |e, b| e.bool(*b));
+                self.option(upper_bound, |e, b| e.bool(*b));
+            }
+            UciInfoAttribute::CurrMove(m) => {
+                self.byte(7);
+                self.a_move(m);
+            }
+            UciInfoAttribute::CurrMoveNum(n) => {
+                self.byte(8);
+                self.unsigned(*n as u64);
+            }
+            UciInfoAttribute::HashFull(n) => {
+                self.byte(9);
+                self.unsigned(*n as u64);
+            }
+            UciInfoAttribute::Nps(n) => {
+                self.byte(10);
+                self.unsigned(*n);
+            }
+            UciInfoAttribute::TbHits(n) => {
+                self.byte(11);
+                self.unsigned(*n);
+            }
+            UciInfoAttribute::SbHits(n) => {
+                self.byte(12);
+                self.unsigned(*n);
+            }
+            UciInfoAttribute::CpuLoad(n) => {
+                self.byte(13);
+                self.unsigned(*n as u64);
+            }
+            UciInfoAttribute::String(s) => {
+                self.byte(14);
+                self.string(s);
+            }
+            UciInfoAttribute::Refutation(moves) => {
+                self.byte(15);
+                self.list(moves, |e, m| e.a_move(m));
+            }
+            UciInfoAttribute::CurrLine { cpu_nr, line } => {
+                self.byte(16);
+                self.option(cpu_nr, |e, n| e.unsigned(*n as u64));
+                self.list(line, |e, m| e.a_move(m));
+            }
+            UciInfoAttribute::Any(name, value) => {
+                self.byte(17);
+                self.string(name);
+                self.string(value);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.position).ok_or(DecodeError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidData("boolean")),
+        }
+    }
+
+    fn unsigned(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::InvalidData("integer"))
+    }
+
+    fn signed(&mut self) -> Result<i64, DecodeError> {
+        let value = self.unsigned()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    fn float(&mut self) -> Result<UciFloat, DecodeError> {
+        let mut bytes = [0u8; 8];
+        for b in bytes.iter_mut() {
+            *b = self.byte()?;
+        }
+        Ok(UciFloat(f64::from_le_bytes(bytes)))
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        u16::try_from(self.unsigned()?).map_err(|_| DecodeError::InvalidData("integer"))
+    }
+
+    fn duration(&mut self) -> Result<Duration, DecodeError> {
+        Ok(Duration::milliseconds(self.signed()?))
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let length = self.unsigned()? as usize;
+        let end = self.position.checked_add(length).filter(|end| *end <= self.bytes.len())
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        let s = std::str::from_utf8(&self.bytes[self.position..end]).map_err(|_| DecodeError::InvalidData("string"))?;
+        self.position = end;
+        Ok(s.to_string())
+    }
+
+    fn option<T, F: FnOnce(&mut Self) -> Result<T, DecodeError>>(&mut self, decode: F) -> Result<Option<T>, DecodeError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => decode(self).map(Some),
+            _ => Err(DecodeError::InvalidData("option")),
+        }
+    }
+
+    fn list<T, F: FnMut(&mut Self) -> Result<T, DecodeError>>(&mut self, mut decode: F) -> Result<Vec<T>, DecodeError> {
+        let length = self.unsigned()? as usize;
+        // Every element takes at least a byte, which bounds the allocation for corrupted lengths
+        let mut values = Vec::with_capacity(length.min(self.bytes.len() - self.position));
+        for _ in 0..length {
+            values.push(decode(self)?);
+        }
+
+        Ok(values)
+    }
+
+    #[cfg(not(feature = "chess"))]
+    fn square(&mut self) -> Result<UciSquare, DecodeError> {
+        match self.byte()? {
+            index @ 0..=63 => Ok(UciSquare::from((b'a' + index % 8) as char, index / 8 + 1)),
+            OFF_BOARD_SQUARE => {
+                let file = u32::try_from(self.unsigned()?).ok().and_then(char::from_u32)
+                    .ok_or(DecodeError::InvalidData("square"))?;
+                Ok(UciSquare::from(file, self.byte()?))
+            }
+            _ => Err(DecodeError::InvalidData("square")),
+        }
+    }
+
+    #[cfg(not(feature = "chess"))]
+    fn a_move(&mut self) -> Result<UciMove, DecodeError> {
+        let from = self.square()?;
+        let to = self.square()?;
+        let promotion = match self.byte()? {
+            0 => None,
+            1 => Some(UciPiece::Pawn),
+            2 => Some(UciPiece::Knight),
+            3 => Some(UciPiece::Bishop),
+            4 => Some(UciPiece::Rook),
+            5 => Some(UciPiece::Queen),
+            6 => Some(UciPiece::King),
+            _ => return Err(DecodeError::InvalidData("promotion piece")),
+        };
+
+        Ok(UciMove { from, to, promotion })
+    }
+
+    #[cfg(feature = "chess")]
+    fn a_move(&mut self) -> Result<ChessMove, DecodeError> {
+        let from = *ALL_SQUARES.get(self.byte()? as usize).ok_or(DecodeError::InvalidData("square"))?;
+        let to = *ALL_SQUARES.get(self.byte()? as usize).ok_or(DecodeError::InvalidData("square"))?;
+        let promotion: Option<Piece> = match self.byte()? {
+            0 => None,
+            p => Some(*ALL_PIECES.get(p as usize - 1).ok_or(DecodeError::InvalidData("promotion piece"))?),
+        };
+
+        Ok(ChessMove::new(from, to, promotion))
+    }
+
+    fn protection_state(&mut self) -> Result<ProtectionState, DecodeError> {
+        match self.byte()? {
+            0 => Ok(ProtectionState::Checking),
+            1 => Ok(ProtectionState::Ok),
+            2 => Ok(ProtectionState::Error),
+            _ => Err(DecodeError::InvalidData("protection state")),
+        }
+    }
+
+    fn message(&mut self) -> Result<UciMessage, DecodeError> {
+        let message = match self.byte()? {
+            0 => UciMessage::Uci,
+            1 => UciMessage::Debug(self.bool()?),
+            2 => UciMessage::IsReady,
+            3 => UciMessage::Register {
+                later: self.bool()?,
+                name: self.option(Decoder::string)?,
+                code: self.option(Decoder::string)?,
+            },
+            4 => UciMessage::Position {
+                startpos: self.bool()?,
+                fen: self.option(|d| d.string().map(UciFen))?,
+                moves: self.list(Decoder::a_move)?,
+            },
+            5 => UciMessage::SetOption {
+                name: self.string()?,
+                value: self.option(Decoder::string)?,
+            },
+            6 => UciMessage::UciNewGame,
+            7 => UciMessage::Stop,
+            8 => UciMessage::PonderHit,
+            9 => UciMessage::Quit,
+            10 => UciMessage::Go {
+                time_control: self.option(Decoder::time_control)?,
+                search_control: self.option(Decoder::search_control)?,
+            },
+            11 => UciMessage::Id {
+                name: self.option(Decoder::string)?,
+                author: self.option(Decoder::string)?,
+            },
+            12 => UciMessage::UciOk,
+            13 => UciMessage::ReadyOk,
+            14 => UciMessage::BestMove {
+                best_move: self.a_move()?,
+                ponder: self.option(Decoder::a_move)?,
+                #[cfg(feature = "extensions")]
+                offer: None,
+            },
+            15 => UciMessage::CopyProtection(self.protection_state()?),
+            16 => UciMessage::Registration(self.protection_state()?),
+            17 => UciMessage::Option(self.option_config()?),
+            18 => UciMessage::Info(self.list(Decoder::info_attribute)?),
+            19 => UciMessage::Unknown(self.string()?, None),
+            #[cfg(feature = "extensions")]
+            20 => UciMessage::BestMove {
+                best_move: self.a_move()?,
+                ponder: self.option(Decoder::a_move)?,
+                offer: Some(match self.byte()? {
+                    0 => Offer::Draw,
+                    1 => Offer::Resign,
+                    _ => return Err(DecodeError::InvalidData("offer")),
+                }),
+            },
+            _ => return Err(DecodeError::InvalidData("message kind")),
+        };
+
+        Ok(message)
+    }
+
+    fn time_control(&mut self) -> Result<UciTimeControl, DecodeError> {
+        let time_control = match self.byte()? {
+            0 => UciTimeControl::Ponder,
+            1 => UciTimeControl::Infinite,
+            2 => UciTimeControl::TimeLeft {
+                white_time: self.option(Decoder::duration)?,
+                black_time: self.option(Decoder::duration)?,
+                white_increment: self.option(Decoder::duration)?,
+                black_increment: self.option(Decoder::duration)?,
+                moves_to_go: self.option(Decoder::byte)?,
+            },
+            3 => UciTimeControl::MoveTime(self.duration()?),
+            _ => return Err(DecodeError::InvalidData("time control")),
+        };
+
+        Ok(time_control)
+    }
+
+    fn search_control(&mut self) -> Result<UciSearchControl, DecodeError> {
+        Ok(UciSearchControl {
+            search_moves: self.list(Decoder::a_move)?,
+            mate: self.option(Decoder::byte)?,
+            depth: self.option(Decoder::byte)?,
+            nodes: self.option(Decoder::unsigned)?,
+        })
+    }
+
+    fn option_config(&mut self) -> Result<UciOptionConfig, DecodeError> {
+        let config = match self.byte()? {
+            0 => UciOptionConfig::Check {
+                name: self.string()?,
+                default: self.option(Decoder::bool)?,
+            },
+            1 => UciOptionConfig::Spin {
+                name: self.string()?,
+                default: self.option(Decoder::signed)?,
+                min: self.option(Decoder::signed)?,
+                max: self.option(Decoder::signed)?,
+            },
+            2 => UciOptionConfig::Combo {
+                name: self.string()?,
+                default: self.option(Decoder::string)?,
+                var: self.list(Decoder::string)?,
+            },
+            3 => UciOptionConfig::Button {
+                name: self.string()?,
+            },
+            4 => UciOptionConfig::String {
+                name: self.string()?,
+                default: self.option(Decoder::string)?,
+            },
+            5 => UciOptionConfig::Float {
+                name: self.string()?,
+                default: self.option(Decoder::float)?,
+                min: self.option(Decoder::float)?,
+                max: self.option(Decoder::float)?,
+            },
+            _ => return Err(DecodeError::InvalidData("option type")),
+        };
+
+        Ok(config)
+    }
+
+    fn info_attribute(&mut self) -> Result<UciInfoAttribute, DecodeError> {
+        let attribute = match self.byte()? {
+            0 => UciInfoAttribute::Depth(self.byte()?),
+            1 => UciInfoAttribute::SelDepth(self.byte()?),
+            2 => UciInfoAttribute::Time(self.duration()?),
+            3 => UciInfoAttribute::Nodes(self.unsigned()?),
+            4 => UciInfoAttribute::Pv(self.list(Decoder::a_move)?),
+            5 => UciInfoAttribute::MultiPv(self.u16()?),
+            6 => UciInfoAttribute::Score {
+                cp: self.option(|d| i32::try_from(d.signed()?).map_err(|_| DecodeError::InvalidData("score")))?,
+                mate: self.option(|d| i8::try_from(d.signed()?).map_err(|_| DecodeError::InvalidData("score")))?,
+                lower_bound: self.option(Decoder::bool)?,
+                upper_bound: self.option(Decoder::bool)?,
+            },
+            7 => UciInfoAttribute::CurrMove(self.a_move()?),
+            8 => UciInfoAttribute::CurrMoveNum(self.u16()?),
+            9 => UciInfoAttribute::HashFull(self.u16()?),
+            10 => UciInfoAttribute::Nps(self.unsigned()?),
+            11 => UciInfoAttribute::TbHits(self.unsigned()?),
+            12 => UciInfoAttribute::SbHits(self.unsigned()?),
+            13 => UciInfoAttribute::CpuLoad(self.u16()?),
+            14 => UciInfoAttribute::String(self.string()?),
+            15 => UciInfoAttribute::Refutation(self.list(Decoder::a_move)?),
+            16 => UciInfoAttribute::CurrLine {
+                cpu_nr: self.option(Decoder::u16)?,
+                line: self.list(Decoder::a_move)?,
+            },
+            17 => UciInfoAttribute::Any(self.string()?, self.string()?),
+            _ => return Err(DecodeError::InvalidData("info attribute")),
+        };
+
+        Ok(attribute)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_one, parse_strict};
+
+    use super::*;
+
+    fn encode(message: &UciMessage) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        message.encode_into(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = parse_strict("uci\n\
+            debug off\n\
+            isready\n\
+            register later\n\
+            register name Matija code 42\n\
+            position startpos moves e2e4 e7e5 g1f3\n\
+            position fen 8/4P3/8/8/8/8/8/4K2k w - - 0 1 moves e7e8q h1g2\n\
+            setoption name Clear Hash\n\
+            setoption name Hash value 128\n\
+            ucinewgame\nstop\nponderhit\nquit\n\
+            go ponder\ngo infinite\ngo movetime 2500\n\
+            go wtime -500 btime 1000 winc 10 binc 10 movestogo 12 searchmoves e2e4 d2d4 depth 10 mate 3 nodes 1000000\n\
+            id name Vampirc 0.12\nid author Matija\n\
+            uciok\nreadyok\n\
+            bestmove a7a8n ponder h1g2\nbestmove e2e4\n\
+            copyprotection ok\nregistration error\n\
+            option name Nullmove type check default true\n\
+            option name Selectivity type spin default 2 min -4 max 4\n\
+            option name Style type combo default Normal var Solid var Normal var Risky\n\
+            option name Clear Hash type button\n\
+            option name NalimovPath type string default c:\\\n\
+            info depth 12 seldepth 20 time 1523 nodes 2134223 score mate -3 upperbound multipv 2 pv e2e4 e7e5 currmove g1f3 currmovenumber 5 hashfull 999 nps 1000000000 tbhits 7 sbhits 0 cpuload 500 currline 1 e2e4 refutation d1h5 g6h5\n\
+            info string hello, world\n").unwrap();
+        assert_eq!(messages.len(), 32);
+
+        for message in &messages {
+            assert_eq!(&UciMessage::decode(&encode(message)).unwrap(), message, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        let message = parse_one("bestmove e2e4 ponder e7e5");
+        assert_eq!(encode(&message), vec![ENCODING_VERSION, 14, 12, 28, 0, 1, 52, 36, 0]);
+    }
+
+    #[test]
+    fn test_unknown() {
+        let message = parse_one("foo bar");
+        assert_eq!(UciMessage::decode(&encode(&message)).unwrap(), UciMessage::Unknown("foo bar".to_string(), None));
+    }
+
+    #[test]
+    #[cfg(not(feature = "chess"))]
+    fn test_off_board_square() {
+        let message = UciMessage::best_move(UciMove::from_to(UciSquare::default(), UciSquare::from('z', 9)));
+        assert_eq!(UciMessage::decode(&encode(&message)).unwrap(), message);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytes = encode(&parse_one("setoption name Hash value 128"));
+
+        assert_eq!(UciMessage::decode(&[]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(UciMessage::decode(&[2, 0]), Err(DecodeError::UnsupportedVersion(2)));
+        assert_eq!(UciMessage::decode(&[ENCODING_VERSION, 200]), Err(DecodeError::InvalidData("message kind")));
+        assert_eq!(UciMessage::decode(&bytes[..bytes.len() - 1]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(UciMessage::decode(&[&bytes[..], &[0, 0]].concat()), Err(DecodeError::TrailingBytes(2)));
+        assert_eq!(UciMessage::decode(&[ENCODING_VERSION, 1, 2]), Err(DecodeError::InvalidData("boolean")));
+        assert_eq!(UciMessage::decode(&[ENCODING_VERSION, 18, 0xff, 0xff, 0xff, 0xff, 0x0f]), Err(DecodeError::UnexpectedEnd));
+    }
+}
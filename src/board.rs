@@ -0,0 +1,581 @@
+//! The `board` module contains helpers that tie UCI messages to the [chess crate's](https://crates.io/crates/chess)
+//! board representation. It is only available with the `chess` feature enabled.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use chess::{Board, BoardStatus, ChessMove, Game, MoveGen, Piece};
+
+use crate::uci::{UciFen, UciInfoAttribute, UciMessage};
+
+/// An error describing why a [UciMessage::Position](../uci/enum.UciMessage.html#variant.Position) message cannot be
+/// applied to a chess board.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PositionError {
+    /// The message is not a `position` message.
+    NotAPosition,
+
+    /// The message specifies neither `startpos` nor a FEN.
+    MissingPosition,
+
+    /// The FEN could not be turned into a valid chess board.
+    InvalidFen(String),
+
+    /// A move in the `moves` list is not legal in the position it is applied to.
+    IllegalMove {
+        /// The zero-based index of the move in the `moves` list.
+        index: usize,
+
+        /// The illegal move.
+        chess_move: ChessMove,
+    },
+
+    /// A move in Standard Algebraic Notation does not match any legal move in the position.
+    InvalidSan(String),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            PositionError::NotAPosition => write!(f, "not a `position` message"),
+            PositionError::MissingPosition => write!(f, "neither `startpos` nor `fen` specified"),
+            PositionError::InvalidFen(fen) => write!(f, "invalid FEN: {}", fen),
+            PositionError::IllegalMove { index, chess_move } => {
+                write!(f, "illegal move {} at index {}", chess_move, index)
+            }
+            PositionError::InvalidSan(san) => write!(f, "no legal move matches {}", san),
+        }
+    }
+}
+
+impl StdError for PositionError {}
+
+/// Creates a board from the `startpos` and `fen` parts of a `position` message. If `startpos` is `true`, the `fen` is
+/// ignored.
+pub fn start_board(startpos: bool, fen: Option<&UciFen>) -> Result<Board, PositionError> {
+    if startpos {
+        return Ok(Board::default());
+    }
+
+    match fen {
+        Some(uci_fen) => {
+            let invalid = || PositionError::InvalidFen(uci_fen.to_string());
+
+            // The chess crate panics on boards without exactly one king per side, so check this up front.
+            let placement = uci_fen.as_str().split_whitespace().next().unwrap_or("");
+            if placement.matches('K').count() != 1 || placement.matches('k').count() != 1 {
+                return Err(invalid());
+            }
+
+            // The chess crate expects all six FEN fields, separated by exactly one space.
+            let normalized = uci_fen.with_counters().as_str().split_whitespace().collect::<Vec<&str>>().join(" ");
+            Board::from_str(normalized.as_str()).map_err(|_| invalid())
+        }
+        None => Err(PositionError::MissingPosition)
+    }
+}
+
+/// Resolves the `startpos` or FEN of a `position` message and plays out its `moves` list, returning the resulting
+/// board. Fails on the first illegal move. A null move (`0000`) passes the turn, unless the side to move is in check.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse_one;
+/// use vampirc_uci::board::position_to_board;
+///
+/// let board = position_to_board(&parse_one("position startpos moves e2e4 e7e5")).unwrap();
+/// assert_eq!(format!("{}", board), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+/// ```
+pub fn position_to_board(message: &UciMessage) -> Result<Board, PositionError> {
+    match message {
+        UciMessage::Position { startpos, fen, moves } => {
+            let mut board = start_board(*startpos, fen.as_ref())?;
+
+            for (index, m) in moves.iter().enumerate() {
+                // The null move, which cannot be played while in check
+                if *m == ChessMove::default() {
+                    board = board.null_move().ok_or(PositionError::IllegalMove { index, chess_move: *m })?;
+                    continue;
+                }
+
+                if !board.legal(*m) {
+                    return Err(PositionError::IllegalMove { index, chess_move: *m });
+                }
+                board = board.make_move_new(*m);
+            }
+
+            Ok(board)
+        }
+        _ => Err(PositionError::NotAPosition)
+    }
+}
+
+/// Like `position_to_board`, but returns a `chess::Game` that keeps the history of the played moves, which is needed
+/// for detecting draws by repetition. As a `chess::Game` cannot pass the turn, a null move is reported as illegal.
+pub fn position_to_game(message: &UciMessage) -> Result<Game, PositionError> {
+    match message {
+        UciMessage::Position { startpos, fen, moves } => {
+            let mut game = Game::new_with_board(start_board(*startpos, fen.as_ref())?);
+
+            for (index, m) in moves.iter().enumerate() {
+                if !game.make_move(*m) {
+                    return Err(PositionError::IllegalMove { index, chess_move: *m });
+                }
+            }
+
+            Ok(game)
+        }
+        _ => Err(PositionError::NotAPosition)
+    }
+}
+
+/// Constructs the canonical `position` message that sets up `board` and then plays `moves` from it. The standard
+/// starting position is expressed as `startpos`, any other board as its FEN. The legality of the moves is not checked.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{ChessMove, Serializable, Square};
+/// use vampirc_uci::board::position_from_board;
+///
+/// let m = position_from_board(&Default::default(), &[ChessMove::new(Square::E2, Square::E4, None)]);
+/// assert_eq!(m.serialize(), "position startpos moves e2e4");
+/// ```
+pub fn position_from_board(board: &Board, moves: &[ChessMove]) -> UciMessage {
+    UciMessage::position_from_fen(board.to_string().as_str(), moves.to_vec())
+}
+
+fn san_piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "",
+        Piece::Knight => "N",
+        Piece::Bishop => "B",
+        Piece::Rook => "R",
+        Piece::Queen => "Q",
+        Piece::King => "K",
+    }
+}
+
+/// Renders a move in [Standard Algebraic Notation](https://en.wikipedia.org/wiki/Algebraic_notation_(chess)), such as
+/// `Nf3`, `exd5+`, `e8=Q#` or `O-O`. The move has to be legal in the given position.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{ChessMove, Square};
+/// use vampirc_uci::board::move_to_san;
+///
+/// let san = move_to_san(&Default::default(), ChessMove::new(Square::G1, Square::F3, None)).unwrap();
+/// assert_eq!(san, "Nf3");
+/// ```
+pub fn move_to_san(board: &Board, chess_move: ChessMove) -> Result<String, PositionError> {
+    if !board.legal(chess_move) {
+        return Err(PositionError::IllegalMove { index: 0, chess_move });
+    }
+
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = board.piece_on(source).unwrap();
+
+    let source_file = source.get_file().to_index();
+    let dest_file = dest.get_file().to_index();
+
+    let mut san = if piece == Piece::King && (source_file as i32 - dest_file as i32).abs() == 2 {
+        if dest_file > source_file { String::from("O-O") } else { String::from("O-O-O") }
+    } else {
+        let capture = board.piece_on(dest).is_some() || (piece == Piece::Pawn && source.get_file() != dest.get_file());
+        let mut s = String::from(san_piece_letter(piece));
+
+        if piece == Piece::Pawn {
+            if capture {
+                s += &source.to_string()[0..1];
+            }
+        } else {
+            let rivals: Vec<ChessMove> = MoveGen::new_legal(board)
+                .filter(|m| m.get_dest() == dest && m.get_source() != source && board.piece_on(m.get_source()) == Some(piece))
+                .collect();
+
+            if !rivals.is_empty() {
+                let same_file = rivals.iter().any(|m| m.get_source().get_file() == source.get_file());
+                let same_rank = rivals.iter().any(|m| m.get_source().get_rank() == source.get_rank());
+
+                if !same_file {
+                    s += &source.to_string()[0..1];
+                } else if !same_rank {
+                    s += &source.to_string()[1..2];
+                } else {
+                    s += &source.to_string();
+                }
+            }
+        }
+
+        if capture {
+            s += "x";
+        }
+
+        s += &dest.to_string();
+
+        if let Some(promotion) = chess_move.get_promotion() {
+            s += "=";
+            s += san_piece_letter(promotion);
+        }
+
+        s
+    };
+
+    let after = board.make_move_new(chess_move);
+    if after.status() == BoardStatus::Checkmate {
+        san += "#";
+    } else if after.checkers().popcnt() > 0 {
+        san += "+";
+    }
+
+    Ok(san)
+}
+
+/// Renders a sequence of moves, played one after another from the given position, in Standard Algebraic Notation.
+/// Fails on the first illegal move, reporting its index in the sequence.
+pub fn moves_to_san(board: &Board, moves: &[ChessMove]) -> Result<Vec<String>, PositionError> {
+    let mut current = *board;
+    let mut sans: Vec<String> = Vec::with_capacity(moves.len());
+
+    for (index, m) in moves.iter().enumerate() {
+        let san = move_to_san(&current, *m).map_err(|_| PositionError::IllegalMove { index, chess_move: *m })?;
+        sans.push(san);
+        current = current.make_move_new(*m);
+    }
+
+    Ok(sans)
+}
+
+/// Renders the moves of a `pv`, `refutation` or `currline` info attribute in Standard Algebraic Notation. Returns
+/// `None` for any other attribute.
+pub fn info_attribute_to_san(board: &Board, attribute: &UciInfoAttribute) -> Option<Result<Vec<String>, PositionError>> {
+    match attribute {
+        UciInfoAttribute::Pv(moves) | UciInfoAttribute::Refutation(moves) | UciInfoAttribute::CurrLine { line: moves, .. } => {
+            Some(moves_to_san(board, moves))
+        }
+        _ => None
+    }
+}
+
+/// Renders the best move and, if present, the ponder move of a `bestmove` message in Standard Algebraic Notation. The
+/// ponder move is rendered in the position after the best move. Returns `None` if the message is not a `bestmove`.
+pub fn best_move_to_san(board: &Board, message: &UciMessage) -> Option<Result<(String, Option<String>), PositionError>> {
+    match message {
+        UciMessage::BestMove { best_move, ponder, .. } => {
+            let mut moves = vec![*best_move];
+            moves.extend(ponder);
+
+            Some(moves_to_san(board, &moves).map(|mut sans| {
+                let ponder_san = if sans.len() > 1 { sans.pop() } else { None };
+                (sans.pop().unwrap(), ponder_san)
+            }))
+        }
+        _ => None
+    }
+}
+
+/// Parses a move in Standard Algebraic Notation in the context of the given position. Check and mate markers
+/// (`+`, `#`), annotations (`!`, `?`) and the `e.p.` suffix are ignored, as is the use of zeroes instead of letters in
+/// castling (`0-0`).
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{ChessMove, Square};
+/// use vampirc_uci::board::san_to_move;
+///
+/// let m = san_to_move(&Default::default(), "Nf3").unwrap();
+/// assert_eq!(m, ChessMove::new(Square::G1, Square::F3, None));
+/// ```
+pub fn san_to_move(board: &Board, san: &str) -> Result<ChessMove, PositionError> {
+    fn strip(s: &str) -> String {
+        s.trim()
+            .trim_end_matches("e.p.")
+            .trim_end()
+            .trim_end_matches(['+', '#', '!', '?'])
+            .replace('0', "O")
+    }
+
+    let wanted = strip(san);
+
+    MoveGen::new_legal(board)
+        .find(|m| move_to_san(board, *m).map(|s| strip(s.as_str()) == wanted).unwrap_or(false))
+        .ok_or_else(|| PositionError::InvalidSan(san.to_string()))
+}
+
+/// Returns the longest prefix of `moves` that forms a legal move sequence when played from `board`.
+pub fn legal_prefix<'a>(board: &Board, moves: &'a [ChessMove]) -> &'a [ChessMove] {
+    let mut current = *board;
+
+    for (index, m) in moves.iter().enumerate() {
+        if !current.legal(*m) {
+            return &moves[..index];
+        }
+        current = current.make_move_new(*m);
+    }
+
+    moves
+}
+
+/// Returns a copy of the longest prefix of the principal variation `pv` that is legal when played from `position`,
+/// dropping the first illegal move and everything after it. See `legal_prefix(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse_one, UciInfoAttribute, UciMessage};
+/// use vampirc_uci::board::sanitize_pv;
+///
+/// if let UciMessage::Info(attributes) = parse_one("info depth 3 pv e2e4 e7e5 e4e5") {
+///     if let UciInfoAttribute::Pv(pv) = &attributes[1] {
+///         assert_eq!(sanitize_pv(&Default::default(), pv), pv[..2].to_vec());
+///     }
+/// }
+/// ```
+pub fn sanitize_pv(position: &Board, pv: &[ChessMove]) -> Vec<ChessMove> {
+    legal_prefix(position, pv).to_vec()
+}
+
+/// Returns `true` if `moves` form a legal move sequence when played from `board`.
+pub fn is_legal_line(board: &Board, moves: &[ChessMove]) -> bool {
+    legal_prefix(board, moves).len() == moves.len()
+}
+
+/// For a `pv`, `refutation` or `currline` info attribute, returns the longest prefix of its moves that is legal when
+/// played from `board`. Returns `None` for any other attribute.
+///
+/// Engines occasionally report broken lines, usually due to hash collisions, and this can be used to gracefully
+/// truncate them before display.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse_one, UciMessage};
+/// use vampirc_uci::board::info_line_legal_prefix;
+///
+/// if let UciMessage::Info(attributes) = parse_one("info depth 3 pv e2e4 e7e5 e4e5") {
+///     let prefix = info_line_legal_prefix(&Default::default(), &attributes[1]).unwrap();
+///     assert_eq!(prefix.len(), 2);
+/// }
+/// ```
+pub fn info_line_legal_prefix<'a>(board: &Board, attribute: &'a UciInfoAttribute) -> Option<&'a [ChessMove]> {
+    match attribute {
+        UciInfoAttribute::Pv(moves) | UciInfoAttribute::Refutation(moves) | UciInfoAttribute::CurrLine { line: moves, .. } => {
+            Some(legal_prefix(board, moves))
+        }
+        _ => None
+    }
+}
+
+/// Checks that the `position` message describes a valid starting position and that every move in its `moves` list
+/// is legal. On failure, the first problem encountered is returned.
+pub fn validate_position(message: &UciMessage) -> Result<(), PositionError> {
+    position_to_board(message).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::Square;
+
+    use crate::parser::parse_one;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_startpos() {
+        let m = parse_one("position startpos moves e2e4 e7e5 g1f3 b8c6 f1b5");
+        assert_eq!(validate_position(&m), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_illegal_move() {
+        let m = parse_one("position startpos moves e2e4 e7e5 e4e5");
+        assert_eq!(validate_position(&m), Err(PositionError::IllegalMove {
+            index: 2,
+            chess_move: ChessMove::new(Square::E4, Square::E5, None),
+        }));
+    }
+
+    #[test]
+    fn test_validate_fen_with_extra_spaces() {
+        let m = parse_one("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w   - - 0 53 moves g7g8q c4g8");
+        assert_eq!(m.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_position_to_board_fen_without_counters() {
+        let board = position_to_board(&parse_one("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - moves g7g8q")).unwrap();
+        assert_eq!(format!("{}", board), "2k3Q1/7R/8/8/2b4P/8/6K1/8 b - - 0 1");
+    }
+
+    #[test]
+    fn test_position_to_board_null_move() {
+        let board = position_to_board(&parse_one("position startpos moves e2e4 0000 d2d4")).unwrap();
+        assert_eq!(format!("{}", board), "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR b KQkq - 0 1");
+        assert!(position_to_board(&parse_one("position fen 4k3/8/8/8/8/8/8/4K2R b - - 0 1 moves 0000 e8d8 h1h8 0000")).is_err());
+    }
+
+    #[test]
+    fn test_validate_invalid_fen() {
+        let m = parse_one("position fen 8/8/8/8/8/8/8/8 w - - 0 1");
+        assert_eq!(m.validate(), Err(PositionError::InvalidFen(String::from("8/8/8/8/8/8/8/8 w - - 0 1"))));
+    }
+
+    #[test]
+    fn test_validate_king_in_check_on_wrong_side() {
+        let m = parse_one("position fen 4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        assert_eq!(m.validate(), Ok(()));
+
+        let m = parse_one("position fen 4k2R/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(m.validate(), Err(PositionError::InvalidFen(String::from("4k2R/8/8/8/8/8/8/4K3 w - - 0 1"))));
+    }
+
+    #[test]
+    fn test_position_to_board_fen() {
+        let m = parse_one("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 53 moves g7g8q c4g8");
+        let board = position_to_board(&m).unwrap();
+        assert_eq!(board, Board::from_str("2k3b1/7R/8/8/7P/8/6K1/8 w - - 0 1").unwrap());
+    }
+
+    #[test]
+    fn test_position_to_game_history() {
+        let m = parse_one("position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+        let game = position_to_game(&m).unwrap();
+        assert_eq!(game.actions().len(), 8);
+        assert_eq!(game.current_position(), Board::default());
+        assert!(game.can_declare_draw());
+    }
+
+    #[test]
+    fn test_position_to_game_illegal_move() {
+        let m = parse_one("position startpos moves e2e5");
+        assert_eq!(position_to_game(&m).unwrap_err(), PositionError::IllegalMove {
+            index: 0,
+            chess_move: ChessMove::new(Square::E2, Square::E5, None),
+        });
+    }
+
+    #[test]
+    fn test_position_from_board_round_trip() {
+        let m = parse_one("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 1 moves g7g8q c4g8");
+        let board = Board::from_str("2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 1").unwrap();
+        let moves = vec![
+            ChessMove::new(Square::G7, Square::G8, Some(chess::Piece::Queen)),
+            ChessMove::new(Square::C4, Square::G8, None),
+        ];
+
+        assert_eq!(position_from_board(&board, &moves), m);
+    }
+
+    fn san_after(moves: &str, m: &str) -> String {
+        let board = position_to_board(&parse_one(format!("position startpos moves {}", moves).as_str())).unwrap();
+        move_to_san(&board, ChessMove::from_str(m).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        assert_eq!(san_after("e2e4 d7d5", "e4d5"), "exd5");
+        assert_eq!(san_after("e2e4 e7e5 g1f3 b8c6 f1c4 g8f6", "e1g1"), "O-O");
+        assert_eq!(san_after("f2f3 e7e5 g2g4", "d8h4"), "Qh4#");
+        assert_eq!(san_after("e2e4 d7d5 e4d5 e7e6 f1b5", "c7c6"), "c6");
+        assert_eq!(san_after("e2e4 f7f6 d1h5", "g7g6"), "g6");
+        assert_eq!(san_after("e2e4 e7e5 d1h5 b8c6 f1c4 g8f6", "h5f7"), "Qxf7#");
+    }
+
+    #[test]
+    fn test_move_to_san_disambiguation() {
+        assert_eq!(san_after("g1f3 a7a6 b1c3 a6a5 c3b5 a5a4", "f3d4"), "Nfd4");
+        assert_eq!(san_after("a2a4 h7h6 h2h4 h6h5 a1a3 g7g6 h1h3 g6g5", "a3d3"), "Rad3");
+
+        let board = Board::from_str("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&board, ChessMove::new(Square::A1, Square::A3, None)).unwrap(), "R1a3");
+
+        let board = Board::from_str("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&board, ChessMove::new(Square::A3, Square::B2, None)).unwrap(), "Qa3b2");
+    }
+
+    #[test]
+    fn test_move_to_san_promotion_and_en_passant() {
+        let board = Board::from_str("8/3P4/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&board, ChessMove::new(Square::D7, Square::D8, Some(Piece::Queen))).unwrap(), "d8=Q");
+
+        assert_eq!(san_after("e2e4 a7a6 e4e5 d7d5", "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn test_info_pv_to_san() {
+        let m = parse_one("info depth 2 pv e2e4 e7e5 g1f3");
+        if let UciMessage::Info(attributes) = m {
+            let sans = info_attribute_to_san(&Board::default(), &attributes[1]).unwrap().unwrap();
+            assert_eq!(sans, vec!["e4", "e5", "Nf3"]);
+            assert!(info_attribute_to_san(&Board::default(), &attributes[0]).is_none());
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_info_pv_to_san_illegal() {
+        let m = parse_one("info pv e2e4 e2e4");
+        if let UciMessage::Info(attributes) = m {
+            assert_eq!(info_attribute_to_san(&Board::default(), &attributes[0]).unwrap(), Err(PositionError::IllegalMove {
+                index: 1,
+                chess_move: ChessMove::new(Square::E2, Square::E4, None),
+            }));
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_best_move_to_san() {
+        let m = parse_one("bestmove g1f3 ponder d7d5");
+        let r = best_move_to_san(&Board::default(), &m).unwrap().unwrap();
+        assert_eq!(r, (String::from("Nf3"), Some(String::from("d5"))));
+    }
+
+    #[test]
+    fn test_san_to_move() {
+        let board = position_to_board(&parse_one("position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6")).unwrap();
+        assert_eq!(san_to_move(&board, "0-0").unwrap(), ChessMove::new(Square::E1, Square::G1, None));
+        assert_eq!(san_to_move(&board, "Ng5!?").unwrap(), ChessMove::new(Square::F3, Square::G5, None));
+        assert_eq!(san_to_move(&board, "Ng6"), Err(PositionError::InvalidSan(String::from("Ng6"))));
+    }
+
+    #[test]
+    fn test_legal_prefix() {
+        let moves = vec![
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::E7, Square::E5, None),
+            ChessMove::new(Square::E4, Square::E5, None),
+            ChessMove::new(Square::G8, Square::F6, None),
+        ];
+
+        assert_eq!(legal_prefix(&Board::default(), &moves), &moves[..2]);
+        assert_eq!(sanitize_pv(&Board::default(), &moves), moves[..2].to_vec());
+        assert_eq!(sanitize_pv(&Board::default(), &moves[2..]), vec![]);
+        assert!(!is_legal_line(&Board::default(), &moves));
+        assert!(is_legal_line(&Board::default(), &moves[..2]));
+        assert!(is_legal_line(&Board::default(), &[]));
+    }
+
+    #[test]
+    fn test_info_line_legal_prefix() {
+        if let UciMessage::Info(attributes) = parse_one("info currline 1 e2e4 e7e5 g1f3 score cp 20") {
+            let prefix = info_line_legal_prefix(&Board::default(), &attributes[0]).unwrap();
+            assert_eq!(prefix.len(), 3);
+            assert!(info_line_legal_prefix(&Board::default(), &attributes[1]).is_none());
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_validate_not_a_position() {
+        assert_eq!(UciMessage::Uci.validate(), Err(PositionError::NotAPosition));
+    }
+}
@@ -0,0 +1,777 @@
+//! The `builder` module contains fluent builders for the UCI messages whose struct representation is too clunky to
+//! construct by hand, such as the `go`, `info` and `position` messages.
+//!
+//! # Examples
+//!
+//! ```
+//! use vampirc_uci::{Duration, Serializable, UciMessage};
+//!
+//! let go = UciMessage::go_builder()
+//!     .wtime(Duration::milliseconds(180000))
+//!     .btime(Duration::milliseconds(175000))
+//!     .winc(Duration::milliseconds(2000))
+//!     .binc(Duration::milliseconds(2000))
+//!     .depth(20)
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(go.serialize(), "go wtime 180000 btime 175000 winc 2000 binc 2000 depth 20 ");
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+use chrono::Duration;
+
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+use crate::uci::{UciFen, UciInfoAttribute, UciMessage, UciSearchControl, UciTimeControl};
+
+/// An error returned by the builders in this module when the requested combination of message parameters cannot be
+/// represented by a valid UCI message.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum BuilderError {
+    /// More than one kind of time control was requested for a `go` message (for example, both `movetime` and
+    /// `wtime`). The names of the two conflicting `go` parameters are included.
+    ConflictingTimeControl(&'static str, &'static str),
+
+    /// A `position` message was requested with both `startpos` and `fen`.
+    ConflictingPosition,
+}
+
+impl Display for BuilderError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            BuilderError::ConflictingTimeControl(first, second) => {
+                write!(f, "the `{}` and `{}` go parameters cannot be combined", first, second)
+            }
+            BuilderError::ConflictingPosition => write!(f, "`startpos` and `fen` cannot be combined"),
+        }
+    }
+}
+
+impl StdError for BuilderError {}
+
+/// A builder for the [UciMessage::Go](../uci/enum.UciMessage.html#variant.Go) message. Obtain one through
+/// `UciMessage::go_builder()` or `GoBuilder::new()`, chain the parameters and call `build()`.
+///
+/// Only one kind of time control can be used in a single `go` message: `ponder`, `infinite`, `movetime` or the
+/// clock-based parameters (`wtime`, `btime`, `winc`, `binc` and `movestogo`). Combining them makes `build()` return a
+/// `BuilderError::ConflictingTimeControl`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GoBuilder {
+    ponder: bool,
+    infinite: bool,
+    move_time: Option<Duration>,
+    white_time: Option<Duration>,
+    black_time: Option<Duration>,
+    white_increment: Option<Duration>,
+    black_increment: Option<Duration>,
+    moves_to_go: Option<u8>,
+    search: UciSearchControl,
+}
+
+impl GoBuilder {
+    /// Creates a builder for an empty `go` message.
+    pub fn new() -> GoBuilder {
+        GoBuilder::default()
+    }
+
+    /// Sets the `ponder` parameter.
+    pub fn ponder(mut self) -> GoBuilder {
+        self.ponder = true;
+        self
+    }
+
+    /// Sets the `infinite` parameter.
+    pub fn infinite(mut self) -> GoBuilder {
+        self.infinite = true;
+        self
+    }
+
+    /// Sets the `movetime` parameter.
+    pub fn movetime(mut self, move_time: Duration) -> GoBuilder {
+        self.move_time = Some(move_time);
+        self
+    }
+
+    /// Sets the `wtime` parameter (white's time on the clock).
+    pub fn wtime(mut self, white_time: Duration) -> GoBuilder {
+        self.white_time = Some(white_time);
+        self
+    }
+
+    /// Sets the `btime` parameter (black's time on the clock).
+    pub fn btime(mut self, black_time: Duration) -> GoBuilder {
+        self.black_time = Some(black_time);
+        self
+    }
+
+    /// Sets the `winc` parameter (white's increment per move).
+    pub fn winc(mut self, white_increment: Duration) -> GoBuilder {
+        self.white_increment = Some(white_increment);
+        self
+    }
+
+    /// Sets the `binc` parameter (black's increment per move).
+    pub fn binc(mut self, black_increment: Duration) -> GoBuilder {
+        self.black_increment = Some(black_increment);
+        self
+    }
+
+    /// Sets the `movestogo` parameter.
+    pub fn movestogo(mut self, moves_to_go: u8) -> GoBuilder {
+        self.moves_to_go = Some(moves_to_go);
+        self
+    }
+
+    /// Sets the `depth` parameter.
+    pub fn depth(mut self, depth: u8) -> GoBuilder {
+        self.search.depth = Some(depth);
+        self
+    }
+
+    /// Sets the `nodes` parameter.
+    pub fn nodes(mut self, nodes: u64) -> GoBuilder {
+        self.search.nodes = Some(nodes);
+        self
+    }
+
+    /// Sets the `mate` parameter.
+    pub fn mate(mut self, mate: u8) -> GoBuilder {
+        self.search.mate = Some(mate);
+        self
+    }
+
+    /// Sets the `searchmoves` parameter, replacing any previously set moves.
+    #[cfg(not(feature = "chess"))]
+    pub fn searchmoves<I: IntoIterator<Item=UciMove>>(mut self, moves: I) -> GoBuilder {
+        self.search.search_moves = moves.into_iter().collect();
+        self
+    }
+
+    /// Sets the `searchmoves` parameter, replacing any previously set moves.
+    #[cfg(feature = "chess")]
+    pub fn searchmoves<I: IntoIterator<Item=ChessMove>>(mut self, moves: I) -> GoBuilder {
+        self.search.search_moves = moves.into_iter().collect();
+        self
+    }
+
+    /// Returns the name of the first clock-based parameter that was set, if any.
+    fn time_left_param(&self) -> Option<&'static str> {
+        if self.white_time.is_some() {
+            Some("wtime")
+        } else if self.black_time.is_some() {
+            Some("btime")
+        } else if self.white_increment.is_some() {
+            Some("winc")
+        } else if self.black_increment.is_some() {
+            Some("binc")
+        } else if self.moves_to_go.is_some() {
+            Some("movestogo")
+        } else {
+            None
+        }
+    }
+
+    /// Validates the parameters and constructs the `go` message.
+    pub fn build(self) -> Result<UciMessage, BuilderError> {
+        let mut requested: Vec<&'static str> = Vec::new();
+        if self.ponder {
+            requested.push("ponder");
+        }
+        if self.infinite {
+            requested.push("infinite");
+        }
+        if self.move_time.is_some() {
+            requested.push("movetime");
+        }
+        if let Some(param) = self.time_left_param() {
+            requested.push(param);
+        }
+
+        if requested.len() > 1 {
+            return Err(BuilderError::ConflictingTimeControl(requested[0], requested[1]));
+        }
+
+        let time_control = if self.ponder {
+            Some(UciTimeControl::Ponder)
+        } else if self.infinite {
+            Some(UciTimeControl::Infinite)
+        } else if let Some(mt) = self.move_time {
+            Some(UciTimeControl::MoveTime(mt))
+        } else if self.time_left_param().is_some() {
+            Some(UciTimeControl::TimeLeft {
+                white_time: self.white_time,
+                black_time: self.black_time,
+                white_increment: self.white_increment,
+                black_increment: self.black_increment,
+                moves_to_go: self.moves_to_go,
+            })
+        } else {
+            None
+        };
+
+        let search_control = if self.search.is_empty() { None } else { Some(self.search) };
+
+        Ok(UciMessage::Go {
+            time_control,
+            search_control,
+        })
+    }
+}
+
+/// A builder for the [UciMessage::Info](../uci/enum.UciMessage.html#variant.Info) message. Obtain one through
+/// `UciMessage::info_builder()` or `InfoBuilder::new()`, chain the attributes and call `build()`.
+///
+/// Regardless of the order in which the attributes are set, they are emitted in the order used by the common engines
+/// and expected by the common GUIs: `depth`, `seldepth`, `multipv`, `score`, `currmove`, `currmovenum`, `nodes`,
+/// `nps`, `hashfull`, `tbhits`, `sbhits`, `cpuload`, `time`, `pv`, `refutation`, `currline`, any custom attributes
+/// and finally `string`, which must come last because it consumes the rest of the line. Setting the same attribute
+/// twice replaces the previous value.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{Serializable, UciMessage};
+///
+/// let info = UciMessage::info_builder().nps(1200000).score_cp(34).depth(12).build();
+/// assert_eq!(info.serialize(), "info depth 12 score cp 34 nps 1200000");
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct InfoBuilder {
+    depth: Option<u8>,
+    sel_depth: Option<u8>,
+    multi_pv: Option<u16>,
+    score: Option<UciInfoAttribute>,
+    curr_move: Option<UciInfoAttribute>,
+    curr_move_num: Option<u16>,
+    nodes: Option<u64>,
+    nps: Option<u64>,
+    hash_full: Option<u16>,
+    tb_hits: Option<u64>,
+    sb_hits: Option<u64>,
+    cpu_load: Option<u16>,
+    time: Option<Duration>,
+    pv: Option<UciInfoAttribute>,
+    refutation: Option<UciInfoAttribute>,
+    curr_line: Option<UciInfoAttribute>,
+    any: Vec<UciInfoAttribute>,
+    string: Option<String>,
+}
+
+impl InfoBuilder {
+    /// Creates a builder for an `info` message without any attributes.
+    pub fn new() -> InfoBuilder {
+        InfoBuilder::default()
+    }
+
+    /// Sets the `depth` attribute.
+    pub fn depth(mut self, depth: u8) -> InfoBuilder {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Sets the `seldepth` attribute.
+    pub fn seldepth(mut self, sel_depth: u8) -> InfoBuilder {
+        self.sel_depth = Some(sel_depth);
+        self
+    }
+
+    /// Sets the `multipv` attribute.
+    pub fn multipv(mut self, multi_pv: u16) -> InfoBuilder {
+        self.multi_pv = Some(multi_pv);
+        self
+    }
+
+    /// Sets the `score cp` attribute, replacing any previously set score.
+    pub fn score_cp(mut self, cp: i32) -> InfoBuilder {
+        self.score = Some(UciInfoAttribute::from_centipawns(cp));
+        self
+    }
+
+    /// Sets the `score mate` attribute, replacing any previously set score. A negative value indicates it is the
+    /// engine that is getting mated.
+    pub fn score_mate(mut self, mate: i8) -> InfoBuilder {
+        self.score = Some(UciInfoAttribute::from_mate(mate));
+        self
+    }
+
+    /// Marks the previously set score as a lower bound. Has no effect if no score has been set yet.
+    pub fn lowerbound(mut self) -> InfoBuilder {
+        if let Some(UciInfoAttribute::Score { lower_bound, upper_bound, .. }) = &mut self.score {
+            *lower_bound = Some(true);
+            *upper_bound = None;
+        }
+        self
+    }
+
+    /// Marks the previously set score as an upper bound. Has no effect if no score has been set yet.
+    pub fn upperbound(mut self) -> InfoBuilder {
+        if let Some(UciInfoAttribute::Score { lower_bound, upper_bound, .. }) = &mut self.score {
+            *upper_bound = Some(true);
+            *lower_bound = None;
+        }
+        self
+    }
+
+    /// Sets the `currmove` attribute.
+    #[cfg(not(feature = "chess"))]
+    pub fn currmove(mut self, curr_move: UciMove) -> InfoBuilder {
+        self.curr_move = Some(UciInfoAttribute::CurrMove(curr_move));
+        self
+    }
+
+    /// Sets the `currmove` attribute.
+    #[cfg(feature = "chess")]
+    pub fn currmove(mut self, curr_move: ChessMove) -> InfoBuilder {
+        self.curr_move = Some(UciInfoAttribute::CurrMove(curr_move));
+        self
+    }
+
+    /// Sets the `currmovenum` attribute.
+    pub fn currmovenum(mut self, curr_move_num: u16) -> InfoBuilder {
+        self.curr_move_num = Some(curr_move_num);
+        self
+    }
+
+    /// Sets the `nodes` attribute.
+    pub fn nodes(mut self, nodes: u64) -> InfoBuilder {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Sets the `nps` attribute.
+    pub fn nps(mut self, nps: u64) -> InfoBuilder {
+        self.nps = Some(nps);
+        self
+    }
+
+    /// Sets the `hashfull` attribute (in permills).
+    pub fn hashfull(mut self, hash_full: u16) -> InfoBuilder {
+        self.hash_full = Some(hash_full);
+        self
+    }
+
+    /// Sets the `tbhits` attribute.
+    pub fn tbhits(mut self, tb_hits: u64) -> InfoBuilder {
+        self.tb_hits = Some(tb_hits);
+        self
+    }
+
+    /// Sets the `sbhits` attribute.
+    pub fn sbhits(mut self, sb_hits: u64) -> InfoBuilder {
+        self.sb_hits = Some(sb_hits);
+        self
+    }
+
+    /// Sets the `cpuload` attribute (in permills).
+    pub fn cpuload(mut self, cpu_load: u16) -> InfoBuilder {
+        self.cpu_load = Some(cpu_load);
+        self
+    }
+
+    /// Sets the `time` attribute.
+    pub fn time(mut self, time: Duration) -> InfoBuilder {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets the `pv` attribute.
+    #[cfg(not(feature = "chess"))]
+    pub fn pv<I: IntoIterator<Item=UciMove>>(mut self, moves: I) -> InfoBuilder {
+        self.pv = Some(UciInfoAttribute::Pv(moves.into_iter().collect()));
+        self
+    }
+
+    /// Sets the `pv` attribute.
+    #[cfg(feature = "chess")]
+    pub fn pv<I: IntoIterator<Item=ChessMove>>(mut self, moves: I) -> InfoBuilder {
+        self.pv = Some(UciInfoAttribute::Pv(moves.into_iter().collect()));
+        self
+    }
+
+    /// Sets the `refutation` attribute.
+    #[cfg(not(feature = "chess"))]
+    pub fn refutation<I: IntoIterator<Item=UciMove>>(mut self, moves: I) -> InfoBuilder {
+        self.refutation = Some(UciInfoAttribute::Refutation(moves.into_iter().collect()));
+        self
+    }
+
+    /// Sets the `refutation` attribute.
+    #[cfg(feature = "chess")]
+    pub fn refutation<I: IntoIterator<Item=ChessMove>>(mut self, moves: I) -> InfoBuilder {
+        self.refutation = Some(UciInfoAttribute::Refutation(moves.into_iter().collect()));
+        self
+    }
+
+    /// Sets the `currline` attribute.
+    #[cfg(not(feature = "chess"))]
+    pub fn currline<I: IntoIterator<Item=UciMove>>(mut self, cpu_nr: Option<u16>, moves: I) -> InfoBuilder {
+        self.curr_line = Some(UciInfoAttribute::CurrLine { cpu_nr, line: moves.into_iter().collect() });
+        self
+    }
+
+    /// Sets the `currline` attribute.
+    #[cfg(feature = "chess")]
+    pub fn currline<I: IntoIterator<Item=ChessMove>>(mut self, cpu_nr: Option<u16>, moves: I) -> InfoBuilder {
+        self.curr_line = Some(UciInfoAttribute::CurrLine { cpu_nr, line: moves.into_iter().collect() });
+        self
+    }
+
+    /// Adds a custom attribute, serialized as `<name> <value>`. Custom attributes are emitted in the order they were
+    /// added.
+    pub fn any(mut self, name: &str, value: &str) -> InfoBuilder {
+        self.any.push(UciInfoAttribute::Any(name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the `string` attribute.
+    pub fn string(mut self, s: &str) -> InfoBuilder {
+        self.string = Some(s.to_string());
+        self
+    }
+
+    /// Returns the attributes set so far, in the canonical order.
+    pub fn attributes(self) -> Vec<UciInfoAttribute> {
+        let mut attrs: Vec<UciInfoAttribute> = Vec::new();
+
+        if let Some(d) = self.depth {
+            attrs.push(UciInfoAttribute::Depth(d));
+        }
+        if let Some(sd) = self.sel_depth {
+            attrs.push(UciInfoAttribute::SelDepth(sd));
+        }
+        if let Some(mpv) = self.multi_pv {
+            attrs.push(UciInfoAttribute::MultiPv(mpv));
+        }
+        attrs.extend(self.score);
+        attrs.extend(self.curr_move);
+        if let Some(cmn) = self.curr_move_num {
+            attrs.push(UciInfoAttribute::CurrMoveNum(cmn));
+        }
+        if let Some(n) = self.nodes {
+            attrs.push(UciInfoAttribute::Nodes(n));
+        }
+        if let Some(n) = self.nps {
+            attrs.push(UciInfoAttribute::Nps(n));
+        }
+        if let Some(hf) = self.hash_full {
+            attrs.push(UciInfoAttribute::HashFull(hf));
+        }
+        if let Some(h) = self.tb_hits {
+            attrs.push(UciInfoAttribute::TbHits(h));
+        }
+        if let Some(h) = self.sb_hits {
+            attrs.push(UciInfoAttribute::SbHits(h));
+        }
+        if let Some(cl) = self.cpu_load {
+            attrs.push(UciInfoAttribute::CpuLoad(cl));
+        }
+        if let Some(t) = self.time {
+            attrs.push(UciInfoAttribute::Time(t));
+        }
+        attrs.extend(self.pv);
+        attrs.extend(self.refutation);
+        attrs.extend(self.curr_line);
+        attrs.extend(self.any);
+        if let Some(s) = self.string {
+            attrs.push(UciInfoAttribute::String(s));
+        }
+
+        attrs
+    }
+
+    /// Constructs the `info` message.
+    pub fn build(self) -> UciMessage {
+        UciMessage::Info(self.attributes())
+    }
+}
+
+/// A builder for the [UciMessage::Position](../uci/enum.UciMessage.html#variant.Position) message. Start with
+/// either `PositionBuilder::startpos()` or `PositionBuilder::from_fen(..)`, add the moves and call `build()`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{PositionBuilder, Serializable};
+///
+/// let m = PositionBuilder::startpos().build().unwrap();
+/// assert_eq!(m.serialize(), "position startpos");
+///
+/// let m = PositionBuilder::from_fen("2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 53").build().unwrap();
+/// assert_eq!(m.serialize(), "position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 53");
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PositionBuilder {
+    startpos: bool,
+    fen: Option<UciFen>,
+    #[cfg(not(feature = "chess"))]
+    moves: Vec<UciMove>,
+    #[cfg(feature = "chess")]
+    moves: Vec<ChessMove>,
+}
+
+impl PositionBuilder {
+    /// Creates a builder for a `position startpos` message.
+    pub fn startpos() -> PositionBuilder {
+        PositionBuilder {
+            startpos: true,
+            fen: None,
+            moves: vec![],
+        }
+    }
+
+    /// Creates a builder for a `position fen <fen>` message. The FEN is not validated.
+    pub fn from_fen(fen: &str) -> PositionBuilder {
+        PositionBuilder {
+            startpos: false,
+            fen: Some(UciFen::from(fen)),
+            moves: vec![],
+        }
+    }
+
+    /// Sets the FEN of the starting position. Combined with `PositionBuilder::startpos()`, this makes `build()` fail
+    /// with a `BuilderError::ConflictingPosition`.
+    pub fn fen(mut self, fen: &str) -> PositionBuilder {
+        self.fen = Some(UciFen::from(fen));
+        self
+    }
+
+    /// Sets the moves to play from the starting position, replacing any previously set moves.
+    #[cfg(not(feature = "chess"))]
+    pub fn moves<I: IntoIterator<Item=UciMove>>(mut self, moves: I) -> PositionBuilder {
+        self.moves = moves.into_iter().collect();
+        self
+    }
+
+    /// Sets the moves to play from the starting position, replacing any previously set moves.
+    #[cfg(feature = "chess")]
+    pub fn moves<I: IntoIterator<Item=ChessMove>>(mut self, moves: I) -> PositionBuilder {
+        self.moves = moves.into_iter().collect();
+        self
+    }
+
+    /// Appends a single move to the move list.
+    #[cfg(not(feature = "chess"))]
+    pub fn push_move(mut self, a_move: UciMove) -> PositionBuilder {
+        self.moves.push(a_move);
+        self
+    }
+
+    /// Appends a single move to the move list.
+    #[cfg(feature = "chess")]
+    pub fn push_move(mut self, a_move: ChessMove) -> PositionBuilder {
+        self.moves.push(a_move);
+        self
+    }
+
+    /// Validates the parameters and constructs the `position` message.
+    pub fn build(self) -> Result<UciMessage, BuilderError> {
+        if self.startpos && self.fen.is_some() {
+            return Err(BuilderError::ConflictingPosition);
+        }
+
+        Ok(UciMessage::Position {
+            startpos: self.startpos,
+            fen: self.fen,
+            moves: self.moves,
+        })
+    }
+}
+
+/// The `PositionBuilder` under the name of the message it builds, so that a `position` reads as
+/// `UciPosition::startpos().moves(..)` or `UciPosition::from_fen(..).moves(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{Serializable, UciPosition};
+///
+/// let m = UciPosition::startpos().moves(vec!["e2e4".parse().unwrap()]).build().unwrap();
+/// assert_eq!(m.serialize(), "position startpos moves e2e4");
+/// ```
+pub type UciPosition = PositionBuilder;
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "chess")]
+    use chess::Square;
+
+    #[cfg(not(feature = "chess"))]
+    use crate::uci::UciSquare;
+    use crate::uci::Serializable;
+
+    use super::*;
+
+    #[test]
+    fn test_go_builder_empty() {
+        assert_eq!(GoBuilder::new().build().unwrap(), UciMessage::go());
+    }
+
+    #[test]
+    fn test_go_builder_time_left() {
+        let m = UciMessage::go_builder()
+            .wtime(Duration::milliseconds(903000))
+            .btime(Duration::milliseconds(770908))
+            .winc(Duration::milliseconds(15000))
+            .movestogo(17)
+            .build()
+            .unwrap();
+
+        let tl = UciTimeControl::TimeLeft {
+            white_time: Some(Duration::milliseconds(903000)),
+            black_time: Some(Duration::milliseconds(770908)),
+            white_increment: Some(Duration::milliseconds(15000)),
+            black_increment: None,
+            moves_to_go: Some(17),
+        };
+
+        assert_eq!(m, UciMessage::Go { time_control: Some(tl), search_control: None });
+    }
+
+    #[test]
+    fn test_go_builder_ponder_depth() {
+        let m = UciMessage::go_builder().ponder().depth(6).build().unwrap();
+
+        assert_eq!(m, UciMessage::Go {
+            time_control: Some(UciTimeControl::Ponder),
+            search_control: Some(UciSearchControl::depth(6)),
+        });
+    }
+
+    #[test]
+    fn test_go_builder_searchmoves() {
+        #[cfg(not(feature = "chess"))]
+        let moves = vec![
+            UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4)),
+            UciMove::from_to(UciSquare::from('d', 2), UciSquare::from('d', 4)),
+        ];
+
+        #[cfg(feature = "chess")]
+        let moves = vec![
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::D2, Square::D4, None),
+        ];
+
+        let m = UciMessage::go_builder().movetime(Duration::milliseconds(10000)).nodes(55000000).searchmoves(moves)
+            .build()
+            .unwrap();
+
+        assert_eq!(m.serialize(), "go movetime 10000 nodes 55000000  searchmoves e2e4 d2d4 ");
+    }
+
+    #[test]
+    fn test_go_builder_conflicting_time_control() {
+        let r = UciMessage::go_builder().infinite().wtime(Duration::milliseconds(1000)).build();
+        assert_eq!(r, Err(BuilderError::ConflictingTimeControl("infinite", "wtime")));
+    }
+
+    #[test]
+    fn test_go_builder_conflicting_movetime_ponder() {
+        let r = UciMessage::go_builder().movetime(Duration::milliseconds(1000)).ponder().build();
+        assert_eq!(r, Err(BuilderError::ConflictingTimeControl("ponder", "movetime")));
+    }
+
+    #[test]
+    fn test_info_builder_ordering() {
+        #[cfg(not(feature = "chess"))]
+        let pv = vec![
+            UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4)),
+            UciMove::from_to(UciSquare::from('e', 7), UciSquare::from('e', 5)),
+            UciMove::from_to(UciSquare::from('g', 1), UciSquare::from('f', 3)),
+        ];
+
+        #[cfg(feature = "chess")]
+        let pv = vec![
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::E7, Square::E5, None),
+            ChessMove::new(Square::G1, Square::F3, None),
+        ];
+
+        let m = UciMessage::info_builder()
+            .pv(pv)
+            .nps(34928)
+            .time(Duration::milliseconds(1242))
+            .score_cp(214)
+            .nodes(2124)
+            .depth(2)
+            .build();
+
+        assert_eq!(m.serialize(), "info depth 2 score cp 214 nodes 2124 nps 34928 time 1242 pv e2e4 e7e5 g1f3");
+    }
+
+    #[test]
+    fn test_info_builder_string_last() {
+        let m = InfoBuilder::new().string("Hello world").any("ebf", "1.85").hashfull(455).build();
+        assert_eq!(m.serialize(), "info hashfull 455 ebf 1.85 string Hello world");
+    }
+
+    #[test]
+    fn test_info_builder_score_bound() {
+        let m = InfoBuilder::new().score_mate(-3).upperbound().build();
+        assert_eq!(m, UciMessage::Info(vec![UciInfoAttribute::Score {
+            cp: None,
+            mate: Some(-3),
+            lower_bound: None,
+            upper_bound: Some(true),
+        }]));
+    }
+
+    #[test]
+    fn test_info_builder_empty() {
+        assert_eq!(InfoBuilder::new().build(), UciMessage::Info(vec![]));
+    }
+
+    #[test]
+    fn test_position_builder_startpos_moves() {
+        #[cfg(not(feature = "chess"))]
+        let moves = vec![
+            UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4)),
+            UciMove::from_to(UciSquare::from('e', 7), UciSquare::from('e', 5)),
+        ];
+
+        #[cfg(feature = "chess")]
+        let moves = vec![
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::E7, Square::E5, None),
+        ];
+
+        let m = PositionBuilder::startpos().moves(moves.clone()).build().unwrap();
+        assert_eq!(m, UciMessage::Position { startpos: true, fen: None, moves });
+    }
+
+    #[test]
+    fn test_position_builder_fen_push_move() {
+        #[cfg(not(feature = "chess"))]
+        let a_move = UciMove::from_to(UciSquare::from('d', 2), UciSquare::from('d', 4));
+
+        #[cfg(feature = "chess")]
+        let a_move = ChessMove::new(Square::D2, Square::D4, None);
+
+        let m = PositionBuilder::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .push_move(a_move)
+            .build()
+            .unwrap();
+
+        assert_eq!(m.serialize(), "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves d2d4");
+    }
+
+    #[test]
+    fn test_position_builder_conflict() {
+        let r = PositionBuilder::startpos().fen("8/8/8/8/8/8/8/K6k w - - 0 1").build();
+        assert_eq!(r, Err(BuilderError::ConflictingPosition));
+
+        let r = UciPosition::startpos().fen("8/8/8/8/8/8/8/K6k w - - 0 1").build();
+        assert_eq!(r, Err(BuilderError::ConflictingPosition));
+    }
+
+    #[test]
+    fn test_uci_position() {
+        let fen = "8/8/8/8/8/8/8/K6k w - - 0 1";
+        assert_eq!(UciPosition::startpos().build(), PositionBuilder::startpos().build());
+        assert_eq!(UciPosition::from_fen(fen).build().unwrap().serialize(), format!("position fen {}", fen));
+    }
+}
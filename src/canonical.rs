@@ -0,0 +1,554 @@
+//! The `canonical` module contains the canonical form of the messages, for code that re-emits the messages it has
+//! parsed, such as proxies and recorders.
+//!
+//! `UciMessage::normalize()` brings a message to its canonical form and `UciMessage::serialize_canonical()` serializes
+//! it without the quirks of `serialize()` (the trailing spaces of `go`, the `cpunr` keyword of `info currline` ...).
+//! Together they guarantee that, for every message except `UciMessage::Unknown`,
+//!
+//! ```text
+//! parse_one(&m.serialize_canonical()) == m.normalize()
+//! ```
+//!
+//! as long as the message has a textual form at all: the free text (names, values, `info string` ...) must not
+//! contain line breaks or the protocol's keywords, an `id` message needs a name or an author and a `register`
+//! message either `later` or a name or a code.
+//!
+//! GUIs and engines that are picky about the details of the serialization can instead use
+//! `UciMessage::serialize_with(..)` and set the policy through the `SerializeOptions`.
+
+use chrono::Duration;
+
+use crate::uci::{Serializable, UciFen, UciInfoAttribute, UciMessage, UciOptionConfig, UciTimeControl};
+
+/// The line terminator written by `UciMessage::serialize_with(..)`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum LineEnding {
+    /// `\n`, the default.
+    Lf,
+
+    /// `\r\n`, as used on Windows.
+    CrLf,
+
+    /// No terminator at all.
+    None,
+}
+
+impl LineEnding {
+    /// Returns the terminator as a string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::None => "",
+        }
+    }
+}
+
+/// How `UciMessage::serialize_with(..)` writes a `setoption` message without a value (or with an empty one).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum EmptyValue {
+    /// `setoption name <name> value <empty>`, as written by `serialize()`. The default.
+    Placeholder,
+
+    /// `setoption name <name>`, as expected for the `button` options.
+    Omit,
+}
+
+/// The order in which `UciMessage::serialize_with(..)` writes the attributes of an `info` message.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum InfoOrder {
+    /// The attributes are written in the order they are in. The default.
+    AsGiven,
+
+    /// The attributes are written in the canonical order of `normalize()`, which is also the order of the
+    /// `InfoBuilder`.
+    Canonical,
+}
+
+/// The serialization policy of `UciMessage::serialize_with(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::canonical::{EmptyValue, LineEnding, SerializeOptions};
+///
+/// let options = SerializeOptions::new().line_ending(LineEnding::CrLf).empty_value(EmptyValue::Omit);
+/// assert_eq!(UciMessage::set_option("Clear Hash", None).serialize_with(&options), "setoption name Clear Hash\r\n");
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SerializeOptions {
+    line_ending: LineEnding,
+    empty_value: EmptyValue,
+    info_order: InfoOrder,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            line_ending: LineEnding::Lf,
+            empty_value: EmptyValue::Placeholder,
+            info_order: InfoOrder::AsGiven,
+        }
+    }
+}
+
+impl SerializeOptions {
+    /// Creates the default options.
+    pub fn new() -> SerializeOptions {
+        SerializeOptions::default()
+    }
+
+    /// Terminates the line with the `line_ending`.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> SerializeOptions {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Writes a `setoption` message without a value as specified by `empty_value`.
+    pub fn empty_value(mut self, empty_value: EmptyValue) -> SerializeOptions {
+        self.empty_value = empty_value;
+        self
+    }
+
+    /// Writes the attributes of an `info` message in the `info_order`.
+    pub fn info_order(mut self, info_order: InfoOrder) -> SerializeOptions {
+        self.info_order = info_order;
+        self
+    }
+
+    /// Returns the line terminator.
+    pub fn get_line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Returns how a `setoption` message without a value is written.
+    pub fn get_empty_value(&self) -> EmptyValue {
+        self.empty_value
+    }
+
+    /// Returns the order of the attributes of an `info` message.
+    pub fn get_info_order(&self) -> InfoOrder {
+        self.info_order
+    }
+}
+
+impl UciMessage {
+    /// Returns the canonical form of this message, which survives the round trip through `serialize_canonical()` and
+    /// `parse_one(..)` unchanged:
+    ///
+    /// * free text is trimmed, and an empty `setoption` value or `combo` default is `None`;
+    /// * durations are whole milliseconds;
+    /// * `register later` has neither the name nor the code, and `position startpos` has no FEN;
+    /// * an empty search control or time control of `go` is `None`;
+    /// * an `id` with both the name and the author keeps only the name;
+    /// * `info` attributes are in the order of the `InfoBuilder`, with `info string` and the unknown attributes last.
+    ///   As they take the rest of the line, the first one of those absorbs the ones after it. A `score` keeps the mate
+    ///   over the centipawns and at most one bound, and empty attributes (such as a `pv` without moves) are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{parse_one, Serializable, UciInfoAttribute, UciMessage};
+    ///
+    /// let m = UciMessage::Info(vec![UciInfoAttribute::String(String::from("hello")), UciInfoAttribute::Depth(3)]);
+    /// assert_eq!(m.serialize_canonical(), "info depth 3 string hello");
+    /// assert_eq!(parse_one(&m.serialize_canonical()), m.normalize());
+    /// ```
+    pub fn normalize(&self) -> UciMessage {
+        match self {
+            UciMessage::Register { later: true, .. } => UciMessage::register_later(),
+            UciMessage::Register { later: false, name, code } => UciMessage::Register {
+                later: false,
+                name: name.as_deref().map(trim),
+                code: code.as_deref().map(trim),
+            },
+            UciMessage::Position { startpos, fen, moves } => {
+                let fen = fen.as_ref().filter(|_| !*startpos)
+                    .map(|f| f.as_str().split_whitespace().collect::<Vec<&str>>().join(" ")).map(UciFen);
+                UciMessage::Position {
+                    startpos: fen.is_none(),
+                    fen,
+                    moves: moves.clone(),
+                }
+            }
+            UciMessage::SetOption { name, value } => UciMessage::SetOption {
+                name: trim(name),
+                value: non_empty(value),
+            },
+            UciMessage::Go { time_control, search_control } => UciMessage::Go {
+                time_control: time_control.as_ref().and_then(normalize_time_control),
+                search_control: search_control.clone().filter(|sc| !sc.is_empty()),
+            },
+            UciMessage::Id { name: Some(name), .. } => UciMessage::Id { name: Some(trim(name)), author: None },
+            UciMessage::Id { name: None, author } => UciMessage::Id { name: None, author: author.as_deref().map(trim) },
+            UciMessage::Option(config) => UciMessage::Option(normalize_option(config)),
+            UciMessage::Info(attributes) => UciMessage::Info(normalize_info(attributes)),
+            _ => self.clone(),
+        }
+    }
+
+    /// Serializes the canonical form of this message (see `normalize()`): tokens are separated by a single space,
+    /// there are no trailing spaces and every message is written in the form the parser reads back. An unknown message
+    /// is written verbatim.
+    pub fn serialize_canonical(&self) -> String {
+        let m = self.normalize();
+
+        match &m {
+            UciMessage::Go { .. } => m.serialize().split_whitespace().collect::<Vec<&str>>().join(" "),
+            UciMessage::SetOption { name, value: None } => format!("setoption name {}", name),
+            UciMessage::Option(UciOptionConfig::String { name, default: Some(default) }) if default.is_empty() => {
+                format!("option name {} type string default <empty>", name)
+            }
+            UciMessage::Info(attributes) => {
+                let mut s = String::from("info");
+                for a in attributes {
+                    s += " ";
+                    s += &serialize_info_attribute(a);
+                }
+                s
+            }
+            UciMessage::Unknown(text, _) => text.clone(),
+            _ => m.serialize(),
+        }
+    }
+
+    /// Returns a hash of the semantic content of the message, for deduplication in queues and caches: messages with
+    /// the same canonical form (see `normalize()`) have the same fingerprint, regardless of how they were formatted.
+    /// The fingerprint is the 64-bit FNV-1a hash of `serialize_canonical()`, so it is stable across runs, platforms
+    /// and versions of this crate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse_one;
+    ///
+    /// assert_eq!(parse_one("info  nodes 120 depth 3").fingerprint(), parse_one("info depth 3 nodes 120").fingerprint());
+    /// assert_ne!(parse_one("info depth 3").fingerprint(), parse_one("info depth 4").fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        self.serialize_canonical().bytes().fold(OFFSET_BASIS, |hash, b| (hash ^ b as u64).wrapping_mul(PRIME))
+    }
+
+    /// Serializes the message into a line, terminated as specified by the `options`. Apart from the policy set in the
+    /// `options`, the line is the same as the one returned by `serialize()`, except that it has no redundant spaces
+    /// and that `info currline` is written in the form the parser reads back.
+    pub fn serialize_with(&self, options: &SerializeOptions) -> String {
+        let mut s = match self {
+            UciMessage::SetOption { name, value } if value.as_deref().unwrap_or("").is_empty() => {
+                match options.empty_value {
+                    EmptyValue::Placeholder => format!("setoption name {} value <empty>", name),
+                    EmptyValue::Omit => format!("setoption name {}", name),
+                }
+            }
+            UciMessage::Go { .. } => self.serialize().split_whitespace().collect::<Vec<&str>>().join(" "),
+            UciMessage::Info(attributes) => {
+                let attributes = match options.info_order {
+                    InfoOrder::AsGiven => attributes.clone(),
+                    InfoOrder::Canonical => normalize_info(attributes),
+                };
+
+                let mut s = String::from("info");
+                for a in &attributes {
+                    s += " ";
+                    s += &serialize_info_attribute(a);
+                }
+                s
+            }
+            _ => self.serialize(),
+        };
+
+        s += options.line_ending.as_str();
+        s
+    }
+}
+
+fn trim(s: &str) -> String {
+    s.trim().to_string()
+}
+
+fn non_empty(s: &Option<String>) -> Option<String> {
+    s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+fn whole_millis(d: &Duration) -> Duration {
+    Duration::milliseconds(d.num_milliseconds())
+}
+
+fn normalize_time_control(tc: &UciTimeControl) -> Option<UciTimeControl> {
+    match tc {
+        UciTimeControl::MoveTime(t) => Some(UciTimeControl::MoveTime(whole_millis(t))),
+        UciTimeControl::TimeLeft { white_time, black_time, white_increment, black_increment, moves_to_go } => {
+            let tc = UciTimeControl::TimeLeft {
+                white_time: white_time.as_ref().map(whole_millis),
+                black_time: black_time.as_ref().map(whole_millis),
+                white_increment: white_increment.as_ref().map(whole_millis),
+                black_increment: black_increment.as_ref().map(whole_millis),
+                moves_to_go: *moves_to_go,
+            };
+
+            if tc == UciTimeControl::time_left() { None } else { Some(tc) }
+        }
+        _ => Some(tc.clone()),
+    }
+}
+
+fn normalize_option(config: &UciOptionConfig) -> UciOptionConfig {
+    match config {
+        UciOptionConfig::Check { name, default } => UciOptionConfig::Check { name: trim(name), default: *default },
+        UciOptionConfig::Spin { name, default, min, max } => UciOptionConfig::Spin {
+            name: trim(name),
+            default: *default,
+            min: *min,
+            max: *max,
+        },
+        UciOptionConfig::Float { name, default, min, max } => UciOptionConfig::Float {
+            name: trim(name),
+            default: *default,
+            min: *min,
+            max: *max,
+        },
+        UciOptionConfig::Combo { name, default, var } => UciOptionConfig::Combo {
+            name: trim(name),
+            default: non_empty(default),
+            var: var.iter().map(|v| trim(v)).filter(|v| !v.is_empty()).collect(),
+        },
+        UciOptionConfig::Button { name } => UciOptionConfig::Button { name: trim(name) },
+        UciOptionConfig::String { name, default } => UciOptionConfig::String {
+            name: trim(name),
+            default: default.as_deref().map(trim),
+        },
+    }
+}
+
+/// The position of the attribute in the order of the `InfoBuilder`.
+fn info_rank(attribute: &UciInfoAttribute) -> u8 {
+    match attribute {
+        UciInfoAttribute::Depth(..) => 0,
+        UciInfoAttribute::SelDepth(..) => 1,
+        UciInfoAttribute::MultiPv(..) => 2,
+        UciInfoAttribute::Score { .. } => 3,
+        UciInfoAttribute::CurrMove(..) => 4,
+        UciInfoAttribute::CurrMoveNum(..) => 5,
+        UciInfoAttribute::Nodes(..) => 6,
+        UciInfoAttribute::Nps(..) => 7,
+        UciInfoAttribute::HashFull(..) => 8,
+        UciInfoAttribute::TbHits(..) => 9,
+        UciInfoAttribute::SbHits(..) => 10,
+        UciInfoAttribute::CpuLoad(..) => 11,
+        UciInfoAttribute::Time(..) => 12,
+        UciInfoAttribute::Pv(..) => 13,
+        UciInfoAttribute::Refutation(..) => 14,
+        UciInfoAttribute::CurrLine { .. } => 15,
+        UciInfoAttribute::Any(..) | UciInfoAttribute::String(..) => 16,
+    }
+}
+
+fn normalize_info(attributes: &[UciInfoAttribute]) -> Vec<UciInfoAttribute> {
+    let mut normalized: Vec<UciInfoAttribute> = Vec::with_capacity(attributes.len());
+    // The name (for an unknown attribute) and the text of the first attribute that takes the rest of the line
+    let mut text: Option<(Option<String>, String)> = None;
+
+    for a in attributes {
+        let a = match a {
+            UciInfoAttribute::Time(t) => UciInfoAttribute::Time(whole_millis(t)),
+            UciInfoAttribute::Pv(moves) | UciInfoAttribute::Refutation(moves) if moves.is_empty() => continue,
+            UciInfoAttribute::CurrLine { line, .. } if line.is_empty() => continue,
+            UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound } => {
+                if cp.is_none() && mate.is_none() {
+                    continue;
+                }
+
+                let lower_bound = if *lower_bound == Some(true) { Some(true) } else { None };
+                UciInfoAttribute::Score {
+                    cp: if mate.is_some() { None } else { *cp },
+                    mate: *mate,
+                    lower_bound,
+                    upper_bound: if lower_bound.is_none() && *upper_bound == Some(true) { Some(true) } else { None },
+                }
+            }
+            UciInfoAttribute::String(..) | UciInfoAttribute::Any(..) => {
+                text = Some(match (text.take(), a) {
+                    (Some((name, value)), _) => (name, append(&value, a)),
+                    (None, UciInfoAttribute::String(s)) => (None, trim(s)),
+                    (None, UciInfoAttribute::Any(name, value)) => (Some(trim(name)), trim(value)),
+                    (None, _) => unreachable!(),
+                });
+                continue;
+            }
+            _ => a.clone(),
+        };
+
+        normalized.push(a);
+    }
+
+    normalized.sort_by_key(info_rank);
+
+    if let Some((name, value)) = text.filter(|(_, value)| !value.is_empty()) {
+        normalized.push(match name {
+            Some(name) => UciInfoAttribute::Any(name, value),
+            None => UciInfoAttribute::String(value),
+        });
+    }
+
+    normalized
+}
+
+/// Appends the serialization of a text attribute to the text of the one before it, as the parser would read them.
+fn append(text: &str, next: &UciInfoAttribute) -> String {
+    let next = trim(&next.serialize());
+    if text.is_empty() { next } else { format!("{} {}", text, next) }
+}
+
+fn serialize_info_attribute(attribute: &UciInfoAttribute) -> String {
+    match attribute {
+        UciInfoAttribute::CurrLine { cpu_nr: Some(cpu_nr), line } => {
+            let moves: Vec<String> = line.iter().map(|m| m.to_string()).collect();
+            format!("currline {} {}", cpu_nr, moves.join(" "))
+        }
+        _ => attribute.serialize(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+    use crate::uci::{ProtectionState, UciSearchControl};
+
+    use super::*;
+
+    fn assert_round_trip(m: &UciMessage) {
+        let normalized = m.normalize();
+        assert_eq!(parse_one(&m.serialize_canonical()), normalized, "{}", m.serialize_canonical());
+        assert_eq!(normalized.normalize(), normalized);
+        assert_eq!(normalized.serialize_canonical(), m.serialize_canonical());
+    }
+
+    #[test]
+    fn test_go_canonical() {
+        let m = UciMessage::Go {
+            time_control: Some(UciTimeControl::TimeLeft {
+                white_time: Some(Duration::microseconds(60_000_500)),
+                black_time: Some(Duration::milliseconds(-50)),
+                white_increment: None,
+                black_increment: Some(Duration::seconds(1)),
+                moves_to_go: None,
+            }),
+            search_control: Some(UciSearchControl::depth(7)),
+        };
+
+        assert_eq!(m.serialize_canonical(), "go wtime 60000 btime -50 binc 1000 depth 7");
+        assert_round_trip(&m);
+
+        let empty = UciMessage::Go { time_control: Some(UciTimeControl::time_left()), search_control: Some(UciSearchControl::default()) };
+        assert_eq!(empty.normalize(), UciMessage::go());
+        assert_eq!(empty.serialize_canonical(), "go");
+        assert_round_trip(&empty);
+    }
+
+    #[test]
+    fn test_engine_bound_canonical() {
+        let messages = vec![
+            UciMessage::Register { later: true, name: Some(String::from("Matija")), code: None },
+            UciMessage::register_code(" Matija Kejžar ", "4359874324"),
+            UciMessage::Position { startpos: true, fen: Some(UciFen::startpos()), moves: vec![] },
+            UciMessage::Position { startpos: false, fen: Some(UciFen::from("8/8/8/8/8/8/8/K6k  w - -  0 1")), moves: vec![] },
+            UciMessage::set_option("Clear Hash", None),
+            UciMessage::set_option("Empty", String::from("  ")),
+            UciMessage::set_option(" Style ", String::from("Risky  ")),
+        ];
+
+        for m in &messages {
+            assert_round_trip(m);
+        }
+
+        assert_eq!(messages[0].serialize_canonical(), "register later");
+        assert_eq!(messages[4].serialize_canonical(), "setoption name Clear Hash");
+        assert_eq!(messages[5].normalize(), UciMessage::set_option("Empty", None));
+        assert_eq!(messages[6].serialize_canonical(), "setoption name Style value Risky");
+    }
+
+    #[test]
+    fn test_gui_bound_canonical() {
+        let messages = vec![
+            UciMessage::Id { name: Some(String::from("Vampirc")), author: Some(String::from("Matija Kejžar")) },
+            UciMessage::CopyProtection(ProtectionState::Checking),
+            UciMessage::Option(UciOptionConfig::String { name: String::from("Path"), default: Some(String::new()) }),
+            UciMessage::Option(UciOptionConfig::Combo {
+                name: String::from("Style"),
+                default: Some(String::new()),
+                var: vec![String::from("Solid"), String::new(), String::from(" Risky")],
+            }),
+        ];
+
+        for m in &messages {
+            assert_round_trip(m);
+        }
+
+        assert_eq!(messages[0].serialize_canonical(), "id name Vampirc");
+        assert_eq!(messages[2].serialize_canonical(), "option name Path type string default <empty>");
+        assert_eq!(messages[3].serialize_canonical(), "option name Style type combo var Solid var Risky");
+    }
+
+    #[test]
+    fn test_info_canonical() {
+        let m = UciMessage::Info(vec![
+            UciInfoAttribute::String(String::from("first")),
+            UciInfoAttribute::Pv(vec![]),
+            UciInfoAttribute::Time(Duration::microseconds(1500)),
+            UciInfoAttribute::Score { cp: Some(20), mate: Some(-3), lower_bound: Some(false), upper_bound: Some(true) },
+            UciInfoAttribute::Any(String::from("hint"), String::from("e2e4")),
+            UciInfoAttribute::Depth(5),
+            UciInfoAttribute::String(String::from("second")),
+        ]);
+
+        assert_eq!(m.serialize_canonical(), "info depth 5 score mate -3 upperbound time 1 string first hint e2e4 string second");
+        assert_round_trip(&m);
+
+        let m = parse_one("info currline 1 d1h5 g6h5");
+        assert_eq!(m.serialize(), "info currline cpunr 1 d1h5 g6h5");
+        assert_eq!(m.serialize_canonical(), "info currline 1 d1h5 g6h5");
+        assert_round_trip(&m);
+    }
+
+    #[test]
+    fn test_serialize_with_defaults() {
+        let options = SerializeOptions::default();
+
+        assert_eq!(UciMessage::go_movetime(Duration::seconds(2)).serialize_with(&options), "go movetime 2000\n");
+        assert_eq!(UciMessage::set_option("Clear Hash", None).serialize_with(&options), "setoption name Clear Hash value <empty>\n");
+        assert_eq!(parse_one("info nodes 10 depth 2").serialize_with(&options), "info nodes 10 depth 2\n");
+    }
+
+    #[test]
+    fn test_serialize_with_options() {
+        let options = SerializeOptions::new()
+            .line_ending(LineEnding::CrLf)
+            .empty_value(EmptyValue::Omit)
+            .info_order(InfoOrder::Canonical);
+
+        assert_eq!(UciMessage::set_option("Clear Hash", String::new()).serialize_with(&options), "setoption name Clear Hash\r\n");
+        assert_eq!(UciMessage::set_option_i64("Hash", 64).serialize_with(&options), "setoption name Hash value 64\r\n");
+        assert_eq!(parse_one("info string hi nodes 10").serialize_with(&options), "info string hi nodes 10\r\n");
+        assert_eq!(parse_one("info nodes 10 depth 2").serialize_with(&options.line_ending(LineEnding::None)), "info depth 2 nodes 10");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(UciMessage::Uci.fingerprint(), 0x4c88_8419_3dfa_2ae0);
+        assert_eq!(parse_one("go  depth 5 wtime 1000").fingerprint(), parse_one("go wtime 1000 depth 5").fingerprint());
+        assert_eq!(UciMessage::set_option(" Hash ", String::from("8")).fingerprint(), parse_one("setoption name Hash value 8").fingerprint());
+        assert_ne!(parse_one("bestmove e2e4").fingerprint(), parse_one("bestmove e2e4 ponder e7e5").fingerprint());
+        assert_ne!(parse_one("foo").fingerprint(), parse_one("bar").fingerprint());
+    }
+
+    #[test]
+    fn test_unknown_canonical() {
+        let m = parse_one("foo bar");
+        assert_eq!(m.serialize_canonical(), "foo bar");
+        assert_eq!(m.normalize(), m);
+    }
+}
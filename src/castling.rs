@@ -0,0 +1,306 @@
+//! The `castling` module contains helpers for converting castling moves between the two encodings in use by UCI
+//! engines and GUIs.
+//!
+//! In standard chess, castling is encoded as a two-square king move (`e1g1`). In Chess960 (when `UCI_Chess960` is
+//! enabled), castling is encoded as the king capturing its own rook (`e1h1`). Engines and GUIs that disagree on the
+//! encoding can be bridged by converting the moves with `CastlingSquares`.
+
+#[cfg(feature = "chess")]
+use chess::{Board, CastleRights, ChessMove, Color, File, Rank, Square};
+
+#[cfg(not(feature = "chess"))]
+use crate::uci::{UciMove, UciSquare};
+
+/// The squares involved in castling for one side: the king's starting square and the starting squares of the rooks
+/// it still has the right to castle with.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(not(feature = "chess"))]
+/// # {
+/// use vampirc_uci::{UciMove, UciSquare};
+/// use vampirc_uci::castling::CastlingSquares;
+///
+/// let white = CastlingSquares::standard_white();
+/// let two_squares = UciMove::from_to(UciSquare::from('e', 1), UciSquare::from('g', 1));
+/// let king_takes_rook = UciMove::from_to(UciSquare::from('e', 1), UciSquare::from('h', 1));
+///
+/// assert_eq!(white.to_king_takes_rook(two_squares), king_takes_rook);
+/// assert_eq!(white.to_king_two_squares(king_takes_rook), two_squares);
+/// # }
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CastlingSquares {
+    /// The starting square of the king.
+    #[cfg(not(feature = "chess"))]
+    pub king: UciSquare,
+
+    /// The starting square of the king.
+    #[cfg(feature = "chess")]
+    pub king: Square,
+
+    /// The starting squares of the rooks the king may castle with (at most one on either side of the king).
+    #[cfg(not(feature = "chess"))]
+    pub rooks: Vec<UciSquare>,
+
+    /// The starting squares of the rooks the king may castle with (at most one on either side of the king).
+    #[cfg(feature = "chess")]
+    pub rooks: Vec<Square>,
+}
+
+impl CastlingSquares {
+    /// Creates the castling squares from the king's square and the squares of the rooks it may castle with.
+    #[cfg(not(feature = "chess"))]
+    pub fn new(king: UciSquare, rooks: Vec<UciSquare>) -> CastlingSquares {
+        CastlingSquares {
+            king,
+            rooks,
+        }
+    }
+
+    /// Creates the castling squares from the king's square and the squares of the rooks it may castle with.
+    #[cfg(feature = "chess")]
+    pub fn new(king: Square, rooks: Vec<Square>) -> CastlingSquares {
+        CastlingSquares {
+            king,
+            rooks,
+        }
+    }
+
+    /// The castling squares of White in standard chess, with both castling rights intact.
+    pub fn standard_white() -> CastlingSquares {
+        CastlingSquares::standard(1)
+    }
+
+    /// The castling squares of Black in standard chess, with both castling rights intact.
+    pub fn standard_black() -> CastlingSquares {
+        CastlingSquares::standard(8)
+    }
+
+    fn standard(rank: u8) -> CastlingSquares {
+        CastlingSquares::new(make_square(4, rank - 1), vec![make_square(0, rank - 1), make_square(7, rank - 1)])
+    }
+
+    /// Reads the castling squares of the `color` side from the `board`'s castling rights. Note that the `chess`
+    /// crate only supports standard chess, so the king is always on the e-file and the rooks on the a- and h-files.
+    #[cfg(feature = "chess")]
+    pub fn from_board(board: &Board, color: Color) -> CastlingSquares {
+        let rank = color.to_my_backrank();
+        let rights: CastleRights = board.castle_rights(color);
+        let mut rooks = Vec::new();
+
+        if rights.has_queenside() {
+            rooks.push(Square::make_square(rank, File::A));
+        }
+        if rights.has_kingside() {
+            rooks.push(Square::make_square(rank, File::H));
+        }
+
+        CastlingSquares::new(Square::make_square(rank, File::E), rooks)
+    }
+
+    /// Converts a castling move in the king-two-squares encoding (as in, `e1g1`) into the king-takes-rook encoding
+    /// (as in, `e1h1`). Any other move is returned unchanged.
+    ///
+    /// A king move to the g-file (or c-file) is considered castling if the king may castle with a rook on that side.
+    /// In Chess960 positions where the king starts next to its castling destination, this is ambiguous with a
+    /// regular king move, which is the reason Chess960 uses the king-takes-rook encoding in the first place.
+    #[cfg(not(feature = "chess"))]
+    pub fn to_king_takes_rook(&self, m: UciMove) -> UciMove {
+        if m.from != self.king || m.promotion.is_some() {
+            return m;
+        }
+
+        match self.rook_for_destination(indices(m.to)) {
+            Some((file, rank)) => UciMove::from_to(m.from, make_square(file, rank)),
+            None => m
+        }
+    }
+
+    /// Converts a castling move in the king-two-squares encoding (as in, `e1g1`) into the king-takes-rook encoding
+    /// (as in, `e1h1`). Any other move is returned unchanged.
+    ///
+    /// A king move to the g-file (or c-file) is considered castling if the king may castle with a rook on that side.
+    /// In Chess960 positions where the king starts next to its castling destination, this is ambiguous with a
+    /// regular king move, which is the reason Chess960 uses the king-takes-rook encoding in the first place.
+    #[cfg(feature = "chess")]
+    pub fn to_king_takes_rook(&self, m: ChessMove) -> ChessMove {
+        if m.get_source() != self.king || m.get_promotion().is_some() {
+            return m;
+        }
+
+        match self.rook_for_destination(indices(m.get_dest())) {
+            Some((file, rank)) => ChessMove::new(m.get_source(), make_square(file, rank), None),
+            None => m
+        }
+    }
+
+    /// Converts a castling move in the king-takes-rook encoding (as in, `e1h1`) into the king-two-squares encoding
+    /// (as in, `e1g1`), where the king's destination is on the g-file when castling kingside and on the c-file when
+    /// castling queenside. Any other move is returned unchanged.
+    #[cfg(not(feature = "chess"))]
+    pub fn to_king_two_squares(&self, m: UciMove) -> UciMove {
+        if m.from != self.king || m.promotion.is_some() {
+            return m;
+        }
+
+        match self.destination_for_rook(indices(m.to)) {
+            Some((file, rank)) => UciMove::from_to(m.from, make_square(file, rank)),
+            None => m
+        }
+    }
+
+    /// Converts a castling move in the king-takes-rook encoding (as in, `e1h1`) into the king-two-squares encoding
+    /// (as in, `e1g1`), where the king's destination is on the g-file when castling kingside and on the c-file when
+    /// castling queenside. Any other move is returned unchanged.
+    #[cfg(feature = "chess")]
+    pub fn to_king_two_squares(&self, m: ChessMove) -> ChessMove {
+        if m.get_source() != self.king || m.get_promotion().is_some() {
+            return m;
+        }
+
+        match self.destination_for_rook(indices(m.get_dest())) {
+            Some((file, rank)) => ChessMove::new(m.get_source(), make_square(file, rank), None),
+            None => m
+        }
+    }
+
+    // The helpers below work on (file, rank) indices, so that the conversion logic is shared between the two square
+    // representations.
+
+    fn rook_for_destination(&self, (to_file, to_rank): (u8, u8)) -> Option<(u8, u8)> {
+        let (king_file, king_rank) = indices(self.king);
+        if to_rank != king_rank || to_file == king_file {
+            return None;
+        }
+
+        let kingside = match to_file {
+            KINGSIDE_FILE => true,
+            QUEENSIDE_FILE => false,
+            _ => return None
+        };
+
+        self.rooks
+            .iter()
+            .map(|r| indices(*r))
+            .find(|&(file, rank)| rank == king_rank && file != king_file && (file > king_file) == kingside)
+    }
+
+    fn destination_for_rook(&self, (rook_file, rook_rank): (u8, u8)) -> Option<(u8, u8)> {
+        let (king_file, king_rank) = indices(self.king);
+        if rook_rank != king_rank || rook_file == king_file || !self.rooks.iter().any(|r| indices(*r) == (rook_file, rook_rank)) {
+            return None;
+        }
+
+        if rook_file > king_file {
+            Some((KINGSIDE_FILE, king_rank))
+        } else {
+            Some((QUEENSIDE_FILE, king_rank))
+        }
+    }
+}
+
+/// The file index of the king's destination when castling kingside.
+const KINGSIDE_FILE: u8 = 6;
+
+/// The file index of the king's destination when castling queenside.
+const QUEENSIDE_FILE: u8 = 2;
+
+#[cfg(not(feature = "chess"))]
+fn indices(sq: UciSquare) -> (u8, u8) {
+    ((sq.file as u8).wrapping_sub(b'a'), sq.rank.wrapping_sub(1))
+}
+
+#[cfg(feature = "chess")]
+fn indices(sq: Square) -> (u8, u8) {
+    (sq.get_file().to_index() as u8, sq.get_rank().to_index() as u8)
+}
+
+#[cfg(not(feature = "chess"))]
+fn make_square(file: u8, rank: u8) -> UciSquare {
+    UciSquare::from((b'a' + file) as char, rank + 1)
+}
+
+#[cfg(feature = "chess")]
+fn make_square(file: u8, rank: u8) -> Square {
+    Square::make_square(Rank::from_index(rank as usize), File::from_index(file as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "chess"))]
+    fn mv(s: &str) -> UciMove {
+        let c: Vec<char> = s.chars().collect();
+        UciMove::from_to(
+            UciSquare::from(c[0], c[1].to_digit(10).unwrap() as u8),
+            UciSquare::from(c[2], c[3].to_digit(10).unwrap() as u8),
+        )
+    }
+
+    #[cfg(feature = "chess")]
+    fn mv(s: &str) -> ChessMove {
+        use std::str::FromStr;
+
+        ChessMove::from_str(s).unwrap()
+    }
+
+    #[cfg(not(feature = "chess"))]
+    fn sq(s: &str) -> UciSquare {
+        mv(&format!("{}{}", s, s)).from
+    }
+
+    #[cfg(feature = "chess")]
+    fn sq(s: &str) -> Square {
+        mv(&format!("{}{}", s, s)).get_source()
+    }
+
+    #[test]
+    fn test_standard_conversion() {
+        let white = CastlingSquares::standard_white();
+        let black = CastlingSquares::standard_black();
+
+        assert_eq!(white.to_king_takes_rook(mv("e1g1")), mv("e1h1"));
+        assert_eq!(white.to_king_takes_rook(mv("e1c1")), mv("e1a1"));
+        assert_eq!(black.to_king_takes_rook(mv("e8g8")), mv("e8h8"));
+        assert_eq!(white.to_king_two_squares(mv("e1h1")), mv("e1g1"));
+        assert_eq!(black.to_king_two_squares(mv("e8a8")), mv("e8c8"));
+    }
+
+    #[test]
+    fn test_non_castling_unchanged() {
+        let white = CastlingSquares::standard_white();
+
+        assert_eq!(white.to_king_takes_rook(mv("e1f1")), mv("e1f1"));
+        assert_eq!(white.to_king_takes_rook(mv("e2e4")), mv("e2e4"));
+        assert_eq!(white.to_king_two_squares(mv("e1f1")), mv("e1f1"));
+        assert_eq!(white.to_king_two_squares(mv("d1h1")), mv("d1h1"));
+
+        let kingside_only = CastlingSquares::new(sq("e1"), vec![sq("h1")]);
+        assert_eq!(kingside_only.to_king_takes_rook(mv("e1c1")), mv("e1c1"));
+        assert_eq!(kingside_only.to_king_two_squares(mv("e1a1")), mv("e1a1"));
+    }
+
+    #[test]
+    fn test_chess960_conversion() {
+        // King on b1, rooks on a1 and f1
+        let white = CastlingSquares::new(sq("b1"), vec![sq("a1"), sq("f1")]);
+
+        assert_eq!(white.to_king_two_squares(mv("b1f1")), mv("b1g1"));
+        assert_eq!(white.to_king_two_squares(mv("b1a1")), mv("b1c1"));
+        assert_eq!(white.to_king_takes_rook(mv("b1g1")), mv("b1f1"));
+        assert_eq!(white.to_king_takes_rook(mv("b1c1")), mv("b1a1"));
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_from_board() {
+        use std::str::FromStr;
+
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K1R1 w Qkq - 0 1").unwrap();
+        assert_eq!(CastlingSquares::from_board(&board, Color::White), CastlingSquares::new(Square::E1, vec![Square::A1]));
+        assert_eq!(CastlingSquares::from_board(&board, Color::Black), CastlingSquares::standard_black());
+    }
+}
@@ -0,0 +1,223 @@
+//! The `coalesce` module contains the `InfoCoalescer`, which buffers the `info` messages of an engine and delivers
+//! them to the GUI as snapshots, at a limited rate.
+//!
+//! Like the `Watchdog`, the `InfoCoalescer` does not perform any I/O or keep a timer itself: the caller pushes the
+//! messages as they arrive and calls `poll(..)` when `next_delivery()` is due.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::uci::{UciInfoAttribute, UciMessage};
+
+/// The `info` messages received since the previous snapshot, coalesced.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct InfoSnapshot {
+    /// The latest `info` message with a `pv` or `score` for each `multipv` index (1 if not specified), sorted by the
+    /// index.
+    pub lines: Vec<Vec<UciInfoAttribute>>,
+
+    /// The latest `info` message without a `pv` or `score` (e.g. `currmove`, `nodes` or `hashfull` updates).
+    pub status: Option<Vec<UciInfoAttribute>>,
+
+    /// All of the `info string` messages, in order. These are never dropped.
+    pub strings: Vec<String>,
+}
+
+impl InfoSnapshot {
+    /// Returns `true` if the snapshot contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.status.is_none() && self.strings.is_empty()
+    }
+}
+
+/// Coalesces bursts of `info` messages into at most a set number of snapshots per second, keeping only the latest
+/// message per `multipv` index.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use vampirc_uci::parse;
+/// use vampirc_uci::coalesce::InfoCoalescer;
+///
+/// let mut coalescer = InfoCoalescer::new(10);
+/// let start = Instant::now();
+///
+/// let mut snapshots = Vec::new();
+/// for (i, m) in parse("info depth 1 pv e2e4\ninfo depth 2 pv e2e4 e7e5\ninfo depth 3 pv d2d4\n").iter().enumerate() {
+///     snapshots.extend(coalescer.push(m, start + Duration::from_millis(i as u64)));
+/// }
+/// snapshots.extend(coalescer.poll(start + Duration::from_millis(100)));
+///
+/// // The first message is delivered right away, the other two are coalesced into the second snapshot
+/// assert_eq!(snapshots.len(), 2);
+/// assert_eq!(snapshots[1].lines.len(), 1);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InfoCoalescer {
+    interval: Duration,
+    last_delivery: Option<Instant>,
+    pending_since: Option<Instant>,
+    pending: InfoSnapshotBuilder,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+struct InfoSnapshotBuilder {
+    lines: BTreeMap<u16, Vec<UciInfoAttribute>>,
+    status: Option<Vec<UciInfoAttribute>>,
+    strings: Vec<String>,
+}
+
+impl InfoCoalescer {
+    /// Creates an `InfoCoalescer` that delivers at most `max_per_second` snapshots per second (at least one).
+    pub fn new(max_per_second: u32) -> InfoCoalescer {
+        InfoCoalescer::with_interval(Duration::from_secs(1) / max_per_second.max(1))
+    }
+
+    /// Creates an `InfoCoalescer` that delivers the snapshots at least `interval` apart.
+    pub fn with_interval(interval: Duration) -> InfoCoalescer {
+        InfoCoalescer {
+            interval,
+            last_delivery: None,
+            pending_since: None,
+            pending: InfoSnapshotBuilder::default(),
+        }
+    }
+
+    /// Pushes a message received at `now`. Messages other than `info` are ignored. Returns a snapshot if one is due.
+    pub fn push(&mut self, message: &UciMessage, now: Instant) -> Option<InfoSnapshot> {
+        if let UciMessage::Info(attributes) = message {
+            self.push_info(attributes, now)
+        } else {
+            None
+        }
+    }
+
+    /// Pushes the attributes of an `info` message received at `now`. Returns a snapshot if one is due.
+    pub fn push_info(&mut self, attributes: &[UciInfoAttribute], now: Instant) -> Option<InfoSnapshot> {
+        let mut multipv = None;
+        let mut is_line = false;
+        let mut string = None;
+
+        for a in attributes {
+            match a {
+                UciInfoAttribute::MultiPv(n) => multipv = Some(*n),
+                UciInfoAttribute::Pv(..) | UciInfoAttribute::Score { .. } => is_line = true,
+                UciInfoAttribute::String(s) => string = Some(s),
+                _ => {}
+            }
+        }
+
+        if self.pending_since.is_none() {
+            self.pending_since = Some(now);
+        }
+
+        if let Some(s) = string {
+            self.pending.strings.push(s.clone());
+        } else if is_line {
+            self.pending.lines.insert(multipv.unwrap_or(1), attributes.to_vec());
+        } else {
+            self.pending.status = Some(attributes.to_vec());
+        }
+
+        self.poll(now)
+    }
+
+    /// Returns a snapshot of the messages pushed so far if one is due at `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<InfoSnapshot> {
+        match self.next_delivery() {
+            Some(due) if due <= now => {
+                self.last_delivery = Some(now);
+                self.take()
+            }
+            _ => None
+        }
+    }
+
+    /// Returns a snapshot of the messages pushed so far, regardless of the rate. This is typically called when the
+    /// engine sends `bestmove`.
+    pub fn flush(&mut self) -> Option<InfoSnapshot> {
+        self.take()
+    }
+
+    /// Returns when the next snapshot is due, or `None` if there is nothing to deliver.
+    pub fn next_delivery(&self) -> Option<Instant> {
+        let pending_since = self.pending_since?;
+
+        match self.last_delivery {
+            Some(last) => Some(pending_since.max(last + self.interval)),
+            None => Some(pending_since),
+        }
+    }
+
+    /// Drops the messages pushed so far and forgets the time of the last delivery.
+    pub fn reset(&mut self) {
+        self.last_delivery = None;
+        self.pending_since = None;
+        self.pending = InfoSnapshotBuilder::default();
+    }
+
+    fn take(&mut self) -> Option<InfoSnapshot> {
+        self.pending_since = None;
+        let pending = std::mem::take(&mut self.pending);
+        let snapshot = InfoSnapshot {
+            lines: pending.lines.into_values().collect(),
+            status: pending.status,
+            strings: pending.strings,
+        };
+
+        if snapshot.is_empty() {
+            None
+        } else {
+            Some(snapshot)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_and_latest_per_multipv() {
+        let mut c = InfoCoalescer::new(4);
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+
+        let messages = parse("info depth 1 multipv 1 pv e2e4\ninfo depth 1 multipv 2 pv d2d4\ninfo depth 2 multipv 1 pv g1f3\n\
+                              info currmove e2e4 currmovenum 1\ninfo string hello\ninfo string world\n");
+        let delivered: Vec<bool> = messages.iter().enumerate().map(|(i, m)| c.push(m, t0 + ms(i as u64)).is_some()).collect();
+        assert_eq!(delivered, vec![true, false, false, false, false, false]);
+
+        assert_eq!(c.next_delivery(), Some(t0 + ms(250)));
+        assert_eq!(c.poll(t0 + ms(249)), None);
+
+        let snapshot = c.poll(t0 + ms(250)).unwrap();
+        assert_eq!(snapshot.lines.len(), 2);
+        assert_eq!(snapshot.lines[0][0], UciInfoAttribute::Depth(2));
+        assert_eq!(snapshot.lines[1][1], UciInfoAttribute::MultiPv(2));
+        assert!(snapshot.status.is_some());
+        assert_eq!(snapshot.strings, vec!["hello", "world"]);
+
+        assert_eq!(c.next_delivery(), None);
+        assert_eq!(c.poll(t0 + ms(1000)), None);
+    }
+
+    #[test]
+    fn test_flush_and_reset() {
+        let mut c = InfoCoalescer::with_interval(Duration::from_secs(1));
+        let t0 = Instant::now();
+
+        assert!(c.push(&UciMessage::info_string(String::from("first")), t0).is_some());
+        assert!(c.push(&UciMessage::info_string(String::from("second")), t0).is_none());
+        assert!(c.push(&UciMessage::UciOk, t0).is_none());
+        assert_eq!(c.flush().unwrap().strings, vec!["second"]);
+        assert_eq!(c.flush(), None);
+
+        assert!(c.push(&UciMessage::info_string(String::from("third")), t0).is_none());
+        c.reset();
+        assert_eq!(c.flush(), None);
+    }
+}
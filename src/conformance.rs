@@ -0,0 +1,617 @@
+//! The `conformance` module contains the `ConformanceSuite`, which runs a battery of protocol checks against an
+//! engine and produces a `ConformanceReport`: whether the engine completes the handshake, answers `isready`, accepts
+//! its own options, searches FEN positions, stops on `stop`, handles `ponderhit`, survives malformed input and exits
+//! on `quit`.
+//!
+//! The checks only observe the engine's replies and their timing; they do not judge the quality of its moves.
+
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, Read, Result as IoResult, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::discovery::EngineInfo;
+use crate::parser::parse_one;
+use crate::uci::{UciFen, UciMessage, UciMessageKind, UciOptionConfig, UciSearchControl, UciTimeControl};
+
+/// The positions searched by the `FenPositions` check: the starting position, a middlegame, a position with an en
+/// passant square and an endgame.
+const FENS: [&str; 4] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    "8/8/4k3/8/2K5/8/4P3/8 w - - 0 1",
+];
+
+/// The malformed lines sent by the `IllegalInput` check.
+const ILLEGAL_INPUT: [&str; 5] = [
+    "hello engine",
+    "go depth",
+    "setoption name",
+    "position fen not/a/fen w - - 0 1",
+    "isready now please",
+];
+
+/// A check performed by the `ConformanceSuite`, in the order they are run.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum ConformanceCheck {
+    /// The engine answers `uci` with its `id name` and `uciok`.
+    Handshake,
+
+    /// The engine answers `isready` with `readyok`.
+    IsReady,
+
+    /// The engine accepts a `setoption` with the default value of each option it declared, and is ready afterwards.
+    OptionEcho,
+
+    /// The engine answers `go depth 1` with a `bestmove` in a few positions set up with `position fen`.
+    FenPositions,
+
+    /// The engine does not end a `go infinite` search by itself, and answers `stop` with a `bestmove`.
+    StopResponsiveness,
+
+    /// The engine does not end a `go ponder` search by itself, and still answers `stop` with a `bestmove` after
+    /// `ponderhit`.
+    PonderHit,
+
+    /// The engine survives malformed input and is ready afterwards.
+    IllegalInput,
+
+    /// The engine closes its output after `quit`.
+    Quit,
+}
+
+impl ConformanceCheck {
+    /// All of the checks, in the order they are run.
+    pub const ALL: [ConformanceCheck; 8] = [
+        ConformanceCheck::Handshake,
+        ConformanceCheck::IsReady,
+        ConformanceCheck::OptionEcho,
+        ConformanceCheck::FenPositions,
+        ConformanceCheck::StopResponsiveness,
+        ConformanceCheck::PonderHit,
+        ConformanceCheck::IllegalInput,
+        ConformanceCheck::Quit,
+    ];
+
+    /// Returns the name of the check, as in `"stop-responsiveness"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConformanceCheck::Handshake => "handshake",
+            ConformanceCheck::IsReady => "isready",
+            ConformanceCheck::OptionEcho => "option-echo",
+            ConformanceCheck::FenPositions => "fen-positions",
+            ConformanceCheck::StopResponsiveness => "stop-responsiveness",
+            ConformanceCheck::PonderHit => "ponderhit",
+            ConformanceCheck::IllegalInput => "illegal-input",
+            ConformanceCheck::Quit => "quit",
+        }
+    }
+}
+
+impl Display for ConformanceCheck {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The outcome of a check.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum CheckStatus {
+    /// The engine behaved as expected.
+    Passed,
+
+    /// The engine did not behave as expected, for the specified reason.
+    Failed(String),
+
+    /// The check could not be performed, for the specified reason.
+    Skipped(String),
+}
+
+/// The result of a single check.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CheckResult {
+    /// The check.
+    pub check: ConformanceCheck,
+
+    /// Its outcome.
+    pub status: CheckStatus,
+
+    /// How long the check took.
+    pub elapsed: Duration,
+}
+
+/// The results of a `ConformanceSuite` run.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct ConformanceReport {
+    /// The information the engine reported about itself during the handshake.
+    pub engine: EngineInfo,
+
+    /// The results of the checks, in the order they were run.
+    pub results: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    /// Returns `true` if no check failed. Skipped checks do not count as failed.
+    pub fn passed(&self) -> bool {
+        self.failures().is_empty()
+    }
+
+    /// Returns the results of the failed checks.
+    pub fn failures(&self) -> Vec<&CheckResult> {
+        self.results.iter().filter(|r| matches!(r.status, CheckStatus::Failed(_))).collect()
+    }
+
+    /// Returns the result of the `check`, if it was run.
+    pub fn result(&self, check: ConformanceCheck) -> Option<&CheckResult> {
+        self.results.iter().find(|r| r.check == check)
+    }
+}
+
+impl Display for ConformanceReport {
+    /// Writes a line per check, as in `PASS handshake (12ms)` or `FAIL quit (5000ms): ...`.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for r in &self.results {
+            let millis = r.elapsed.as_millis();
+            match &r.status {
+                CheckStatus::Passed => writeln!(f, "PASS {} ({}ms)", r.check, millis)?,
+                CheckStatus::Failed(reason) => writeln!(f, "FAIL {} ({}ms): {}", r.check, millis, reason)?,
+                CheckStatus::Skipped(reason) => writeln!(f, "SKIP {}: {}", r.check, reason)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the conformance checks against an engine.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use vampirc_uci::conformance::ConformanceSuite;
+///
+/// let report = ConformanceSuite::new()
+///     .timeout(Duration::from_secs(10))
+///     .run_program("stockfish", &[] as &[&str])
+///     .unwrap();
+///
+/// print!("{}", report);
+/// assert!(report.passed());
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ConformanceSuite {
+    timeout: Duration,
+    stop_timeout: Duration,
+    search_time: Duration,
+}
+
+impl Default for ConformanceSuite {
+    /// Five seconds for `uciok`, `readyok` and `bestmove` after `go depth 1`, one second for `bestmove` after `stop`
+    /// and 200 milliseconds of searching before `stop` or `ponderhit`.
+    fn default() -> Self {
+        ConformanceSuite {
+            timeout: Duration::from_secs(5),
+            stop_timeout: Duration::from_secs(1),
+            search_time: Duration::from_millis(200),
+        }
+    }
+}
+
+impl ConformanceSuite {
+    /// Creates a suite with the default timeouts.
+    pub fn new() -> ConformanceSuite {
+        ConformanceSuite::default()
+    }
+
+    /// Sets how long to wait for `uciok`, `readyok`, the `bestmove` of a `go depth 1` search and the engine's exit.
+    pub fn timeout(mut self, timeout: Duration) -> ConformanceSuite {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how long to wait for `bestmove` after `stop`.
+    pub fn stop_timeout(mut self, timeout: Duration) -> ConformanceSuite {
+        self.stop_timeout = timeout;
+        self
+    }
+
+    /// Sets how long to let the engine search before sending `stop` or `ponderhit`.
+    pub fn search_time(mut self, duration: Duration) -> ConformanceSuite {
+        self.search_time = duration;
+        self
+    }
+
+    /// Spawns the engine `program` with the specified `args` and runs the checks against it.
+    pub fn run_program<P: AsRef<OsStr>, A: AsRef<OsStr>>(&self, program: P, args: &[A]) -> IoResult<ConformanceReport> {
+        let mut command = Command::new(program);
+        command.args(args);
+        self.run_command(command)
+    }
+
+    /// Spawns the engine with the prepared `command` and runs the checks against it. The engine is killed if it is
+    /// still running once the checks are done.
+    pub fn run_command(&self, mut command: Command) -> IoResult<ConformanceReport> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let report = self.run(stdin, stdout);
+
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+
+        Ok(report)
+    }
+
+    /// Runs the checks against an engine that reads its messages from `input` and writes its replies to `output`.
+    pub fn run<W: Write, R: Read + Send + 'static>(&self, input: W, output: R) -> ConformanceReport {
+        let mut session = Session::new(input, output);
+        let mut report = ConformanceReport::default();
+
+        for &check in ConformanceCheck::ALL.iter() {
+            let skip = if session.closed {
+                Some("the engine has exited")
+            } else if check != ConformanceCheck::Handshake && !report.results.is_empty()
+                && report.results[0].status != CheckStatus::Passed {
+                Some("the handshake failed")
+            } else {
+                None
+            };
+
+            if let Some(reason) = skip {
+                report.results.push(CheckResult {
+                    check,
+                    status: CheckStatus::Skipped(reason.to_string()),
+                    elapsed: Duration::from_secs(0),
+                });
+                continue;
+            }
+
+            let started = Instant::now();
+            let status = match self.perform(check, &mut session, &mut report.engine) {
+                Ok(None) => CheckStatus::Passed,
+                Ok(Some(reason)) => CheckStatus::Skipped(reason),
+                Err(reason) => CheckStatus::Failed(reason),
+            };
+            report.results.push(CheckResult { check, status, elapsed: started.elapsed() });
+        }
+
+        report
+    }
+
+    /// Performs the `check`. Returns the reason if the check was skipped, or the reason it failed as the error.
+    fn perform<W: Write>(&self, check: ConformanceCheck, session: &mut Session<W>, engine: &mut EngineInfo) -> Result<Option<String>, String> {
+        match check {
+            ConformanceCheck::Handshake => {
+                session.send(&UciMessage::Uci)?;
+                let messages = session.expect(UciMessageKind::UciOk, self.timeout)?;
+                *engine = EngineInfo::from_messages(&messages);
+                if engine.name.is_none() {
+                    return Err(String::from("no `id name` before `uciok`"));
+                }
+            }
+            ConformanceCheck::IsReady => self.ready(session)?,
+            ConformanceCheck::OptionEcho => {
+                let mut echoed = 0;
+                for option in &engine.options {
+                    if let Some(value) = default_value(option) {
+                        session.send(&UciMessage::set_option(option.get_name(), value))?;
+                        echoed += 1;
+                    }
+                }
+                if echoed == 0 {
+                    return Ok(Some(String::from("the engine declared no options with a default value")));
+                }
+                self.ready(session)?;
+            }
+            ConformanceCheck::FenPositions => {
+                session.send(&UciMessage::UciNewGame)?;
+                for fen in FENS.iter() {
+                    session.send(&UciMessage::Position { startpos: false, fen: Some(UciFen::from(*fen)), moves: vec![] })?;
+                    session.send(&UciMessage::Go { time_control: None, search_control: Some(UciSearchControl::depth(1)) })?;
+                    session.expect(UciMessageKind::BestMove, self.timeout)
+                        .map_err(|e| format!("{} (position fen {})", e, fen))?;
+                }
+            }
+            ConformanceCheck::StopResponsiveness => {
+                self.search_then_stop(session, UciTimeControl::Infinite, None)?;
+            }
+            ConformanceCheck::PonderHit => {
+                self.search_then_stop(session, UciTimeControl::Ponder, Some(UciMessage::PonderHit))?;
+            }
+            ConformanceCheck::IllegalInput => {
+                for line in ILLEGAL_INPUT.iter() {
+                    session.send_raw(line)?;
+                }
+                self.ready(session)?;
+            }
+            ConformanceCheck::Quit => {
+                session.send(&UciMessage::Quit)?;
+                session.expect_closed(self.timeout)?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    fn ready<W: Write>(&self, session: &mut Session<W>) -> Result<(), String> {
+        session.send(&UciMessage::IsReady)?;
+        session.expect(UciMessageKind::ReadyOk, self.timeout)?;
+        Ok(())
+    }
+
+    /// Starts an endless search with the `time_control`, checks that it does not end by itself (sending the
+    /// `interim` message halfway through), then stops it.
+    fn search_then_stop<W: Write>(&self, session: &mut Session<W>, time_control: UciTimeControl, interim: Option<UciMessage>) -> Result<(), String> {
+        session.send(&UciMessage::Position { startpos: true, fen: None, moves: vec![] })?;
+        session.send(&UciMessage::Go { time_control: Some(time_control.clone()), search_control: None })?;
+
+        let check_no_best_move = |session: &mut Session<W>, after: &str| -> Result<(), String> {
+            let messages = session.collect(self.search_time / 2)?;
+            if messages.iter().any(|m| m.kind() == UciMessageKind::BestMove) {
+                return Err(format!("`bestmove` sent after `{}` before `stop`", after));
+            }
+            Ok(())
+        };
+
+        let started = UciMessage::Go { time_control: Some(time_control), search_control: None }.serialize_canonical();
+        check_no_best_move(session, &started)?;
+        if let Some(m) = interim {
+            session.send(&m)?;
+            check_no_best_move(session, &m.serialize_canonical())?;
+        } else {
+            check_no_best_move(session, &started)?;
+        }
+
+        session.send(&UciMessage::Stop)?;
+        session.expect(UciMessageKind::BestMove, self.stop_timeout)?;
+        Ok(())
+    }
+}
+
+/// Returns the value of a `setoption` that sets the `option` to its default, or `None` if it has no default.
+fn default_value(option: &UciOptionConfig) -> Option<String> {
+    match option {
+        UciOptionConfig::Check { default, .. } => default.map(|d| d.to_string()),
+        UciOptionConfig::Spin { default, .. } => default.map(|d| d.to_string()),
+        UciOptionConfig::Float { default, .. } => default.map(|d| d.to_string()),
+        UciOptionConfig::Combo { default, .. } => default.clone(),
+        UciOptionConfig::String { default, .. } => default.clone(),
+        UciOptionConfig::Button { .. } => None,
+    }
+}
+
+/// The connection to the engine under test. The engine's output is read on another thread, so that the waits can be
+/// bounded.
+struct Session<W: Write> {
+    input: W,
+    receiver: Receiver<UciMessage>,
+    closed: bool,
+}
+
+impl<W: Write> Session<W> {
+    fn new<R: Read + Send + 'static>(input: W, output: R) -> Session<W> {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => if sender.send(parse_one(&line)).is_err() {
+                        break;
+                    },
+                    Err(_) => break
+                }
+            }
+        });
+
+        Session { input, receiver, closed: false }
+    }
+
+    fn send(&mut self, message: &UciMessage) -> Result<(), String> {
+        self.send_raw(&message.serialize_canonical())
+    }
+
+    fn send_raw(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.input, "{}", line).and_then(|_| self.input.flush()).map_err(|e| {
+            self.closed = true;
+            format!("could not send `{}`: {}", line, e)
+        })
+    }
+
+    /// Receives messages until one of the `kind` arrives, and returns them all.
+    fn expect(&mut self, kind: UciMessageKind, timeout: Duration) -> Result<Vec<UciMessage>, String> {
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(m) => {
+                    let done = m.kind() == kind;
+                    messages.push(m);
+                    if done {
+                        return Ok(messages);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!("no `{}` within {}ms", kind.as_str(), timeout.as_millis()));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.closed = true;
+                    return Err(format!("the engine exited while waiting for `{}`", kind.as_str()));
+                }
+            }
+        }
+    }
+
+    /// Receives messages for the `duration`.
+    fn collect(&mut self, duration: Duration) -> Result<Vec<UciMessage>, String> {
+        let deadline = Instant::now() + duration;
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(m) => messages.push(m),
+                Err(RecvTimeoutError::Timeout) => return Ok(messages),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.closed = true;
+                    return Err(String::from("the engine exited during the search"));
+                }
+            }
+        }
+    }
+
+    /// Waits for the engine to close its output, skipping any messages it still sends.
+    fn expect_closed(&mut self, timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => return Err(format!("still running {}ms after `quit`", timeout.as_millis())),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.closed = true;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use crate::engine::{run, EngineOutput, UciEngine};
+    use crate::testing::{pipe, MockEngine, MockScript};
+
+    use super::*;
+
+    /// Answers `go depth` at once and endless searches on `stop`, unless it is `sloppy`, in which case it answers
+    /// every `go` at once and ignores `stop`.
+    struct TestEngine {
+        sloppy: bool,
+        searching: bool,
+        options: Vec<UciOptionConfig>,
+    }
+
+    impl TestEngine {
+        fn new(sloppy: bool) -> TestEngine {
+            TestEngine {
+                sloppy,
+                searching: false,
+                options: vec![
+                    UciOptionConfig::Spin { name: String::from("Hash"), default: Some(16), min: Some(1), max: Some(64) },
+                    UciOptionConfig::Button { name: String::from("Clear Hash") },
+                ],
+            }
+        }
+
+        fn best_move(output: &EngineOutput) -> IoResult<()> {
+            output.send(&UciMessage::best_move("e2e4".parse().unwrap()))
+        }
+    }
+
+    impl UciEngine for TestEngine {
+        fn name(&self) -> String {
+            String::from("Test")
+        }
+
+        fn author(&self) -> String {
+            String::from("Vampirc")
+        }
+
+        fn options(&self) -> Vec<UciOptionConfig> {
+            self.options.clone()
+        }
+
+        fn on_go(&mut self, time_control: Option<&UciTimeControl>, _: Option<&UciSearchControl>, output: &EngineOutput) -> IoResult<()> {
+            match time_control {
+                Some(UciTimeControl::Infinite) | Some(UciTimeControl::Ponder) if !self.sloppy => {
+                    self.searching = true;
+                    Ok(())
+                }
+                _ => TestEngine::best_move(output)
+            }
+        }
+
+        fn on_stop(&mut self, output: &EngineOutput) -> IoResult<()> {
+            if self.searching {
+                self.searching = false;
+                TestEngine::best_move(output)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn run_suite(engine: TestEngine) -> ConformanceReport {
+        let (input, engine_input) = pipe();
+        let (engine_output, output) = pipe();
+        let mut engine = engine;
+        thread::spawn(move || run(&mut engine, BufReader::new(engine_input), engine_output));
+
+        ConformanceSuite::new()
+            .timeout(Duration::from_millis(500))
+            .stop_timeout(Duration::from_millis(200))
+            .search_time(Duration::from_millis(40))
+            .run(input, output)
+    }
+
+    #[test]
+    fn test_conformant_engine() {
+        let report = run_suite(TestEngine::new(false));
+
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.results.len(), ConformanceCheck::ALL.len());
+        assert_eq!(report.engine.name.as_deref(), Some("Test"));
+        assert!(report.results.iter().all(|r| r.status == CheckStatus::Passed));
+    }
+
+    #[test]
+    fn test_sloppy_engine() {
+        let report = run_suite(TestEngine::new(true));
+
+        let failed: Vec<ConformanceCheck> = report.failures().iter().map(|r| r.check).collect();
+        assert_eq!(failed, vec![ConformanceCheck::StopResponsiveness, ConformanceCheck::PonderHit]);
+        assert_eq!(report.result(ConformanceCheck::StopResponsiveness).unwrap().status,
+                   CheckStatus::Failed(String::from("`bestmove` sent after `go infinite` before `stop`")));
+    }
+
+    #[test]
+    fn test_skipped_checks() {
+        // Declares no options and never answers isready
+        let (input, engine_input) = pipe();
+        let (engine_output, output) = pipe();
+        let mock = MockEngine::new("Mock", "Vampirc").on(UciMessageKind::IsReady, MockScript::new());
+        thread::spawn(move || mock.run(BufReader::new(engine_input), engine_output));
+
+        let report = ConformanceSuite::new()
+            .timeout(Duration::from_millis(50))
+            .stop_timeout(Duration::from_millis(50))
+            .search_time(Duration::from_millis(20))
+            .run(input, output);
+
+        assert_eq!(report.result(ConformanceCheck::Handshake).unwrap().status, CheckStatus::Passed);
+        assert_eq!(report.result(ConformanceCheck::IsReady).unwrap().status,
+                   CheckStatus::Failed(String::from("no `readyok` within 50ms")));
+        assert!(matches!(report.result(ConformanceCheck::OptionEcho).unwrap().status, CheckStatus::Skipped(_)));
+        assert!(report.to_string().starts_with("PASS handshake ("));
+    }
+
+    #[test]
+    fn test_failed_handshake() {
+        let (input, _engine_input) = pipe();
+        let (engine_output, output) = pipe();
+        drop(engine_output);
+
+        let report = ConformanceSuite::new().run(input, output);
+
+        assert_eq!(report.results[0].status,
+                   CheckStatus::Failed(String::from("the engine exited while waiting for `uciok`")));
+        assert!(report.results[1..].iter().all(|r| r.status == CheckStatus::Skipped(String::from("the engine has exited"))));
+    }
+}
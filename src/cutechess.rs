@@ -0,0 +1,171 @@
+//! The `cutechess` module reads the debug logs of [cutechess-cli](https://github.com/cutechess/cutechess), as written
+//! with its `-debug` flag. Each line of the protocol is logged with the milliseconds elapsed since cutechess-cli started,
+//! `>` for the lines sent to an engine or `<` for the ones received from it, and the engine's name and index:
+//!
+//! ```text
+//! 305 >Stockfish(0): uci
+//! 312 <Stockfish(0): id name Stockfish 16
+//! ```
+//!
+//! The other lines of the log (such as `Started game 1 of 10`) are skipped.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Result as IoResult};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::parser::parse_one;
+use crate::record::RecordedMessage;
+use crate::uci::{CommunicationDirection, UciMessage};
+
+/// A message read from a cutechess-cli debug log.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CutechessMessage {
+    /// The time elapsed since cutechess-cli started.
+    pub elapsed: Duration,
+
+    /// The name of the engine the message was sent to or received from.
+    pub engine: String,
+
+    /// The index of the engine, in the order the engines were specified on cutechess-cli's command line.
+    pub engine_index: usize,
+
+    /// The direction of the message.
+    pub direction: CommunicationDirection,
+
+    /// The message.
+    pub message: UciMessage,
+}
+
+impl From<CutechessMessage> for RecordedMessage {
+    /// Drops the engine's name and index.
+    fn from(m: CutechessMessage) -> Self {
+        RecordedMessage {
+            elapsed: m.elapsed,
+            direction: m.direction,
+            message: m.message,
+        }
+    }
+}
+
+/// Parses a line of a cutechess-cli debug log. Returns `None` if the line does not log a line of the protocol.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{CommunicationDirection, UciMessage};
+/// use vampirc_uci::cutechess::parse_line;
+///
+/// let m = parse_line("312 <Stockfish(0): id name Stockfish 16").unwrap();
+/// assert_eq!(m.elapsed.as_millis(), 312);
+/// assert_eq!((m.engine.as_str(), m.engine_index), ("Stockfish", 0));
+/// assert_eq!(m.direction, CommunicationDirection::EngineToGui);
+/// assert_eq!(m.message, UciMessage::id_name("Stockfish 16"));
+///
+/// assert_eq!(parse_line("Started game 1 of 10 (Stockfish vs Vampirc)"), None);
+/// ```
+pub fn parse_line(line: &str) -> Option<CutechessMessage> {
+    let (time, rest) = line.trim().split_once(' ')?;
+    let elapsed = Duration::from_millis(time.parse().ok()?);
+
+    let direction = match rest.chars().next()? {
+        '>' => CommunicationDirection::GuiToEngine,
+        '<' => CommunicationDirection::EngineToGui,
+        _ => return None,
+    };
+    let rest = &rest[1..];
+
+    // The engine's name may contain parentheses and the text may contain "): ", so take the first "): " that closes
+    // an index
+    let mut search_from = 0;
+    loop {
+        let end = search_from + rest[search_from..].find("):")?;
+        if let Some(open) = rest[..end].rfind('(') {
+            if let Ok(engine_index) = rest[open + 1..end].parse() {
+                return Some(CutechessMessage {
+                    elapsed,
+                    engine: rest[..open].to_string(),
+                    engine_index,
+                    direction,
+                    message: parse_one(rest[end + 2..].trim()),
+                });
+            }
+        }
+        search_from = end + 2;
+    }
+}
+
+/// Reads the messages of a cutechess-cli debug log, skipping the lines that do not log a line of the protocol.
+pub fn load<R: BufRead>(input: R) -> IoResult<Vec<CutechessMessage>> {
+    let mut messages = Vec::new();
+
+    for line in input.lines() {
+        if let Some(m) = parse_line(&line?) {
+            messages.push(m);
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Reads the messages of the cutechess-cli debug log at `path`. See `load(..)`.
+pub fn load_file<P: AsRef<Path>>(path: P) -> IoResult<Vec<CutechessMessage>> {
+    load(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uci::UciInfoAttribute;
+
+    use super::*;
+
+    const LOG: &str = "Started game 1 of 2 (Stockfish vs Komodo (dev))
+305 >Stockfish(0): uci
+306 >Komodo (dev)(1): uci
+312 <Stockfish(0): id name Stockfish 16
+313 <Stockfish(0): uciok
+320 <Komodo (dev)(1): info string (note): ok
+1024 >Stockfish(0): go wtime 60000 btime 60000
+Finished game 1 (Stockfish vs Komodo (dev)): 1-0 {White mates}
+";
+
+    #[test]
+    fn test_load() {
+        let messages = load(LOG.as_bytes()).unwrap();
+
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[1].engine, "Komodo (dev)");
+        assert_eq!(messages[1].engine_index, 1);
+        assert_eq!(messages[1].message, UciMessage::Uci);
+        assert_eq!(messages[5].elapsed, Duration::from_millis(1024));
+        assert!(matches!(messages[5].message, UciMessage::Go { .. }));
+
+        let stockfish: Vec<&CutechessMessage> = messages.iter().filter(|m| m.engine_index == 0).collect();
+        assert_eq!(stockfish.len(), 4);
+    }
+
+    #[test]
+    fn test_parentheses_in_text() {
+        let m = parse_line("320 <Komodo (dev)(1): info string (note): ok").unwrap();
+
+        assert_eq!(m.engine, "Komodo (dev)");
+        assert_eq!(m.message, UciMessage::Info(vec![UciInfoAttribute::String(String::from("(note): ok"))]));
+    }
+
+    #[test]
+    fn test_unrelated_lines() {
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("Finished game 1 (Stockfish vs Komodo (dev)): 1-0 {White mates}"), None);
+        assert_eq!(parse_line("305 Stockfish(0): uci"), None);
+        assert_eq!(parse_line("305 >Stockfish: uci"), None);
+    }
+
+    #[test]
+    fn test_into_recorded() {
+        let recorded: RecordedMessage = parse_line("313 <Stockfish(0): uciok").unwrap().into();
+
+        assert_eq!(recorded.elapsed, Duration::from_millis(313));
+        assert_eq!(recorded.direction, CommunicationDirection::EngineToGui);
+        assert_eq!(recorded.message, UciMessage::UciOk);
+    }
+}
@@ -0,0 +1,308 @@
+//! The `detect` module contains the `ProtocolDetector`, which inspects the first lines received from an unknown
+//! counterpart (an engine or a GUI) and guesses which protocol it speaks: UCI, CECP (better known as the xboard or
+//! WinBoard protocol) or USI, its shogi offspring. An adapter can then pick the matching parser.
+//!
+//! The detector works incrementally: feed it lines (or raw chunks of a stream) as they arrive and check the current
+//! `Detection` after each, until its confidence is high enough. Lines that say nothing about the protocol, such as the
+//! banner many engines print on startup, are ignored.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A protocol spoken between a chess (or shogi) GUI and an engine.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Protocol {
+    /// The Universal Chess Interface, the protocol of this crate.
+    Uci,
+
+    /// The Chess Engine Communication Protocol, used by xboard and WinBoard.
+    Xboard,
+
+    /// The Universal Shogi Interface, an adaptation of UCI to shogi.
+    Usi,
+}
+
+impl Protocol {
+    const ALL: [Protocol; 3] = [Protocol::Uci, Protocol::Xboard, Protocol::Usi];
+
+    fn index(self) -> usize {
+        match self {
+            Protocol::Uci => 0,
+            Protocol::Xboard => 1,
+            Protocol::Usi => 2,
+        }
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let s = match self {
+            Protocol::Uci => "UCI",
+            Protocol::Xboard => "CECP",
+            Protocol::Usi => "USI",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// The protocol the `ProtocolDetector` considers the most likely, with how confident it is.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Detection {
+    /// The most likely protocol.
+    pub protocol: Protocol,
+
+    /// How confident the detector is, between 0 and 1. A single line that only one protocol has (such as `uciok` or
+    /// `protover 2`) yields a confidence over 0.8, every further piece of evidence raises it, and evidence for the
+    /// other protocols lowers it.
+    pub confidence: f64,
+}
+
+/// The weight of a line that only one protocol has, such as `uci` or `feature`.
+const DECISIVE: u32 = 10;
+
+/// The weight of a line that is much more likely in one protocol, such as `bestmove` with a move in its notation.
+const STRONG: u32 = 4;
+
+/// The weight of a line that several protocols share, such as `isready`.
+const WEAK: u32 = 1;
+
+/// The evidence assumed to be unseen, which keeps the confidence in a guess from a single line below 1.
+const PRIOR: u32 = 2;
+
+/// Guesses the protocol of a counterpart from the lines it sent (or received), one at a time.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::detect::{Protocol, ProtocolDetector};
+///
+/// let mut detector = ProtocolDetector::new();
+/// assert_eq!(detector.push_line("Stockfish 16 by the Stockfish developers"), None);
+///
+/// // UCI and USI share `id`
+/// assert_eq!(detector.push_line("id name Stockfish 16"), None);
+///
+/// // Raw chunks of a stream may split the lines anywhere
+/// detector.push_str("option name Hash type spin default 16 min 1 max 33554432\nuci");
+/// let detection = detector.push_str("ok\n").unwrap();
+/// assert_eq!(detection.protocol, Protocol::Uci);
+/// assert!(detection.confidence > 0.7);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ProtocolDetector {
+    scores: [u32; 3],
+    lines: usize,
+    partial: String,
+}
+
+impl ProtocolDetector {
+    /// Creates a detector that has not seen any lines.
+    pub fn new() -> ProtocolDetector {
+        ProtocolDetector::default()
+    }
+
+    /// Takes a line into account and returns the resulting `detection()`.
+    pub fn push_line(&mut self, line: &str) -> Option<Detection> {
+        self.lines += 1;
+        for (protocol, weight) in evidence(line) {
+            self.scores[protocol.index()] += weight;
+        }
+
+        self.detection()
+    }
+
+    /// Takes a chunk of a stream into account, such as the bytes returned by a single read, and returns the resulting
+    /// `detection()`. The complete lines of the chunk are pushed; the incomplete last line is kept until a later chunk
+    /// completes it.
+    pub fn push_str(&mut self, chunk: &str) -> Option<Detection> {
+        self.partial.push_str(chunk);
+
+        if let Some(end) = self.partial.rfind('\n') {
+            let complete: String = self.partial.drain(..=end).collect();
+            for line in complete.lines() {
+                self.push_line(line);
+            }
+        }
+
+        self.detection()
+    }
+
+    /// Returns the most likely protocol, with the confidence in it, or `None` if none of the lines so far told the
+    /// protocols apart.
+    pub fn detection(&self) -> Option<Detection> {
+        let total: u32 = self.scores.iter().sum();
+        let best = Protocol::ALL.iter().copied().max_by_key(|p| self.scores[p.index()])?;
+        let best_score = self.scores[best.index()];
+
+        let tied = Protocol::ALL.iter().filter(|p| self.scores[p.index()] == best_score).count() > 1;
+        if best_score == 0 || tied {
+            return None;
+        }
+
+        Some(Detection {
+            protocol: best,
+            confidence: f64::from(best_score) / f64::from(total + PRIOR),
+        })
+    }
+
+    /// Returns the weight of the evidence for the `protocol` so far.
+    pub fn score(&self, protocol: Protocol) -> u32 {
+        self.scores[protocol.index()]
+    }
+
+    /// Returns the number of complete lines pushed so far.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Forgets all of the lines pushed so far, including an incomplete one.
+    pub fn reset(&mut self) {
+        *self = ProtocolDetector::default();
+    }
+}
+
+/// Guesses the protocol of a whole `transcript`, with a message per line. See `ProtocolDetector`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::detect::{detect, Protocol};
+///
+/// assert_eq!(detect("xboard\nprotover 2\nnew\n").unwrap().protocol, Protocol::Xboard);
+/// assert_eq!(detect("usi\nposition sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1\n")
+///     .unwrap().protocol, Protocol::Usi);
+/// assert_eq!(detect("isready\nreadyok\n"), None);
+/// ```
+pub fn detect(transcript: &str) -> Option<Detection> {
+    let mut detector = ProtocolDetector::new();
+    for line in transcript.lines() {
+        detector.push_line(line);
+    }
+
+    detector.detection()
+}
+
+/// Returns the protocols a line is evidence for, with the weight of the evidence.
+fn evidence(line: &str) -> Vec<(Protocol, u32)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let first = match tokens.first() {
+        Some(t) => *t,
+        None => return Vec::new(),
+    };
+    let rest = &tokens[1..];
+    let shared = vec![(Protocol::Uci, WEAK), (Protocol::Usi, WEAK)];
+
+    match first {
+        "uci" | "uciok" | "ucinewgame" => vec![(Protocol::Uci, DECISIVE)],
+        "usi" | "usiok" | "usinewgame" | "gameover" | "checkmate" => vec![(Protocol::Usi, DECISIVE)],
+        "xboard" | "protover" | "feature" | "usermove" | "setboard" | "ping" | "pong" | "tellics" | "telluser"
+        | "tellopponent" => vec![(Protocol::Xboard, DECISIVE)],
+        "new" | "force" | "level" | "st" | "sd" | "time" | "otim" | "post" | "nopost" | "hard" | "easy" | "random"
+        | "computer" | "white" | "black" | "edit" | "undo" | "remove" | "result" | "move" | "resign" | "Illegal"
+        | "Error" | "hint" | "analyze" | "exit" | "memory" | "cores" | "egtpath" | "variant" | "accepted"
+        | "rejected" => vec![(Protocol::Xboard, STRONG)],
+        "id" | "isready" | "readyok" | "ponderhit" | "stop" => shared,
+        "option" if line.contains("name USI_") || line.contains("type filename") => vec![(Protocol::Usi, STRONG)],
+        "option" => shared,
+        "position" => match rest.first() {
+            Some(&"sfen") => vec![(Protocol::Usi, DECISIVE)],
+            Some(&"fen") => vec![(Protocol::Uci, STRONG)],
+            _ => moves_evidence(rest).unwrap_or(shared),
+        },
+        "bestmove" => match rest.first() {
+            Some(&"resign") | Some(&"win") => vec![(Protocol::Usi, STRONG)],
+            Some(m) => move_evidence(m).map(|e| vec![e]).unwrap_or(shared),
+            None => Vec::new(),
+        },
+        "go" if rest.is_empty() => vec![(Protocol::Uci, WEAK), (Protocol::Xboard, WEAK), (Protocol::Usi, WEAK)],
+        "go" if rest.contains(&"byoyomi") => vec![(Protocol::Usi, STRONG)],
+        "go" => shared,
+        "info" => moves_evidence(rest).unwrap_or(shared),
+        _ if is_thinking_output(&tokens) => vec![(Protocol::Xboard, STRONG)],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the evidence of the first move among the `tokens` that is in the notation of either UCI or USI.
+fn moves_evidence(tokens: &[&str]) -> Option<Vec<(Protocol, u32)>> {
+    tokens.iter().find_map(|t| move_evidence(t)).map(|e| vec![e])
+}
+
+/// Returns the protocol whose notation the `token` is a move in: `e2e4` and `e7e8q` in UCI, `7g7f`, `8h2b+` and `P*5e`
+/// in USI.
+fn move_evidence(token: &str) -> Option<(Protocol, u32)> {
+    let b = token.as_bytes();
+    let square = |file: u8, rank: u8, files: std::ops::RangeInclusive<u8>, ranks: std::ops::RangeInclusive<u8>| {
+        files.contains(&file) && ranks.contains(&rank)
+    };
+
+    let chess_square = |i: usize| square(b[i], b[i + 1], b'a'..=b'h', b'1'..=b'8');
+    let shogi_square = |i: usize| square(b[i], b[i + 1], b'1'..=b'9', b'a'..=b'i');
+
+    match b.len() {
+        4 | 5 if chess_square(0) && chess_square(2) && (b.len() == 4 || b"qrbn".contains(&b[4])) => {
+            Some((Protocol::Uci, STRONG))
+        }
+        4 | 5 if shogi_square(0) && shogi_square(2) && (b.len() == 4 || b[4] == b'+') => Some((Protocol::Usi, STRONG)),
+        4 if b"PLNSGBR".contains(&b[0]) && b[1] == b'*' && shogi_square(2) => Some((Protocol::Usi, STRONG)),
+        _ => None,
+    }
+}
+
+/// Returns whether the line is the thinking output of a CECP engine: the depth, the score, the time and the nodes,
+/// followed by the principal variation.
+fn is_thinking_output(tokens: &[&str]) -> bool {
+    tokens.len() >= 4 && tokens[..4].iter().all(|t| t.trim_end_matches(['.', '&']).parse::<i64>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let uci = detect("Stockfish 16 by the Stockfish developers\nuci\nid name Stockfish 16\nuciok\n").unwrap();
+        assert_eq!(uci.protocol, Protocol::Uci);
+        assert!(uci.confidence > 0.8);
+
+        let xboard = detect("xboard\nprotover 2\nfeature ping=1 setboard=1 done=1\nnew\nusermove e2e4\n").unwrap();
+        assert_eq!(xboard.protocol, Protocol::Xboard);
+        assert!(xboard.confidence > 0.9);
+
+        assert_eq!(detect("usi\nid name Apery\nusiok\n").unwrap().protocol, Protocol::Usi);
+
+        // Without the handshake, the moves give the protocol away
+        assert_eq!(detect("position startpos moves e2e4 e7e5\ngo depth 5\n").unwrap().protocol, Protocol::Uci);
+        assert_eq!(detect("position startpos moves 7g7f 3c3d\n").unwrap().protocol, Protocol::Usi);
+        assert_eq!(detect("info depth 3 pv P*5e 5a4b\n").unwrap().protocol, Protocol::Usi);
+        assert_eq!(detect("bestmove e7e8q\n").unwrap().protocol, Protocol::Uci);
+        assert_eq!(detect("9 156 1084 48000 Nf3 Nc6 Nc3\nmove Nf3\n").unwrap().protocol, Protocol::Xboard);
+
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("hello\nisready\nreadyok\ngo\nquit\n"), None);
+    }
+
+    #[test]
+    fn test_detector_incremental() {
+        let mut detector = ProtocolDetector::new();
+        assert_eq!(detector.push_str("isready\nready"), None);
+        assert_eq!(detector.lines(), 1);
+        assert_eq!(detector.score(Protocol::Uci), WEAK);
+
+        let first = detector.push_str("ok\nbestmove e2e4 ponder e7e5\n").unwrap();
+        assert_eq!(detector.lines(), 3);
+        assert_eq!(first.protocol, Protocol::Uci);
+
+        // Contradicting evidence outweighs the guess so far, but with a low confidence
+        let second = detector.push_line("feature done=1").unwrap();
+        assert_eq!(second.protocol, Protocol::Xboard);
+        assert!(second.confidence < 0.6);
+        assert_eq!(detector.push_line("uciok").unwrap().protocol, Protocol::Uci);
+        assert_eq!(Protocol::Xboard.to_string(), "CECP");
+
+        detector.push_str("uci");
+        detector.reset();
+        assert_eq!(detector, ProtocolDetector::new());
+        assert_eq!(detector.push_str("\n"), None);
+    }
+}
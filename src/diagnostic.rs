@@ -0,0 +1,113 @@
+//! The `diagnostic` module (available with the `miette` feature) makes `ParseError` a `miette::Diagnostic`, so that
+//! tools built on the crate can render malformed UCI input with the offending part of the line underlined and the
+//! problem written next to it:
+//!
+//! ```text
+//!   × line 1, column 30: rank out of range 1–8: '9' in move 'e7e9'
+//!    ╭────
+//!  1 │ position startpos moves e2e4 e7e9
+//!    ·                              ──┬─
+//!    ·                                ╰── rank out of range 1–8: '9' in move 'e7e9'
+//!    ╰────
+//! ```
+//!
+//! The source code of the diagnostic is the offending line alone, but it keeps its line number in the input, so that
+//! the report points at the right line of a multi-line transcript.
+
+use std::fmt::Display;
+
+use miette::{Diagnostic, LabeledSpan, MietteError, MietteSpanContents, SourceCode, SourceSpan, SpanContents};
+use pest::error::LineColLocation;
+
+use crate::error::ParseError;
+
+impl ParseError {
+    /// Returns the span of the problem within `text()`, in bytes. A problem that extends beyond its line is
+    /// underlined to the end of the line, and one at the end of the line covers nothing.
+    pub fn span(&self) -> SourceSpan {
+        let text = self.text();
+        let offset = |column: usize| text.char_indices().nth(column - 1).map_or(text.len(), |(i, _)| i);
+
+        let start = offset(self.column());
+        let end = match self.pest_error().line_col {
+            LineColLocation::Span(_, (line, column)) if line == self.line() => offset(column),
+            LineColLocation::Span(..) => text.len(),
+            LineColLocation::Pos(_) => start,
+        };
+
+        SourceSpan::new(start.into(), end.saturating_sub(start))
+    }
+}
+
+impl Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("vampirc_uci::parse"))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(self)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item=LabeledSpan> + '_>> {
+        let label = LabeledSpan::new_primary_with_span(Some(self.message().into_owned()), self.span());
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+impl SourceCode for ParseError {
+    fn read_span<'a>(&'a self, span: &SourceSpan, context_lines_before: usize, context_lines_after: usize)
+                     -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self.text().read_span(span, context_lines_before, context_lines_after)?;
+
+        Ok(Box::new(MietteSpanContents::new(
+            contents.data(),
+            *contents.span(),
+            contents.line() + self.line() - 1,
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::NarratableReportHandler;
+
+    use crate::parser::parse_strict;
+
+    use super::*;
+
+    fn render(error: &ParseError) -> String {
+        let mut out = String::new();
+        NarratableReportHandler::new().render_report(&mut out, error).unwrap();
+
+        out
+    }
+
+    #[test]
+    fn test_parse_error_diagnostic() {
+        let err = parse_strict("uci\nisready\nposition startpos moves e2e4 e7e9\n").unwrap_err();
+        assert_eq!(err.code().unwrap().to_string(), "vampirc_uci::parse");
+        assert_eq!(err.span(), SourceSpan::new(29.into(), 4));
+
+        let labels: Vec<LabeledSpan> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].label(), Some("rank out of range 1–8: '9' in move 'e7e9'"));
+        assert!(labels[0].primary());
+
+        let report = render(&err);
+        assert!(report.contains("Begin snippet starting at line 3, column 1"));
+        assert!(report.contains("snippet line 3: position startpos moves e2e4 e7e9"));
+    }
+
+    #[test]
+    fn test_parse_error_diagnostic_multibyte() {
+        let err = parse_strict("uci\noption name Kejžar type bogus\n").unwrap_err();
+        assert_eq!(err.column(), 25);
+        assert_eq!(&err.text()[err.span().offset()..], "bogus");
+
+        let err = parse_strict("isready\n\ngo depth x\n").unwrap_err();
+        assert_eq!((err.span().offset(), err.span().len()), (9, 0));
+        assert!(render(&err).contains("snippet line 3: go depth x"));
+    }
+}
@@ -0,0 +1,145 @@
+//! The `discovery` module contains functions that start an engine, perform the `uci` handshake and return the
+//! `EngineInfo` the engine reports about itself: its name, its author and the options it supports. The asynchronous
+//! flavors are available with the `tokio` feature.
+
+use std::ffi::OsStr;
+use std::io::Result as IoResult;
+use std::process::Command;
+
+use crate::process::EngineProcess;
+use crate::uci::{UciMessage, UciOptionConfig};
+
+/// The information an engine reports about itself during the `uci` handshake.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct EngineInfo {
+    /// The name of the engine, as reported by `id name`.
+    pub name: Option<String>,
+
+    /// The author of the engine, as reported by `id author`.
+    pub author: Option<String>,
+
+    /// The options the engine supports, in the order they were declared.
+    pub options: Vec<UciOptionConfig>,
+}
+
+impl EngineInfo {
+    /// Collects the `EngineInfo` from the messages the engine sent in reply to `uci`. Messages other than `id` and
+    /// `option` are ignored; if the engine reported its name or author more than once, the last report wins.
+    pub fn from_messages<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> EngineInfo {
+        let mut info = EngineInfo::default();
+
+        for m in messages {
+            match m {
+                UciMessage::Id { name, author } => {
+                    if name.is_some() {
+                        info.name = name.clone();
+                    }
+                    if author.is_some() {
+                        info.author = author.clone();
+                    }
+                }
+                UciMessage::Option(config) => info.options.push(config.clone()),
+                _ => {}
+            }
+        }
+
+        info
+    }
+
+    /// Returns the declaration of the option with the specified name. Option names are case-insensitive.
+    pub fn option(&self, name: &str) -> Option<&UciOptionConfig> {
+        self.options.iter().find(|o| o.get_name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Spawns the engine `program` with the specified `args`, performs the `uci` handshake and quits the engine.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::discovery::discover;
+///
+/// let info = discover("stockfish", &[] as &[&str]).unwrap();
+/// for option in &info.options {
+///     println!("{}: {}", option.get_name(), option.get_type_str());
+/// }
+/// ```
+pub fn discover<P: AsRef<OsStr>, A: AsRef<OsStr>>(program: P, args: &[A]) -> IoResult<EngineInfo> {
+    let mut command = Command::new(program);
+    command.args(args);
+    discover_command(command)
+}
+
+/// Spawns the engine with the prepared `command`, performs the `uci` handshake and quits the engine.
+pub fn discover_command(command: Command) -> IoResult<EngineInfo> {
+    let mut engine = EngineProcess::spawn_command(command)?;
+    let info = EngineInfo::from_messages(&engine.handshake()?);
+    engine.quit()?;
+
+    Ok(info)
+}
+
+/// Spawns the engine `program` with the specified `args`, performs the `uci` handshake and quits the engine,
+/// asynchronously.
+#[cfg(feature = "tokio")]
+pub async fn discover_async<P: AsRef<OsStr>, A: AsRef<OsStr>>(program: P, args: &[A]) -> IoResult<EngineInfo> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args);
+    discover_command_async(command).await
+}
+
+/// Spawns the engine with the prepared `command`, performs the `uci` handshake and quits the engine,
+/// asynchronously.
+#[cfg(feature = "tokio")]
+pub async fn discover_command_async(command: tokio::process::Command) -> IoResult<EngineInfo> {
+    let engine = crate::async_engine::EngineHandle::spawn_command(command).await?;
+    let info = engine.info();
+    engine.quit().await?;
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[cfg(unix)]
+    const FAKE_ENGINE: &str = r#"
+        while read -r line; do
+            case "$line" in
+                uci) echo "id name Fake"; echo "id author Tester"; echo "option name Hash type spin default 16 min 1 max 64";
+                     echo "option name Ponder type check default false"; echo "uciok";;
+                quit) exit 0;;
+            esac
+        done
+    "#;
+
+    #[test]
+    fn test_from_messages() {
+        let info = EngineInfo::from_messages(&parse("id name First\nid author Me\nid name Second\n\
+                                                     option name Threads type spin default 1 min 1 max 8\nuciok\n"));
+        assert_eq!(info.name.as_deref(), Some("Second"));
+        assert_eq!(info.author.as_deref(), Some("Me"));
+        assert_eq!(info.options.len(), 1);
+        assert!(info.option("threads").is_some());
+        assert!(info.option("Hash").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover() {
+        let info = discover("sh", &["-c", FAKE_ENGINE]).unwrap();
+        assert_eq!(info.name.as_deref(), Some("Fake"));
+        assert_eq!(info.author.as_deref(), Some("Tester"));
+        assert_eq!(info.options.iter().map(|o| o.get_name()).collect::<Vec<&str>>(), vec!["Hash", "Ponder"]);
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test]
+    async fn test_discover_async() {
+        let info = discover_async("sh", &["-c", FAKE_ENGINE]).await.unwrap();
+        assert_eq!(info, discover("sh", &["-c", FAKE_ENGINE]).unwrap());
+    }
+}
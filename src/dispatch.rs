@@ -0,0 +1,238 @@
+//! The `dispatch` module contains the `MessageDispatcher`, which calls the handlers registered for each kind of
+//! message. It is an event-driven alternative to matching on `UciMessage` directly.
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use crate::uci::{UciMessage, UciMessageKind};
+
+/// A handler for a message.
+pub type MessageHandler<'a> = Box<dyn FnMut(&UciMessage) + 'a>;
+
+/// Dispatches messages to the handlers registered for their kind. Messages without a handler of their own go to the
+/// fallback handler, if one is registered.
+///
+/// The handlers may borrow from their environment for the lifetime `'a`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, UciMessage};
+/// use vampirc_uci::dispatch::MessageDispatcher;
+///
+/// let mut searches = 0;
+/// let mut others = Vec::new();
+///
+/// let mut dispatcher = MessageDispatcher::new()
+///     .on_go(|_| searches += 1)
+///     .fallback(|m| others.push(m.clone()));
+///
+/// for m in parse("position startpos\ngo depth 10\nstop\n") {
+///     dispatcher.dispatch(&m);
+/// }
+/// drop(dispatcher);
+///
+/// assert_eq!(searches, 1);
+/// assert_eq!(others.len(), 2);
+/// ```
+#[derive(Default)]
+pub struct MessageDispatcher<'a> {
+    handlers: HashMap<UciMessageKind, MessageHandler<'a>>,
+    fallback: Option<MessageHandler<'a>>,
+}
+
+impl<'a> MessageDispatcher<'a> {
+    /// Creates a `MessageDispatcher` without any handlers.
+    pub fn new() -> MessageDispatcher<'a> {
+        MessageDispatcher {
+            handlers: HashMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Registers the `handler` for the `uci` messages, replacing the previous one.
+    pub fn on_uci<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Uci, handler)
+    }
+
+    /// Registers the `handler` for the `debug` messages, replacing the previous one.
+    pub fn on_debug<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Debug, handler)
+    }
+
+    /// Registers the `handler` for the `isready` messages, replacing the previous one.
+    pub fn on_is_ready<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::IsReady, handler)
+    }
+
+    /// Registers the `handler` for the `register` messages, replacing the previous one.
+    pub fn on_register<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Register, handler)
+    }
+
+    /// Registers the `handler` for the `position` messages, replacing the previous one.
+    pub fn on_position<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Position, handler)
+    }
+
+    /// Registers the `handler` for the `setoption` messages, replacing the previous one.
+    pub fn on_set_option<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::SetOption, handler)
+    }
+
+    /// Registers the `handler` for the `ucinewgame` messages, replacing the previous one.
+    pub fn on_uci_new_game<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::UciNewGame, handler)
+    }
+
+    /// Registers the `handler` for the `stop` messages, replacing the previous one.
+    pub fn on_stop<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Stop, handler)
+    }
+
+    /// Registers the `handler` for the `ponderhit` messages, replacing the previous one.
+    pub fn on_ponder_hit<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::PonderHit, handler)
+    }
+
+    /// Registers the `handler` for the `quit` messages, replacing the previous one.
+    pub fn on_quit<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Quit, handler)
+    }
+
+    /// Registers the `handler` for the `go` messages, replacing the previous one.
+    pub fn on_go<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Go, handler)
+    }
+
+    /// Registers the `handler` for the `id` messages, replacing the previous one.
+    pub fn on_id<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Id, handler)
+    }
+
+    /// Registers the `handler` for the `uciok` messages, replacing the previous one.
+    pub fn on_uci_ok<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::UciOk, handler)
+    }
+
+    /// Registers the `handler` for the `readyok` messages, replacing the previous one.
+    pub fn on_ready_ok<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::ReadyOk, handler)
+    }
+
+    /// Registers the `handler` for the `bestmove` messages, replacing the previous one.
+    pub fn on_best_move<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::BestMove, handler)
+    }
+
+    /// Registers the `handler` for the `copyprotection` messages, replacing the previous one.
+    pub fn on_copy_protection<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::CopyProtection, handler)
+    }
+
+    /// Registers the `handler` for the `registration` messages, replacing the previous one.
+    pub fn on_registration<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Registration, handler)
+    }
+
+    /// Registers the `handler` for the `option` messages, replacing the previous one.
+    pub fn on_option<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Option, handler)
+    }
+
+    /// Registers the `handler` for the `info` messages, replacing the previous one.
+    pub fn on_info<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Info, handler)
+    }
+
+    /// Registers the `handler` for the unrecognized messages, replacing the previous one.
+    pub fn on_unknown<F: FnMut(&UciMessage) + 'a>(self, handler: F) -> MessageDispatcher<'a> {
+        self.on(UciMessageKind::Unknown, handler)
+    }
+
+    /// Registers the `handler` for the messages that have no handler of their own, replacing the previous one.
+    pub fn fallback<F: FnMut(&UciMessage) + 'a>(mut self, handler: F) -> MessageDispatcher<'a> {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Calls the handler registered for the `message`'s kind or, if there is none, the fallback handler. Returns
+    /// `true` if a handler was called.
+    pub fn dispatch(&mut self, message: &UciMessage) -> bool {
+        let handler = match self.handlers.get_mut(&message.kind()) {
+            Some(h) => h,
+            None => match &mut self.fallback {
+                Some(h) => h,
+                None => return false,
+            }
+        };
+
+        handler(message);
+        true
+    }
+
+    /// Returns `true` if a handler (other than the fallback) is registered for the `message`'s kind.
+    pub fn handles(&self, message: &UciMessage) -> bool {
+        self.handlers.contains_key(&message.kind())
+    }
+
+    /// Registers the `handler` for the messages of the `kind`, replacing the previous one.
+    pub fn on<F: FnMut(&UciMessage) + 'a>(mut self, kind: UciMessageKind, handler: F) -> MessageDispatcher<'a> {
+        self.handlers.insert(kind, Box::new(handler));
+        self
+    }
+}
+
+impl<'a> Debug for MessageDispatcher<'a> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("MessageDispatcher")
+            .field("handlers", &self.handlers.keys().collect::<Vec<&UciMessageKind>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::parser::parse_with_unknown;
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        let log = RefCell::new(Vec::new());
+
+        let mut dispatcher = MessageDispatcher::new()
+            .on_set_option(|m| log.borrow_mut().push(format!("option {}", m)))
+            .on_go(|_| log.borrow_mut().push(String::from("go")))
+            .on_unknown(|_| log.borrow_mut().push(String::from("unknown")));
+
+        let messages = parse_with_unknown("setoption name Hash value 32\ngo infinite\nstop\nfoo\n");
+        let handled: Vec<bool> = messages.iter().map(|m| dispatcher.dispatch(m)).collect();
+
+        assert_eq!(handled, vec![true, true, false, true]);
+        assert!(dispatcher.handles(&UciMessage::go()));
+        assert!(!dispatcher.handles(&UciMessage::Stop));
+        drop(dispatcher);
+
+        assert_eq!(*log.borrow(), vec!["option setoption name Hash value 32", "go", "unknown"]);
+    }
+
+    #[test]
+    fn test_fallback_and_replacement() {
+        let mut calls = Vec::new();
+        {
+            let mut dispatcher = MessageDispatcher::new()
+                .on_stop(|_| panic!("replaced"))
+                .on_stop(|_| {})
+                .fallback(|m| calls.push(m.clone()));
+
+            assert!(dispatcher.dispatch(&UciMessage::Stop));
+            assert!(dispatcher.dispatch(&UciMessage::Quit));
+        }
+
+        assert_eq!(calls, vec![UciMessage::Quit]);
+    }
+}
@@ -0,0 +1,414 @@
+//! The `engine` module contains the `UciEngine` trait and the `run` loop, which together form a skeleton for
+//! implementing a UCI chess engine.
+//!
+//! The `run` loop reads the GUI's messages line by line, parses them and calls the corresponding `UciEngine`
+//! callback. Messages to the GUI are sent through the `EngineOutput`, which serializes and flushes them. The
+//! `EngineOutput` can be cloned and sent to another thread, so that a search can run in the background while the
+//! loop keeps handling `stop` and `ponderhit`.
+
+use std::io::{BufRead, Result as IoResult, Write};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+
+use crate::parser::parse_one;
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+use crate::uci::{Serializable, UciFen, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+
+/// The handle through which an engine sends messages to the GUI. Each message is written on its own line and the
+/// output is flushed immediately, as required by the UCI protocol.
+///
+/// Cloning the `EngineOutput` produces another handle to the same output.
+#[derive(Clone)]
+pub struct EngineOutput {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl EngineOutput {
+    /// Creates an `EngineOutput` writing to `writer`, usually the standard output.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> EngineOutput {
+        EngineOutput {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Serializes and sends the `message`.
+    pub fn send(&self, message: &UciMessage) -> IoResult<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", message.serialize())?;
+        writer.flush()
+    }
+
+    /// Sends all of the `messages`, in order.
+    pub fn send_all<'a, I: IntoIterator<Item=&'a UciMessage>>(&self, messages: I) -> IoResult<()> {
+        for m in messages {
+            self.send(m)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Answers the handshake messages on behalf of an engine that runs its own loop instead of implementing `UciEngine`:
+/// `uci` is answered with the `id`, `option` and `uciok` sequence, and `isready` with `readyok`. All other messages
+/// are handed back to the caller.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{UciMessage, UciOptionConfig};
+/// use vampirc_uci::engine::{EngineOutput, Handshake};
+///
+/// let handshake = Handshake::new("My Engine", "Me")
+///     .option(UciOptionConfig::Check { name: String::from("Ponder"), default: Some(false) });
+/// let output = EngineOutput::new(std::io::sink());
+///
+/// assert_eq!(handshake.handle_line("uci", &output).unwrap(), None);
+/// assert_eq!(handshake.handle_line("stop", &output).unwrap(), Some(UciMessage::Stop));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Handshake {
+    name: String,
+    author: String,
+    options: Vec<UciOptionConfig>,
+}
+
+impl Handshake {
+    /// Creates a `Handshake` for the engine with the specified name and author, with no options.
+    pub fn new(name: &str, author: &str) -> Handshake {
+        Handshake {
+            name: name.to_string(),
+            author: author.to_string(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Adds an option to announce in reply to `uci`.
+    pub fn option(mut self, option: UciOptionConfig) -> Handshake {
+        self.options.push(option);
+        self
+    }
+
+    /// Adds several options to announce in reply to `uci`.
+    pub fn options<I: IntoIterator<Item=UciOptionConfig>>(mut self, options: I) -> Handshake {
+        self.options.extend(options);
+        self
+    }
+
+    /// Returns the replies to the `message` if it is a handshake message (`uci` or `isready`), or `None` otherwise.
+    pub fn replies(&self, message: &UciMessage) -> Option<Vec<UciMessage>> {
+        match message {
+            UciMessage::Uci => {
+                let mut replies = vec![UciMessage::id_name(&self.name), UciMessage::id_author(&self.author)];
+                replies.extend(self.options.iter().cloned().map(UciMessage::Option));
+                replies.push(UciMessage::UciOk);
+                Some(replies)
+            }
+            UciMessage::IsReady => Some(vec![UciMessage::ReadyOk]),
+            _ => None
+        }
+    }
+
+    /// Answers the `message` if it is a handshake message and returns `None`, or returns the message back if it is
+    /// not.
+    pub fn handle(&self, message: UciMessage, output: &EngineOutput) -> IoResult<Option<UciMessage>> {
+        match self.replies(&message) {
+            Some(replies) => {
+                output.send_all(&replies)?;
+                Ok(None)
+            }
+            None => Ok(Some(message))
+        }
+    }
+
+    /// Parses the `line` and handles the resulting message as `handle` does. Blank lines result in `None`.
+    pub fn handle_line(&self, line: &str, output: &EngineOutput) -> IoResult<Option<UciMessage>> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+
+        self.handle(parse_one(line), output)
+    }
+}
+
+/// A UCI chess engine. The `run` loop calls the callback corresponding to each message it receives from the GUI.
+///
+/// Apart from `name` and `author`, all of the methods have default implementations that do nothing, so an engine
+/// only needs to implement the ones it cares about. The replies mandated by the protocol (`id`, `option` and `uciok`
+/// after `uci`, `readyok` after `isready`) are sent by the `run` loop itself.
+pub trait UciEngine {
+    /// The name of the engine, sent as `id name`.
+    fn name(&self) -> String;
+
+    /// The author of the engine, sent as `id author`.
+    fn author(&self) -> String;
+
+    /// The options the engine supports, sent as `option` messages in reply to `uci`.
+    fn options(&self) -> Vec<UciOptionConfig> {
+        Vec::new()
+    }
+
+    /// Called upon `uci`, after the `id` and `option` messages have been sent but before `uciok` is.
+    fn on_uci(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `debug on` or `debug off`.
+    fn on_debug(&mut self, _on: bool, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `isready`, before `readyok` is sent. An engine that is still initializing should block here until
+    /// it is done.
+    fn on_is_ready(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `setoption`.
+    fn on_set_option(&mut self, _name: &str, _value: Option<&str>, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `register`.
+    fn on_register(&mut self, _later: bool, _name: Option<&str>, _code: Option<&str>, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `ucinewgame`.
+    fn on_new_game(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `position`.
+    #[cfg(not(feature = "chess"))]
+    fn on_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, _moves: &[UciMove], _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `position`.
+    #[cfg(feature = "chess")]
+    fn on_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, _moves: &[ChessMove], _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `go`. The engine should eventually reply with `bestmove`; a search that takes longer than an
+    /// instant should run on another thread, with a clone of the `output`, so that `stop` can be received.
+    fn on_go(&mut self, _time_control: Option<&UciTimeControl>, _search_control: Option<&UciSearchControl>, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `stop`.
+    fn on_stop(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `ponderhit`.
+    fn on_ponder_hit(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon `quit`, after which the `run` loop ends.
+    fn on_quit(&mut self, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Called upon any other message, including the unrecognized ones.
+    fn on_unknown(&mut self, _message: &UciMessage, _output: &EngineOutput) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+/// Runs the engine: reads the GUI's messages from `input` line by line and calls the `engine`'s callbacks, until
+/// `quit` is received or the `input` is exhausted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io;
+/// use vampirc_uci::engine::{run, UciEngine};
+///
+/// struct MyEngine;
+///
+/// impl UciEngine for MyEngine {
+///     fn name(&self) -> String {
+///         String::from("My Engine")
+///     }
+///
+///     fn author(&self) -> String {
+///         String::from("Me")
+///     }
+/// }
+///
+/// run(&mut MyEngine, io::stdin().lock(), io::stdout()).unwrap();
+/// ```
+pub fn run<E: UciEngine, R: BufRead, W: Write + Send + 'static>(engine: &mut E, input: R, output: W) -> IoResult<()> {
+    run_with_output(engine, input, &EngineOutput::new(output))
+}
+
+/// Like `run`, except that the messages are sent through an existing `EngineOutput`.
+pub fn run_with_output<E: UciEngine, R: BufRead>(engine: &mut E, input: R, output: &EngineOutput) -> IoResult<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if !dispatch(engine, parse_one(&line), output)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Calls the callback corresponding to the `message`. Returns `false` if the `run` loop should end.
+fn dispatch<E: UciEngine>(engine: &mut E, message: UciMessage, output: &EngineOutput) -> IoResult<bool> {
+    match message {
+        UciMessage::Uci => {
+            output.send(&UciMessage::id_name(&engine.name()))?;
+            output.send(&UciMessage::id_author(&engine.author()))?;
+            for o in engine.options() {
+                output.send(&UciMessage::Option(o))?;
+            }
+            engine.on_uci(output)?;
+            output.send(&UciMessage::UciOk)?;
+        }
+        UciMessage::Debug(on) => engine.on_debug(on, output)?,
+        UciMessage::IsReady => {
+            engine.on_is_ready(output)?;
+            output.send(&UciMessage::ReadyOk)?;
+        }
+        UciMessage::SetOption { name, value } => engine.on_set_option(&name, value.as_deref(), output)?,
+        UciMessage::Register { later, name, code } => {
+            engine.on_register(later, name.as_deref(), code.as_deref(), output)?
+        }
+        UciMessage::UciNewGame => engine.on_new_game(output)?,
+        UciMessage::Position { startpos, fen, moves } => engine.on_position(startpos, fen.as_ref(), &moves, output)?,
+        UciMessage::Go { time_control, search_control } => {
+            engine.on_go(time_control.as_ref(), search_control.as_ref(), output)?
+        }
+        UciMessage::Stop => engine.on_stop(output)?,
+        UciMessage::PonderHit => engine.on_ponder_hit(output)?,
+        UciMessage::Quit => {
+            engine.on_quit(output)?;
+            return Ok(false);
+        }
+        m => engine.on_unknown(&m, output)?,
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::testing::SharedBuffer;
+    use crate::uci::UciInfoAttribute;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestEngine {
+        options: Vec<(String, Option<String>)>,
+        moves: usize,
+        new_games: u32,
+        unknown: u32,
+    }
+
+    impl UciEngine for TestEngine {
+        fn name(&self) -> String {
+            String::from("Test Engine")
+        }
+
+        fn author(&self) -> String {
+            String::from("Tester")
+        }
+
+        fn options(&self) -> Vec<UciOptionConfig> {
+            vec![UciOptionConfig::Check { name: String::from("Nullmove"), default: Some(true) }]
+        }
+
+        fn on_set_option(&mut self, name: &str, value: Option<&str>, _output: &EngineOutput) -> IoResult<()> {
+            self.options.push((name.to_string(), value.map(String::from)));
+            Ok(())
+        }
+
+        fn on_new_game(&mut self, _output: &EngineOutput) -> IoResult<()> {
+            self.new_games += 1;
+            Ok(())
+        }
+
+        #[cfg(not(feature = "chess"))]
+        fn on_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, moves: &[UciMove], _output: &EngineOutput) -> IoResult<()> {
+            self.moves = moves.len();
+            Ok(())
+        }
+
+        #[cfg(feature = "chess")]
+        fn on_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, moves: &[ChessMove], _output: &EngineOutput) -> IoResult<()> {
+            self.moves = moves.len();
+            Ok(())
+        }
+
+        fn on_go(&mut self, _tc: Option<&UciTimeControl>, _sc: Option<&UciSearchControl>, output: &EngineOutput) -> IoResult<()> {
+            output.send(&UciMessage::Info(vec![UciInfoAttribute::Depth(1)]))?;
+            output.send(&crate::parser::parse_one("bestmove e7e5"))
+        }
+
+        fn on_unknown(&mut self, _message: &UciMessage, _output: &EngineOutput) -> IoResult<()> {
+            self.unknown += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_session() {
+        let input = "uci\n\nsetoption name Nullmove value false\nisready\nucinewgame\nposition startpos moves e2e4\n\
+                     go movetime 100\nfoo bar\nquit\nisready\n";
+        let buffer = SharedBuffer::default();
+        let mut engine = TestEngine::default();
+
+        run(&mut engine, Cursor::new(input), buffer.clone()).unwrap();
+
+        assert_eq!(buffer.contents(), "id name Test Engine\nid author Tester\noption name Nullmove type check default true\n\
+                                       uciok\nreadyok\ninfo depth 1\nbestmove e7e5\n");
+        assert_eq!(engine.options, vec![(String::from("Nullmove"), Some(String::from("false")))]);
+        assert_eq!(engine.new_games, 1);
+        assert_eq!(engine.moves, 1);
+        assert_eq!(engine.unknown, 1);
+    }
+
+    #[test]
+    fn test_handshake() {
+        let buffer = SharedBuffer::default();
+        let output = EngineOutput::new(buffer.clone());
+        let handshake = Handshake::new("Test Engine", "Tester").options(vec![
+            UciOptionConfig::Check { name: String::from("Nullmove"), default: Some(true) },
+            UciOptionConfig::Button { name: String::from("Clear Hash") },
+        ]);
+
+        let forwarded: Vec<UciMessage> = ["uci", "isready", "", "ucinewgame", "isready", "quit"]
+            .iter()
+            .filter_map(|l| handshake.handle_line(l, &output).unwrap())
+            .collect();
+
+        assert_eq!(forwarded, vec![UciMessage::UciNewGame, UciMessage::Quit]);
+        assert_eq!(buffer.contents(), "id name Test Engine\nid author Tester\noption name Nullmove type check default true\n\
+                                       option name Clear Hash type button\nuciok\nreadyok\nreadyok\n");
+    }
+
+    #[test]
+    fn test_output_clone_shares_writer() {
+        let buffer = SharedBuffer::default();
+        let output = EngineOutput::new(buffer.clone());
+        let clone = output.clone();
+
+        std::thread::spawn(move || clone.send(&UciMessage::ReadyOk).unwrap()).join().unwrap();
+        output.send_all(&[UciMessage::UciOk]).unwrap();
+
+        assert_eq!(buffer.contents(), "readyok\nuciok\n");
+    }
+}
@@ -0,0 +1,344 @@
+//! The `engine_match` module contains a minimal driver for playing games between two engines, available with the
+//! `chess` feature. It alternates the `position` and `go` messages between the engines, keeps their clocks, checks
+//! the legality of their moves and detects the end of the game.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Result as IoResult;
+use std::time::{Duration, Instant};
+
+use chess::{Board, BoardStatus, ChessMove, Color, Game};
+use chrono::Duration as ChronoDuration;
+
+use crate::board::position_from_board;
+use crate::process::EngineProcess;
+use crate::uci::{UciMessage, UciSearchControl, UciTimeControl};
+
+/// The limit imposed on the engines' searches.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum MatchTimeControl {
+    /// Each side has a clock starting at `base` time, which gains the `increment` after every move. A side that
+    /// runs out of time loses.
+    Clock {
+        /// The time on each side's clock at the start of the game.
+        base: Duration,
+
+        /// The time added to the clock after every move.
+        increment: Duration,
+    },
+
+    /// Each move is searched for a fixed time (`go movetime`). The time is not enforced.
+    MoveTime(Duration),
+
+    /// Each move is searched to a fixed depth (`go depth`).
+    Depth(u8),
+}
+
+/// The settings of the games played by the driver.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MatchSettings {
+    /// The limit imposed on the engines' searches.
+    pub time_control: MatchTimeControl,
+
+    /// The number of full moves after which the game is adjudicated a draw.
+    pub max_moves: u32,
+
+    /// The starting position of the games.
+    pub start: Board,
+}
+
+impl Default for MatchSettings {
+    /// 100 ms per move, 200 moves at most, from the standard starting position.
+    fn default() -> Self {
+        MatchSettings {
+            time_control: MatchTimeControl::MoveTime(Duration::from_millis(100)),
+            max_moves: 200,
+            start: Board::default(),
+        }
+    }
+}
+
+/// The outcome of a game.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Outcome {
+    /// White won.
+    WhiteWins,
+
+    /// Black won.
+    BlackWins,
+
+    /// The game was drawn.
+    Draw,
+}
+
+impl Outcome {
+    fn win_for(color: Color) -> Outcome {
+        match color {
+            Color::White => Outcome::WhiteWins,
+            Color::Black => Outcome::BlackWins,
+        }
+    }
+}
+
+impl Display for Outcome {
+    /// Formats the outcome as in PGN: `1-0`, `0-1` or `1/2-1/2`.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Outcome::WhiteWins => write!(f, "1-0"),
+            Outcome::BlackWins => write!(f, "0-1"),
+            Outcome::Draw => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+/// The reason a game ended.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Termination {
+    /// The side to move was checkmated.
+    Checkmate,
+
+    /// The side to move was stalemated.
+    Stalemate,
+
+    /// The game was drawn by threefold repetition or the fifty-move rule.
+    DrawByRule,
+
+    /// The game reached the maximum number of moves.
+    MoveLimit,
+
+    /// The side to move ran out of time.
+    TimeForfeit,
+
+    /// The side to move played an illegal move.
+    IllegalMove(ChessMove),
+}
+
+/// A game played by the driver.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GameRecord {
+    /// The starting position.
+    pub start: Board,
+
+    /// The moves played.
+    pub moves: Vec<ChessMove>,
+
+    /// The outcome of the game.
+    pub outcome: Outcome,
+
+    /// The reason the game ended.
+    pub termination: Termination,
+}
+
+/// The result of a match, from the point of view of the first engine.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct MatchResult {
+    /// The games won by the first engine.
+    pub wins: u32,
+
+    /// The games lost by the first engine.
+    pub losses: u32,
+
+    /// The games drawn.
+    pub draws: u32,
+
+    /// The games played, in order. The first engine plays White in the even games (counting from zero) and Black in
+    /// the odd ones.
+    pub games: Vec<GameRecord>,
+}
+
+impl MatchResult {
+    /// The score of the first engine: a point per win and half a point per draw.
+    pub fn score(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2.0
+    }
+}
+
+/// Plays a game between the `white` and `black` engines, which must have completed the `uci` handshake. I/O errors,
+/// such as an engine exiting mid-game, abort the game.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::process::EngineProcess;
+/// use vampirc_uci::engine_match::{play_game, MatchSettings};
+///
+/// let mut white = EngineProcess::spawn("stockfish", &[] as &[&str]).unwrap();
+/// let mut black = EngineProcess::spawn("stockfish", &[] as &[&str]).unwrap();
+/// white.handshake().unwrap();
+/// black.handshake().unwrap();
+///
+/// let game = play_game(&mut white, &mut black, &MatchSettings::default()).unwrap();
+/// println!("{} ({:?})", game.outcome, game.termination);
+/// ```
+pub fn play_game(white: &mut EngineProcess, black: &mut EngineProcess, settings: &MatchSettings) -> IoResult<GameRecord> {
+    for engine in [&mut *white, &mut *black] {
+        engine.send(&UciMessage::UciNewGame)?;
+        engine.is_ready()?;
+    }
+
+    let (base, increment) = match settings.time_control {
+        MatchTimeControl::Clock { base, increment } => (base, increment),
+        _ => (Duration::ZERO, Duration::ZERO),
+    };
+    let mut clocks = [base, base];
+
+    let mut game = Game::new_with_board(settings.start);
+    let mut moves = Vec::new();
+
+    let end = |moves: Vec<ChessMove>, outcome, termination| Ok(GameRecord {
+        start: settings.start,
+        moves,
+        outcome,
+        termination,
+    });
+
+    loop {
+        let board = game.current_position();
+        let side = board.side_to_move();
+
+        match board.status() {
+            BoardStatus::Checkmate => return end(moves, Outcome::win_for(!side), Termination::Checkmate),
+            BoardStatus::Stalemate => return end(moves, Outcome::Draw, Termination::Stalemate),
+            BoardStatus::Ongoing => {}
+        }
+        if game.can_declare_draw() {
+            return end(moves, Outcome::Draw, Termination::DrawByRule);
+        }
+        if moves.len() as u32 >= settings.max_moves * 2 {
+            return end(moves, Outcome::Draw, Termination::MoveLimit);
+        }
+
+        let engine = match side {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+
+        engine.send(&position_from_board(&settings.start, &moves))?;
+        engine.send(&go_message(settings.time_control, clocks))?;
+
+        let started = Instant::now();
+        let reply = engine.recv_until(|m| matches!(m, UciMessage::BestMove { .. }))?;
+        let elapsed = started.elapsed();
+
+        if let MatchTimeControl::Clock { .. } = settings.time_control {
+            let clock = &mut clocks[side.to_index()];
+            if elapsed > *clock {
+                return end(moves, Outcome::win_for(!side), Termination::TimeForfeit);
+            }
+            *clock = *clock - elapsed + increment;
+        }
+
+        let best_move = match reply.last() {
+            Some(UciMessage::BestMove { best_move, .. }) => *best_move,
+            _ => unreachable!("recv_until returns the matching message last"),
+        };
+
+        if !game.make_move(best_move) {
+            return end(moves, Outcome::win_for(!side), Termination::IllegalMove(best_move));
+        }
+        moves.push(best_move);
+    }
+}
+
+/// Plays a match of `games` games between the `first` and `second` engines, which must have completed the `uci`
+/// handshake. The engines alternate colors, the `first` playing White in the first game.
+pub fn play_match(first: &mut EngineProcess, second: &mut EngineProcess, games: u32, settings: &MatchSettings) -> IoResult<MatchResult> {
+    let mut result = MatchResult::default();
+
+    for i in 0..games {
+        let first_is_white = i % 2 == 0;
+        let game = if first_is_white {
+            play_game(first, second, settings)?
+        } else {
+            play_game(second, first, settings)?
+        };
+
+        match (game.outcome, first_is_white) {
+            (Outcome::Draw, _) => result.draws += 1,
+            (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => result.wins += 1,
+            _ => result.losses += 1,
+        }
+        result.games.push(game);
+    }
+
+    Ok(result)
+}
+
+fn go_message(time_control: MatchTimeControl, clocks: [Duration; 2]) -> UciMessage {
+    let millis = |d: Duration| ChronoDuration::milliseconds(d.as_millis() as i64);
+
+    match time_control {
+        MatchTimeControl::Clock { increment, .. } => UciMessage::Go {
+            time_control: Some(UciTimeControl::TimeLeft {
+                white_time: Some(millis(clocks[Color::White.to_index()])),
+                black_time: Some(millis(clocks[Color::Black.to_index()])),
+                white_increment: Some(millis(increment)),
+                black_increment: Some(millis(increment)),
+                moves_to_go: None,
+            }),
+            search_control: None,
+        },
+        MatchTimeControl::MoveTime(t) => UciMessage::go_movetime(millis(t)),
+        MatchTimeControl::Depth(d) => UciMessage::Go {
+            time_control: None,
+            search_control: Some(UciSearchControl::depth(d)),
+        },
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// An engine that plays the `moves`, one per `go`, regardless of the position.
+    fn scripted_engine(moves: &[&str]) -> EngineProcess {
+        let script = format!(r#"
+            set -- {}
+            while read -r line; do
+                case "$line" in
+                    uci) echo "uciok";;
+                    isready) echo "readyok";;
+                    go*) echo "info depth 1"; echo "bestmove $1"; shift;;
+                    quit) exit 0;;
+                esac
+            done
+        "#, moves.join(" "));
+
+        let mut engine = EngineProcess::spawn("sh", &["-c", &script]).unwrap();
+        engine.handshake().unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let mut white = scripted_engine(&["f2f3", "g2g4"]);
+        let mut black = scripted_engine(&["e7e5", "d8h4"]);
+
+        let settings = MatchSettings {
+            time_control: MatchTimeControl::Clock { base: Duration::from_secs(60), increment: Duration::ZERO },
+            ..MatchSettings::default()
+        };
+        let game = play_game(&mut white, &mut black, &settings).unwrap();
+
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.outcome, Outcome::BlackWins);
+        assert_eq!(game.termination, Termination::Checkmate);
+        assert_eq!(game.outcome.to_string(), "0-1");
+    }
+
+    #[test]
+    fn test_illegal_move_and_move_limit() {
+        let mut white = scripted_engine(&["e2e4", "e4e5"]);
+        let mut black = scripted_engine(&["e7e5"]);
+        let game = play_game(&mut white, &mut black, &MatchSettings::default()).unwrap();
+        assert_eq!(game.outcome, Outcome::BlackWins);
+        assert_eq!(game.termination, Termination::IllegalMove(ChessMove::new(chess::Square::E4, chess::Square::E5, None)));
+
+        let mut white = scripted_engine(&["g1f3", "f3g1", "g1f3", "f3g1"]);
+        let mut black = scripted_engine(&["g8f6", "f6g8", "g8f6", "f6g8"]);
+        let settings = MatchSettings { max_moves: 1, ..MatchSettings::default() };
+        let result = play_match(&mut white, &mut black, 1, &settings).unwrap();
+        assert_eq!((result.wins, result.losses, result.draws), (0, 0, 1));
+        assert_eq!(result.games[0].termination, Termination::MoveLimit);
+        assert_eq!(result.score(), 0.5);
+    }
+}
@@ -0,0 +1,415 @@
+//! The `epd` module reads and writes records in the [Extended Position Description](https://www.chessprogramming.org/Extended_Position_Description)
+//! format, in which test suites such as WAC and STS are distributed. A record is a position (the first four fields of
+//! a FEN) followed by operations, each an opcode with its operands and terminated by a semicolon:
+//!
+//! ```text
+//! 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+//! ```
+//!
+//! The moves of the `bm` (best move) and `am` (avoid move) operations are in the standard algebraic notation; with the
+//! `chess` feature, `EpdRecord::resolve_moves(..)` turns them into moves.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error as IoError, ErrorKind, Result as IoResult};
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+
+#[cfg(feature = "chess")]
+use crate::board::{position_to_board, san_to_move, PositionError};
+use crate::uci::{UciFen, UciMessage};
+
+/// An error describing why a line is not a valid EPD record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum EpdError {
+    /// The record does not start with the four fields of the position.
+    MissingFields,
+
+    /// The side to move is neither `w` nor `b`.
+    InvalidSideToMove(String),
+
+    /// A quoted operand is not closed.
+    UnterminatedString,
+
+    /// An operation has no opcode, as in `bm Qg6; ;`.
+    MissingOpcode,
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            EpdError::MissingFields => write!(f, "the record does not start with a position"),
+            EpdError::InvalidSideToMove(side) => write!(f, "invalid side to move: {}", side),
+            EpdError::UnterminatedString => write!(f, "unterminated string operand"),
+            EpdError::MissingOpcode => write!(f, "an operation is missing its opcode"),
+        }
+    }
+}
+
+impl StdError for EpdError {}
+
+/// An operation of an EPD record: an opcode, such as `bm` or `id`, and its operands.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct EpdOperation {
+    /// The opcode.
+    pub opcode: String,
+
+    /// The operands, without the quotes of the string operands.
+    pub operands: Vec<String>,
+}
+
+impl EpdOperation {
+    /// Creates an operation from its opcode and operands.
+    pub fn new(opcode: &str, operands: &[&str]) -> EpdOperation {
+        EpdOperation {
+            opcode: opcode.to_string(),
+            operands: operands.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+}
+
+impl Display for EpdOperation {
+    /// Writes the operation, terminated by a semicolon. The operands of the `id` and the comment (`c0` to `c9`)
+    /// operations are quoted, as are the ones that would not read back otherwise.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.opcode)?;
+
+        let quoted = self.opcode == "id" || (self.opcode.len() == 2 && self.opcode.starts_with('c')
+            && self.opcode.as_bytes()[1].is_ascii_digit());
+        for operand in &self.operands {
+            if quoted || operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"') {
+                write!(f, " \"{}\"", operand.replace('"', "'"))?;
+            } else {
+                write!(f, " {}", operand)?;
+            }
+        }
+
+        write!(f, ";")
+    }
+}
+
+/// A record of an EPD file: a position and its operations.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::Serializable;
+/// use vampirc_uci::epd::EpdRecord;
+///
+/// let record: EpdRecord = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";"
+///     .parse().unwrap();
+///
+/// assert_eq!(record.id(), Some("WAC.001"));
+/// assert_eq!(record.best_moves(), vec!["Qg6"]);
+/// assert_eq!(record.to_position().serialize(),
+///            "position fen 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct EpdRecord {
+    /// The first four fields of the FEN: the piece placement, the side to move, the castling rights and the en
+    /// passant square.
+    pub position: String,
+
+    /// The operations, in the order they appear in the record.
+    pub operations: Vec<EpdOperation>,
+}
+
+impl EpdRecord {
+    /// Creates a record without operations for a FEN. If the FEN includes the halfmove clock and the fullmove
+    /// number, they are kept as the `hmvc` and `fmvn` operations.
+    pub fn from_fen(fen: &UciFen) -> EpdRecord {
+        let fields: Vec<&str> = fen.as_str().split_whitespace().collect();
+        let mut record = EpdRecord {
+            position: fields[..fields.len().min(4)].join(" "),
+            operations: Vec::new(),
+        };
+
+        if fen.has_counters() {
+            record.set("hmvc", &[&fen.halfmove_clock().to_string()]);
+            record.set("fmvn", &[&fen.fullmove_number().to_string()]);
+        }
+
+        record
+    }
+
+    /// Returns the first operation with the `opcode`.
+    pub fn operation(&self, opcode: &str) -> Option<&EpdOperation> {
+        self.operations.iter().find(|o| o.opcode == opcode)
+    }
+
+    /// Returns the operands of the first operation with the `opcode`, or an empty list if there is none.
+    pub fn operands(&self, opcode: &str) -> Vec<&str> {
+        self.operation(opcode).map(|o| o.operands.iter().map(String::as_str).collect()).unwrap_or_default()
+    }
+
+    /// Sets the operands of the operation with the `opcode`, replacing the existing operation or appending a new one.
+    pub fn set(&mut self, opcode: &str, operands: &[&str]) {
+        let operation = EpdOperation::new(opcode, operands);
+        match self.operations.iter_mut().find(|o| o.opcode == opcode) {
+            Some(existing) => *existing = operation,
+            None => self.operations.push(operation),
+        }
+    }
+
+    /// Removes the operation with the `opcode`, returning it.
+    pub fn remove(&mut self, opcode: &str) -> Option<EpdOperation> {
+        let index = self.operations.iter().position(|o| o.opcode == opcode)?;
+        Some(self.operations.remove(index))
+    }
+
+    /// Returns the identifier of the record (the `id` operation).
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id").first().copied()
+    }
+
+    /// Returns the best moves (the `bm` operation), in the standard algebraic notation.
+    pub fn best_moves(&self) -> Vec<&str> {
+        self.operands("bm")
+    }
+
+    /// Returns the moves to avoid (the `am` operation), in the standard algebraic notation.
+    pub fn avoid_moves(&self) -> Vec<&str> {
+        self.operands("am")
+    }
+
+    /// Returns the evaluation of the position in centipawns, from the side to move's point of view (the `ce`
+    /// operation).
+    pub fn centipawn_evaluation(&self) -> Option<i32> {
+        self.operands("ce").first().and_then(|ce| ce.parse().ok())
+    }
+
+    /// Returns the number of moves to a forced mate (the `dm` operation).
+    pub fn direct_mate(&self) -> Option<u32> {
+        self.operands("dm").first().and_then(|dm| dm.parse().ok())
+    }
+
+    /// Returns the FEN of the position, with the halfmove clock and the fullmove number of the `hmvc` and `fmvn`
+    /// operations, or `0` and `1` if they are absent.
+    pub fn fen(&self) -> UciFen {
+        let counter = |opcode: &str, default: u32| {
+            self.operands(opcode).first().and_then(|c| c.parse().ok()).unwrap_or(default)
+        };
+
+        UciFen(format!("{} {} {}", self.position, counter("hmvc", 0), counter("fmvn", 1)))
+    }
+
+    /// Returns the `position` message that sets up the position of the record.
+    pub fn to_position(&self) -> UciMessage {
+        UciMessage::Position {
+            startpos: false,
+            fen: Some(self.fen()),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Resolves the moves of the operation with the `opcode` (usually `bm` or `am`), which are in the standard
+    /// algebraic notation, on the position of the record.
+    #[cfg(feature = "chess")]
+    pub fn resolve_moves(&self, opcode: &str) -> Result<Vec<ChessMove>, PositionError> {
+        let board = position_to_board(&self.to_position())?;
+        self.operands(opcode).iter().map(|san| san_to_move(&board, san)).collect()
+    }
+}
+
+impl FromStr for EpdRecord {
+    type Err = EpdError;
+
+    fn from_str(s: &str) -> Result<EpdRecord, EpdError> {
+        let mut rest = s.trim();
+        let mut fields = Vec::with_capacity(4);
+        while fields.len() < 4 {
+            let field = rest.split_whitespace().next().ok_or(EpdError::MissingFields)?;
+            fields.push(field);
+            rest = rest[field.len()..].trim_start();
+        }
+
+        if fields[1] != "w" && fields[1] != "b" {
+            return Err(EpdError::InvalidSideToMove(fields[1].to_string()));
+        }
+
+        Ok(EpdRecord {
+            position: fields.join(" "),
+            operations: parse_operations(rest)?,
+        })
+    }
+}
+
+impl Display for EpdRecord {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.position)?;
+        for operation in &self.operations {
+            write!(f, " {}", operation)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits the operations of a record into opcodes and operands. The semicolon after the last operation may be
+/// missing.
+fn parse_operations(s: &str) -> Result<Vec<EpdOperation>, EpdError> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        match chars.next() {
+            None => {
+                if !tokens.is_empty() {
+                    let opcode = tokens.remove(0);
+                    operations.push(EpdOperation { opcode, operands: tokens });
+                }
+                break;
+            }
+            Some(';') => {
+                if tokens.is_empty() {
+                    return Err(EpdError::MissingOpcode);
+                }
+
+                let opcode = tokens.remove(0);
+                operations.push(EpdOperation { opcode, operands: std::mem::take(&mut tokens) });
+            }
+            Some('"') => {
+                let mut operand = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => operand.push(c),
+                        None => return Err(EpdError::UnterminatedString),
+                    }
+                }
+                tokens.push(operand);
+            }
+            Some(c) => {
+                let mut token = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(operations)
+}
+
+/// Reads the records of an EPD file, a record per line. Blank lines are skipped. A malformed record results in an
+/// error of the `InvalidData` kind.
+pub fn load<R: BufRead>(input: R) -> IoResult<Vec<EpdRecord>> {
+    let mut records = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = line.parse().map_err(|e| {
+            IoError::new(ErrorKind::InvalidData, format!("malformed record on line {}: {}", index + 1, e))
+        })?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Reads the records of the EPD file at `path`. See `load(..)`.
+pub fn load_file<P: AsRef<Path>>(path: P) -> IoResult<Vec<EpdRecord>> {
+    load(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        let record: EpdRecord = "r1b2rk1/2q1b1pp/p2ppn2/1p6/3QP3/1BN1B3/PPP3PP/R4RK1 w - -  bm Nd5 Bxe6;\tam f5; \
+                                 id \"STS(v1.0) Undermine.001\"; c0 \"Nd5=10, Bxe6=8\"; ce +35; hmvc 2; fmvn 17"
+            .parse()
+            .unwrap();
+
+        assert_eq!(record.position, "r1b2rk1/2q1b1pp/p2ppn2/1p6/3QP3/1BN1B3/PPP3PP/R4RK1 w - -");
+        assert_eq!(record.operations.len(), 7);
+        assert_eq!(record.best_moves(), vec!["Nd5", "Bxe6"]);
+        assert_eq!(record.avoid_moves(), vec!["f5"]);
+        assert_eq!(record.id(), Some("STS(v1.0) Undermine.001"));
+        assert_eq!(record.operands("c0"), vec!["Nd5=10, Bxe6=8"]);
+        assert_eq!(record.centipawn_evaluation(), Some(35));
+        assert_eq!(record.direct_mate(), None);
+        assert_eq!(record.fen().as_str(), "r1b2rk1/2q1b1pp/p2ppn2/1p6/3QP3/1BN1B3/PPP3PP/R4RK1 w - - 2 17");
+
+        assert_eq!(record.to_string(), "r1b2rk1/2q1b1pp/p2ppn2/1p6/3QP3/1BN1B3/PPP3PP/R4RK1 w - - bm Nd5 Bxe6; am f5; \
+                                        id \"STS(v1.0) Undermine.001\"; c0 \"Nd5=10, Bxe6=8\"; ce +35; hmvc 2; fmvn 17;");
+        assert_eq!(record.to_string().parse::<EpdRecord>().unwrap(), record);
+
+        let bare: EpdRecord = "8/8/8/8/8/8/8/K6k b - -".parse().unwrap();
+        assert!(bare.operations.is_empty());
+        assert_eq!(bare.to_string(), "8/8/8/8/8/8/8/K6k b - -");
+    }
+
+    #[test]
+    fn test_parse_record_errors() {
+        assert_eq!("8/8/8/8/8/8/8/K6k b -".parse::<EpdRecord>(), Err(EpdError::MissingFields));
+        assert_eq!("8/8/8/8/8/8/8/K6k x - -".parse::<EpdRecord>(), Err(EpdError::InvalidSideToMove(String::from("x"))));
+        assert_eq!("8/8/8/8/8/8/8/K6k b - - id \"open;".parse::<EpdRecord>(), Err(EpdError::UnterminatedString));
+        assert_eq!("8/8/8/8/8/8/8/K6k b - - bm Kb2; ; id x;".parse::<EpdRecord>(), Err(EpdError::MissingOpcode));
+        assert_eq!(EpdError::MissingFields.to_string(), "the record does not start with a position");
+    }
+
+    #[test]
+    fn test_build_record() {
+        let mut record = EpdRecord::from_fen(&UciFen::from("4k3/8/8/8/8/8/8/4K2R w K - 3 40"));
+        assert_eq!(record.to_string(), "4k3/8/8/8/8/8/8/4K2R w K - hmvc 3; fmvn 40;");
+
+        record.set("bm", &["O-O"]);
+        record.set("id", &["castle"]);
+        record.set("hmvc", &["0"]);
+        assert_eq!(record.remove("fmvn").unwrap().operands, vec!["40"]);
+        assert_eq!(record.remove("fmvn"), None);
+        assert_eq!(record.to_string(), "4k3/8/8/8/8/8/8/4K2R w K - hmvc 0; bm O-O; id \"castle\";");
+        assert_eq!(record.to_position(), UciMessage::Position {
+            startpos: false,
+            fen: Some(UciFen::from("4k3/8/8/8/8/8/8/4K2R w K - 0 1")),
+            moves: Vec::new(),
+        });
+    }
+
+    #[test]
+    fn test_load() {
+        let records = load(Cursor::new("\n8/8/8/8/8/8/8/K6k b - - id \"1\";\n\n8/8/8/8/8/8/8/K6k w - - id \"2\";\n"))
+            .unwrap();
+        assert_eq!(records.iter().map(|r| r.id()).collect::<Vec<_>>(), vec![Some("1"), Some("2")]);
+
+        let error = load(Cursor::new("8/8/8/8/8/8/8/K6k b - -\nnonsense\n")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "malformed record on line 2: the record does not start with a position");
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_resolve_moves() {
+        use chess::Square;
+
+        let record: EpdRecord = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; am Kh1 Kh2;"
+            .parse()
+            .unwrap();
+        assert_eq!(record.resolve_moves("bm"), Ok(vec![ChessMove::new(Square::G3, Square::G6, None)]));
+        assert_eq!(record.resolve_moves("id"), Ok(vec![]));
+        assert_eq!(record.resolve_moves("am"), Err(PositionError::InvalidSan(String::from("Kh2"))));
+    }
+}
@@ -0,0 +1,292 @@
+//! The `error` module contains the errors of the crate: the `ParseError` returned by the strict parsing methods, and
+//! the `UciError`, which gathers the errors of all of the crate's parts (parsing, validation, the binary encoding,
+//! I/O with an engine, ...) for applications that want a single error type. The specific error is always available
+//! as the `source()` of a `UciError`, whose own message only names the kind of the error, so that reporters walking
+//! the chain of sources print each message once.
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+
+use pest::error::{Error as PestError, LineColLocation};
+#[cfg(feature = "json")]
+use serde_json::Error as JsonError;
+
+use crate::binary::DecodeError;
+#[cfg(feature = "chess")]
+use crate::board::PositionError;
+use crate::builder::BuilderError;
+use crate::epd::EpdError;
+use crate::options::OptionError;
+use crate::parser::Rule;
+use crate::ponder::PonderError;
+use crate::protocol::ProtocolViolation;
+
+/// An error describing why the input of `parse_strict(..)` is not valid UCI: where the problem is and what it is.
+///
+/// The detailed report of the underlying PEST parser, which points at the offending part of the line, is the
+/// `source()` of the error and is also available through `pest_error()`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse_strict;
+///
+/// let err = parse_strict("uci\nposition startpos moves e2e4 e7e9\n").unwrap_err();
+///
+/// assert_eq!((err.line(), err.column()), (2, 30));
+/// assert_eq!(err.text(), "position startpos moves e2e4 e7e9");
+/// assert_eq!(err.to_string(), "line 2, column 30: rank out of range 1–8: '9' in move 'e7e9'");
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ParseError {
+    error: Box<PestError<Rule>>,
+}
+
+impl ParseError {
+    /// Returns the number of the line the problem is on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.start().0
+    }
+
+    /// Returns the column, in characters and starting at 1, where the problem starts.
+    pub fn column(&self) -> usize {
+        self.start().1
+    }
+
+    /// Returns the description of the problem, without its location.
+    pub fn message(&self) -> Cow<'_, str> {
+        self.error.variant.message()
+    }
+
+    /// Returns the text of the offending line, without the line break.
+    pub fn text(&self) -> &str {
+        self.error.line().trim_end_matches(['\r', '\n'])
+    }
+
+    /// Returns the error reported by the PEST parser.
+    pub fn pest_error(&self) -> &PestError<Rule> {
+        &self.error
+    }
+
+    /// Returns the error reported by the PEST parser, consuming this error.
+    pub fn into_pest_error(self) -> PestError<Rule> {
+        *self.error
+    }
+
+    fn start(&self) -> (usize, usize) {
+        match self.error.line_col {
+            LineColLocation::Pos(start) | LineColLocation::Span(start, _) => start,
+        }
+    }
+}
+
+impl From<PestError<Rule>> for ParseError {
+    fn from(error: PestError<Rule>) -> Self {
+        ParseError { error: Box::new(error) }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "line {}, column {}: {}", self.line(), self.column(), self.message())
+    }
+}
+
+impl StdError for ParseError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Any error of the crate.
+#[derive(Debug)]
+pub enum UciError {
+    /// The input is not valid UCI.
+    Parse(ParseError),
+
+    /// A message could not be built.
+    Builder(BuilderError),
+
+    /// A `setoption` message does not match the declared options.
+    Option(OptionError),
+
+    /// A message violates the order of the protocol.
+    Protocol(ProtocolViolation),
+
+    /// A step of the pondering state machine was taken in the wrong state.
+    Ponder(PonderError),
+
+    /// A message could not be decoded from the binary encoding.
+    Decode(DecodeError),
+
+    /// A line is not a valid EPD record.
+    Epd(EpdError),
+
+    /// A `position` message does not describe a valid position.
+    #[cfg(feature = "chess")]
+    Position(PositionError),
+
+    /// A message could not be converted to or from JSON.
+    #[cfg(feature = "json")]
+    Json(JsonError),
+
+    /// The communication with an engine (or the reading of a file) failed.
+    Io(IoError),
+}
+
+impl Display for UciError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            UciError::Parse(..) => write!(f, "parse error"),
+            UciError::Builder(..) => write!(f, "cannot build the message"),
+            UciError::Option(..) => write!(f, "invalid option"),
+            UciError::Protocol(..) => write!(f, "protocol violation"),
+            UciError::Ponder(..) => write!(f, "pondering error"),
+            UciError::Decode(..) => write!(f, "cannot decode the message"),
+            UciError::Epd(..) => write!(f, "invalid EPD record"),
+            #[cfg(feature = "chess")]
+            UciError::Position(..) => write!(f, "invalid position"),
+            #[cfg(feature = "json")]
+            UciError::Json(..) => write!(f, "JSON error"),
+            UciError::Io(..) => write!(f, "I/O error"),
+        }
+    }
+}
+
+impl StdError for UciError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            UciError::Parse(e) => Some(e),
+            UciError::Builder(e) => Some(e),
+            UciError::Option(e) => Some(e),
+            UciError::Protocol(e) => Some(e),
+            UciError::Ponder(e) => Some(e),
+            UciError::Decode(e) => Some(e),
+            UciError::Epd(e) => Some(e),
+            #[cfg(feature = "chess")]
+            UciError::Position(e) => Some(e),
+            #[cfg(feature = "json")]
+            UciError::Json(e) => Some(e),
+            UciError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ParseError> for UciError {
+    fn from(e: ParseError) -> Self {
+        UciError::Parse(e)
+    }
+}
+
+impl From<PestError<Rule>> for UciError {
+    fn from(e: PestError<Rule>) -> Self {
+        UciError::Parse(ParseError::from(e))
+    }
+}
+
+impl From<BuilderError> for UciError {
+    fn from(e: BuilderError) -> Self {
+        UciError::Builder(e)
+    }
+}
+
+impl From<OptionError> for UciError {
+    fn from(e: OptionError) -> Self {
+        UciError::Option(e)
+    }
+}
+
+impl From<ProtocolViolation> for UciError {
+    fn from(e: ProtocolViolation) -> Self {
+        UciError::Protocol(e)
+    }
+}
+
+impl From<PonderError> for UciError {
+    fn from(e: PonderError) -> Self {
+        UciError::Ponder(e)
+    }
+}
+
+impl From<DecodeError> for UciError {
+    fn from(e: DecodeError) -> Self {
+        UciError::Decode(e)
+    }
+}
+
+impl From<EpdError> for UciError {
+    fn from(e: EpdError) -> Self {
+        UciError::Epd(e)
+    }
+}
+
+#[cfg(feature = "chess")]
+impl From<PositionError> for UciError {
+    fn from(e: PositionError) -> Self {
+        UciError::Position(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<JsonError> for UciError {
+    fn from(e: JsonError) -> Self {
+        UciError::Json(e)
+    }
+}
+
+impl From<IoError> for UciError {
+    fn from(e: IoError) -> Self {
+        UciError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::parser::parse_strict;
+    use crate::protocol::UciProtocolState;
+
+    use super::*;
+
+    fn check_position(transcript: &str) -> Result<(), UciError> {
+        let mut state = UciProtocolState::new();
+        for m in parse_strict(transcript)? {
+            state.consume(&m)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = parse_strict("isready\r\n\r\ngo depth x\r\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (3, 10));
+        assert_eq!(err.text(), "go depth x");
+        assert_eq!(err.source().unwrap().to_string(), err.pest_error().to_string());
+        assert!(err.to_string().starts_with("line 3, column 10: "));
+        assert_eq!(err.clone().into_pest_error(), *err.pest_error());
+    }
+
+    #[test]
+    fn test_uci_error_chain() {
+        assert!(check_position("uci\nuciok\nisready\n").is_ok());
+
+        let err = check_position("uci\nuciok\nbestmove e2e4\n").unwrap_err();
+        assert!(matches!(err, UciError::Protocol(..)));
+        assert_eq!(err.to_string(), "protocol violation");
+        assert!(!err.source().unwrap().to_string().is_empty());
+
+        let err = check_position("uci\nuciok\ngo depht 5\n").unwrap_err();
+        let parse_error = err.source().unwrap();
+        assert_eq!(err.to_string(), "parse error");
+        assert!(parse_error.to_string().starts_with("line 3, column "));
+        assert!(parse_error.source().unwrap().to_string().contains("go depht 5"));
+
+        let err = UciError::from(IoError::new(ErrorKind::BrokenPipe, "the engine exited"));
+        assert_eq!(err.to_string(), "I/O error");
+        assert_eq!(err.source().unwrap().to_string(), "the engine exited");
+    }
+}
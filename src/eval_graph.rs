@@ -0,0 +1,168 @@
+//! The `eval_graph` module extracts the series of the engine's evaluations over the moves of a game from a recorded
+//! `Session`, for plotting evaluation graphs.
+//!
+//! Each search of a game yields an `EvalPoint`: where in the game it took place, the depth the engine reached and its
+//! final score. Searches that cannot be placed in the game (a `go` without a `position`) are left out, and a point
+//! without a score (the engine never reported one) is kept, so that plots can show the gap. Mate scores do not fit on
+//! a centipawn axis; `EvalPoint::plot_value(..)` maps them to a cap.
+
+use crate::analysis::EvalTracker;
+use crate::score::Score;
+use crate::session::{GameSegment, SearchEpisode, Session};
+use crate::uci::UciMessage;
+
+/// The evaluation of the position of a search.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct EvalPoint {
+    /// The number of half-moves played from the standard starting position (or the game's FEN, counting from its move
+    /// number) before the searched position, starting at 0.
+    pub ply: u32,
+
+    /// The fullmove number of the searched position, as in a FEN.
+    pub move_number: u32,
+
+    /// `true` if White was to move in the searched position.
+    pub white_to_move: bool,
+
+    /// The deepest depth reported during the search.
+    pub depth: Option<u8>,
+
+    /// The final score of the search, from the point of view of the side to move. The last exact score is preferred
+    /// over a later bound.
+    pub score: Option<Score>,
+}
+
+impl EvalPoint {
+    /// Creates the point of a `search`, or returns `None` if the search has no `position` to place it in the game.
+    pub fn from_search(search: &SearchEpisode) -> Option<EvalPoint> {
+        let (fen, moves) = match &search.position {
+            Some(UciMessage::Position { fen, moves, .. }) => (fen, moves.len() as u32),
+            _ => return None,
+        };
+
+        let (black_starts, start_move) = match fen {
+            Some(f) => (f.as_str().split_whitespace().nth(1) == Some("b"), f.fullmove_number()),
+            None => (false, 1),
+        };
+        let ply = (start_move.max(1) - 1) * 2 + u32::from(black_starts) + moves;
+
+        let mut tracker = EvalTracker::new();
+        for info in &search.infos {
+            tracker.consume(info);
+        }
+
+        Some(EvalPoint {
+            ply,
+            move_number: ply / 2 + 1,
+            white_to_move: ply % 2 == 0,
+            depth: tracker.depth(),
+            score: tracker.exact_score().or_else(|| tracker.score().map(|(s, _)| s)),
+        })
+    }
+
+    /// Returns the score from White's point of view in centipawns, limited to `-cap..=cap`, with the mates at the
+    /// limits: `cap` if White mates, `-cap` if Black does. Returns `None` if the search had no score.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse;
+    /// use vampirc_uci::eval_graph::eval_graph;
+    /// use vampirc_uci::session::Session;
+    ///
+    /// let messages = parse("position startpos moves e2e4\ngo depth 9\ninfo depth 9 score cp 25\nbestmove e7e5\n\
+    ///     position startpos moves e2e4 e7e5 d1h5\ngo depth 9\ninfo depth 9 score mate 3\nbestmove b8c6\n");
+    /// let points = eval_graph(&Session::from_messages(&messages).games[0]);
+    ///
+    /// assert_eq!(points[0].plot_value(1000), Some(-25));
+    /// assert_eq!(points[1].plot_value(1000), Some(-1000));
+    /// ```
+    pub fn plot_value(&self, cap: i32) -> Option<i32> {
+        let cap = cap.abs();
+        let value = match self.score? {
+            Score::Mate(m) if m > 0 => cap,
+            Score::Mate(_) => -cap,
+            Score::Centipawns(c) => c.clamp(-cap, cap),
+        };
+
+        Some(if self.white_to_move { value } else { -value })
+    }
+}
+
+/// Returns the evaluation points of the searches of a game, ordered by ply. When the engine searched the same ply more
+/// than once (after a missed ponder, for example), the last search is kept.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::eval_graph::eval_graph;
+/// use vampirc_uci::score::Score;
+/// use vampirc_uci::session::Session;
+///
+/// let messages = parse("ucinewgame\nposition startpos\ngo depth 12\ninfo depth 11 score cp 20\n\
+///     info depth 12 score cp 31 pv e2e4\nbestmove e2e4\nposition startpos moves e2e4 e7e5\ngo depth 12\n\
+///     bestmove g1f3\n");
+/// let points = eval_graph(&Session::from_messages(&messages).games[0]);
+///
+/// assert_eq!(points.len(), 2);
+/// assert_eq!((points[0].move_number, points[0].depth, points[0].score), (1, Some(12), Some(Score::Centipawns(31))));
+/// assert_eq!((points[1].move_number, points[1].depth, points[1].score), (2, None, None));
+/// ```
+pub fn eval_graph(game: &GameSegment) -> Vec<EvalPoint> {
+    let mut points: Vec<EvalPoint> = Vec::new();
+
+    for point in game.searches.iter().filter_map(EvalPoint::from_search) {
+        match points.iter_mut().find(|p| p.ply == point.ply) {
+            Some(existing) => *existing = point,
+            None => points.push(point),
+        }
+    }
+    points.sort_by_key(|p| p.ply);
+
+    points
+}
+
+/// Returns the evaluation points of each game of a session. See `eval_graph(..)`.
+pub fn session_eval_graphs(session: &Session) -> Vec<Vec<EvalPoint>> {
+    session.games.iter().map(eval_graph).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_eval_graph() {
+        let messages = parse("ucinewgame\nposition fen 4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\ngo depth 5\n\
+            info depth 5 score cp -150\ninfo depth 6 score cp -120 upperbound\nbestmove e8d7\n\
+            position fen 4k3/8/8/8/8/8/4P3/4K3 b - - 0 30 moves e8d7 e2e4\ngo infinite\ninfo depth 3 score cp 10\n\
+            info depth 4 multipv 2 score cp -500\nstop\nbestmove d7e6\n\
+            position fen 4k3/8/8/8/8/8/4P3/4K3 b - - 0 30 moves e8d7 e2e4\ngo depth 20\n\
+            info depth 20 score mate -7\ngo depth 1\nbestmove d7e6\n\
+            ucinewgame\nposition startpos moves e2e4\ngo depth 1\nbestmove e7e5\n");
+        let graphs = session_eval_graphs(&Session::from_messages(&messages));
+        assert_eq!(graphs.len(), 2);
+
+        let points = &graphs[0];
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0], EvalPoint {
+            ply: 59,
+            move_number: 30,
+            white_to_move: false,
+            depth: Some(6),
+            score: Some(Score::Centipawns(-150)),
+        });
+        assert_eq!(points[0].plot_value(100), Some(100));
+
+        // The search at the same ply that ended with a mate score replaced the earlier one
+        assert_eq!((points[1].ply, points[1].move_number), (61, 31));
+        assert_eq!(points[1].score, Some(Score::Mate(-7)));
+        assert_eq!(points[1].plot_value(-1000), Some(1000));
+
+        assert_eq!(graphs[1], vec![EvalPoint { ply: 1, move_number: 1, white_to_move: false, depth: None, score: None }]);
+        assert_eq!(graphs[1][0].plot_value(1000), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! The `explain` module renders messages in plain English, for logs and teaching tools, as an alternative to the wire
+//! format. The explanation is available through `UciMessage::explain()` or the alternate flag of the `Display`
+//! implementation (`{:#}`).
+
+use std::fmt::Display;
+
+use chrono::Duration;
+
+#[cfg(feature = "extensions")]
+use crate::uci::Offer;
+use crate::uci::{ProtectionState, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+
+impl UciMessage {
+    /// Describes the message in plain English. The description starts with the name of the message kind, followed by
+    /// a colon and what the message says.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse_one;
+    ///
+    /// let m = parse_one("go wtime 180000 winc 2000 depth 20");
+    /// assert_eq!(m.explain(), "Go: search to depth 20, white has 3:00 remaining with 2s increment");
+    /// assert_eq!(format!("{:#}", m), m.explain());
+    /// ```
+    pub fn explain(&self) -> String {
+        match self {
+            UciMessage::Uci => String::from("Uci: switch to the UCI mode"),
+            UciMessage::Debug(on) => format!("Debug: turn the debug mode {}", if *on { "on" } else { "off" }),
+            UciMessage::IsReady => String::from("IsReady: ask whether the engine is ready"),
+            UciMessage::Register { later: true, .. } => String::from("Register: register later"),
+            UciMessage::Register { name, code, .. } => {
+                let mut s = String::from("Register: register");
+                if let Some(name) = name {
+                    s += &format!(" as {}", name);
+                }
+                if let Some(code) = code {
+                    s += &format!(" with the code {}", code);
+                }
+                s
+            }
+            UciMessage::Position { startpos, fen, moves } => {
+                let mut s = match fen {
+                    Some(fen) if !*startpos => format!("Position: set up the position {}", fen),
+                    _ => String::from("Position: set up the starting position"),
+                };
+                if !moves.is_empty() {
+                    s += &format!(" and play {}", join(moves, " "));
+                }
+                s
+            }
+            UciMessage::SetOption { name, value: Some(value) } => format!("SetOption: set {} to {}", name, value),
+            UciMessage::SetOption { name, value: None } => format!("SetOption: press {}", name),
+            UciMessage::UciNewGame => String::from("UciNewGame: the next search is from a new game"),
+            UciMessage::Stop => String::from("Stop: stop searching"),
+            UciMessage::PonderHit => String::from("PonderHit: the opponent played the expected move"),
+            UciMessage::Quit => String::from("Quit: quit the program"),
+            UciMessage::Go { time_control, search_control } => {
+                let mut parts = Vec::new();
+                if let Some(sc) = search_control {
+                    explain_search_control(sc, &mut parts);
+                }
+                if let Some(tc) = time_control {
+                    explain_time_control(tc, &mut parts);
+                }
+                if parts.is_empty() {
+                    parts.push(String::from("search without limits"));
+                }
+                format!("Go: {}", parts.join(", "))
+            }
+            UciMessage::Id { name, author } => match (name, author) {
+                (Some(name), Some(author)) => format!("Id: the engine is {} by {}", name, author),
+                (Some(name), None) => format!("Id: the engine is {}", name),
+                (None, Some(author)) => format!("Id: the author is {}", author),
+                (None, None) => String::from("Id: nothing"),
+            },
+            UciMessage::UciOk => String::from("UciOk: the engine has sent its id and options"),
+            UciMessage::ReadyOk => String::from("ReadyOk: the engine is ready"),
+            #[cfg(feature = "extensions")]
+            UciMessage::BestMove { offer: Some(Offer::Resign), .. } => String::from("BestMove: resign"),
+            UciMessage::BestMove { best_move, ponder, .. } => {
+                let s = match ponder {
+                    Some(ponder) => format!("BestMove: play {} and ponder on {}", best_move, ponder),
+                    None => format!("BestMove: play {}", best_move),
+                };
+                #[cfg(feature = "extensions")]
+                let s = match self {
+                    UciMessage::BestMove { offer: Some(Offer::Draw), .. } => s + " and offer a draw",
+                    _ => s,
+                };
+                s
+            }
+            UciMessage::CopyProtection(state) => format!("CopyProtection: {}", explain_state("copy protection", *state)),
+            UciMessage::Registration(state) => format!("Registration: {}", explain_state("registration", *state)),
+            UciMessage::Option(config) => format!("Option: {}", explain_option(config)),
+            UciMessage::Info(attributes) => {
+                let parts: Vec<String> = attributes.iter().map(explain_info_attribute).collect();
+                format!("Info: {}", parts.join(", "))
+            }
+            UciMessage::Unknown(s, _) => format!("Unknown: {}", s),
+        }
+    }
+}
+
+fn join<T: Display>(items: &[T], separator: &str) -> String {
+    items.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(separator)
+}
+
+/// Formats a duration as seconds (`2s`, `0.5s`) below a minute and as a clock (`3:00`, `1:02:03`) above it.
+fn format_duration(d: &Duration) -> String {
+    let millis = d.num_milliseconds();
+    let sign = if millis < 0 { "-" } else { "" };
+    let millis = millis.abs();
+
+    if millis < 60_000 {
+        format!("{}{}s", sign, millis as f64 / 1000.0)
+    } else if millis < 3_600_000 {
+        format!("{}{}:{:02}", sign, millis / 60_000, millis / 1000 % 60)
+    } else {
+        format!("{}{}:{:02}:{:02}", sign, millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60)
+    }
+}
+
+fn explain_search_control(sc: &UciSearchControl, parts: &mut Vec<String>) {
+    if !sc.search_moves.is_empty() {
+        parts.push(format!("consider only {}", join(&sc.search_moves, ", ")));
+    }
+    if let Some(depth) = sc.depth {
+        parts.push(format!("search to depth {}", depth));
+    }
+    if let Some(mate) = sc.mate {
+        parts.push(format!("search for a mate in {}", mate));
+    }
+    if let Some(nodes) = sc.nodes {
+        parts.push(format!("search at most {} nodes", nodes));
+    }
+}
+
+fn explain_clock(side: &str, time: &Option<Duration>, increment: &Option<Duration>) -> Option<String> {
+    match (time, increment) {
+        (Some(t), Some(i)) => Some(format!("{} has {} remaining with {} increment", side, format_duration(t), format_duration(i))),
+        (Some(t), None) => Some(format!("{} has {} remaining", side, format_duration(t))),
+        (None, Some(i)) => Some(format!("{} has {} increment", side, format_duration(i))),
+        (None, None) => None,
+    }
+}
+
+fn explain_time_control(tc: &UciTimeControl, parts: &mut Vec<String>) {
+    match tc {
+        UciTimeControl::Ponder => parts.push(String::from("ponder")),
+        UciTimeControl::Infinite => parts.push(String::from("search until stopped")),
+        UciTimeControl::MoveTime(t) => parts.push(format!("search for {}", format_duration(t))),
+        UciTimeControl::TimeLeft { white_time, black_time, white_increment, black_increment, moves_to_go } => {
+            parts.extend(explain_clock("white", white_time, white_increment));
+            parts.extend(explain_clock("black", black_time, black_increment));
+            if let Some(moves) = moves_to_go {
+                parts.push(format!("{} moves to the next time control", moves));
+            }
+        }
+    }
+}
+
+fn explain_state(what: &str, state: ProtectionState) -> String {
+    match state {
+        ProtectionState::Checking => format!("checking the {}", what),
+        ProtectionState::Ok => format!("the {} is ok", what),
+        ProtectionState::Error => format!("the {} failed", what),
+    }
+}
+
+fn explain_option(config: &UciOptionConfig) -> String {
+    let mut s = format!("{} option {}", config.get_type_str(), config.get_name());
+    match config {
+        UciOptionConfig::Check { default, .. } => {
+            if let Some(default) = default {
+                s += &format!(", default {}", default);
+            }
+        }
+        UciOptionConfig::Spin { default, min, max, .. } => {
+            if let Some(default) = default {
+                s += &format!(", default {}", default);
+            }
+            if let Some(min) = min {
+                s += &format!(", at least {}", min);
+            }
+            if let Some(max) = max {
+                s += &format!(", at most {}", max);
+            }
+        }
+        UciOptionConfig::Float { default, min, max, .. } => {
+            if let Some(default) = default {
+                s += &format!(", default {}", default);
+            }
+            if let Some(min) = min {
+                s += &format!(", at least {}", min);
+            }
+            if let Some(max) = max {
+                s += &format!(", at most {}", max);
+            }
+        }
+        UciOptionConfig::Combo { default, var, .. } => {
+            if let Some(default) = default {
+                s += &format!(", default {}", default);
+            }
+            if !var.is_empty() {
+                s += &format!(", one of {}", var.join(", "));
+            }
+        }
+        UciOptionConfig::String { default, .. } => {
+            if let Some(default) = default {
+                s += &format!(", default {}", default);
+            }
+        }
+        UciOptionConfig::Button { .. } => {}
+    }
+
+    s
+}
+
+fn explain_info_attribute(attribute: &UciInfoAttribute) -> String {
+    match attribute {
+        UciInfoAttribute::Depth(depth) => format!("depth {}", depth),
+        UciInfoAttribute::SelDepth(depth) => format!("selective depth {}", depth),
+        UciInfoAttribute::Time(t) => format!("{} elapsed", format_duration(t)),
+        UciInfoAttribute::Nodes(nodes) => format!("{} nodes searched", nodes),
+        UciInfoAttribute::Pv(pv) => format!("principal variation {}", join(pv, " ")),
+        UciInfoAttribute::MultiPv(n) => format!("line {}", n),
+        UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound } => {
+            let mut s = match (mate, cp) {
+                (Some(m), _) if *m < 0 => format!("mated in {}", -(*m as i16)),
+                (Some(m), _) => format!("mate in {}", m),
+                (None, Some(cp)) => format!("score {:+.2} pawns", *cp as f64 / 100.0),
+                (None, None) => String::from("no score"),
+            };
+            if *lower_bound == Some(true) {
+                s += " (lower bound)";
+            } else if *upper_bound == Some(true) {
+                s += " (upper bound)";
+            }
+            s
+        }
+        UciInfoAttribute::CurrMove(m) => format!("searching {}", m),
+        UciInfoAttribute::CurrMoveNum(n) => format!("move number {}", n),
+        UciInfoAttribute::HashFull(permill) => format!("hash {}% full", *permill as f64 / 10.0),
+        UciInfoAttribute::Nps(nps) => format!("{} nodes per second", nps),
+        UciInfoAttribute::TbHits(hits) => format!("{} tablebase hits", hits),
+        UciInfoAttribute::SbHits(hits) => format!("{} ShredderBase hits", hits),
+        UciInfoAttribute::CpuLoad(permill) => format!("CPU load {}%", *permill as f64 / 10.0),
+        UciInfoAttribute::String(s) => format!("message \"{}\"", s),
+        UciInfoAttribute::Refutation(line) => match line.split_first() {
+            Some((refuted, rest)) if !rest.is_empty() => format!("{} is refuted by {}", refuted, join(rest, " ")),
+            Some((refuted, _)) => format!("{} is not refuted", refuted),
+            None => String::from("no refutation"),
+        },
+        UciInfoAttribute::CurrLine { cpu_nr: Some(cpu), line } => format!("CPU {} is calculating {}", cpu, join(line, " ")),
+        UciInfoAttribute::CurrLine { cpu_nr: None, line } => format!("calculating {}", join(line, " ")),
+        UciInfoAttribute::Any(name, value) => format!("{} {}", name, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+
+    use super::*;
+
+    fn explain(s: &str) -> String {
+        parse_one(s).explain()
+    }
+
+    #[test]
+    fn test_explain_engine_bound() {
+        assert_eq!(explain("debug on"), "Debug: turn the debug mode on");
+        assert_eq!(explain("register name Matija Kejzar code 4359874324"),
+                   "Register: register as Matija Kejzar with the code 4359874324");
+        assert_eq!(explain("position startpos moves e2e4 e7e5"), "Position: set up the starting position and play e2e4 e7e5");
+        assert_eq!(explain("setoption name Hash value 128"), "SetOption: set Hash to 128");
+        assert_eq!(explain("setoption name Clear Hash"), "SetOption: press Clear Hash");
+    }
+
+    #[test]
+    fn test_explain_go() {
+        assert_eq!(explain("go infinite searchmoves e2e4 d2d4"), "Go: consider only e2e4, d2d4, search until stopped");
+        assert_eq!(explain("go movetime 1500"), "Go: search for 1.5s");
+        assert_eq!(explain("go wtime 3723000 btime 59000 binc 500 movestogo 40"),
+                   "Go: white has 1:02:03 remaining, black has 59s remaining with 0.5s increment, 40 moves to the next time control");
+        assert_eq!(UciMessage::go().explain(), "Go: search without limits");
+    }
+
+    #[test]
+    fn test_explain_gui_bound() {
+        assert_eq!(explain("id name Vampirc 0.5.0"), "Id: the engine is Vampirc 0.5.0");
+        assert_eq!(explain("bestmove e2e4 ponder e7e5"), "BestMove: play e2e4 and ponder on e7e5");
+        assert_eq!(explain("copyprotection error"), "CopyProtection: the copy protection failed");
+        assert_eq!(explain("option name Hash type spin default 16 min 1 max 1024"),
+                   "Option: spin option Hash, default 16, at least 1, at most 1024");
+        assert_eq!(explain("option name Style type combo default Normal var Solid var Normal var Risky"),
+                   "Option: combo option Style, default Normal, one of Solid, Normal, Risky");
+    }
+
+    #[test]
+    fn test_explain_info() {
+        assert_eq!(explain("info depth 12 seldepth 20 multipv 2 score cp 31 lowerbound nodes 100 nps 2000 hashfull 512 pv e2e4 e7e5"),
+                   "Info: depth 12, selective depth 20, line 2, score +0.31 pawns (lower bound), 100 nodes searched, \
+                   2000 nodes per second, hash 51.2% full, principal variation e2e4 e7e5");
+        assert_eq!(explain("info score mate -3 time 250"), "Info: mated in 3, 0.25s elapsed");
+        assert_eq!(explain("info refutation d1h5 g6h5"), "Info: d1h5 is refuted by g6h5");
+        assert_eq!(explain("info string hello there"), "Info: message \"hello there\"");
+    }
+
+    #[test]
+    fn test_explain_display_alternate() {
+        let m = parse_one("uci");
+        assert_eq!(format!("{}", m), "uci");
+        assert_eq!(format!("{:#}", m), "Uci: switch to the UCI mode");
+    }
+}
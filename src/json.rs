@@ -0,0 +1,234 @@
+//! The `json` module (available with the `json` feature) contains the JSON representation of the `UciMessage`s, for
+//! dashboards and log pipelines, and the `JsonlWriter`, which streams a sequence of messages as JSON Lines.
+//!
+//! The representation is the one derived through the `serde` feature. A message without fields is a string with the
+//! name of its variant; any other message is an object with a single key, the name of its variant, holding the fields
+//! (by name for the variants with named fields, or the value itself for the ones with a single unnamed field):
+//!
+//! ```text
+//! "Uci"
+//! {"Debug":true}
+//! {"SetOption":{"name":"Hash","value":"128"}}
+//! {"Go":{"time_control":{"TimeLeft":{"white_time":60000,"black_time":58000,"white_increment":null,"black_increment":null,"moves_to_go":null}},"search_control":null}}
+//! {"BestMove":{"best_move":"e2e4","ponder":"e7e5"}}
+//! {"Info":[{"Depth":12},{"Score":{"cp":31,"mate":null,"lower_bound":null,"upper_bound":null}},{"Pv":["e2e4","e7e5"]}]}
+//! {"Unknown":["foo bar"]}
+//! ```
+//!
+//! Moves are strings in the UCI move notation and durations are numbers of milliseconds. Absent optional fields are
+//! `null`. The parse error of a `UciMessage::Unknown` is not included.
+//!
+//! The module also reads and writes the `OptionProfile`s that hold saved option settings.
+
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Error as IoError, ErrorKind, Result as IoResult, Write};
+use std::path::Path;
+
+use serde_json::Error as JsonError;
+
+use crate::options::OptionProfile;
+use crate::uci::UciMessage;
+
+impl UciMessage {
+    /// Returns the JSON representation of this message, as described in the `json` module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::UciMessage;
+    ///
+    /// assert_eq!(UciMessage::Debug(true).to_json(), r#"{"Debug":true}"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a UciMessage is always representable in JSON")
+    }
+
+    /// Reads a message from its JSON representation, as described in the `json` module.
+    pub fn from_json(json: &str) -> Result<UciMessage, JsonError> {
+        serde_json::from_str(json)
+    }
+}
+
+impl OptionProfile {
+    /// Returns the JSON representation of this profile, an object from the option names to the values, pretty-printed.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("an OptionProfile is always representable in JSON")
+    }
+
+    /// Reads a profile from its JSON representation. Booleans and numbers are accepted as values, as well as strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::OptionProfile;
+    ///
+    /// let profile = OptionProfile::from_json(r#"{"Threads": 8, "Ponder": false, "SyzygyPath": "/tb"}"#).unwrap();
+    /// assert_eq!(profile.get("threads"), Some("8"));
+    /// assert_eq!(profile.get("Ponder"), Some("false"));
+    /// ```
+    pub fn from_json(json: &str) -> Result<OptionProfile, JsonError> {
+        serde_json::from_str(json)
+    }
+
+    /// Writes the profile to the file at `path` as JSON, creating or truncating it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> IoResult<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", self.to_json())
+    }
+
+    /// Reads a profile from the JSON file at `path`. A malformed file results in an error of the `InvalidData` kind.
+    pub fn load<P: AsRef<Path>>(path: P) -> IoResult<OptionProfile> {
+        let json = std::fs::read_to_string(path)?;
+        OptionProfile::from_json(&json).map_err(|e| IoError::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Writes a sequence of messages in the JSON Lines format: the JSON representation of each message on its own line.
+/// Each line is flushed immediately, so that the consumer sees the messages as they are written.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::json::{read_jsonl, JsonlWriter};
+///
+/// let mut writer = JsonlWriter::new(Vec::new());
+/// writer.write(&UciMessage::Uci).unwrap();
+/// writer.write(&UciMessage::UciOk).unwrap();
+///
+/// let output = writer.into_inner();
+/// assert_eq!(String::from_utf8(output.clone()).unwrap(), "\"Uci\"\n\"UciOk\"\n");
+/// assert_eq!(read_jsonl(output.as_slice()).unwrap(), vec![UciMessage::Uci, UciMessage::UciOk]);
+/// ```
+pub struct JsonlWriter<W: Write> {
+    writer: W,
+}
+
+impl JsonlWriter<BufWriter<File>> {
+    /// Creates (or truncates) the file at `path` and writes to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> IoResult<JsonlWriter<BufWriter<File>>> {
+        Ok(JsonlWriter::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> JsonlWriter<W> {
+    /// Creates a `JsonlWriter` writing to the `writer`.
+    pub fn new(writer: W) -> JsonlWriter<W> {
+        JsonlWriter {
+            writer,
+        }
+    }
+
+    /// Writes the `message` on its own line.
+    pub fn write(&mut self, message: &UciMessage) -> IoResult<()> {
+        writeln!(self.writer, "{}", message.to_json())?;
+        self.writer.flush()
+    }
+
+    /// Writes all of the `messages`, each on its own line.
+    pub fn write_messages<'a, I: IntoIterator<Item=&'a UciMessage>>(&mut self, messages: I) -> IoResult<()> {
+        for message in messages {
+            self.write(message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the messages written by the `JsonlWriter`. Blank lines are skipped. A malformed line results in an error of
+/// the `InvalidData` kind.
+pub fn read_jsonl<R: BufRead>(input: R) -> IoResult<Vec<UciMessage>> {
+    let mut messages = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message = UciMessage::from_json(&line).map_err(|e| {
+            IoError::new(ErrorKind::InvalidData, format!("malformed message on line {}: {}", index + 1, e))
+        })?;
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let messages = parse("setoption name Hash value 128\ngo wtime 60000 btime 58000\nbestmove e2e4 ponder e7e5\n");
+        let json: Vec<String> = messages.iter().map(|m| m.to_json()).collect();
+
+        assert_eq!(json[0], r#"{"SetOption":{"name":"Hash","value":"128"}}"#);
+        assert_eq!(json[1], r#"{"Go":{"time_control":{"TimeLeft":{"white_time":60000,"black_time":58000,"white_increment":null,"black_increment":null,"moves_to_go":null}},"search_control":null}}"#);
+        assert_eq!(json[2], r#"{"BestMove":{"best_move":"e2e4","ponder":"e7e5"}}"#);
+
+        for (message, json) in messages.iter().zip(json.iter()) {
+            assert_eq!(&UciMessage::from_json(json).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_from_json_invalid() {
+        assert!(UciMessage::from_json(r#"{"Nope":1}"#).is_err());
+        assert!(UciMessage::from_json("uci").is_err());
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let messages = parse("uci\nid name Vampirc\nuciok\ninfo depth 3 score cp 20 pv e2e4 e7e5\nbestmove e2e4\n");
+
+        let mut writer = JsonlWriter::new(Vec::new());
+        writer.write_messages(&messages).unwrap();
+        let output = writer.into_inner();
+
+        assert_eq!(String::from_utf8(output.clone()).unwrap().lines().count(), 5);
+        assert_eq!(read_jsonl(output.as_slice()).unwrap(), messages);
+    }
+
+    #[test]
+    fn test_option_profile_json() {
+        let mut profile = OptionProfile::new();
+        profile.set("Threads", "8");
+        profile.set("Hash", "1024");
+        profile.set("SyzygyPath", "");
+
+        let json = profile.to_json();
+        assert_eq!(json, "{\n  \"Threads\": \"8\",\n  \"Hash\": \"1024\",\n  \"SyzygyPath\": \"\"\n}");
+        assert_eq!(OptionProfile::from_json(&json).unwrap(), profile);
+
+        let profile = OptionProfile::from_json(r#"{"Ponder": true, "Contempt": -10, "Skill": 1.5, "Skill": 2.0}"#).unwrap();
+        assert_eq!(profile.iter().collect::<Vec<_>>(), vec![("Ponder", "true"), ("Contempt", "-10"), ("Skill", "2.0")]);
+
+        assert!(OptionProfile::from_json(r#"{"Hash": [1]}"#).is_err());
+        assert!(OptionProfile::from_json(r#"["Hash"]"#).is_err());
+
+        let path = std::env::temp_dir().join(format!("vampirc-uci-profile-{}.json", std::process::id()));
+        profile.save(&path).unwrap();
+        assert_eq!(OptionProfile::load(&path).unwrap(), profile);
+        std::fs::write(&path, "{").unwrap();
+        assert_eq!(OptionProfile::load(&path).unwrap_err().kind(), ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_jsonl_malformed() {
+        let err = read_jsonl("\"Uci\"\n\n{\"Debug\":\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 3"));
+    }
+}
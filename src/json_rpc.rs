@@ -0,0 +1,436 @@
+//! The `json_rpc` module (available with the `json` feature) contains the `JsonRpcBridge`, which exposes an engine as
+//! a [JSON-RPC 2.0](https://www.jsonrpc.org/specification) service, so that it can be used by clients that do not
+//! speak UCI.
+//!
+//! The requests and responses are exchanged one per line. Their parameters and results use the JSON representation
+//! of the messages described in the `json` module. The methods are:
+//!
+//! * `analyze`, with the optional parameters `fen` (the starting position if absent), `moves`, `time_control` and
+//!   `search_control`: sets up the position and searches it. The search must be bounded by a time control other than
+//!   `Infinite` and `Ponder`, or by a depth, a number of nodes or a mate in the search control, since the requests are
+//!   handled one at a time and so a search cannot be stopped. While the engine searches, an `info` notification is
+//!   sent for each of its `info` messages, with the `id` of the request and the attributes as `info`. The result is
+//!   the best move, as in `{"best_move":"e2e4","ponder":"e7e5"}`.
+//! * `set_option`, with the parameters `name` and `value` (absent for a `button` option).
+//! * `new_game`: sends `ucinewgame` and waits for the engine to be ready.
+//! * `is_ready`: waits for the engine to be ready.
+//! * `send`, with the parameter `message`: sends any message to the engine, without waiting for a reply.
+//! * `engine_info`: returns the `name`, the `author` and the `options` the engine reported in the handshake.
+//!
+//! The methods that do not return anything result in `null`. Batch requests are not supported.
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":1,"method":"analyze","params":{"moves":["e2e4"],"search_control":{"search_moves":[],"depth":2}}}
+//! <-- {"jsonrpc":"2.0","method":"info","params":{"id":1,"info":[{"Depth":1},{"Pv":["e7e5"]}]}}
+//! <-- {"jsonrpc":"2.0","method":"info","params":{"id":1,"info":[{"Depth":2},{"Pv":["e7e5","g1f3"]}]}}
+//! <-- {"jsonrpc":"2.0","id":1,"result":{"best_move":"e7e5","ponder":"g1f3"}}
+//! ```
+
+use std::io::{BufRead, Result as IoResult, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::discovery::EngineInfo;
+use crate::process::EngineProcess;
+use crate::uci::{UciFen, UciMessage, UciSearchControl, UciTimeControl};
+
+/// The error code for a request that is not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The error code for a request that is not a valid JSON-RPC request object.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// The error code for a request for a method the bridge does not offer.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The error code for a request with parameters the method does not accept.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The error code for a request that failed because of an error of the bridge itself.
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// The error code for a request that failed because of the engine, such as an engine that exited.
+pub const ENGINE_ERROR: i64 = -32000;
+
+/// An error response to a request.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The parameters of the `analyze` method.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct AnalyzeParams {
+    fen: Option<UciFen>,
+    moves: Vec<String>,
+    time_control: Option<UciTimeControl>,
+    search_control: Option<UciSearchControl>,
+}
+
+/// The parameters of the `set_option` method.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SetOptionParams {
+    name: String,
+    #[serde(default)]
+    value: Option<String>,
+}
+
+/// The parameters of the `send` method.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SendParams {
+    message: UciMessage,
+}
+
+/// Exposes an engine as a JSON-RPC service, see the `json_rpc` module for the methods it offers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io;
+/// use vampirc_uci::json_rpc::JsonRpcBridge;
+/// use vampirc_uci::process::EngineProcess;
+///
+/// let engine = EngineProcess::spawn("stockfish", &[] as &[&str]).unwrap();
+/// let mut bridge = JsonRpcBridge::new(engine).unwrap();
+/// bridge.serve(io::stdin().lock(), io::stdout()).unwrap();
+/// ```
+pub struct JsonRpcBridge {
+    engine: EngineProcess,
+    info: EngineInfo,
+}
+
+impl JsonRpcBridge {
+    /// Creates the bridge for the `engine`, performing the `uci` handshake.
+    pub fn new(mut engine: EngineProcess) -> IoResult<JsonRpcBridge> {
+        let handshake = engine.handshake()?;
+
+        Ok(JsonRpcBridge {
+            engine,
+            info: EngineInfo::from_messages(&handshake),
+        })
+    }
+
+    /// Returns the information the engine reported about itself during the handshake.
+    pub fn info(&self) -> &EngineInfo {
+        &self.info
+    }
+
+    /// Returns the engine.
+    pub fn into_engine(self) -> EngineProcess {
+        self.engine
+    }
+
+    /// Reads the requests from `input` a line at a time and writes the responses (and the `info` notifications) to
+    /// `output`, until the `input` is exhausted. Blank lines are skipped.
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> IoResult<()> {
+        for line in input.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                self.handle(&line, &mut output)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a single `request`, writing the response (unless the request is a notification) and any `info`
+    /// notifications to `output`, each on its own line. The errors returned are the ones writing to `output`; the
+    /// errors of the request, including the ones talking to the engine, are reported in the response.
+    pub fn handle<W: Write>(&mut self, request: &str, output: &mut W) -> IoResult<()> {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => return write_response(output, &Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+            _ => {
+                let error = RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request");
+                return write_response(output, id.as_ref().unwrap_or(&Value::Null), Err(error));
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let notification_id = id.clone().unwrap_or(Value::Null);
+
+        let result = self.call(method, params, |info| write_notification(output, &notification_id, info));
+        match id {
+            Some(id) => write_response(output, &id, result),
+            None => Ok(()),
+        }
+    }
+
+    fn call<F: FnMut(Value) -> IoResult<()>>(&mut self, method: &str, params: Value, mut notify: F) -> Result<Value, RpcError> {
+        match method {
+            "analyze" => {
+                let params: AnalyzeParams = parse_params(params)?;
+                if !is_bounded(&params) {
+                    return Err(RpcError::new(INVALID_PARAMS, "the search is not bounded by a time, a depth, a number of nodes or a mate"));
+                }
+                let moves = params.moves.iter()
+                    .map(|m| m.parse().map_err(|_| RpcError::new(INVALID_PARAMS, format!("invalid move: {}", m))))
+                    .collect::<Result<_, _>>()?;
+
+                self.engine.send(&UciMessage::Position { startpos: params.fen.is_none(), fen: params.fen, moves })
+                    .map_err(engine_error)?;
+                self.engine.send(&UciMessage::Go { time_control: params.time_control, search_control: params.search_control })
+                    .map_err(engine_error)?;
+
+                loop {
+                    match self.engine.recv().map_err(engine_error)? {
+                        UciMessage::Info(attributes) => {
+                            let notified = serde_json::to_value(attributes).map_err(internal_error)
+                                .and_then(|info| notify(info).map_err(engine_error));
+                            if let Err(e) = notified {
+                                // Ends the search, so that its bestmove is not taken for the result of the next one
+                                self.engine.send(&UciMessage::Stop)
+                                    .and_then(|_| self.engine.recv_until(UciMessage::ends_search))
+                                    .map_err(engine_error)?;
+                                return Err(e);
+                            }
+                        }
+                        m @ UciMessage::BestMove { .. } => {
+                            return serde_json::to_value(m).map(|mut m| m["BestMove"].take()).map_err(internal_error);
+                        }
+                        UciMessage::Unknown(text, _) if text.trim_start().starts_with("bestmove") => {
+                            return Err(engine_error(format!("invalid bestmove: {}", text)));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "set_option" => {
+                let params: SetOptionParams = parse_params(params)?;
+                self.engine.send(&UciMessage::set_option(&params.name, params.value)).map_err(engine_error)?;
+                Ok(Value::Null)
+            }
+            "new_game" => {
+                self.engine.send(&UciMessage::UciNewGame).map_err(engine_error)?;
+                self.engine.is_ready().map_err(engine_error)?;
+                Ok(Value::Null)
+            }
+            "is_ready" => {
+                self.engine.is_ready().map_err(engine_error)?;
+                Ok(Value::Null)
+            }
+            "send" => {
+                let params: SendParams = parse_params(params)?;
+                self.engine.send(&params.message).map_err(engine_error)?;
+                Ok(Value::Null)
+            }
+            "engine_info" => Ok(json!({
+                "name": self.info.name,
+                "author": self.info.author,
+                "options": self.info.options,
+            })),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("no such method: {}", method))),
+        }
+    }
+}
+
+/// Reads the `params` of a request, where absent parameters are the same as an empty object.
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// Returns `true` if the search of the `params` ends without a `stop`.
+fn is_bounded(params: &AnalyzeParams) -> bool {
+    let timed = match &params.time_control {
+        Some(UciTimeControl::MoveTime(_)) => true,
+        Some(tc @ UciTimeControl::TimeLeft { .. }) => tc.white_time().is_some() || tc.black_time().is_some(),
+        Some(UciTimeControl::Infinite) | Some(UciTimeControl::Ponder) => return false,
+        None => false,
+    };
+    let limited = params.search_control.as_ref()
+        .is_some_and(|sc| sc.depth.is_some() || sc.nodes.is_some() || sc.mate.is_some());
+
+    timed || limited
+}
+
+fn engine_error<E: ToString>(e: E) -> RpcError {
+    RpcError::new(ENGINE_ERROR, e.to_string())
+}
+
+fn internal_error<E: ToString>(e: E) -> RpcError {
+    RpcError::new(INTERNAL_ERROR, e.to_string())
+}
+
+fn write_response<W: Write>(output: &mut W, id: &Value, result: Result<Value, RpcError>) -> IoResult<()> {
+    let response = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": e.code, "message": e.message}}),
+    };
+
+    writeln!(output, "{}", response)?;
+    output.flush()
+}
+
+fn write_notification<W: Write>(output: &mut W, id: &Value, info: Value) -> IoResult<()> {
+    writeln!(output, "{}", json!({"jsonrpc": "2.0", "method": "info", "params": {"id": id, "info": info}}))?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error as IoError, ErrorKind};
+
+    use crate::parser::parse_one;
+    use crate::testing::{pipe, MockEngine, MockScript};
+    use crate::uci::{UciMessageKind, UciOptionConfig};
+
+    use super::*;
+
+    const ANALYZE: &str = r#"{"jsonrpc":"2.0","id":1,"method":"analyze","params":{"search_control":{"search_moves":[],"depth":1}}}"#;
+
+    fn mock_engine() -> MockEngine {
+        MockEngine::new("Fake", "Tester")
+            .option(UciOptionConfig::Spin { name: String::from("Hash"), default: Some(16), min: Some(1), max: Some(64) })
+            .on(UciMessageKind::Go, MockScript::new().send_all(
+                ["info depth 1 pv e7e5", "info depth 2 pv e7e5 g1f3", "bestmove e7e5 ponder g1f3"].iter().map(|m| parse_one(m))))
+    }
+
+    fn call(requests: &str) -> Vec<Value> {
+        let mut bridge = JsonRpcBridge::new(mock_engine().spawn()).unwrap();
+        assert_eq!(bridge.info().name.as_deref(), Some("Fake"));
+
+        let mut output = Vec::new();
+        bridge.serve(requests.as_bytes(), &mut output).unwrap();
+        bridge.into_engine().quit().unwrap();
+
+        String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_analyze() {
+        let responses = call(r#"{"jsonrpc":"2.0","id":1,"method":"analyze","params":{"moves":["e2e4"],"search_control":{"search_moves":[],"depth":2}}}"#);
+
+        assert_eq!(responses, vec![
+            json!({"jsonrpc": "2.0", "method": "info", "params": {"id": 1, "info": [{"Depth": 1}, {"Pv": ["e7e5"]}]}}),
+            json!({"jsonrpc": "2.0", "method": "info", "params": {"id": 1, "info": [{"Depth": 2}, {"Pv": ["e7e5", "g1f3"]}]}}),
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"best_move": "e7e5", "ponder": "g1f3"}}),
+        ]);
+    }
+
+    #[test]
+    fn test_methods() {
+        let responses = call(concat!(
+            r#"{"jsonrpc":"2.0","id":"a","method":"set_option","params":{"name":"Hash","value":"32"}}"#, "\n",
+            r#"{"jsonrpc":"2.0","method":"send","params":{"message":"Stop"}}"#, "\n",
+            "\n",
+            r#"{"jsonrpc":"2.0","id":"b","method":"new_game"}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":"c","method":"engine_info"}"#, "\n",
+        ));
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0], json!({"jsonrpc": "2.0", "id": "a", "result": null}));
+        assert_eq!(responses[1], json!({"jsonrpc": "2.0", "id": "b", "result": null}));
+        assert_eq!(responses[2]["result"]["name"], json!("Fake"));
+        assert_eq!(responses[2]["result"]["options"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let responses = call(concat!(
+            "{not json\n",
+            r#"{"id":1,"method":"is_ready"}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"fly"}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":3,"method":"analyze","params":{"moves":["e2e9"],"time_control":{"MoveTime":100}}}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":4,"method":"set_option","params":{"value":"1"}}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":5,"method":"analyze"}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":6,"method":"analyze","params":{"time_control":"Infinite","search_control":{"search_moves":[],"depth":2}}}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":7,"method":"is_ready"}"#, "\n",
+        ));
+
+        let codes: Vec<Value> = responses.iter().map(|r| r["error"]["code"].clone()).collect();
+        assert_eq!(codes, vec![json!(PARSE_ERROR), json!(INVALID_REQUEST), json!(METHOD_NOT_FOUND), json!(INVALID_PARAMS),
+                               json!(INVALID_PARAMS), json!(INVALID_PARAMS), json!(INVALID_PARAMS), Value::Null]);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert_eq!(responses[1]["id"], json!(1));
+        assert_eq!(responses[7]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_engine_error() {
+        let (input, engine_input) = pipe();
+        let (mut engine_output, output) = pipe();
+        engine_output.write_all(b"uciok\n").unwrap();
+        let mut bridge = JsonRpcBridge::new(EngineProcess::from_streams(input, output)).unwrap();
+        drop(engine_input);
+
+        let mut output = Vec::new();
+        bridge.handle(ANALYZE, &mut output).unwrap();
+        let response: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(response["error"]["code"], json!(ENGINE_ERROR));
+    }
+
+    #[test]
+    fn test_invalid_best_move() {
+        let (input, _engine_input) = pipe();
+        let (mut engine_output, output) = pipe();
+        engine_output.write_all(b"uciok\ninfo depth 1\nbestmove e9e4\n").unwrap();
+        let mut bridge = JsonRpcBridge::new(EngineProcess::from_streams(input, output)).unwrap();
+
+        let mut output = Vec::new();
+        bridge.handle(ANALYZE, &mut output).unwrap();
+        let responses: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[1]["error"]["code"], json!(ENGINE_ERROR));
+        assert!(!bridge.into_engine().is_searching());
+    }
+
+    /// Fails the first write, which is the one of the first notification, and accepts the others.
+    #[derive(Default)]
+    struct FailFirstWrite {
+        failed: bool,
+        written: Vec<u8>,
+    }
+
+    impl Write for FailFirstWrite {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            if !self.failed {
+                self.failed = true;
+                return Err(IoError::from(ErrorKind::BrokenPipe));
+            }
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failed_notification() {
+        let mock = MockEngine::new("Fake", "Tester")
+            .on(UciMessageKind::Go, MockScript::new().send(parse_one("info depth 1 pv e7e5")).send(parse_one("bestmove e7e5")))
+            .on(UciMessageKind::Go, MockScript::new().send(parse_one("bestmove d7d5")));
+        let log = mock.log();
+        let mut bridge = JsonRpcBridge::new(mock.spawn()).unwrap();
+
+        let mut output = FailFirstWrite::default();
+        bridge.handle(ANALYZE, &mut output).unwrap();
+        let response: Value = serde_json::from_slice(&output.written).unwrap();
+        assert_eq!(response["error"]["code"], json!(ENGINE_ERROR));
+
+        // The next search does not take the bestmove of the failed one
+        let mut output = Vec::new();
+        bridge.handle(ANALYZE, &mut output).unwrap();
+        let response: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(response["result"]["best_move"], json!("d7d5"));
+        assert!(log.messages().contains(&UciMessage::Stop));
+    }
+}
@@ -8,10 +8,22 @@
 //! See the [README.md](https://github.com/vampirc/vampirc-uci/blob/master/README.md) file for usage instructions.
 
 
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
 #[cfg(feature = "chess")]
 extern crate chess;
 extern crate chrono;
 extern crate pest;
+#[cfg(feature = "schemars")]
+extern crate schemars;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
 #[macro_use]
 extern crate pest_derive;
 
@@ -24,19 +36,61 @@ pub use chess::Square;
 pub use chrono::Duration;
 pub use pest::error::Error;
 
+#[cfg(feature = "chess")]
+pub use self::board::position_from_board;
+#[cfg(feature = "chess")]
+pub use self::board::position_to_board;
+#[cfg(feature = "chess")]
+pub use self::board::position_to_game;
+#[cfg(feature = "chess")]
+pub use self::board::PositionError;
+pub use self::builder::BuilderError;
+pub use self::builder::GoBuilder;
+pub use self::builder::InfoBuilder;
+pub use self::builder::PositionBuilder;
+pub use self::builder::UciPosition;
+pub use self::canonical::SerializeOptions;
+pub use self::castling::CastlingSquares;
+pub use self::engine::EngineOutput;
+pub use self::engine::Handshake;
+pub use self::engine::UciEngine;
+pub use self::error::ParseError;
+pub use self::error::UciError;
+pub use self::options::EngineOptions;
+pub use self::options::OptionError;
+pub use self::options::OptionProfile;
+pub use self::options::OptionRegistry;
+pub use self::options::OptionValue;
+pub use self::options::OptionValues;
+pub use self::parser::classify;
 pub use self::parser::parse;
+pub use self::parser::parse_engine_bound;
+pub use self::parser::parse_gui_bound;
 pub use self::parser::parse_one;
+pub use self::parser::parse_one_with_options;
 pub use self::parser::parse_strict;
+pub use self::parser::parse_strict_with_options;
+pub use self::parser::parse_with_options;
 pub use self::parser::parse_with_unknown;
+pub use self::parser::KeywordCase;
+pub use self::parser::ParseOptions;
 pub use self::parser::Rule;
+pub use self::query::MessageQuery;
 pub use self::uci::ByteVecUciMessage;
 pub use self::uci::CommunicationDirection;
+pub use self::uci::DuplicateAttributes;
+pub use self::uci::InfoAttributes;
 pub use self::uci::MessageList;
+#[cfg(feature = "extensions")]
+pub use self::uci::Offer;
 pub use self::uci::ProtectionState;
 pub use self::uci::Serializable;
+pub use self::uci::STARTPOS_FEN;
 pub use self::uci::UciFen;
+pub use self::uci::UciFloat;
 pub use self::uci::UciInfoAttribute;
 pub use self::uci::UciMessage;
+pub use self::uci::UciMessageKind;
 #[cfg(not(feature = "chess"))]
 pub use self::uci::UciMove;
 pub use self::uci::UciOptionConfig;
@@ -46,9 +100,74 @@ pub use self::uci::UciSearchControl;
 #[cfg(not(feature = "chess"))]
 pub use self::uci::UciSquare;
 pub use self::uci::UciTimeControl;
+pub use self::visitor::UciMessageVisitor;
 
 pub mod uci;
 pub mod parser;
+pub mod builder;
+pub mod options;
+pub mod castling;
+pub mod engine;
+pub mod process;
+pub mod watchdog;
+pub mod protocol;
+pub mod proxy;
+pub mod dispatch;
+pub mod score;
+pub mod analysis;
+pub mod reporter;
+pub mod time_manager;
+pub mod discovery;
+pub mod manager;
+pub mod record;
+pub mod coalesce;
+pub mod ponder;
+pub mod reader;
+pub mod binary;
+pub mod canonical;
+pub mod explain;
+pub mod query;
+pub mod visitor;
+pub mod testing;
+pub mod conformance;
+pub mod metrics;
+pub mod cutechess;
+pub mod session;
+pub mod lint;
+pub mod timing;
+pub mod detect;
+pub mod epd;
+pub mod eval_graph;
+pub mod stats;
+pub mod error;
+#[cfg(feature = "chess")]
+pub mod board;
+#[cfg(feature = "chess")]
+pub mod engine_match;
+#[cfg(feature = "chess")]
+pub mod pgn;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "json")]
+pub mod json_rpc;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_support;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "tokio")]
+pub mod async_process;
+#[cfg(feature = "tokio")]
+pub mod async_engine;
+#[cfg(feature = "tokio")]
+pub mod tcp;
+#[cfg(feature = "miette")]
+pub mod diagnostic;
+#[cfg(feature = "futures")]
+pub mod async_io;
 
 #[cfg(test)]
 mod tests {
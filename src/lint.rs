@@ -0,0 +1,255 @@
+//! The `lint` module contains the `lint(..)` method, which checks a transcript of a conversation between a GUI and an
+//! engine against the UCI protocol and reports the problems it finds, each with its severity and the span of the
+//! offending line. It is meant for the continuous integration of engines: record a session (for example, with
+//! `cutechess-cli` or the `Recorder`), then fail the build on any `LintSeverity::Error`.
+//!
+//! The order of the messages is checked by the `UciProtocolState` of the `protocol` module; `lint(..)` adds the
+//! checks that need the whole transcript, such as a `readyok` that never came.
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Range;
+
+use crate::parser::parse_one;
+use crate::protocol::{ProtocolViolation, UciProtocolState};
+use crate::uci::{CommunicationDirection, UciMessage};
+
+/// How serious a `LintWarning` is, from the least to the most.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum LintSeverity {
+    /// Something that is allowed, but worth a look, such as a line that is not a UCI message.
+    Info,
+
+    /// Something that is likely to confuse the other side, but that the protocol does not strictly forbid.
+    Warning,
+
+    /// A violation of the protocol.
+    Error,
+}
+
+impl Display for LintSeverity {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let s = match self {
+            LintSeverity::Info => "info",
+            LintSeverity::Warning => "warning",
+            LintSeverity::Error => "error",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// A problem found by `lint(..)`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum LintKind {
+    /// A message violates the order mandated by the protocol, see `UciProtocolState`.
+    Protocol(ProtocolViolation),
+
+    /// The engine sent a message before the GUI sent `uci`.
+    EngineOutputBeforeUci,
+
+    /// The GUI sent `uci`, but the engine never replied with `uciok`.
+    MissingUciOk,
+
+    /// The GUI sent `isready`, but the engine never replied with `readyok`.
+    MissingReadyOk,
+
+    /// The GUI sent `go`, but the engine never replied with `bestmove` (nor did the GUI send `quit`).
+    MissingBestMove,
+
+    /// The engine sent `info` after the `bestmove` that ended a search, before the next `go`.
+    InfoAfterBestMove,
+
+    /// The line is not a UCI message.
+    UnknownMessage,
+}
+
+impl LintKind {
+    /// Returns how serious the problem is.
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            LintKind::Protocol(..) | LintKind::MissingUciOk | LintKind::MissingReadyOk => LintSeverity::Error,
+            LintKind::EngineOutputBeforeUci | LintKind::MissingBestMove | LintKind::InfoAfterBestMove => LintSeverity::Warning,
+            LintKind::UnknownMessage => LintSeverity::Info,
+        }
+    }
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let description = match self {
+            LintKind::Protocol(violation) => return write!(f, "{}", violation),
+            LintKind::EngineOutputBeforeUci => "engine output before `uci`",
+            LintKind::MissingUciOk => "`uci` never answered with `uciok`",
+            LintKind::MissingReadyOk => "`isready` never answered with `readyok`",
+            LintKind::MissingBestMove => "`go` never answered with `bestmove`",
+            LintKind::InfoAfterBestMove => "`info` sent after `bestmove`",
+            LintKind::UnknownMessage => "not a UCI message",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+/// A problem found by `lint(..)`, along with where it was found.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct LintWarning {
+    /// The problem.
+    pub kind: LintKind,
+
+    /// How serious the problem is, the same as `kind.severity()`.
+    pub severity: LintSeverity,
+
+    /// The number of the offending line, starting at 1. For a reply that never came, this is the line of the message
+    /// that went unanswered.
+    pub line: usize,
+
+    /// The byte range of the offending line in the transcript, without the line break.
+    pub span: Range<usize>,
+}
+
+impl LintWarning {
+    fn new(kind: LintKind, line: usize, span: Range<usize>) -> LintWarning {
+        LintWarning {
+            kind,
+            severity: kind.severity(),
+            line,
+            span,
+        }
+    }
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "line {}: {}: {}", self.line, self.severity, self.kind)
+    }
+}
+
+/// Checks the `transcript` of a conversation, with a message per line in the order they were exchanged, in both
+/// directions, and returns the problems found, in the order of the offending lines. Blank lines are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::lint::{lint, LintKind, LintSeverity};
+///
+/// let warnings = lint("uci\nuciok\nisready\nposition startpos\ngo depth 5\nbestmove e2e4\ninfo depth 6\n");
+///
+/// assert_eq!(warnings.len(), 2);
+/// assert_eq!(warnings[0].kind, LintKind::MissingReadyOk);
+/// assert_eq!(warnings[0].line, 3);
+/// assert_eq!(warnings[0].severity, LintSeverity::Error);
+/// assert_eq!(warnings[1].to_string(), "line 7: warning: `info` sent after `bestmove`");
+/// ```
+pub fn lint(transcript: &str) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let mut state = UciProtocolState::new();
+    let mut uci_sent = false;
+    let mut after_best_move = false;
+    let mut pending_uci: Option<(usize, Range<usize>)> = None;
+    let mut pending_ready: VecDeque<(usize, Range<usize>)> = VecDeque::new();
+    let mut pending_go: Option<(usize, Range<usize>)> = None;
+
+    let mut offset = 0;
+    for (index, raw_line) in transcript.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += raw_line.len();
+
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let number = index + 1;
+        let span = start..start + line.len();
+        let message = parse_one(line);
+        if message.is_unknown() {
+            warnings.push(LintWarning::new(LintKind::UnknownMessage, number, span));
+            continue;
+        }
+
+        let searching = state.is_searching();
+        if let Err(violation) = state.consume(&message) {
+            warnings.push(LintWarning::new(LintKind::Protocol(violation), number, span.clone()));
+        } else if message.direction() == CommunicationDirection::EngineToGui && !uci_sent {
+            warnings.push(LintWarning::new(LintKind::EngineOutputBeforeUci, number, span.clone()));
+        } else if let UciMessage::Info(..) = message {
+            if after_best_move && !searching {
+                warnings.push(LintWarning::new(LintKind::InfoAfterBestMove, number, span.clone()));
+            }
+        }
+
+        match message {
+            UciMessage::Uci => {
+                uci_sent = true;
+                pending_uci = Some((number, span));
+            }
+            UciMessage::UciOk => pending_uci = None,
+            UciMessage::IsReady => pending_ready.push_back((number, span)),
+            UciMessage::ReadyOk => {
+                pending_ready.pop_front();
+            }
+            UciMessage::Go { .. } => {
+                after_best_move = false;
+                pending_go = Some((number, span));
+            }
+            UciMessage::BestMove { .. } => {
+                after_best_move = true;
+                pending_go = None;
+            }
+            UciMessage::Quit => pending_go = None,
+            _ => {}
+        }
+    }
+
+    let unanswered = pending_uci.into_iter().map(|p| (LintKind::MissingUciOk, p))
+        .chain(pending_ready.into_iter().map(|p| (LintKind::MissingReadyOk, p)))
+        .chain(pending_go.into_iter().map(|p| (LintKind::MissingBestMove, p)));
+    for (kind, (number, span)) in unanswered {
+        warnings.push(LintWarning::new(kind, number, span));
+    }
+    warnings.sort_by_key(|w| w.line);
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_transcript() {
+        let transcript = "uci\nid name Fake\nuciok\nisready\nreadyok\n\nucinewgame\nposition startpos\ngo depth 2\n\
+                          info depth 1\ninfo depth 2\nbestmove e2e4\nposition startpos moves e2e4\ngo infinite\nquit\n";
+        assert_eq!(lint(transcript), vec![]);
+    }
+
+    #[test]
+    fn test_lint() {
+        let transcript = "info string NNUE loaded\r\nuci\r\nuciok\r\nposition startpos\r\ngo infinite\r\n\
+                          setoption name Hash value 32\r\nstop\r\nbestmove e2e4\r\ninfo depth 9\r\nbestmove d2d4\r\n\
+                          what is this\r\nuci\r\nisready\r\nposition startpos\r\ngo depth 1\r\n";
+        let warnings = lint(transcript);
+
+        let kinds: Vec<(usize, LintKind)> = warnings.iter().map(|w| (w.line, w.kind)).collect();
+        assert_eq!(kinds, vec![
+            (1, LintKind::EngineOutputBeforeUci),
+            (6, LintKind::Protocol(ProtocolViolation::SetOptionDuringSearch)),
+            (9, LintKind::InfoAfterBestMove),
+            (10, LintKind::Protocol(ProtocolViolation::BestMoveWithoutGo)),
+            (11, LintKind::UnknownMessage),
+            (12, LintKind::MissingUciOk),
+            (13, LintKind::MissingReadyOk),
+            (14, LintKind::Protocol(ProtocolViolation::NotInitialized)),
+            (15, LintKind::Protocol(ProtocolViolation::NotInitialized)),
+            (15, LintKind::MissingBestMove),
+        ]);
+
+        assert_eq!(&transcript[warnings[4].span.clone()], "what is this");
+        assert_eq!(warnings[4].severity, LintSeverity::Info);
+        assert_eq!(warnings[0].severity, LintSeverity::Warning);
+        assert_eq!(warnings[1].severity, LintSeverity::Error);
+        assert_eq!(warnings[1].to_string(), "line 6: error: `setoption` sent while a search was in progress");
+        assert_eq!(warnings.iter().map(|w| w.severity).max(), Some(LintSeverity::Error));
+    }
+}
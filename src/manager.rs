@@ -0,0 +1,294 @@
+//! The `manager` module contains the `EngineManager`, which runs several engines at once and merges their messages
+//! into a single channel, each message tagged with the id of the engine that sent it.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::parser::parse_one;
+use crate::uci::{Serializable, UciMessage};
+
+/// The id of an engine run by the `EngineManager`. Ids are assigned in the order the engines are added and are never
+/// reused.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct EngineId(pub usize);
+
+impl Display for EngineId {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "engine #{}", self.0)
+    }
+}
+
+/// An event received from one of the engines run by the `EngineManager`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum EngineEvent {
+    /// The engine sent a message.
+    Message(EngineId, UciMessage),
+
+    /// The engine closed its output, usually because it exited.
+    Closed(EngineId),
+}
+
+impl EngineEvent {
+    /// The id of the engine the event is from.
+    pub fn engine_id(&self) -> EngineId {
+        match self {
+            EngineEvent::Message(id, _) | EngineEvent::Closed(id) => *id,
+        }
+    }
+}
+
+struct ManagedEngine {
+    child: Option<Child>,
+    stdin: Box<dyn Write + Send>,
+}
+
+/// Runs several engines as child processes (or over other streams). Messages are sent to each engine separately (or to all of them at
+/// once), while the messages of all the engines are received from a single channel, as `EngineEvent`s.
+///
+/// The engines still running when the `EngineManager` is dropped are killed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::manager::{EngineEvent, EngineManager};
+///
+/// let mut manager = EngineManager::new();
+/// let stockfish = manager.spawn("stockfish", &[] as &[&str]).unwrap();
+/// let lc0 = manager.spawn("lc0", &[] as &[&str]).unwrap();
+///
+/// manager.broadcast(&UciMessage::Uci).unwrap();
+/// while let Some(event) = manager.recv() {
+///     if let EngineEvent::Message(id, UciMessage::Id { name: Some(name), .. }) = event {
+///         println!("{} is {}", id, name);
+///     }
+/// }
+/// ```
+pub struct EngineManager {
+    engines: BTreeMap<EngineId, ManagedEngine>,
+    next_id: usize,
+    open: usize,
+    sender: Sender<EngineEvent>,
+    receiver: Receiver<EngineEvent>,
+}
+
+impl Default for EngineManager {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        EngineManager {
+            engines: BTreeMap::new(),
+            next_id: 0,
+            open: 0,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl EngineManager {
+    /// Creates an `EngineManager` without any engines.
+    pub fn new() -> EngineManager {
+        EngineManager::default()
+    }
+
+    /// Spawns the engine `program` with the specified `args` and returns its id.
+    pub fn spawn<P: AsRef<OsStr>, A: AsRef<OsStr>>(&mut self, program: P, args: &[A]) -> IoResult<EngineId> {
+        let mut command = Command::new(program);
+        command.args(args);
+        self.spawn_command(command)
+    }
+
+    /// Spawns the engine with the prepared `command` and returns its id. The command's standard input and output are
+    /// replaced with pipes.
+    pub fn spawn_command(&mut self, mut command: Command) -> IoResult<EngineId> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| IoError::other("the engine's stdin is not piped"))?;
+        let stdout = child.stdout.take().ok_or_else(|| IoError::other("the engine's stdout is not piped"))?;
+
+        Ok(self.add(Some(child), Box::new(stdin), stdout))
+    }
+
+    /// Adds an engine that is not a child process, but is reached over the given streams, and returns its id: the
+    /// messages are written to `input` and read from `output`. The engine is considered to have exited once it closes
+    /// its `output`.
+    pub fn add_streams<W, R>(&mut self, input: W, output: R) -> EngineId
+        where W: Write + Send + 'static, R: Read + Send + 'static {
+        self.add(None, Box::new(input), output)
+    }
+
+    fn add<R: Read + Send + 'static>(&mut self, child: Option<Child>, stdin: Box<dyn Write + Send>, stdout: R) -> EngineId {
+        let id = EngineId(self.next_id);
+        self.next_id += 1;
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(l) => l,
+                    Err(_) => break,
+                };
+
+                if !line.trim().is_empty() && sender.send(EngineEvent::Message(id, parse_one(&line))).is_err() {
+                    return;
+                }
+            }
+
+            let _ = sender.send(EngineEvent::Closed(id));
+        });
+
+        self.engines.insert(id, ManagedEngine { child, stdin });
+        self.open += 1;
+        id
+    }
+
+    /// Returns the ids of the engines, in the order they were added.
+    pub fn engine_ids(&self) -> Vec<EngineId> {
+        self.engines.keys().copied().collect()
+    }
+
+    /// Returns the number of engines.
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+
+    /// Returns `true` if there are no engines.
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+    }
+
+    /// Sends the `message` to the engine with the specified `id`. An error of the `NotFound` kind is returned if
+    /// there is no such engine.
+    pub fn send(&mut self, id: EngineId, message: &UciMessage) -> IoResult<()> {
+        let engine = self.engines
+            .get_mut(&id)
+            .ok_or_else(|| IoError::new(ErrorKind::NotFound, format!("there is no {}", id)))?;
+
+        writeln!(engine.stdin, "{}", message.serialize())?;
+        engine.stdin.flush()
+    }
+
+    /// Sends the `message` to all of the engines. Stops at the first error.
+    pub fn broadcast(&mut self, message: &UciMessage) -> IoResult<()> {
+        for id in self.engine_ids() {
+            self.send(id, message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Blocks until an event arrives from any of the engines. Returns `None` once all of the engines have closed
+    /// their output and all of their events have been received.
+    pub fn recv(&mut self) -> Option<EngineEvent> {
+        if self.open == 0 {
+            return self.try_recv();
+        }
+
+        let event = self.receiver.recv().ok();
+        self.track(event)
+    }
+
+    /// Returns an event that has already arrived, without blocking.
+    pub fn try_recv(&mut self) -> Option<EngineEvent> {
+        let event = match self.receiver.try_recv() {
+            Ok(e) => Some(e),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        };
+        self.track(event)
+    }
+
+    /// Blocks until an event arrives from any of the engines, but no longer than the `timeout`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<EngineEvent> {
+        let event = match self.receiver.recv_timeout(timeout) {
+            Ok(e) => Some(e),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        };
+        self.track(event)
+    }
+
+    /// Sends `quit` to the engine with the specified `id`, waits for it to exit and removes it. Its remaining
+    /// messages, followed by `EngineEvent::Closed`, can still be received. An engine that is not a child process is
+    /// not waited for: its input is closed, and its `EngineEvent::Closed` follows once it closes its output.
+    pub fn quit(&mut self, id: EngineId) -> IoResult<()> {
+        self.send(id, &UciMessage::Quit)?;
+        if let Some(ManagedEngine { child: Some(mut child), .. }) = self.engines.remove(&id) {
+            child.wait()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends `quit` to all of the engines and waits for them to exit.
+    pub fn quit_all(&mut self) -> IoResult<()> {
+        for id in self.engine_ids() {
+            self.quit(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn track(&mut self, event: Option<EngineEvent>) -> Option<EngineEvent> {
+        if let Some(EngineEvent::Closed(..)) = event {
+            self.open -= 1;
+        }
+
+        event
+    }
+}
+
+impl Drop for EngineManager {
+    fn drop(&mut self) {
+        for child in self.engines.values_mut().filter_map(|e| e.child.as_mut()) {
+            if let Ok(None) = child.try_wait() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::MockEngine;
+
+    use super::*;
+
+    fn add_mock(manager: &mut EngineManager, name: &str) -> EngineId {
+        let (input, output) = MockEngine::new(name, "Tester").spawn_pipes();
+        manager.add_streams(input, output)
+    }
+
+    #[test]
+    fn test_multiplexing() {
+        let mut manager = EngineManager::new();
+        let first = add_mock(&mut manager, "First");
+        let second = add_mock(&mut manager, "Second");
+        assert_eq!(manager.engine_ids(), vec![EngineId(0), EngineId(1)]);
+
+        manager.send(second, &UciMessage::Uci).unwrap();
+        assert_eq!(manager.recv(), Some(EngineEvent::Message(second, UciMessage::id_name("Second"))));
+        assert_eq!(manager.recv(), Some(EngineEvent::Message(second, UciMessage::id_author("Tester"))));
+        assert_eq!(manager.recv(), Some(EngineEvent::Message(second, UciMessage::UciOk)));
+        assert_eq!(manager.try_recv(), None);
+
+        manager.broadcast(&UciMessage::Uci).unwrap();
+        let mut events: Vec<EngineEvent> = (0..6).map(|_| manager.recv().unwrap()).collect();
+        events.sort_by_key(|e| e.engine_id());
+        assert_eq!(events[0], EngineEvent::Message(first, UciMessage::id_name("First")));
+        assert_eq!(events[5], EngineEvent::Message(second, UciMessage::UciOk));
+
+        manager.quit(first).unwrap();
+        assert_eq!(manager.recv(), Some(EngineEvent::Closed(first)));
+        assert_eq!(manager.send(first, &UciMessage::Uci).unwrap_err().kind(), ErrorKind::NotFound);
+
+        manager.quit_all().unwrap();
+        assert!(manager.is_empty());
+        assert_eq!(manager.recv(), Some(EngineEvent::Closed(second)));
+        assert_eq!(manager.recv(), None);
+    }
+}
@@ -0,0 +1,265 @@
+//! The `metrics` module contains `UciMetrics`, a collector of protocol statistics: the number of messages by kind and
+//! direction, the bytes parsed and serialized, the parse failures and the rate of `info` lines. A `MetricsSnapshot` of
+//! the statistics can be taken at any time, and written in the Prometheus text exposition format.
+
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::proxy::{Middleware, ProxyContext};
+use crate::record::direction_name;
+use crate::uci::{CommunicationDirection, UciMessage, UciMessageKind};
+
+/// The statistics collected by `UciMetrics` at a point in time.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct MetricsSnapshot {
+    /// The time elapsed since the collection started (or was last reset).
+    pub elapsed: Duration,
+
+    /// The number of messages, by direction and kind.
+    pub messages: HashMap<(CommunicationDirection, UciMessageKind), u64>,
+
+    /// The number of bytes of the parsed lines.
+    pub bytes_parsed: u64,
+
+    /// The number of bytes of the serialized lines.
+    pub bytes_serialized: u64,
+
+    /// The number of lines that could not be parsed.
+    pub parse_failures: u64,
+
+    /// The number of `info` messages.
+    pub info_lines: u64,
+}
+
+impl MetricsSnapshot {
+    /// Returns the number of messages of the `kind` in the `direction`.
+    pub fn count(&self, direction: CommunicationDirection, kind: UciMessageKind) -> u64 {
+        self.messages.get(&(direction, kind)).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of messages in the `direction`.
+    pub fn total(&self, direction: CommunicationDirection) -> u64 {
+        self.messages.iter().filter(|((d, _), _)| *d == direction).map(|(_, n)| n).sum()
+    }
+
+    /// Returns the average number of `info` messages per second.
+    pub fn info_rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.info_lines as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Writes the statistics in the Prometheus text exposition format, with the names of the metrics starting with the
+    /// `prefix` (as in, `uci_messages_total{direction="gui-to-engine",kind="go"} 3`).
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut text = String::new();
+
+        let mut messages: Vec<(&'static str, &'static str, u64)> = self.messages.iter()
+            .map(|((d, k), n)| (direction_name(*d), k.as_str(), *n))
+            .collect();
+        messages.sort();
+
+        let _ = writeln!(text, "# TYPE {}_messages_total counter", prefix);
+        for (direction, kind, n) in messages {
+            let _ = writeln!(text, "{}_messages_total{{direction=\"{}\",kind=\"{}\"}} {}", prefix, direction, kind, n);
+        }
+
+        for (name, value) in &[
+            ("bytes_parsed_total", self.bytes_parsed),
+            ("bytes_serialized_total", self.bytes_serialized),
+            ("parse_failures_total", self.parse_failures),
+            ("info_lines_total", self.info_lines),
+        ] {
+            let _ = writeln!(text, "# TYPE {}_{} counter", prefix, name);
+            let _ = writeln!(text, "{}_{} {}", prefix, name, value);
+        }
+
+        text
+    }
+}
+
+#[derive(Debug)]
+struct MetricsState {
+    started: Instant,
+    snapshot: MetricsSnapshot,
+}
+
+impl MetricsState {
+    fn new() -> MetricsState {
+        MetricsState {
+            started: Instant::now(),
+            snapshot: MetricsSnapshot::default(),
+        }
+    }
+}
+
+/// Collects statistics about the messages exchanged with an engine. The collector is a cheap handle to shared state,
+/// so it can be cloned and updated from several threads while another one takes snapshots.
+///
+/// Call `record_parsed(..)` for each line received and parsed, and `record_serialized(..)` for each message serialized
+/// and sent. `UciMetrics` is also a `Middleware`, which counts each message passing through a `UciProxy` once (but
+/// does not see the bytes).
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse_one, CommunicationDirection, UciMessage, UciMessageKind};
+/// use vampirc_uci::metrics::UciMetrics;
+///
+/// let metrics = UciMetrics::new();
+/// metrics.record_serialized(CommunicationDirection::GuiToEngine, "go depth 2", &UciMessage::go());
+/// for line in &["info depth 1", "info depth 2", "bestmove e2e4", "bestmov e2e4"] {
+///     metrics.record_parsed(CommunicationDirection::EngineToGui, line, &parse_one(line));
+/// }
+///
+/// let snapshot = metrics.snapshot();
+/// assert_eq!(snapshot.count(CommunicationDirection::EngineToGui, UciMessageKind::Info), 2);
+/// assert_eq!(snapshot.total(CommunicationDirection::EngineToGui), 4);
+/// assert_eq!(snapshot.parse_failures, 1);
+/// assert_eq!(snapshot.bytes_serialized, 10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UciMetrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Default for UciMetrics {
+    fn default() -> Self {
+        UciMetrics::new()
+    }
+}
+
+impl UciMetrics {
+    /// Creates a collector with all of the statistics at zero.
+    pub fn new() -> UciMetrics {
+        UciMetrics {
+            state: Arc::new(Mutex::new(MetricsState::new())),
+        }
+    }
+
+    /// Records a `message` in the `direction`, parsed from the `line`. An unknown message with a parse error counts
+    /// as a parse failure.
+    pub fn record_parsed(&self, direction: CommunicationDirection, line: &str, message: &UciMessage) {
+        let mut state = self.state.lock().unwrap();
+        state.snapshot.bytes_parsed += line.len() as u64;
+        count(&mut state.snapshot, direction, message);
+    }
+
+    /// Records a `message` in the `direction`, serialized into the `line`.
+    pub fn record_serialized(&self, direction: CommunicationDirection, line: &str, message: &UciMessage) {
+        let mut state = self.state.lock().unwrap();
+        state.snapshot.bytes_serialized += line.len() as u64;
+        count(&mut state.snapshot, direction, message);
+    }
+
+    /// Records a `message` in the `direction`, without its bytes.
+    pub fn record(&self, direction: CommunicationDirection, message: &UciMessage) {
+        count(&mut self.state.lock().unwrap().snapshot, direction, message);
+    }
+
+    /// Returns the statistics collected so far.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let state = self.state.lock().unwrap();
+        MetricsSnapshot {
+            elapsed: state.started.elapsed(),
+            ..state.snapshot.clone()
+        }
+    }
+
+    /// Sets all of the statistics back to zero and restarts the clock.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = MetricsState::new();
+    }
+}
+
+fn count(snapshot: &mut MetricsSnapshot, direction: CommunicationDirection, message: &UciMessage) {
+    let kind = message.kind();
+    *snapshot.messages.entry((direction, kind)).or_insert(0) += 1;
+
+    match message {
+        UciMessage::Info(_) => snapshot.info_lines += 1,
+        UciMessage::Unknown(_, Some(_)) => snapshot.parse_failures += 1,
+        _ => {}
+    }
+}
+
+impl Middleware for UciMetrics {
+    fn process(&mut self, message: UciMessage, context: &mut ProxyContext) -> Option<UciMessage> {
+        self.record(context.direction(), &message);
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::parser::parse_one;
+
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let metrics = UciMetrics::new();
+        for line in &["uci", "isready", "go infinite", "stop"] {
+            metrics.record_serialized(CommunicationDirection::GuiToEngine, line, &parse_one(line));
+        }
+        for line in &["id name Vampirc", "uciok", "readyok", "info depth 1", "info depth 2", "info depth 3", "bestmove e2e4"] {
+            metrics.record_parsed(CommunicationDirection::EngineToGui, line, &parse_one(line));
+        }
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.total(CommunicationDirection::GuiToEngine), 4);
+        assert_eq!(snapshot.total(CommunicationDirection::EngineToGui), 7);
+        assert_eq!(snapshot.count(CommunicationDirection::EngineToGui, UciMessageKind::Info), 3);
+        assert_eq!(snapshot.count(CommunicationDirection::GuiToEngine, UciMessageKind::Info), 0);
+        assert_eq!(snapshot.bytes_serialized, 3 + 7 + 11 + 4);
+        assert_eq!(snapshot.bytes_parsed, 15 + 5 + 7 + 12 * 3 + 13);
+        assert_eq!(snapshot.info_lines, 3);
+        assert_eq!(snapshot.parse_failures, 0);
+        assert!(snapshot.info_rate() > 0.0);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot().messages.len(), 0);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let metrics = UciMetrics::new();
+        let handles: Vec<_> = (0..4).map(|_| {
+            let metrics = metrics.clone();
+            thread::spawn(move || {
+                for _ in 0..25 {
+                    metrics.record(CommunicationDirection::EngineToGui, &UciMessage::ReadyOk);
+                }
+            })
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(metrics.snapshot().count(CommunicationDirection::EngineToGui, UciMessageKind::ReadyOk), 100);
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let metrics = UciMetrics::new();
+        metrics.record(CommunicationDirection::GuiToEngine, &UciMessage::go());
+        metrics.record(CommunicationDirection::EngineToGui, &UciMessage::info_depth(1));
+        metrics.record_parsed(CommunicationDirection::EngineToGui, "bestmov", &parse_one("bestmov"));
+
+        assert_eq!(metrics.snapshot().to_prometheus("uci"), "# TYPE uci_messages_total counter\n\
+            uci_messages_total{direction=\"engine-to-gui\",kind=\"info\"} 1\n\
+            uci_messages_total{direction=\"engine-to-gui\",kind=\"unknown\"} 1\n\
+            uci_messages_total{direction=\"gui-to-engine\",kind=\"go\"} 1\n\
+            # TYPE uci_bytes_parsed_total counter\nuci_bytes_parsed_total 7\n\
+            # TYPE uci_bytes_serialized_total counter\nuci_bytes_serialized_total 0\n\
+            # TYPE uci_parse_failures_total counter\nuci_parse_failures_total 1\n\
+            # TYPE uci_info_lines_total counter\nuci_info_lines_total 1\n");
+    }
+}
@@ -0,0 +1,1047 @@
+//! The `options` module contains utilities for working with the engine options declared through the `option`
+//! message and changed through the `setoption` message.
+
+use std::error::Error as StdError;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::path::PathBuf;
+
+use crate::uci::{UciFloat, UciMessage, UciOptionConfig};
+
+/// A typed value of an engine option.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum OptionValue {
+    /// The value of a `check` option.
+    Check(bool),
+
+    /// The value of a `spin` option.
+    Spin(i64),
+
+    /// The value of a `spin` option declared with decimal values (see `UciOptionConfig::Float`).
+    Float(UciFloat),
+
+    /// The value of a `combo` option, spelled as in the option's declaration.
+    Combo(String),
+
+    /// The value of a `string` option. The UCI `<empty>` value is represented by an empty string.
+    String(String),
+
+    /// A `button` option does not carry a value; this signifies the button was pressed.
+    Button,
+}
+
+impl OptionValue {
+    /// Returns the default value of the declared option. If the declaration does not specify a default, the value
+    /// is `false` for `check` options, the minimum (or `0`) for `spin` options (decimal or not), the first `var` for `combo` options
+    /// and the empty string for `string` options.
+    pub fn from_default(config: &UciOptionConfig) -> OptionValue {
+        match config {
+            UciOptionConfig::Check { default, .. } => OptionValue::Check(default.unwrap_or(false)),
+            UciOptionConfig::Spin { default, min, .. } => OptionValue::Spin(default.or(*min).unwrap_or(0)),
+            UciOptionConfig::Float { default, min, .. } => OptionValue::Float(default.or(*min).unwrap_or(UciFloat(0.0))),
+            UciOptionConfig::Combo { default, var, .. } => {
+                OptionValue::Combo(default.clone().or_else(|| var.first().cloned()).unwrap_or_default())
+            }
+            UciOptionConfig::String { default, .. } => {
+                let d = default.as_deref().unwrap_or("");
+                OptionValue::String(if d.eq_ignore_ascii_case("<empty>") { String::new() } else { d.to_string() })
+            }
+            UciOptionConfig::Button { .. } => OptionValue::Button,
+        }
+    }
+}
+
+impl Display for OptionValue {
+    /// Formats the value as it would appear in a `setoption` message.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            OptionValue::Check(b) => write!(f, "{}", b),
+            OptionValue::Spin(i) => write!(f, "{}", i),
+            OptionValue::Float(d) => write!(f, "{}", d),
+            OptionValue::Combo(s) | OptionValue::String(s) => {
+                if s.is_empty() {
+                    write!(f, "<empty>")
+                } else {
+                    write!(f, "{}", s)
+                }
+            }
+            OptionValue::Button => Ok(()),
+        }
+    }
+}
+
+/// An error describing why a `setoption` message does not match the declared options.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum OptionError {
+    /// The message is not a `setoption` message.
+    NotASetOption,
+
+    /// No option with this name has been declared.
+    UnknownOption(String),
+
+    /// The option requires a value, but none was given.
+    MissingValue(String),
+
+    /// The value cannot be interpreted as the option's type. The name of the expected type (as in `"check"` or
+    /// `"spin"`) is included.
+    InvalidValue {
+        /// The name of the option.
+        name: String,
+
+        /// The offending value.
+        value: String,
+
+        /// The type of the option.
+        expected: &'static str,
+    },
+
+    /// The value of a `spin` option lies outside of its declared bounds.
+    OutOfRange {
+        /// The name of the option.
+        name: String,
+
+        /// The offending value.
+        value: i64,
+
+        /// The declared minimal value.
+        min: Option<i64>,
+
+        /// The declared maximal value.
+        max: Option<i64>,
+    },
+
+    /// The value of a decimal `spin` option lies outside of its declared bounds.
+    FloatOutOfRange {
+        /// The name of the option.
+        name: String,
+
+        /// The offending value.
+        value: UciFloat,
+
+        /// The declared minimal value.
+        min: Option<UciFloat>,
+
+        /// The declared maximal value.
+        max: Option<UciFloat>,
+    },
+
+    /// The value of a `combo` option is not one of its declared `var`s.
+    InvalidComboValue {
+        /// The name of the option.
+        name: String,
+
+        /// The offending value.
+        value: String,
+    },
+}
+
+impl Display for OptionError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            OptionError::NotASetOption => write!(f, "not a `setoption` message"),
+            OptionError::UnknownOption(name) => write!(f, "unknown option `{}`", name),
+            OptionError::MissingValue(name) => write!(f, "option `{}` requires a value", name),
+            OptionError::InvalidValue { name, value, expected } => {
+                write!(f, "value `{}` of option `{}` is not a valid {} value", value, name, expected)
+            }
+            OptionError::OutOfRange { name, value, min, max } => {
+                write!(f, "value {} of option `{}` is out of range", value, name)?;
+                match (min, max) {
+                    (Some(mn), Some(mx)) => write!(f, " {}..{}", mn, mx),
+                    (Some(mn), None) => write!(f, " (min {})", mn),
+                    (None, Some(mx)) => write!(f, " (max {})", mx),
+                    (None, None) => Ok(()),
+                }
+            }
+            OptionError::FloatOutOfRange { name, value, min, max } => {
+                write!(f, "value {} of option `{}` is out of range", value, name)?;
+                match (min, max) {
+                    (Some(mn), Some(mx)) => write!(f, " {}..{}", mn, mx),
+                    (Some(mn), None) => write!(f, " (min {})", mn),
+                    (None, Some(mx)) => write!(f, " (max {})", mx),
+                    (None, None) => Ok(()),
+                }
+            }
+            OptionError::InvalidComboValue { name, value } => {
+                write!(f, "`{}` is not one of the values of option `{}`", value, name)
+            }
+        }
+    }
+}
+
+impl StdError for OptionError {}
+
+/// A `setoption` message for one of the options most engines declare, with its value parsed, as returned by
+/// `UciMessage::as_standard_option()`. It spares the engines the matching of the names and the parsing of the values.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum StandardSetOption {
+    /// The number of search threads (`Threads`).
+    Threads(u32),
+
+    /// The size of the hash table in MB (`Hash`).
+    Hash(u64),
+
+    /// The number of principal variations to report (`MultiPV`).
+    MultiPv(u16),
+
+    /// Whether the engine may ponder (`Ponder`).
+    Ponder(bool),
+
+    /// The directories of the Syzygy tablebases (`SyzygyPath`), separated by `;` on Windows and by `:` elsewhere. The
+    /// UCI `<empty>` value gives no directories.
+    SyzygyPath(Vec<PathBuf>),
+}
+
+impl UciMessage {
+    /// If this is a `setoption` message for one of the standard options (`Threads`, `Hash`, `MultiPV`, `Ponder` or
+    /// `SyzygyPath`, matched case-insensitively) with a valid value, returns it with its value parsed. Returns `None`
+    /// for any other message or option, or if the value does not parse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse_one;
+    /// use vampirc_uci::options::StandardSetOption;
+    ///
+    /// assert_eq!(parse_one("setoption name Hash value 256").as_standard_option(), Some(StandardSetOption::Hash(256)));
+    /// assert_eq!(parse_one("setoption name multipv value 3").as_standard_option(), Some(StandardSetOption::MultiPv(3)));
+    /// assert_eq!(parse_one("setoption name Threads value many").as_standard_option(), None);
+    /// assert_eq!(parse_one("setoption name Style value Risky").as_standard_option(), None);
+    /// ```
+    pub fn as_standard_option(&self) -> Option<StandardSetOption> {
+        let (name, value) = match self {
+            UciMessage::SetOption { name, value: Some(value) } => (name.to_ascii_lowercase(), value.trim()),
+            _ => return None,
+        };
+
+        match name.as_str() {
+            "threads" => value.parse().ok().map(StandardSetOption::Threads),
+            "hash" => value.parse().ok().map(StandardSetOption::Hash),
+            "multipv" => value.parse().ok().map(StandardSetOption::MultiPv),
+            "ponder" => {
+                if value.eq_ignore_ascii_case("true") {
+                    Some(StandardSetOption::Ponder(true))
+                } else if value.eq_ignore_ascii_case("false") {
+                    Some(StandardSetOption::Ponder(false))
+                } else {
+                    None
+                }
+            }
+            "syzygypath" => {
+                let separator = if cfg!(windows) { ';' } else { ':' };
+                let paths = if value.eq_ignore_ascii_case("<empty>") {
+                    vec![]
+                } else {
+                    value.split(separator).map(str::trim).filter(|p| !p.is_empty()).map(PathBuf::from).collect()
+                };
+                Some(StandardSetOption::SyzygyPath(paths))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A registry of the options an engine has declared through `option` messages, used to validate `setoption` messages
+/// against them. As suggested by the UCI specification, option names are matched case-insensitively.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, parse_one};
+/// use vampirc_uci::options::{OptionError, OptionRegistry, OptionValue};
+///
+/// let registry = OptionRegistry::from_messages(&parse("option name Hash type spin default 16 min 1 max 1024\n"));
+///
+/// assert_eq!(registry.validate(&parse_one("setoption name hash value 128")), Ok(OptionValue::Spin(128)));
+/// assert!(registry.validate(&parse_one("setoption name Hash value 4096")).is_err());
+/// assert_eq!(registry.validate_clamped(&parse_one("setoption name Hash value 4096")), Ok(OptionValue::Spin(1024)));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OptionRegistry {
+    options: Vec<UciOptionConfig>,
+}
+
+impl OptionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> OptionRegistry {
+        OptionRegistry::default()
+    }
+
+    /// Creates a registry from the `option` messages in `messages`. All other messages are ignored.
+    pub fn from_messages<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> OptionRegistry {
+        let mut registry = OptionRegistry::new();
+        for m in messages {
+            if let UciMessage::Option(config) = m {
+                registry.register(config.clone());
+            }
+        }
+
+        registry
+    }
+
+    /// Registers an option, replacing any previously registered option with the same name.
+    pub fn register(&mut self, config: UciOptionConfig) {
+        match self.options.iter().position(|o| o.get_name().eq_ignore_ascii_case(config.get_name())) {
+            Some(index) => self.options[index] = config,
+            None => self.options.push(config),
+        }
+    }
+
+    /// Returns the declaration of the option with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&UciOptionConfig> {
+        self.options.iter().find(|o| o.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns an iterator over the registered options, in the order of their registration.
+    pub fn iter(&self) -> impl Iterator<Item=&UciOptionConfig> {
+        self.options.iter()
+    }
+
+    /// Returns the number of registered options.
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Returns `true` if no options have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Validates a `setoption` message against the declared options and returns the typed value.
+    pub fn validate(&self, message: &UciMessage) -> Result<OptionValue, OptionError> {
+        self.do_validate(message, false)
+    }
+
+    /// Like `validate`, except that out-of-range `spin` values are clamped to the declared bounds instead of being
+    /// rejected.
+    pub fn validate_clamped(&self, message: &UciMessage) -> Result<OptionValue, OptionError> {
+        self.do_validate(message, true)
+    }
+
+    fn do_validate(&self, message: &UciMessage, clamp: bool) -> Result<OptionValue, OptionError> {
+        match message {
+            UciMessage::SetOption { name, value } => {
+                let config = self.get(name).ok_or_else(|| OptionError::UnknownOption(name.clone()))?;
+                parse_option_value(config, value.as_deref(), clamp)
+            }
+            _ => Err(OptionError::NotASetOption)
+        }
+    }
+}
+
+/// Interprets the `value` of a `setoption` message according to the option's declaration. If `clamp` is `true`,
+/// out-of-range `spin` values are clamped to the declared bounds.
+pub fn parse_option_value(config: &UciOptionConfig, value: Option<&str>, clamp: bool) -> Result<OptionValue, OptionError> {
+    let name = config.get_name();
+    let invalid = |v: &str| OptionError::InvalidValue {
+        name: name.to_string(),
+        value: v.to_string(),
+        expected: config.get_type_str(),
+    };
+
+    if let UciOptionConfig::Button { .. } = config {
+        return match value {
+            None => Ok(OptionValue::Button),
+            Some(v) => Err(invalid(v)),
+        };
+    }
+
+    let val = value.ok_or_else(|| OptionError::MissingValue(name.to_string()))?;
+    let val_or_empty = if val.eq_ignore_ascii_case("<empty>") { "" } else { val };
+
+    match config {
+        UciOptionConfig::Check { .. } => {
+            if val.eq_ignore_ascii_case("true") {
+                Ok(OptionValue::Check(true))
+            } else if val.eq_ignore_ascii_case("false") {
+                Ok(OptionValue::Check(false))
+            } else {
+                Err(invalid(val))
+            }
+        }
+        UciOptionConfig::Spin { min, max, .. } => {
+            let mut i = str::parse::<i64>(val.trim()).map_err(|_| invalid(val))?;
+            let below = min.is_some_and(|mn| i < mn);
+            let above = max.is_some_and(|mx| i > mx);
+
+            if below || above {
+                if !clamp {
+                    return Err(OptionError::OutOfRange { name: name.to_string(), value: i, min: *min, max: *max });
+                }
+
+                if below {
+                    i = min.unwrap();
+                } else {
+                    i = max.unwrap();
+                }
+            }
+
+            Ok(OptionValue::Spin(i))
+        }
+        UciOptionConfig::Float { min, max, .. } => {
+            let mut d = str::parse::<f64>(val.trim()).ok().filter(|d| d.is_finite()).ok_or_else(|| invalid(val))?;
+            let below = min.is_some_and(|mn| d < mn.0);
+            let above = max.is_some_and(|mx| d > mx.0);
+
+            if below || above {
+                if !clamp {
+                    return Err(OptionError::FloatOutOfRange { name: name.to_string(), value: UciFloat(d), min: *min, max: *max });
+                }
+
+                if below {
+                    d = min.unwrap().0;
+                } else {
+                    d = max.unwrap().0;
+                }
+            }
+
+            Ok(OptionValue::Float(UciFloat(d)))
+        }
+        UciOptionConfig::Combo { var, .. } => {
+            var.iter()
+                .find(|v| v.eq_ignore_ascii_case(val_or_empty))
+                .map(|v| OptionValue::Combo(v.clone()))
+                .ok_or_else(|| OptionError::InvalidComboValue { name: name.to_string(), value: val.to_string() })
+        }
+        UciOptionConfig::String { .. } => Ok(OptionValue::String(val_or_empty.to_string())),
+        UciOptionConfig::Button { .. } => unreachable!(),
+    }
+}
+
+/// The callback invoked when the value of an engine option changes.
+pub type OptionCallback = Box<dyn FnMut(&OptionValue) + Send>;
+
+struct EngineOption {
+    config: UciOptionConfig,
+    value: OptionValue,
+    on_change: Option<OptionCallback>,
+}
+
+/// The options of an engine, along with their current values. The engine declares its options, announces them with
+/// `messages()` in reply to `uci`, and hands each `setoption` message to `apply(..)`, which validates it, stores the
+/// new value and invokes the option's change callback, if any. The current values can then be read through the
+/// typed getters.
+///
+/// As with the `OptionRegistry`, option names are matched case-insensitively.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse_one, UciOptionConfig};
+/// use vampirc_uci::options::{EngineOptions, OptionValue};
+///
+/// let mut options = EngineOptions::new();
+/// options.declare(UciOptionConfig::Spin { name: String::from("Hash"), default: Some(16), min: Some(1), max: Some(1024) });
+/// options.declare_with_callback(UciOptionConfig::Button { name: String::from("Clear Hash") }, |_| println!("Clearing"));
+///
+/// assert_eq!(options.get_spin("Hash"), Some(16));
+/// assert_eq!(options.apply(&parse_one("setoption name hash value 256")), Ok(OptionValue::Spin(256)));
+/// assert_eq!(options.get_spin("Hash"), Some(256));
+/// assert!(options.apply(&parse_one("setoption name Hash value 0")).is_err());
+/// ```
+#[derive(Default)]
+pub struct EngineOptions {
+    options: Vec<EngineOption>,
+}
+
+impl EngineOptions {
+    /// Creates an empty set of options.
+    pub fn new() -> EngineOptions {
+        EngineOptions::default()
+    }
+
+    /// Declares an option, with its default value as the current value. An option previously declared with the same
+    /// name is replaced.
+    pub fn declare(&mut self, config: UciOptionConfig) -> &mut EngineOptions {
+        self.do_declare(config, None)
+    }
+
+    /// Declares an option like `declare(..)` does, with a callback to invoke whenever its value is set through
+    /// `apply(..)` or `set(..)`.
+    pub fn declare_with_callback<F: FnMut(&OptionValue) + Send + 'static>(&mut self, config: UciOptionConfig, on_change: F) -> &mut EngineOptions {
+        self.do_declare(config, Some(Box::new(on_change)))
+    }
+
+    fn do_declare(&mut self, config: UciOptionConfig, on_change: Option<OptionCallback>) -> &mut EngineOptions {
+        let option = EngineOption {
+            value: OptionValue::from_default(&config),
+            config,
+            on_change,
+        };
+
+        match self.position(option.config.get_name()) {
+            Some(index) => self.options[index] = option,
+            None => self.options.push(option),
+        }
+
+        self
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.options.iter().position(|o| o.config.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the declarations of the options, in the order they were declared.
+    pub fn configs(&self) -> Vec<UciOptionConfig> {
+        self.options.iter().map(|o| o.config.clone()).collect()
+    }
+
+    /// Returns the `option` messages announcing the options, in the order they were declared.
+    pub fn messages(&self) -> Vec<UciMessage> {
+        self.options.iter().map(|o| UciMessage::Option(o.config.clone())).collect()
+    }
+
+    /// Validates the `setoption` message and, if it is valid, stores the new value, invokes the option's change
+    /// callback and returns the value.
+    pub fn apply(&mut self, message: &UciMessage) -> Result<OptionValue, OptionError> {
+        match message {
+            UciMessage::SetOption { name, value } => self.set(name, value.as_deref()),
+            _ => Err(OptionError::NotASetOption)
+        }
+    }
+
+    /// Validates the `value` of the option named `name` and, if it is valid, stores it, invokes the option's change
+    /// callback and returns the value.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<OptionValue, OptionError> {
+        let index = self.position(name).ok_or_else(|| OptionError::UnknownOption(name.to_string()))?;
+        let option = &mut self.options[index];
+        let new_value = parse_option_value(&option.config, value, false)?;
+
+        if let Some(on_change) = &mut option.on_change {
+            on_change(&new_value);
+        }
+        option.value = new_value.clone();
+
+        Ok(new_value)
+    }
+
+    /// Returns the current value of the option named `name`.
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.position(name).map(|i| &self.options[i].value)
+    }
+
+    /// Returns the current value of the `check` option named `name`.
+    pub fn get_check(&self, name: &str) -> Option<bool> {
+        match self.get(name) {
+            Some(OptionValue::Check(b)) => Some(*b),
+            _ => None
+        }
+    }
+
+    /// Returns the current value of the `spin` option named `name`.
+    pub fn get_spin(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(OptionValue::Spin(i)) => Some(*i),
+            _ => None
+        }
+    }
+
+    /// Returns the current value of the decimal `spin` option named `name`.
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        match self.get(name) {
+            Some(OptionValue::Float(d)) => Some(d.0),
+            _ => None
+        }
+    }
+
+    /// Returns the current value of the `combo` or `string` option named `name`.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(OptionValue::Combo(s)) | Some(OptionValue::String(s)) => Some(s.as_str()),
+            _ => None
+        }
+    }
+
+    /// Returns the number of declared options.
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Returns `true` if no options have been declared.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+}
+
+/// The current values of the options an engine declared, as the GUI sees them. The store starts with the default
+/// value of each option, applies the `setoption` messages the GUI sends (rejecting the invalid ones), and tells which
+/// options differ from their defaults. Given a desired configuration, it emits only the `setoption` messages that
+/// change a value.
+///
+/// As with the `OptionRegistry`, option names are matched case-insensitively. A `button` option has no value to
+/// store; pressing it leaves the store unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, parse_one, UciMessage};
+/// use vampirc_uci::options::{OptionValue, OptionValues};
+///
+/// let mut values = OptionValues::from_messages(&parse("option name Hash type spin default 16 min 1 max 1024\n\
+///     option name Ponder type check default false\noption name Threads type spin default 1 min 1 max 64\n"));
+///
+/// values.apply(&parse_one("setoption name Hash value 256")).unwrap();
+/// assert_eq!(values.changed(), vec![("Hash", &OptionValue::Spin(256))]);
+///
+/// let messages = values.messages_to_reach(&[("hash", "256"), ("Threads", "8"), ("Ponder", "false")]).unwrap();
+/// assert_eq!(messages, vec![UciMessage::set_option("Threads", Some(String::from("8")))]);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OptionValues {
+    options: Vec<(UciOptionConfig, OptionValue)>,
+}
+
+impl OptionValues {
+    /// Creates an empty store.
+    pub fn new() -> OptionValues {
+        OptionValues::default()
+    }
+
+    /// Creates a store from the `option` messages in `messages`, with the default values. All other messages are
+    /// ignored.
+    pub fn from_messages<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> OptionValues {
+        let mut values = OptionValues::new();
+        for m in messages {
+            if let UciMessage::Option(config) = m {
+                values.declare(config.clone());
+            }
+        }
+
+        values
+    }
+
+    /// Creates a store from the options of the `registry`, with the default values.
+    pub fn from_registry(registry: &OptionRegistry) -> OptionValues {
+        let mut values = OptionValues::new();
+        for config in registry.iter() {
+            values.declare(config.clone());
+        }
+
+        values
+    }
+
+    /// Declares an option, with its default value as the current value. An option previously declared with the same
+    /// name is replaced.
+    pub fn declare(&mut self, config: UciOptionConfig) {
+        let value = OptionValue::from_default(&config);
+        match self.position(config.get_name()) {
+            Some(index) => self.options[index] = (config, value),
+            None => self.options.push((config, value)),
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.options.iter().position(|(c, _)| c.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Validates the `setoption` message and, if it is valid, stores the new value and returns it.
+    pub fn apply(&mut self, message: &UciMessage) -> Result<OptionValue, OptionError> {
+        match message {
+            UciMessage::SetOption { name, value } => self.set(name, value.as_deref()),
+            _ => Err(OptionError::NotASetOption)
+        }
+    }
+
+    /// Validates the `value` of the option named `name` and, if it is valid, stores it and returns it.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<OptionValue, OptionError> {
+        let index = self.position(name).ok_or_else(|| OptionError::UnknownOption(name.to_string()))?;
+        let option = &mut self.options[index];
+        let new_value = parse_option_value(&option.0, value, false)?;
+        option.1 = new_value.clone();
+
+        Ok(new_value)
+    }
+
+    /// Returns the current value of the option named `name`.
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.position(name).map(|i| &self.options[i].1)
+    }
+
+    /// Returns the declaration of the option named `name`.
+    pub fn config(&self, name: &str) -> Option<&UciOptionConfig> {
+        self.position(name).map(|i| &self.options[i].0)
+    }
+
+    /// Returns `true` if the option named `name` is declared and has its default value.
+    pub fn is_default(&self, name: &str) -> bool {
+        self.position(name).is_some_and(|i| self.options[i].1 == OptionValue::from_default(&self.options[i].0))
+    }
+
+    /// Returns the names (as declared) and the values of the options whose values differ from their defaults, in the
+    /// order they were declared.
+    pub fn changed(&self) -> Vec<(&str, &OptionValue)> {
+        self.options.iter()
+            .filter(|(c, v)| *v != OptionValue::from_default(c))
+            .map(|(c, v)| (c.get_name(), v))
+            .collect()
+    }
+
+    /// Returns an iterator over the declarations and the current values of the options, in the order they were
+    /// declared.
+    pub fn iter(&self) -> impl Iterator<Item=(&UciOptionConfig, &OptionValue)> {
+        self.options.iter().map(|(c, v)| (c, v))
+    }
+
+    /// Returns the `setoption` messages that bring the options to the `desired` values, given as pairs of names and
+    /// values as they would appear in `setoption` messages. Only the options whose values would change get a message,
+    /// in the order of `desired`. The store itself is not changed; apply the messages once they are sent.
+    ///
+    /// Returns an error, and no messages, if any of the desired values is invalid. `button` options cannot be desired,
+    /// since they have no value.
+    pub fn messages_to_reach<N: AsRef<str>, V: AsRef<str>>(&self, desired: &[(N, V)]) -> Result<Vec<UciMessage>, OptionError> {
+        let mut messages = Vec::new();
+        for (name, value) in desired {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            let index = self.position(name).ok_or_else(|| OptionError::UnknownOption(name.to_string()))?;
+            let (config, current) = &self.options[index];
+            let new_value = parse_option_value(config, Some(value), false)?;
+
+            if new_value != *current {
+                messages.push(UciMessage::set_option(config.get_name(), Some(new_value.to_string())));
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Returns the number of declared options.
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Returns `true` if no options have been declared.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+}
+
+/// A saved option configuration: the names of the options and the values chosen for them, as they would appear in
+/// `setoption` messages, in order. A GUI saves the options the user tuned into a profile (see `from_values(..)`) and
+/// restores them in a later session by sending the profile's `messages()`.
+///
+/// With the `serde` feature, a profile is represented as a map from the names to the values, in order, so it can be
+/// stored in any format supported by serde, such as TOML. When reading it, booleans and numbers are accepted as well
+/// as strings. With the `json` feature, `to_json()`, `from_json(..)`, `save(..)` and `load(..)` handle JSON directly:
+///
+/// ```text
+/// {
+///   "Threads": "8",
+///   "Hash": "1024",
+///   "SyzygyPath": "/tb"
+/// }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, Serializable};
+/// use vampirc_uci::options::{OptionProfile, OptionValues};
+///
+/// let values = OptionValues::from_messages(&parse("option name Hash type spin default 16 min 1 max 1024\n\
+///     option name Threads type spin default 1 min 1 max 64\n"));
+///
+/// let mut profile = OptionProfile::new();
+/// profile.set("Threads", "8");
+/// profile.set("Hash", "16");
+///
+/// let lines: Vec<String> = profile.messages().iter().map(|m| m.serialize()).collect();
+/// assert_eq!(lines, vec!["setoption name Threads value 8", "setoption name Hash value 16"]);
+///
+/// // Only Threads differs from what the engine has
+/// assert_eq!(profile.messages_for(&values).unwrap().len(), 1);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct OptionProfile {
+    settings: Vec<(String, String)>,
+}
+
+impl OptionProfile {
+    /// Creates an empty profile.
+    pub fn new() -> OptionProfile {
+        OptionProfile::default()
+    }
+
+    /// Creates a profile of the options whose values differ from their defaults in the `values`.
+    pub fn from_values(values: &OptionValues) -> OptionProfile {
+        let mut profile = OptionProfile::new();
+        for (name, value) in values.changed() {
+            profile.set(name, &value.to_string());
+        }
+
+        profile
+    }
+
+    /// Sets the `value` of the option named `name`, replacing any value previously set for it (the names are matched
+    /// case-insensitively). A new option is added at the end.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.settings.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some(setting) => setting.1 = value.to_string(),
+            None => self.settings.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns the value set for the option named `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.settings.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    /// Removes the option named `name` from the profile and returns its value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.settings.iter().position(|(n, _)| n.eq_ignore_ascii_case(name))?;
+        Some(self.settings.remove(index).1)
+    }
+
+    /// Returns an iterator over the names and the values, in order.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &str)> {
+        self.settings.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Returns the `setoption` messages that set all of the options of the profile, in order, without validating
+    /// them.
+    pub fn messages(&self) -> Vec<UciMessage> {
+        self.settings.iter().map(|(n, v)| UciMessage::set_option(n, Some(v.clone()))).collect()
+    }
+
+    /// Returns the `setoption` messages that bring the engine's options from the `values` to the profile, validated
+    /// against the declared options. Only the options whose values would change get a message, see
+    /// `OptionValues::messages_to_reach(..)`.
+    pub fn messages_for(&self, values: &OptionValues) -> Result<Vec<UciMessage>, OptionError> {
+        values.messages_to_reach(&self.settings)
+    }
+
+    /// Returns the number of options in the profile.
+    pub fn len(&self) -> usize {
+        self.settings.len()
+    }
+
+    /// Returns `true` if the profile has no options.
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+}
+
+impl Debug for EngineOptions {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_map()
+            .entries(self.options.iter().map(|o| (o.config.get_name(), &o.value)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, parse_one};
+    use crate::uci::Serializable;
+
+    use super::*;
+
+    fn registry() -> OptionRegistry {
+        OptionRegistry::from_messages(&parse(
+            "id name Vampirc\n\
+            option name Nullmove type check default true\n\
+            option name Selectivity type spin default 2 min 0 max 4\n\
+            option name Style type combo default Normal var Solid var Normal var Risky\n\
+            option name NalimovPath type string default c:\\\n\
+            option name Clear Hash type button\n\
+            uciok\n"
+        ))
+    }
+
+    #[test]
+    fn test_registry_from_messages() {
+        let r = registry();
+        assert_eq!(r.len(), 5);
+        assert_eq!(r.get("clear hash").unwrap().get_type_str(), "button");
+        assert!(r.get("Hash").is_none());
+    }
+
+    #[test]
+    fn test_validate_check() {
+        let r = registry();
+        assert_eq!(r.validate(&parse_one("setoption name Nullmove value FALSE")), Ok(OptionValue::Check(false)));
+        assert_eq!(r.validate(&parse_one("setoption name Nullmove value 1")), Err(OptionError::InvalidValue {
+            name: String::from("Nullmove"),
+            value: String::from("1"),
+            expected: "check",
+        }));
+    }
+
+    #[test]
+    fn test_validate_spin_range() {
+        let r = registry();
+        assert_eq!(r.validate(&parse_one("setoption name Selectivity value 3")), Ok(OptionValue::Spin(3)));
+        assert_eq!(r.validate(&parse_one("setoption name Selectivity value -1")), Err(OptionError::OutOfRange {
+            name: String::from("Selectivity"),
+            value: -1,
+            min: Some(0),
+            max: Some(4),
+        }));
+        assert_eq!(r.validate_clamped(&parse_one("setoption name Selectivity value -1")), Ok(OptionValue::Spin(0)));
+        assert_eq!(r.validate_clamped(&parse_one("setoption name Selectivity value 10")), Ok(OptionValue::Spin(4)));
+    }
+
+    #[test]
+    fn test_validate_float_range() {
+        let r = OptionRegistry::from_messages(&parse("option name CPuct type spin default 1.745 min 0 max 100\n"));
+        assert_eq!(r.validate(&parse_one("setoption name CPuct value 2.5")), Ok(OptionValue::Float(UciFloat(2.5))));
+        assert_eq!(r.validate(&parse_one("setoption name CPuct value 3")), Ok(OptionValue::Float(UciFloat(3.0))));
+        assert_eq!(r.validate(&parse_one("setoption name CPuct value 100.5")), Err(OptionError::FloatOutOfRange {
+            name: String::from("CPuct"),
+            value: UciFloat(100.5),
+            min: Some(UciFloat(0.0)),
+            max: Some(UciFloat(100.0)),
+        }));
+        assert_eq!(r.validate_clamped(&parse_one("setoption name CPuct value -1")), Ok(OptionValue::Float(UciFloat(0.0))));
+        assert!(r.validate(&parse_one("setoption name CPuct value NaN")).is_err());
+        assert_eq!(OptionValue::from_default(r.get("cpuct").unwrap()).to_string(), "1.745");
+    }
+
+    #[test]
+    fn test_standard_options() {
+        assert_eq!(parse_one("setoption name THREADS value 8").as_standard_option(), Some(StandardSetOption::Threads(8)));
+        assert_eq!(parse_one("setoption name Hash value -1").as_standard_option(), None);
+        assert_eq!(parse_one("setoption name MultiPV value 70000").as_standard_option(), None);
+        assert_eq!(parse_one("setoption name Ponder value TRUE").as_standard_option(), Some(StandardSetOption::Ponder(true)));
+        assert_eq!(parse_one("setoption name Ponder value 1").as_standard_option(), None);
+        assert_eq!(parse_one("setoption name SyzygyPath value <empty>").as_standard_option(),
+                   Some(StandardSetOption::SyzygyPath(vec![])));
+        assert_eq!(parse_one("setoption name Clear Hash").as_standard_option(), None);
+        assert_eq!(parse_one("isready").as_standard_option(), None);
+
+        #[cfg(not(windows))]
+        assert_eq!(parse_one("setoption name SyzygyPath value /tb/wdl:/tb/dtz").as_standard_option(),
+                   Some(StandardSetOption::SyzygyPath(vec![PathBuf::from("/tb/wdl"), PathBuf::from("/tb/dtz")])));
+        #[cfg(windows)]
+        assert_eq!(parse_one("setoption name SyzygyPath value C:\\tb\\wdl;D:\\dtz").as_standard_option(),
+                   Some(StandardSetOption::SyzygyPath(vec![PathBuf::from("C:\\tb\\wdl"), PathBuf::from("D:\\dtz")])));
+    }
+
+    #[test]
+    fn test_validate_combo() {
+        let r = registry();
+        assert_eq!(r.validate(&parse_one("setoption name Style value risky")), Ok(OptionValue::Combo(String::from("Risky"))));
+        assert_eq!(r.validate(&parse_one("setoption name Style value Crazy")), Err(OptionError::InvalidComboValue {
+            name: String::from("Style"),
+            value: String::from("Crazy"),
+        }));
+    }
+
+    #[test]
+    fn test_validate_string_and_button() {
+        let r = registry();
+        assert_eq!(r.validate(&parse_one("setoption name NalimovPath value <empty>")), Ok(OptionValue::String(String::new())));
+        assert_eq!(r.validate(&parse_one("setoption name Clear Hash")), Ok(OptionValue::Button));
+        assert_eq!(r.validate(&parse_one("setoption name NalimovPath")), Err(OptionError::MissingValue(String::from("NalimovPath"))));
+    }
+
+    #[test]
+    fn test_validate_unknown() {
+        let r = registry();
+        assert_eq!(r.validate(&parse_one("setoption name Threads value 4")), Err(OptionError::UnknownOption(String::from("Threads"))));
+        assert_eq!(r.validate(&UciMessage::Uci), Err(OptionError::NotASetOption));
+    }
+
+    #[test]
+    fn test_option_value_from_default() {
+        assert_eq!(OptionValue::from_default(&UciOptionConfig::Spin { name: String::from("A"), default: None, min: Some(2), max: None }), OptionValue::Spin(2));
+        assert_eq!(OptionValue::from_default(&UciOptionConfig::Combo {
+            name: String::from("B"),
+            default: None,
+            var: vec![String::from("X"), String::from("Y")],
+        }), OptionValue::Combo(String::from("X")));
+        assert_eq!(OptionValue::from_default(&UciOptionConfig::String { name: String::from("C"), default: Some(String::from("<empty>")) }), OptionValue::String(String::new()));
+    }
+
+    #[test]
+    fn test_engine_options() {
+        use std::sync::{Arc, Mutex};
+
+        let cleared = Arc::new(Mutex::new(0));
+        let cleared_in_callback = cleared.clone();
+
+        let mut options = EngineOptions::new();
+        for m in &parse("option name Nullmove type check default true\noption name Style type combo default Normal var Solid var Normal var Risky\n") {
+            if let UciMessage::Option(config) = m {
+                options.declare(config.clone());
+            }
+        }
+        options.declare_with_callback(UciOptionConfig::Button { name: String::from("Clear Hash") }, move |_| *cleared_in_callback.lock().unwrap() += 1);
+
+        assert_eq!(options.len(), 3);
+        assert_eq!(options.messages()[2].serialize(), "option name Clear Hash type button");
+        assert_eq!(options.get_check("Nullmove"), Some(true));
+        assert_eq!(options.get_str("style"), Some("Normal"));
+        assert_eq!(options.get_spin("Style"), None);
+
+        assert_eq!(options.apply(&parse_one("setoption name Nullmove value false")), Ok(OptionValue::Check(false)));
+        assert_eq!(options.apply(&parse_one("setoption name Style value solid")), Ok(OptionValue::Combo(String::from("Solid"))));
+        assert!(options.apply(&parse_one("setoption name Style value Crazy")).is_err());
+        assert_eq!(options.apply(&parse_one("setoption name Clear Hash")), Ok(OptionValue::Button));
+        assert_eq!(options.set("Threads", Some("2")), Err(OptionError::UnknownOption(String::from("Threads"))));
+
+        assert_eq!(options.get_check("Nullmove"), Some(false));
+        assert_eq!(options.get_str("Style"), Some("Solid"));
+        assert_eq!(*cleared.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_option_values() {
+        let mut values = OptionValues::from_registry(&registry());
+        assert_eq!(values.len(), 5);
+        assert!(values.changed().is_empty());
+        assert!(values.is_default("selectivity"));
+        assert!(!values.is_default("Hash"));
+
+        assert_eq!(values.apply(&parse_one("setoption name Style value risky")), Ok(OptionValue::Combo(String::from("Risky"))));
+        assert_eq!(values.apply(&parse_one("setoption name Clear Hash")), Ok(OptionValue::Button));
+        assert_eq!(values.set("NalimovPath", Some("<empty>")), Ok(OptionValue::String(String::new())));
+        assert!(values.set("Selectivity", Some("5")).is_err());
+        assert!(values.apply(&UciMessage::Uci).is_err());
+
+        assert_eq!(values.get("selectivity"), Some(&OptionValue::Spin(2)));
+        assert_eq!(values.changed(), vec![
+            ("Style", &OptionValue::Combo(String::from("Risky"))),
+            ("NalimovPath", &OptionValue::String(String::new())),
+        ]);
+
+        let messages = values.messages_to_reach(&[
+            ("style", "Risky"), ("Selectivity", "3"), ("nalimovpath", "<empty>"), ("Nullmove", "FALSE"),
+        ]).unwrap();
+        let lines: Vec<String> = messages.iter().map(|m| m.serialize()).collect();
+        assert_eq!(lines, vec!["setoption name Selectivity value 3", "setoption name Nullmove value false"]);
+
+        for m in &messages {
+            values.apply(m).unwrap();
+        }
+        assert_eq!(values.changed().len(), 4);
+        assert!(values.messages_to_reach(&[("Selectivity", "3")]).unwrap().is_empty());
+
+        assert!(values.messages_to_reach(&[("Selectivity", "9")]).is_err());
+        assert!(values.messages_to_reach(&[("Clear Hash", "")]).is_err());
+        assert_eq!(values.messages_to_reach(&[("Hash", "1")]), Err(OptionError::UnknownOption(String::from("Hash"))));
+    }
+
+    #[test]
+    fn test_option_profile() {
+        let mut values = OptionValues::from_registry(&registry());
+        values.set("Selectivity", Some("3")).unwrap();
+        values.set("Style", Some("solid")).unwrap();
+
+        let mut profile = OptionProfile::from_values(&values);
+        assert_eq!(profile.iter().collect::<Vec<_>>(), vec![("Selectivity", "3"), ("Style", "Solid")]);
+
+        profile.set("style", "Risky");
+        profile.set("Nullmove", "true");
+        assert_eq!(profile.len(), 3);
+        assert_eq!(profile.get("STYLE"), Some("Risky"));
+
+        let lines: Vec<String> = profile.messages().iter().map(|m| m.serialize()).collect();
+        assert_eq!(lines, vec!["setoption name Selectivity value 3", "setoption name Style value Risky",
+                               "setoption name Nullmove value true"]);
+        assert_eq!(profile.messages_for(&values).unwrap(), vec![UciMessage::set_option("Style", Some(String::from("Risky")))]);
+
+        assert_eq!(profile.remove("nullmove"), Some(String::from("true")));
+        assert_eq!(profile.remove("Nullmove"), None);
+        profile.set("Threads", "4");
+        assert_eq!(profile.messages_for(&values), Err(OptionError::UnknownOption(String::from("Threads"))));
+    }
+}
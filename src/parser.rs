@@ -4,20 +4,27 @@
 //! Behind the scenes, it uses the [PEST parser](https://github.com/pest-parser/pest). The corresponding PEG grammar is
 //! available [here](https://github.com/vampirc/vampirc-uci/blob/master/res/uci.pest).
 
+use std::borrow::Cow;
+use std::convert::TryFrom;
 #[cfg(feature = "chess")]
 use std::fmt::Error as FmtError;
+#[cfg(not(feature = "chess"))]
 use std::str::FromStr;
 
 use chrono::Duration;
-use pest::error::Error;
+use pest::error::{Error, ErrorVariant, InputLocation};
 use pest::iterators::Pair;
-use pest::Parser;
+use pest::{Parser, Span};
 
 #[cfg(feature = "chess")]
-use crate::chess::{ChessMove, Piece, Square};
+use crate::chess::{ChessMove, File, Piece, Rank, Square};
+use crate::error::ParseError;
+#[cfg(feature = "extensions")]
+use crate::uci::Offer;
 use crate::uci::ProtectionState;
 use crate::uci::{
-    MessageList, UciFen, UciInfoAttribute, UciMessage, UciSearchControl, UciTimeControl,
+    CommunicationDirection, DuplicateAttributes, InfoAttributes, MessageList, Serializable, UciFen, UciFloat, UciInfoAttribute, UciMessage, UciMessageKind,
+    UciSearchControl, UciTimeControl,
 };
 #[cfg(not(feature = "chess"))]
 use crate::uci::{UciMove, UciPiece, UciSquare};
@@ -25,8 +32,255 @@ use crate::UciOptionConfig;
 
 #[derive(Parser)]
 #[grammar = "../res/uci.pest"]
+#[cfg_attr(feature = "extensions", grammar = "../res/extensions.pest")]
+#[cfg_attr(not(feature = "extensions"), grammar = "../res/no_extensions.pest")]
 struct UciParser;
 
+/// Options that adjust the behaviour of the parser, for use with the `parse_with_options(..)`,
+/// `parse_strict_with_options(..)` and `parse_one_with_options(..)` methods. The default options make these methods
+/// behave exactly like `parse(..)`, `parse_strict(..)` and `parse_one(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{CommunicationDirection, ParseOptions, parse_with_options};
+///
+/// let options = ParseOptions::new().direction(CommunicationDirection::GuiToEngine);
+/// let messages = parse_with_options("uci\nbestmove e2e4\nisready\n", &options);
+/// assert_eq!(messages.len(), 2);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct ParseOptions {
+    direction: Option<CommunicationDirection>,
+    unicode_whitespace: bool,
+    skip_blank_lines: bool,
+    keyword_case: KeywordCase,
+    numeric_suffixes: bool,
+    duplicate_attributes: DuplicateAttributes,
+    startup_banner: bool,
+}
+
+/// The case the keywords of the messages (such as `go`, `depth` or `ponderhit`) are accepted in, see
+/// `ParseOptions::keyword_case(..)`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub enum KeywordCase {
+    /// The keywords are accepted in any case, so that `PonderHit` or `REGISTER LATER` parse like `ponderhit` and
+    /// `register later`. This is the default.
+    #[default]
+    Any,
+
+    /// The keywords must be in lowercase, as the UCI specification writes them. A keyword with an uppercase letter
+    /// makes the message unrecognized.
+    Lowercase,
+}
+
+impl ParseOptions {
+    /// Creates the default options.
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Only accept the messages flowing in the specified `direction`. Messages flowing in the opposite direction (such
+    /// as `bestmove` when parsing engine-bound input) are treated as unrecognized: they are ignored by
+    /// `parse_with_options(..)`, result in an error in `parse_strict_with_options(..)` and in a
+    /// `UciMessage::Unknown` in `parse_one_with_options(..)`.
+    pub fn direction(mut self, direction: CommunicationDirection) -> ParseOptions {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Returns the direction the messages are restricted to, if any.
+    pub fn get_direction(&self) -> Option<CommunicationDirection> {
+        self.direction
+    }
+
+    /// Accept any Unicode whitespace (such as a no-break space, a form feed or an ideographic space) between the
+    /// tokens, rather than only spaces and tabs. Each such character is read as a space, including in free text such
+    /// as option values, and the positions in parse errors refer to the input with the replacements made.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{ParseOptions, UciMessage, parse_one_with_options};
+    ///
+    /// let options = ParseOptions::new().unicode_whitespace(true);
+    /// assert_eq!(parse_one_with_options("go\u{a0}depth\u{3000}3", &options), UciMessage::go_depth(3));
+    /// ```
+    pub fn unicode_whitespace(mut self, unicode_whitespace: bool) -> ParseOptions {
+        self.unicode_whitespace = unicode_whitespace;
+        self
+    }
+
+    /// Returns `true` if any Unicode whitespace is accepted between the tokens.
+    pub fn get_unicode_whitespace(&self) -> bool {
+        self.unicode_whitespace
+    }
+
+    /// Skip the lines that contain nothing but whitespace, wherever they are, before parsing. Runs of blank lines
+    /// between messages are always accepted, but with this option `parse_one_with_options(..)` also looks past the
+    /// blank lines in front of a message, and, together with `unicode_whitespace(..)`, the lines holding only other
+    /// Unicode whitespace are skipped too. The skipped lines still count in the line numbers of the errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{ParseOptions, UciMessage, parse_one_with_options};
+    ///
+    /// let options = ParseOptions::new().skip_blank_lines(true);
+    /// assert_eq!(parse_one_with_options("\n \t\n\nisready\n", &options), UciMessage::IsReady);
+    /// ```
+    pub fn skip_blank_lines(mut self, skip_blank_lines: bool) -> ParseOptions {
+        self.skip_blank_lines = skip_blank_lines;
+        self
+    }
+
+    /// Returns `true` if the blank lines are skipped before parsing.
+    pub fn get_skip_blank_lines(&self) -> bool {
+        self.skip_blank_lines
+    }
+
+    /// Sets the case the keywords are accepted in. With `KeywordCase::Lowercase`, a message with a keyword that is not
+    /// in lowercase is treated as unrecognized: it is ignored by `parse_with_options(..)`, results in an error spanning
+    /// the keyword in `parse_strict_with_options(..)` and in a `UciMessage::Unknown` in `parse_one_with_options(..)`.
+    /// Only the keywords are affected: names, values, free text, FENs and moves keep their own rules.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{KeywordCase, ParseOptions, UciMessage, parse_one_with_options};
+    ///
+    /// assert_eq!(parse_one_with_options("PonderHit", &ParseOptions::new()), UciMessage::PonderHit);
+    ///
+    /// let options = ParseOptions::new().keyword_case(KeywordCase::Lowercase);
+    /// assert!(parse_one_with_options("PonderHit", &options).is_unknown());
+    /// assert_eq!(parse_one_with_options("ponderhit", &options), UciMessage::PonderHit);
+    /// ```
+    pub fn keyword_case(mut self, keyword_case: KeywordCase) -> ParseOptions {
+        self.keyword_case = keyword_case;
+        self
+    }
+
+    /// Returns the case the keywords are accepted in.
+    pub fn get_keyword_case(&self) -> KeywordCase {
+        self.keyword_case
+    }
+
+    /// Accept the counts of nodes with a decimal point and a `k`, `M` or `G` suffix (such as `nodes 12.3M` or
+    /// `nps 1500k`) in the `nodes` and `nps` attributes of `info` messages, as some engines print them. The counts are
+    /// rounded down to a whole number, and the positions in parse errors refer to the input with the counts expanded.
+    /// Without this option, such an attribute is read as a `UciInfoAttribute::Any`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{ParseOptions, UciInfoAttribute, UciMessage, parse_one_with_options};
+    ///
+    /// let options = ParseOptions::new().numeric_suffixes(true);
+    /// assert_eq!(parse_one_with_options("info nodes 12.3M nps 1500k", &options),
+    ///            UciMessage::Info(vec![UciInfoAttribute::Nodes(12_300_000), UciInfoAttribute::Nps(1_500_000)]));
+    /// ```
+    pub fn numeric_suffixes(mut self, numeric_suffixes: bool) -> ParseOptions {
+        self.numeric_suffixes = numeric_suffixes;
+        self
+    }
+
+    /// Returns `true` if the counts of nodes with a suffix are accepted.
+    pub fn get_numeric_suffixes(&self) -> bool {
+        self.numeric_suffixes
+    }
+
+    /// Sets what becomes of an attribute that occurs more than once in an `info` message (as in `info depth 5 depth
+    /// 6`). By default, all of them are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{DuplicateAttributes, ParseOptions, UciInfoAttribute, UciMessage, parse_one_with_options};
+    ///
+    /// let options = ParseOptions::new().duplicate_attributes(DuplicateAttributes::KeepFirst);
+    /// assert_eq!(parse_one_with_options("info depth 5 depth 6", &options),
+    ///            UciMessage::Info(vec![UciInfoAttribute::Depth(5)]));
+    /// ```
+    pub fn duplicate_attributes(mut self, duplicate_attributes: DuplicateAttributes) -> ParseOptions {
+        self.duplicate_attributes = duplicate_attributes;
+        self
+    }
+
+    /// Returns what becomes of an attribute that occurs more than once in an `info` message.
+    pub fn get_duplicate_attributes(&self) -> DuplicateAttributes {
+        self.duplicate_attributes
+    }
+
+    /// Accept the free-text banners many engines print on startup, before they reply to `uci`. Each line before the
+    /// first `uciok` that does not parse is read as an `info string` message holding the line, rather than being an
+    /// error (or being ignored, or an unknown message). The lines after `uciok` are parsed as usual.
+    ///
+    /// `parse_one_with_options(..)` cannot tell whether `uciok` has been received, so it reads any line that does not
+    /// parse as a banner; a reader should stop passing this option once it has received `uciok`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{ParseOptions, UciInfoAttribute, UciMessage, parse_strict_with_options};
+    ///
+    /// let options = ParseOptions::new().startup_banner(true);
+    /// let messages = parse_strict_with_options("Stockfish 16 by the Stockfish developers\nid name Stockfish 16\nuciok\n",
+    ///                                          &options).unwrap();
+    /// assert_eq!(messages[0],
+    ///            UciMessage::Info(vec![UciInfoAttribute::String("Stockfish 16 by the Stockfish developers".to_owned())]));
+    /// assert_eq!(messages.len(), 3);
+    /// ```
+    pub fn startup_banner(mut self, startup_banner: bool) -> ParseOptions {
+        self.startup_banner = startup_banner;
+        self
+    }
+
+    /// Returns `true` if the lines that do not parse before `uciok` are read as `info string` messages.
+    pub fn get_startup_banner(&self) -> bool {
+        self.startup_banner
+    }
+
+    /// Rewrites the input `s` as the whitespace and numeric options require, or returns it as it is if they are off.
+    fn normalize<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let mut s = Cow::Borrowed(s);
+
+        if self.unicode_whitespace && s.chars().any(is_other_whitespace) {
+            s = Cow::Owned(s.chars().map(|c| if is_other_whitespace(c) { ' ' } else { c }).collect());
+        }
+
+        // The blank lines are emptied rather than removed, so that the line numbers in the errors stay right
+        if self.skip_blank_lines && s.split_inclusive('\n').any(|l| is_blank_line(l) && !matches!(l, "\n" | "\r\n")) {
+            s = Cow::Owned(s.split_inclusive('\n')
+                .map(|l| if is_blank_line(l) { l.trim_start_matches([' ', '\t']) } else { l })
+                .collect());
+        }
+
+        if self.numeric_suffixes {
+            if let Some(expanded) = expand_numeric_suffixes(&s) {
+                s = Cow::Owned(expanded);
+            }
+        }
+
+        if self.startup_banner {
+            if let Some(rewritten) = rewrite_banner_lines(&s) {
+                s = Cow::Owned(rewritten);
+            }
+        }
+
+        s
+    }
+}
+
+/// Returns `true` for the whitespace characters the grammar does not accept between tokens, other than line breaks.
+fn is_other_whitespace(c: char) -> bool {
+    c.is_whitespace() && !matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+/// Returns `true` if the `line` holds only the whitespace the grammar accepts.
+fn is_blank_line(line: &str) -> bool {
+    line.chars().all(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+}
+
 /// Parses the specified `&str s` into a list of `UciMessage`s. Please note that this method will return an `Error` if
 /// any of the input violates the grammar rules.
 ///
@@ -35,6 +289,11 @@ struct UciParser;
 /// This method differs from the `parse(..)` method in the fact that any unrecognized tokens/messages will result in
 /// an error being returned.
 ///
+/// If the input fails to parse because of a malformed move (such as `e9e4`, `e2j4` or `e7e8x`), the returned error is
+/// an `ErrorVariant::CustomError` spanning the offending move, with a message describing what is wrong with it.
+/// Likewise, if it fails on a misspelled keyword (such as `posiiton` or `bestmov`), the error spans the keyword and
+/// suggests the closest correct one (`did you mean `position`?`).
+///
 /// # Examples
 ///
 /// ```
@@ -44,10 +303,17 @@ struct UciParser;
 /// let messages = parse_strict("position startpos\ngo ponder searchmoves e2e4 d2d4\n").unwrap();
 /// assert_eq!(messages.len(), 2);
 ///
+/// let err = parse_strict("position startpos moves e2e4 e7e9\n").unwrap_err();
+/// assert!(err.to_string().contains("rank out of range 1–8: '9' in move 'e7e9'"));
 /// ```
-pub fn parse_strict(s: &str) -> Result<MessageList, Error<Rule>> {
+pub fn parse_strict(s: &str) -> Result<MessageList, ParseError> {
+    parse_strict_with_options(s, &ParseOptions::default())
+}
+
+/// Like `parse_strict(..)`, but with the behaviour adjusted by the `options`.
+pub fn parse_strict_with_options(s: &str, options: &ParseOptions) -> Result<MessageList, ParseError> {
     let mut ml = MessageList::new();
-    do_parse_uci(s, Rule::commands, Some(&mut ml))?;
+    do_parse_uci(s, Rule::commands, Some(&mut ml), options).map_err(ParseError::from)?;
 
     Ok(ml)
 }
@@ -70,12 +336,47 @@ pub fn parse_strict(s: &str) -> Result<MessageList, Error<Rule>> {
 ///
 /// ```
 pub fn parse(s: &str) -> MessageList {
+    parse_with_options(s, &ParseOptions::default())
+}
+
+/// Like `parse(..)`, but with the behaviour adjusted by the `options`.
+pub fn parse_with_options(s: &str, options: &ParseOptions) -> MessageList {
     let mut ml = MessageList::new();
-    do_parse_uci(s, Rule::commands_ignore_unknown, Some(&mut ml)).unwrap();
+    do_parse_uci(s, Rule::commands_ignore_unknown, Some(&mut ml), options).unwrap();
 
     ml
 }
 
+/// Parses the messages an engine receives from the GUI, ignoring any GUI-bound messages (such as `bestmove`) along
+/// with the unrecognized ones.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{UciMessage, parse_engine_bound};
+///
+/// let messages = parse_engine_bound("uci\nuciok\nisready\n");
+/// assert_eq!(messages, vec![UciMessage::Uci, UciMessage::IsReady]);
+/// ```
+pub fn parse_engine_bound(s: &str) -> MessageList {
+    parse_with_options(s, &ParseOptions::new().direction(CommunicationDirection::GuiToEngine))
+}
+
+/// Parses the messages a GUI receives from the engine, ignoring any engine-bound messages (such as `go`) along with
+/// the unrecognized ones.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{UciMessage, parse_gui_bound};
+///
+/// let messages = parse_gui_bound("uci\nuciok\nisready\n");
+/// assert_eq!(messages, vec![UciMessage::UciOk]);
+/// ```
+pub fn parse_gui_bound(s: &str) -> MessageList {
+    parse_with_options(s, &ParseOptions::new().direction(CommunicationDirection::EngineToGui))
+}
+
 /// This is like `parse`, except that it returns a `UciMessage::UnknownMessage` variant if it does not recognize the
 /// message.
 ///
@@ -89,7 +390,7 @@ pub fn parse(s: &str) -> MessageList {
 /// ```
 pub fn parse_with_unknown(s: &str) -> MessageList {
     let mut ml = MessageList::new();
-    let parse_att = do_parse_uci(s, Rule::commands_with_unknown, Some(&mut ml));
+    let parse_att = do_parse_uci(s, Rule::commands_with_unknown, Some(&mut ml), &ParseOptions::default());
 
     if let Err(e) = parse_att {
         let m = UciMessage::Unknown(s.trim_end().to_owned(), Some(e));
@@ -118,7 +419,12 @@ pub fn parse_with_unknown(s: &str) -> MessageList {
 ///     }
 /// ```
 pub fn parse_one(s: &str) -> UciMessage {
-    let r = do_parse_uci(s, Rule::single_message_per_line, None);
+    parse_one_with_options(s, &ParseOptions::default())
+}
+
+/// Like `parse_one(..)`, but with the behaviour adjusted by the `options`.
+pub fn parse_one_with_options(s: &str, options: &ParseOptions) -> UciMessage {
+    let r = do_parse_uci(s, Rule::single_message_per_line, None, options);
 
     if let Err(e) = r {
         let m = UciMessage::Unknown(s.trim_end().to_owned(), Some(e));
@@ -132,12 +438,41 @@ pub fn parse_one(s: &str) -> UciMessage {
     return UciMessage::Unknown(String::new(), None);
 }
 
+/// Identifies the kind of the message on the line `s` from its first token alone, without running the grammar. This
+/// is much cheaper than parsing, for routers and queues that only need the kind, but it does not validate the rest of
+/// the line: `classify("go nowhere")` is `Some(UciMessageKind::Go)`, even though `parse_one("go nowhere")` is an
+/// unknown message. Returns `None` if the first token is not a known command.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{classify, UciMessageKind};
+///
+/// assert_eq!(classify("info depth 20 score cp 13 pv e2e4"), Some(UciMessageKind::Info));
+/// assert_eq!(classify("  bestmove e2e4\n"), Some(UciMessageKind::BestMove));
+/// assert_eq!(classify("hello"), None);
+/// ```
+pub fn classify(s: &str) -> Option<UciMessageKind> {
+    s.split_whitespace().next().and_then(UciMessageKind::from_keyword)
+}
+
 fn do_parse_uci(
     s: &str,
     top_rule: Rule,
     mut ml: Option<&mut MessageList>,
+    options: &ParseOptions,
 ) -> Result<Option<UciMessage>, Error<Rule>> {
-    let pairs = UciParser::parse(top_rule, s)?;
+    let normalized = options.normalize(s);
+    let mut s: &str = &normalized;
+    if options.skip_blank_lines && top_rule == Rule::single_message_per_line {
+        s = s.trim_start_matches(['\r', '\n']);
+    }
+    let pairs = UciParser::parse(top_rule, s).map_err(|e| refine_keyword_error(s, refine_move_error(s, e)))?;
+    let spans: Vec<(usize, usize)> = pairs.clone().map(|p| (p.as_span().start(), p.as_span().end())).collect();
+    let mut case_errors: Vec<Option<Error<Rule>>> = match options.keyword_case {
+        KeywordCase::Any => Vec::new(),
+        KeywordCase::Lowercase => pairs.clone().map(|p| check_keyword_case(s, p)).collect(),
+    };
 
     let mut single: Option<UciMessage> = None;
 
@@ -191,31 +526,29 @@ fn do_parse_uci(
                     UciMessage::SetOption { name, value: val }
                 }
                 Rule::register => {
-                    for sp in pair.into_inner() {
-                        match sp.as_rule() {
-                            Rule::register_later => {
-                                return UciMessage::register_later();
-                            }
-                            Rule::register_nc => {
-                                let mut name: &str = "";
-
-                                for spi in sp.into_inner() {
-                                    match spi.as_rule() {
-                                        Rule::register_name => {
-                                            name = spi.as_span().as_str();
-                                        }
-                                        Rule::register_code => {
-                                            return UciMessage::register_code(name, spi.as_str());
-                                        }
-                                        _ => (),
+                    let sp = pair.into_inner().next().unwrap();
+                    match sp.as_rule() {
+                        Rule::register_later => UciMessage::register_later(),
+                        Rule::register_nc => {
+                            let mut name: Option<String> = None;
+                            let mut code: Option<String> = None;
+
+                            for spi in sp.into_inner() {
+                                match spi.as_rule() {
+                                    Rule::register_name => {
+                                        name = Some(spi.as_span().as_str().to_string());
                                     }
+                                    Rule::register_code => {
+                                        code = Some(spi.as_str().to_string());
+                                    }
+                                    _ => (),
                                 }
                             }
-                            _ => unreachable!(),
+
+                            UciMessage::Register { later: false, name, code }
                         }
+                        _ => unreachable!(),
                     }
-
-                    unreachable!()
                 }
                 Rule::ucinewgame => UciMessage::UciNewGame,
                 Rule::stop => UciMessage::Stop,
@@ -233,7 +566,7 @@ fn do_parse_uci(
                                 startpos = true;
                             }
                             Rule::fen => fen = Some(UciFen::from(sp.as_span().as_str())),
-                            Rule::a_move => {
+                            Rule::a_move | Rule::null_move => {
                                 moves.push(parse_a_move(sp));
                             }
                             _ => {}
@@ -298,7 +631,7 @@ fn do_parse_uci(
                                                                 }
                                                                 Rule::movestogo => {
                                                                     moves_to_go =
-                                                                        Some(parse_u8(sspi, Rule::digits3));
+                                                                        Some(parse_u8(sspi, Rule::digits));
                                                                 }
                                                                 _ => {}
                                                             };
@@ -313,13 +646,13 @@ fn do_parse_uci(
                                             for spi in sp_full.into_inner() {
                                                 match spi.as_rule() {
                                                     Rule::depth => {
-                                                        search.depth = Some(parse_u8(spi, Rule::digits3));
+                                                        search.depth = Some(parse_u8(spi, Rule::digits));
                                                     }
                                                     Rule::mate => {
-                                                        search.mate = Some(parse_u8(spi, Rule::digits3))
+                                                        search.mate = Some(parse_u8(spi, Rule::digits))
                                                     }
                                                     Rule::nodes => {
-                                                        search.nodes = Some(parse_u64(spi, Rule::digits12))
+                                                        search.nodes = Some(parse_u64(spi, Rule::digits))
                                                     }
                                                     Rule::searchmoves => {
                                                         for mt in spi.into_inner() {
@@ -330,6 +663,8 @@ fn do_parse_uci(
                                                 }
                                             }
                                         }
+                                        // A trailing space, as in the serialized `UciMessage::go()`
+                                        Rule::EOI => {}
                                         _ => unreachable!()
                                     }
                                 }
@@ -380,11 +715,20 @@ fn do_parse_uci(
                     #[cfg(not(feature = "chess"))] let mut ponder: Option<UciMove> = None;
                     #[cfg(feature = "chess")] let mut bm: Option<ChessMove> = None;
                     #[cfg(feature = "chess")] let mut ponder: Option<ChessMove> = None;
+                    #[cfg(feature = "extensions")] let mut offer: Option<Offer> = None;
                     for sp in pair.into_inner() {
                         match sp.as_rule() {
                             Rule::a_move => {
                                 bm = Some(parse_a_move(sp));
                             }
+                            #[cfg(feature = "extensions")]
+                            Rule::bestmove_resign => {
+                                #[cfg(not(feature = "chess"))] { bm = Some(UciMove::null()); }
+                                #[cfg(feature = "chess")] { bm = Some(ChessMove::default()); }
+                                offer = Some(Offer::Resign);
+                            }
+                            #[cfg(feature = "extensions")]
+                            Rule::bestmove_draw => offer = Some(Offer::Draw),
                             Rule::bestmove_ponder => {
                                 for ssp in sp.into_inner() {
                                     match ssp.as_rule() {
@@ -400,6 +744,8 @@ fn do_parse_uci(
                     UciMessage::BestMove {
                         best_move: bm.unwrap(),
                         ponder,
+                        #[cfg(feature = "extensions")]
+                        offer,
                     }
                 }
                 Rule::copyprotection | Rule::registration => {
@@ -423,8 +769,8 @@ fn do_parse_uci(
                 Rule::option => {
                     let mut name: Option<&str> = None;
                     let mut opt_default: Option<&str> = None;
-                    let mut opt_min: Option<i64> = None;
-                    let mut opt_max: Option<i64> = None;
+                    let mut opt_min: Option<Pair<Rule>> = None;
+                    let mut opt_max: Option<Pair<Rule>> = None;
                     let mut opt_var: Vec<String> = Vec::default();
                     let mut type_pair: Option<Pair<Rule>> = None;
 
@@ -447,14 +793,14 @@ fn do_parse_uci(
                                     }
                                 }
                             }
-                            Rule::option_default => {
+                            Rule::option_default | Rule::option_string_default | Rule::option_combo_default => {
                                 opt_default = Some(sp.as_span().as_str());
                             }
                             Rule::option_min => {
-                                opt_min = Some(parse_i64(sp, Rule::i64));
+                                opt_min = Some(sp);
                             }
                             Rule::option_max => {
-                                opt_max = Some(parse_i64(sp, Rule::i64));
+                                opt_max = Some(sp);
                             }
                             Rule::option_var => {
                                 opt_var.push(String::from(sp.as_span().as_str()));
@@ -476,6 +822,15 @@ fn do_parse_uci(
                                 None
                             },
                         },
+                        Rule::option_spin if [opt_default, opt_min.as_ref().map(Pair::as_str), opt_max.as_ref().map(Pair::as_str)]
+                            .iter().flatten().any(|v| v.contains('.') && parse_decimal(v).is_some()) => {
+                            UciOptionConfig::Float {
+                                name: String::from(name.unwrap()),
+                                default: opt_default.and_then(parse_decimal),
+                                min: opt_min.and_then(|p| parse_decimal(p.as_str())),
+                                max: opt_max.and_then(|p| parse_decimal(p.as_str())),
+                            }
+                        }
                         Rule::option_spin => UciOptionConfig::Spin {
                             name: String::from(name.unwrap()),
                             default: if let Some(def) = opt_default {
@@ -487,16 +842,8 @@ fn do_parse_uci(
                             } else {
                                 None
                             },
-                            min: if let Some(min1) = opt_min {
-                                Some(min1)
-                            } else {
-                                None
-                            },
-                            max: if let Some(max1) = opt_max {
-                                Some(max1)
-                            } else {
-                                None
-                            },
+                            min: opt_min.map(|p| parse_i64(p, Rule::i64)),
+                            max: opt_max.map(|p| parse_i64(p, Rule::i64)),
                         },
                         Rule::option_combo => UciOptionConfig::Combo {
                             name: String::from(name.unwrap()),
@@ -531,7 +878,7 @@ fn do_parse_uci(
 
                     UciMessage::Option(uoc)
                 }
-                Rule::info => {
+                Rule::info | Rule::info_junk => {
                     let mut info_attr: Vec<UciInfoAttribute> = vec![];
 
                     for sp in pair.into_inner() {
@@ -542,7 +889,7 @@ fn do_parse_uci(
                                         Rule::info_depth => {
                                             let info_depth = UciInfoAttribute::Depth(parse_u8(
                                                 spi,
-                                                Rule::digits3,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(info_depth);
                                             break;
@@ -550,7 +897,7 @@ fn do_parse_uci(
                                         Rule::info_seldepth => {
                                             let info_depth = UciInfoAttribute::SelDepth(parse_u8(
                                                 spi,
-                                                Rule::digits3,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(info_depth);
                                             break;
@@ -558,7 +905,7 @@ fn do_parse_uci(
                                         Rule::info_time => {
                                             let info_time = UciInfoAttribute::Time(Duration::milliseconds(parse_i64(
                                                 spi,
-                                                Rule::digits12,
+                                                Rule::digits,
                                             )));
                                             info_attr.push(info_time);
                                             break;
@@ -566,33 +913,25 @@ fn do_parse_uci(
                                         Rule::info_nodes => {
                                             let info_nodes = UciInfoAttribute::Nodes(parse_u64(
                                                 spi,
-                                                Rule::digits12,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(info_nodes);
                                             break;
                                         }
                                         Rule::info_currmovenum => {
-                                            let an_info = UciInfoAttribute::CurrMoveNum(parse_u64(
-                                                spi,
-                                                Rule::digits12,
-                                            )
-                                                as u16);
+                                            let an_info = UciInfoAttribute::CurrMoveNum(parse_u16(spi, Rule::digits));
                                             info_attr.push(an_info);
                                             break;
                                         }
                                         Rule::info_hashfull => {
-                                            let an_info = UciInfoAttribute::HashFull(parse_u64(
-                                                spi,
-                                                Rule::digits12,
-                                            )
-                                                as u16);
+                                            let an_info = UciInfoAttribute::HashFull(parse_u16(spi, Rule::digits));
                                             info_attr.push(an_info);
                                             break;
                                         }
                                         Rule::info_nps => {
                                             let an_info = UciInfoAttribute::Nps(parse_u64(
                                                 spi,
-                                                Rule::digits12,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(an_info);
                                             break;
@@ -600,7 +939,7 @@ fn do_parse_uci(
                                         Rule::info_tbhits => {
                                             let an_info = UciInfoAttribute::TbHits(parse_u64(
                                                 spi,
-                                                Rule::digits12,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(an_info);
                                             break;
@@ -608,26 +947,18 @@ fn do_parse_uci(
                                         Rule::info_sbhits => {
                                             let an_info = UciInfoAttribute::SbHits(parse_u64(
                                                 spi,
-                                                Rule::digits12,
+                                                Rule::digits,
                                             ));
                                             info_attr.push(an_info);
                                             break;
                                         }
                                         Rule::info_cpuload => {
-                                            let an_info = UciInfoAttribute::CpuLoad(parse_u64(
-                                                spi,
-                                                Rule::digits12,
-                                            )
-                                                as u16);
+                                            let an_info = UciInfoAttribute::CpuLoad(parse_u16(spi, Rule::digits));
                                             info_attr.push(an_info);
                                             break;
                                         }
                                         Rule::info_multipv => {
-                                            let an_info = UciInfoAttribute::MultiPv(parse_u64(
-                                                spi,
-                                                Rule::digits12,
-                                            )
-                                                as u16);
+                                            let an_info = UciInfoAttribute::MultiPv(parse_u16(spi, Rule::digits));
                                             info_attr.push(an_info);
                                             break;
                                         }
@@ -673,8 +1004,7 @@ fn do_parse_uci(
                                                     }
                                                     Rule::info_cpunr => {
                                                         cpu_nr =
-                                                            Some(parse_u64(spii, Rule::digits3)
-                                                                as u16);
+                                                            Some(parse_u16(spii, Rule::digits));
                                                     }
                                                     _ => {}
                                                 }
@@ -723,8 +1053,8 @@ fn do_parse_uci(
 
                                             for spii in spi.into_inner() {
                                                 match spii.as_rule() {
-                                                    Rule::info_cp => cp = Some(parse_i64(spii, Rule::i64) as i32),
-                                                    Rule::info_mate => mate = Some(parse_i64(spii, Rule::i64) as i8),
+                                                    Rule::info_cp => cp = Some(saturate(parse_i64(spii, Rule::i64), i32::MIN, i32::MAX)),
+                                                    Rule::info_mate => mate = Some(saturate(parse_i64(spii, Rule::i64), i8::MIN, i8::MAX)),
                                                     Rule::info_lowerbound => lb = Some(true),
                                                     Rule::info_upperbound => ub = Some(true),
                                                     _ => {}
@@ -749,7 +1079,7 @@ fn do_parse_uci(
                                                             spii.as_span().as_str().to_owned(),
                                                         );
                                                     }
-                                                    Rule::info_string_string => {
+                                                    Rule::info_any_value => {
                                                         s = Some(
                                                             spii.as_span().as_str().to_owned(),
                                                         );
@@ -758,7 +1088,7 @@ fn do_parse_uci(
                                                 }
                                             }
                                             let an_info =
-                                                UciInfoAttribute::Any(t.unwrap(), s.unwrap());
+                                                UciInfoAttribute::Any(t.unwrap(), s.unwrap_or_default());
                                             info_attr.push(an_info);
                                             break;
                                         }
@@ -766,10 +1096,12 @@ fn do_parse_uci(
                                     }
                                 }
                             }
+                            Rule::info_junk_text => {}
                             _ => unreachable!(),
                         }
                     }
 
+                    info_attr.dedup_attributes(options.duplicate_attributes);
                     UciMessage::Info(info_attr)
                 }
                 Rule::something_produced => {
@@ -782,17 +1114,395 @@ fn do_parse_uci(
                 _ => unreachable!(),
             }
         })
-        .for_each(|msg| {
+        .enumerate()
+        .try_for_each(|(i, msg)| {
+            let checked = match case_errors.get_mut(i).and_then(Option::take) {
+                Some(e) => Err(e),
+                None => check_direction(s, spans[i], msg, options),
+            };
+            let msg = match checked {
+                Ok(msg) => msg,
+                Err(e) => match top_rule {
+                    Rule::commands => return Err(e),
+                    Rule::commands_ignore_unknown => return Ok(()),
+                    _ => UciMessage::Unknown(s[spans[i].0..spans[i].1].trim_end().to_string(), Some(e)),
+                }
+            };
+
             if let Some(a_ml) = &mut ml {
                 (*a_ml).push(msg);
             } else {
                 single = Some(msg);
             }
-        });
+
+            Ok(())
+        })?;
 
     Ok(single)
 }
 
+/// Returns the message back if it flows in the direction the `options` allow, or an error spanning the message if it
+/// does not.
+fn check_direction(s: &str, span: (usize, usize), msg: UciMessage, options: &ParseOptions) -> Result<UciMessage, Error<Rule>> {
+    let direction = match options.direction {
+        Some(d) if !msg.is_unknown() && msg.direction() != d => d,
+        _ => return Ok(msg),
+    };
+
+    let serialized = msg.serialize();
+    let keyword = serialized.split_whitespace().next().unwrap_or_default();
+    let message = match direction {
+        CommunicationDirection::GuiToEngine => format!("`{}` is not an engine-bound message", keyword),
+        CommunicationDirection::EngineToGui => format!("`{}` is not a GUI-bound message", keyword),
+    };
+
+    let end = span.0 + s[span.0..span.1].trim_end().len();
+    Err(Error::new_from_span(ErrorVariant::CustomError { message }, Span::new(s, span.0, end).unwrap()))
+}
+
+/// The rules that match a value rather than keywords, such as a name, free text, a FEN, a move or a number. Their text
+/// keeps its case, whatever the `KeywordCase`.
+const VALUE_RULES: [Rule; 23] = [
+    Rule::option_name, Rule::option_value, Rule::register_name, Rule::register_code, Rule::fen, Rule::a_move,
+    Rule::id_text, Rule::option_name2, Rule::option_default, Rule::option_string_default, Rule::option_combo_default,
+    Rule::option_min, Rule::option_max, Rule::option_var, Rule::info_string_string, Rule::info_any_value, Rule::token, Rule::milliseconds,
+    Rule::digits, Rule::i64, Rule::info_junk_text, Rule::something_produced,
+    Rule::something_produced_nl,
+];
+
+/// Returns an error spanning the first keyword of the `pair` that is not in lowercase, if any. The keywords are the
+/// words of the `pair`'s text that are not matched by one of the `VALUE_RULES`.
+fn check_keyword_case(s: &str, pair: Pair<Rule>) -> Option<Error<Rule>> {
+    if VALUE_RULES.contains(&pair.as_rule()) {
+        return None;
+    }
+
+    let span = pair.as_span();
+    let mut gaps: Vec<(usize, usize)> = Vec::new();
+    let mut start = span.start();
+    for inner in pair.clone().into_inner() {
+        gaps.push((start, inner.as_span().start()));
+        start = inner.as_span().end();
+    }
+    gaps.push((start, span.end()));
+
+    // The ASCII letters are single bytes, so the words can be found byte by byte
+    let bytes = s.as_bytes();
+    for (gap_start, gap_end) in gaps {
+        let mut i = gap_start;
+        while i < gap_end {
+            let word_end = (i..gap_end).find(|&j| !bytes[j].is_ascii_alphabetic()).unwrap_or(gap_end);
+            if bytes[i..word_end].iter().any(u8::is_ascii_uppercase) {
+                let message = format!("keyword `{}` is not in lowercase", &s[i..word_end]);
+                return Some(Error::new_from_span(ErrorVariant::CustomError { message },
+                                                 Span::new(s, i, word_end).unwrap()));
+            }
+            i = word_end + 1;
+        }
+    }
+
+    pair.into_inner().find_map(|inner| check_keyword_case(s, inner))
+}
+
+/// The keywords that are followed by a list of moves.
+const MOVE_LIST_KEYWORDS: [&str; 5] = ["moves", "searchmoves", "pv", "refutation", "currline"];
+
+/// The keywords that are followed by a single move.
+const MOVE_KEYWORDS: [&str; 3] = ["bestmove", "ponder", "currmove"];
+
+/// Replaces a generic grammar error with a precise one if the line it occurred on contains a malformed move.
+fn refine_move_error(s: &str, e: Error<Rule>) -> Error<Rule> {
+    let pos = match e.location {
+        InputLocation::Pos(p) => p,
+        InputLocation::Span((p, _)) => p,
+    };
+    let line_start = s[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = s[pos..].find('\n').map_or(s.len(), |i| pos + i);
+    let tokens = tokenize(&s[line_start..line_end]);
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let keyword = tokens[i].1;
+        i += 1;
+
+        let single = MOVE_KEYWORDS.contains(&keyword);
+        if !single && !MOVE_LIST_KEYWORDS.contains(&keyword) {
+            continue;
+        }
+
+        // The line of `currline` may be preceded by the CPU number
+        if keyword == "currline" && i < tokens.len() && tokens[i].1.chars().all(|c| c.is_ascii_digit()) {
+            i += 1;
+        }
+
+        // The null move may appear in the lists of moves
+        while i < tokens.len() && (is_move_shaped(tokens[i].1) || (!single && tokens[i].1 == "0000")) {
+            let (offset, token) = tokens[i];
+            if let Some(message) = diagnose_move(token).filter(|_| token != "0000") {
+                let start = line_start + offset;
+                let span = Span::new(s, start, start + token.len()).unwrap();
+                return Error::new_from_span(ErrorVariant::CustomError { message }, span);
+            }
+
+            i += 1;
+            if single {
+                break;
+            }
+        }
+    }
+
+    e
+}
+
+/// The keywords that start a message.
+const COMMAND_KEYWORDS: [&str; 19] = [
+    "uci", "debug", "isready", "setoption", "register", "ucinewgame", "stop", "ponderhit", "quit", "position", "go", "id",
+    "uciok", "readyok", "bestmove", "copyprotection", "registration", "option", "info",
+];
+
+/// The keywords that can follow the first one.
+const ARGUMENT_KEYWORDS: [&str; 48] = [
+    "on", "off", "name", "value", "later", "code", "startpos", "fen", "moves", "searchmoves", "ponder", "wtime", "btime",
+    "winc", "binc", "movestogo", "depth", "nodes", "mate", "movetime", "infinite", "author", "checking", "ok", "error",
+    "type", "default", "min", "max", "var", "check", "spin", "combo", "button", "string", "seldepth", "time", "pv",
+    "multipv", "score", "cp", "lowerbound", "upperbound", "currmove", "currmovenum", "hashfull", "nps", "cpuload",
+];
+
+/// Replaces a generic grammar error with a "did you mean" one if the line it occurred on starts with a misspelled
+/// command or contains a misspelled keyword. Errors that have already been refined are kept.
+fn refine_keyword_error(s: &str, e: Error<Rule>) -> Error<Rule> {
+    let pos = match e.variant {
+        ErrorVariant::ParsingError { .. } => match e.location {
+            InputLocation::Pos(p) => p,
+            InputLocation::Span((p, _)) => p,
+        },
+        ErrorVariant::CustomError { .. } => return e,
+    };
+    let line_start = s[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = s[pos..].find('\n').map_or(s.len(), |i| pos + i);
+    let tokens = tokenize(&s[line_start..line_end]);
+
+    let (offset, token, suggestion) = match tokens.first() {
+        Some(&(offset, token)) if !COMMAND_KEYWORDS.contains(&token.to_ascii_lowercase().as_str()) => {
+            match suggest_keyword(token, &COMMAND_KEYWORDS) {
+                Some(suggestion) => (offset, token, suggestion),
+                None => return e,
+            }
+        }
+        _ => {
+            // Pest does not always locate the error on the offending token, so the first close one is taken
+            let found = tokens.iter().skip(1)
+                .find_map(|&(offset, token)| suggest_keyword(token, &ARGUMENT_KEYWORDS).map(|sg| (offset, token, sg)));
+
+            match found {
+                Some(found) => found,
+                None => return e,
+            }
+        }
+    };
+
+    let start = line_start + offset;
+    let message = format!("unknown keyword `{}`, did you mean `{}`?", token, suggestion);
+    Error::new_from_span(ErrorVariant::CustomError { message }, Span::new(s, start, start + token.len()).unwrap())
+}
+
+/// Finds the keyword closest to the `token`, if the `token` is not a keyword itself but is at most one edit (for
+/// short keywords) or two edits away from one.
+fn suggest_keyword(token: &str, keywords: &[&'static str]) -> Option<&'static str> {
+    let token = token.to_ascii_lowercase();
+    if !token.chars().all(|c| c.is_ascii_alphabetic()) || keywords.contains(&token.as_str()) {
+        return None;
+    }
+
+    keywords.iter()
+        .map(|k| (edit_distance(&token, k), *k))
+        .filter(|(d, k)| *d <= if k.len() <= 4 { 1 } else { 2 })
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+/// The optimal string alignment distance between `a` and `b`: the number of insertions, deletions, substitutions
+/// and transpositions of adjacent characters that turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// The attributes of an `info` message whose counts may have a suffix, see `ParseOptions::numeric_suffixes(..)`.
+const SCALED_COUNT_KEYWORDS: [&str; 2] = ["nodes", "nps"];
+
+/// Rewrites the counts with a suffix (such as `12.3M`) that follow one of the `SCALED_COUNT_KEYWORDS` in the `info`
+/// messages of `s` as whole numbers, stopping at the `string` attribute of each. Returns `None` if there are none.
+fn expand_numeric_suffixes(s: &str) -> Option<String> {
+    let mut expanded = String::with_capacity(s.len());
+    let mut changed = false;
+
+    for line in s.split_inclusive('\n') {
+        let tokens = tokenize(line);
+        let mut copied = 0;
+
+        if tokens.first().is_some_and(|(_, t)| t.eq_ignore_ascii_case("info")) {
+            for pair in tokens.windows(2) {
+                let (keyword, (offset, count)) = (pair[0].1, pair[1]);
+                if keyword.eq_ignore_ascii_case("string") {
+                    break;
+                }
+                if !SCALED_COUNT_KEYWORDS.iter().any(|k| keyword.eq_ignore_ascii_case(k)) {
+                    continue;
+                }
+                if let Some(n) = parse_scaled_count(count) {
+                    expanded.push_str(&line[copied..offset]);
+                    expanded.push_str(&n.to_string());
+                    copied = offset + count.len();
+                    changed = true;
+                }
+            }
+        }
+
+        expanded.push_str(&line[copied..]);
+    }
+
+    if changed {
+        Some(expanded)
+    } else {
+        None
+    }
+}
+
+/// Rewrites the lines of `s` before the first `uciok` that do not parse as `info string` messages holding them, see
+/// `ParseOptions::startup_banner(..)`. Returns `None` if there are none.
+fn rewrite_banner_lines(s: &str) -> Option<String> {
+    let mut rewritten = String::with_capacity(s.len());
+    let mut changed = false;
+    let mut lines = s.split_inclusive('\n');
+
+    for line in lines.by_ref() {
+        let parses = is_blank_line(line) || UciParser::parse(Rule::commands, line).is_ok();
+        if parses {
+            rewritten.push_str(line);
+            if classify(line) == Some(UciMessageKind::UciOk) {
+                break;
+            }
+        } else {
+            rewritten.push_str("info string ");
+            rewritten.push_str(line.trim_start());
+            changed = true;
+        }
+    }
+
+    if changed {
+        rewritten.extend(lines);
+        Some(rewritten)
+    } else {
+        None
+    }
+}
+
+/// Parses a count with a decimal point or a `k`, `M` or `G` suffix (in either case), rounding it down and clamping it
+/// to the range of `u64`. Returns `None` for a plain whole number, or for anything that is not a count.
+fn parse_scaled_count(token: &str) -> Option<u64> {
+    let (number, multiplier): (&str, u128) = match token.as_bytes().last()? {
+        b'k' | b'K' => (&token[..token.len() - 1], 1_000),
+        b'm' | b'M' => (&token[..token.len() - 1], 1_000_000),
+        b'g' | b'G' => (&token[..token.len() - 1], 1_000_000_000),
+        _ => (token, 1),
+    };
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+
+    let is_digits = |t: &str| !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || (number.contains('.') && !is_digits(fraction)) || (multiplier == 1 && fraction.is_empty()) {
+        return None;
+    }
+
+    // Enough digits of the fraction to matter, few enough not to overflow
+    let fraction = &fraction[..fraction.len().min(9)];
+    let whole = whole.parse::<u128>().unwrap_or(u128::from(u64::MAX));
+    let scaled = whole.saturating_mul(multiplier) + match fraction.parse::<u128>() {
+        Ok(f) => f * multiplier / 10u128.pow(fraction.len() as u32),
+        Err(_) => 0,
+    };
+
+    Some(u64::try_from(scaled).unwrap_or(u64::MAX))
+}
+
+/// Splits the line into whitespace-separated tokens, along with their offsets within the line.
+fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, c) in line.char_indices() {
+        if c.is_whitespace() {
+            if let Some(st) = start.take() {
+                tokens.push((st, &line[st..i]));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(st) = start {
+        tokens.push((st, &line[st..]));
+    }
+
+    tokens
+}
+
+/// Whether the token looks like an attempted move, that is, two letter-digit pairs followed by an optional
+/// promotion character.
+fn is_move_shaped(token: &str) -> bool {
+    let chars: Vec<char> = token.chars().collect();
+
+    (chars.len() == 4 || chars.len() == 5)
+        && chars[0].is_ascii_alphabetic()
+        && chars[1].is_ascii_digit()
+        && chars[2].is_ascii_alphabetic()
+        && chars[3].is_ascii_digit()
+}
+
+/// Describes what is wrong with a move-shaped token, or returns `None` if it is a valid move.
+fn diagnose_move(token: &str) -> Option<String> {
+    let chars: Vec<char> = token.chars().collect();
+
+    for &file in &[chars[0], chars[2]] {
+        if !('a'..='h').contains(&file.to_ascii_lowercase()) {
+            return Some(format!("file out of range a–h: '{}' in move '{}'", file, token));
+        }
+    }
+
+    for &rank in &[chars[1], chars[3]] {
+        if !('1'..='8').contains(&rank) {
+            return Some(format!("rank out of range 1–8: '{}' in move '{}'", rank, token));
+        }
+    }
+
+    match chars.get(4) {
+        Some(p) if !"qrnbQRNB".contains(*p) => {
+            Some(format!("invalid promotion piece '{}' in move '{}'", p, token))
+        }
+        _ => None,
+    }
+}
+
 fn parse_id_text(id_pair: Pair<Rule>, rule: Rule) -> UciMessage {
     for sp in id_pair.into_inner() {
         match sp.as_rule() {
@@ -821,61 +1531,26 @@ fn parse_id_text(id_pair: Pair<Rule>, rule: Rule) -> UciMessage {
     unreachable!();
 }
 
-#[cfg(not(feature = "chess"))]
-fn parse_square(sq_pair: Pair<Rule>) -> UciSquare {
-    let mut file: char = '\0';
-    let mut rank: u8 = 0;
-
-    match sq_pair.as_rule() {
-        Rule::square => {
-            for sp in sq_pair.into_inner() {
-                match sp.as_rule() {
-                    Rule::file => {
-                        file = sp.as_span().as_str().chars().into_iter().next().unwrap();
-                    }
-                    Rule::rank => {
-                        rank = str::parse(sp.as_span().as_str()).unwrap();
-                    }
-                    _ => unreachable!(),
-                }
-            }
-        }
-        _ => unreachable!(),
-    }
+// The squares and moves are parsed from the text of the `a_move` pair, which the grammar has already checked, rather than
+// from inner pairs, as long lines of moves (such as the `pv` of a deep search) would otherwise produce several pairs a move.
 
-    UciSquare::from(file, rank)
+#[cfg(not(feature = "chess"))]
+fn parse_square(sq: &[u8]) -> UciSquare {
+    UciSquare::from(sq[0].to_ascii_lowercase() as char, sq[1] - b'0')
 }
 
 #[cfg(feature = "chess")]
-fn parse_square(sq_pair: Pair<Rule>) -> Square {
-    let mut file: char = '\0';
-    let mut rank: u8 = 0;
-
-    match sq_pair.as_rule() {
-        Rule::square => {
-            for sp in sq_pair.into_inner() {
-                match sp.as_rule() {
-                    Rule::file => {
-                        file = sp.as_span().as_str().chars().into_iter().next().unwrap();
-                    }
-                    Rule::rank => {
-                        rank = str::parse(sp.as_span().as_str()).unwrap();
-                    }
-                    _ => unreachable!(),
-                }
-            }
-        }
-        _ => unreachable!(),
-    }
-
-    Square::from_str(format!("{}{}", file.to_string(), rank.to_string()).as_str()).unwrap()
+fn parse_square(sq: &[u8]) -> Square {
+    Square::make_square(Rank::from_index((sq[1] - b'1') as usize),
+                        File::from_index((sq[0].to_ascii_lowercase() - b'a') as usize))
 }
 
 fn parse_milliseconds(pair: Pair<Rule>) -> i64 {
     for sp in pair.into_inner() {
         match sp.as_rule() {
             Rule::milliseconds => {
-                return str::parse::<i64>(sp.as_span().as_str()).unwrap();
+                // Within the range of `Duration`, which stops short of `i64::MIN`
+                return parse_clamped_i64(sp.as_span().as_str()).max(-i64::MAX);
             }
             _ => {}
         }
@@ -885,19 +1560,18 @@ fn parse_milliseconds(pair: Pair<Rule>) -> i64 {
 }
 
 fn parse_u8(pair: Pair<Rule>, rule: Rule) -> u8 {
-    for sp in pair.into_inner() {
-        if sp.as_rule() == rule {
-            return str::parse::<u8>(sp.as_span().as_str()).unwrap();
-        }
-    }
+    saturate(parse_i64(pair, rule), u8::MIN, u8::MAX)
+}
 
-    0
+fn parse_u16(pair: Pair<Rule>, rule: Rule) -> u16 {
+    saturate(parse_i64(pair, rule), u16::MIN, u16::MAX)
 }
 
 fn parse_u64(pair: Pair<Rule>, rule: Rule) -> u64 {
     for sp in pair.into_inner() {
         if sp.as_rule() == rule {
-            return str::parse::<u64>(sp.as_span().as_str()).unwrap();
+            // The grammar only lets digits through, so the only error is an overflow
+            return str::parse::<u64>(sp.as_span().as_str()).unwrap_or(u64::MAX);
         }
     }
 
@@ -907,63 +1581,63 @@ fn parse_u64(pair: Pair<Rule>, rule: Rule) -> u64 {
 fn parse_i64(pair: Pair<Rule>, rule: Rule) -> i64 {
     for sp in pair.into_inner() {
         if sp.as_rule() == rule {
-            return str::parse::<i64>(sp.as_span().as_str()).unwrap();
+            return parse_clamped_i64(sp.as_span().as_str());
         }
     }
 
     0
 }
 
+/// Parses a number the grammar has already checked, clamping it to the range of `i64`.
+fn parse_clamped_i64(s: &str) -> i64 {
+    str::parse::<i64>(s).unwrap_or(if s.starts_with('-') { i64::MIN } else { i64::MAX })
+}
+
+/// Parses a spin option value written as an integer or a decimal, such as `-12` or `1.745`. Anything else (including
+/// the `inf` or `NaN` that `f64` would accept) yields `None`.
+fn parse_decimal(s: &str) -> Option<UciFloat> {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+    let is_digits = |p: &str| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit());
+
+    if is_digits(int) && is_digits(frac) {
+        str::parse::<f64>(s).ok().map(UciFloat)
+    } else {
+        None
+    }
+}
+
+/// Converts the number `n`, clamping it to the range from `min` to `max`.
+fn saturate<T: TryFrom<i64>>(n: i64, min: T, max: T) -> T {
+    T::try_from(n).unwrap_or(if n < 0 { min } else { max })
+}
+
 #[cfg(not(feature = "chess"))]
 fn parse_a_move(sp: Pair<Rule>) -> UciMove {
-    let mut from_sq = UciSquare::default();
-    let mut to_sq = UciSquare::default();
-    let mut promotion: Option<UciPiece> = None;
-
-    for move_token in sp.into_inner() {
-        match move_token.as_rule() {
-            Rule::from_sq => {
-                from_sq = parse_square(move_token.into_inner().next().unwrap());
-            }
-            Rule::to_sq => {
-                to_sq = parse_square(move_token.into_inner().next().unwrap());
-            }
-            Rule::promotion => {
-                promotion = Some(UciPiece::from_str(move_token.as_span().as_str()).unwrap());
-            }
-            _ => unreachable!(),
-        }
+    let text = sp.as_span().as_str();
+    if sp.as_rule() == Rule::null_move {
+        return UciMove::null();
     }
 
     UciMove {
-        from: from_sq,
-        to: to_sq,
-        promotion,
+        from: parse_square(&text.as_bytes()[0..2]),
+        to: parse_square(&text.as_bytes()[2..4]),
+        promotion: text.get(4..).filter(|p| !p.is_empty()).map(|p| UciPiece::from_str(p).unwrap()),
     }
 }
 
 #[cfg(feature = "chess")]
 fn parse_a_move(sp: Pair<Rule>) -> ChessMove {
-    let mut from_sq = Square::default();
-    let mut to_sq = Square::default();
-    let mut promotion: Option<Piece> = None;
-
-    for move_token in sp.into_inner() {
-        match move_token.as_rule() {
-            Rule::from_sq => {
-                from_sq = parse_square(move_token.into_inner().next().unwrap());
-            }
-            Rule::to_sq => {
-                to_sq = parse_square(move_token.into_inner().next().unwrap());
-            }
-            Rule::promotion => {
-                promotion = Some(piece_from_str(move_token.as_span().as_str()).unwrap());
-            }
-            _ => unreachable!(),
-        }
+    let text = sp.as_span().as_str();
+    if sp.as_rule() == Rule::null_move {
+        return ChessMove::default();
     }
 
-    ChessMove::new(from_sq, to_sq, promotion)
+    ChessMove::new(
+        parse_square(&text.as_bytes()[0..2]),
+        parse_square(&text.as_bytes()[2..4]),
+        text.get(4..).filter(|p| !p.is_empty()).map(|p| piece_from_str(p).unwrap()),
+    )
 }
 
 #[cfg(feature = "chess")]
@@ -1072,6 +1746,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_option_value_keyword() {
+        let cases = [
+            ("setoption name value value name", "value", Some("name")),
+            ("setoption name A value B value C", "A value B", Some("C")),
+            ("setoption name Value", "Value", None),
+            ("setoption name value value", "value", None),
+            ("setoption name Hash value", "Hash", None),
+            ("setoption name valuex value valuey", "valuex", Some("valuey")),
+            ("setoption name Use Values VALUE values", "Use Values", Some("values")),
+        ];
+
+        for (input, name, value) in cases.iter() {
+            let ml = parse_strict(input).unwrap();
+            assert_eq!(ml, vec![UciMessage::set_option(name, value.map(String::from))], "{}", input);
+        }
+
+        // The search for a later `value` stops at the end of the line
+        let ml = parse_strict("setoption name A value B\nsetoption name C value D value\n").unwrap();
+        assert_eq!(ml, vec![UciMessage::set_option("A", String::from("B")), UciMessage::set_option("C value D", None)]);
+    }
+
     // setoption name Clear Hash
     #[test]
     fn test_set_option_button() {
@@ -1129,9 +1825,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_register_name_only() {
+        let ml = parse_strict("register name Matija Kejžar \n").unwrap();
+        assert_eq!(ml, vec![UciMessage::Register { later: false, name: Some(String::from("Matija Kejžar")), code: None }]);
+        assert_eq!(ml[0].serialize(), "register name Matija Kejžar");
+    }
+
+    #[test]
+    fn test_register_code_only() {
+        let ml = parse_strict("register code 12345\nregister CODE XX-344 00LP\n").unwrap();
+        assert_eq!(ml, vec![
+            UciMessage::Register { later: false, name: None, code: Some(String::from("12345")) },
+            UciMessage::Register { later: false, name: None, code: Some(String::from("XX-344 00LP")) },
+        ]);
+        assert_eq!(ml[0].serialize(), "register code 12345");
+    }
+
     #[test]
     fn test_register_invalid() {
-        parse_strict("register name Matija Kejžar\n").expect_err("Parse error expected.");
+        parse_strict("register name Matija Kejžar code\n").expect_err("Parse error expected.");
     }
 
     #[test]
@@ -1288,6 +2001,56 @@ mod tests {
             .expect_err("Parse should fail.");
     }
 
+    #[cfg(not(feature = "chess"))]
+    #[test]
+    fn test_null_move() {
+        let ml = parse_strict("position startpos moves e2e4 0000 d2d4\ngo searchmoves 0000 e2e4\n").unwrap();
+        assert_eq!(ml[0], UciMessage::Position {
+            startpos: true,
+            fen: None,
+            moves: vec![
+                UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4)),
+                UciMove::null(),
+                UciMove::from_to(UciSquare::from('d', 2), UciSquare::from('d', 4)),
+            ],
+        });
+        match &ml[1] {
+            UciMessage::Go { search_control: Some(sc), .. } => assert!(sc.search_moves[0].is_null()),
+            m => panic!("unexpected message {:?}", m),
+        }
+        assert_eq!(ml[0].serialize(), "position startpos moves e2e4 0000 d2d4");
+        assert_eq!(UciMove::from_str("0000"), Ok(UciMove::null()));
+        assert_eq!(UciMove::null().to_string(), "0000");
+
+        let mut buffer = vec![];
+        ml[0].encode_into(&mut buffer);
+        assert_eq!(UciMessage::decode(&buffer), Ok(ml[0].clone()));
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_null_move() {
+        let ml = parse_strict("position startpos moves e2e4 0000 d2d4\ngo searchmoves 0000 e2e4\n").unwrap();
+        assert_eq!(ml[0], UciMessage::Position {
+            startpos: true,
+            fen: None,
+            moves: vec![
+                ChessMove::new(Square::E2, Square::E4, None),
+                ChessMove::default(),
+                ChessMove::new(Square::D2, Square::D4, None),
+            ],
+        });
+        assert_eq!(ml[0].serialize(), "position startpos moves e2e4 0000 d2d4");
+        assert_eq!(ml[1].serialize().trim_end(), "go  searchmoves 0000 e2e4");
+    }
+
+    #[test]
+    fn test_null_move_not_a_single_move() {
+        assert!(parse_strict("bestmove 0000\n").is_err());
+        let e = parse_strict("position startpos moves e2e4 0000 e7e9\n").unwrap_err();
+        assert!(e.to_string().contains("rank out of range"));
+    }
+
     #[test]
     fn test_position_startpos_no_moves() {
         let ml = parse_strict("position   startpos\r\n").unwrap();
@@ -1318,6 +2081,18 @@ mod tests {
         assert_eq!(ml[0], pos);
     }
 
+    #[test]
+    fn test_position_fen_without_counters() {
+        let ml = parse_strict("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - -\n\
+            position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - moves g7g8q c4g8\n").unwrap();
+
+        let fen = UciFen(String::from("2k5/6PR/8/8/2b4P/8/6K1/8 w - -"));
+        assert_eq!(ml[0], UciMessage::Position { startpos: false, fen: Some(fen.clone()), moves: vec![] });
+        assert_eq!(ml[1].serialize(), "position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - - moves g7g8q c4g8");
+        assert_eq!(parse_one("position fen 2k5/6PR/8/8/2b4P/8/6K1/8 w - -"), ml[0]);
+        assert!(!fen.has_counters());
+    }
+
     #[test]
     fn test_go_ponder() {
         let ml = parse_strict("go ponder\n").unwrap();
@@ -1550,6 +2325,9 @@ mod tests {
             },
 
             ponder: None,
+
+            #[cfg(feature = "extensions")]
+            offer: None,
         };
 
         #[cfg(feature = "chess")]
@@ -1557,6 +2335,9 @@ mod tests {
             best_move: ChessMove::new(Square::G1, Square::F3, None),
 
             ponder: None,
+
+            #[cfg(feature = "extensions")]
+            offer: None,
         };
 
         assert_eq!(m, ml[0]);
@@ -1581,6 +2362,9 @@ mod tests {
                 to: UciSquare::from('f', 6),
                 promotion: None,
             }),
+
+            #[cfg(feature = "extensions")]
+            offer: None,
         };
 
         #[cfg(feature = "chess")]
@@ -1588,9 +2372,84 @@ mod tests {
             best_move: ChessMove::new(Square::G1, Square::F3, None),
 
             ponder: Some(ChessMove::new(Square::D8, Square::F6, None)),
+
+            #[cfg(feature = "extensions")]
+            offer: None,
+        };
+
+        assert_eq!(m, ml[0]);
+    }
+
+    #[test]
+    fn test_bestmove_uppercase_promotion() {
+        let ml = parse_strict("bestmove A7A8Q ponder b2b1n\n").unwrap();
+        assert_eq!(ml.len(), 1);
+
+        #[cfg(not(feature = "chess"))]
+        let m = UciMessage::BestMove {
+            best_move: UciMove {
+                from: UciSquare::from('a', 7),
+                to: UciSquare::from('a', 8),
+                promotion: Some(UciPiece::Queen),
+            },
+
+            ponder: Some(UciMove {
+                from: UciSquare::from('b', 2),
+                to: UciSquare::from('b', 1),
+                promotion: Some(UciPiece::Knight),
+            }),
+
+            #[cfg(feature = "extensions")]
+            offer: None,
+        };
+
+        #[cfg(feature = "chess")]
+        let m = UciMessage::BestMove {
+            best_move: ChessMove::new(Square::A7, Square::A8, Some(Piece::Queen)),
+
+            ponder: Some(ChessMove::new(Square::B2, Square::B1, Some(Piece::Knight))),
+
+            #[cfg(feature = "extensions")]
+            offer: None,
         };
 
         assert_eq!(m, ml[0]);
+        assert_eq!(ml[0].serialize(), "bestmove a7a8q ponder b2b1n");
+    }
+
+    #[cfg(feature = "extensions")]
+    #[test]
+    fn test_bestmove_offers() {
+        let ml = parse_strict("bestmove e2e4 draw\nbestmove g1f3 ponder d8f6 DRAW\nbestmove resign\nbestmove e2e4\n").unwrap();
+        let offers: Vec<Option<Offer>> = ml.iter().map(|m| match m {
+            UciMessage::BestMove { offer, .. } => *offer,
+            _ => panic!("unexpected message {:?}", m),
+        }).collect();
+
+        assert_eq!(offers, vec![Some(Offer::Draw), Some(Offer::Draw), Some(Offer::Resign), None]);
+        assert_eq!(ml[0].serialize(), "bestmove e2e4 draw");
+        assert_eq!(ml[1].serialize(), "bestmove g1f3 ponder d8f6 draw");
+        assert_eq!(ml[2].serialize(), "bestmove resign");
+        assert_eq!(parse_one(&ml[2].serialize()), ml[2]);
+        assert!(parse_strict("bestmove resign e2e4\n").is_err());
+
+        let mut buffer = vec![];
+        ml[1].encode_into(&mut buffer);
+        assert_eq!(UciMessage::decode(&buffer), Ok(ml[1].clone()));
+    }
+
+    #[cfg(not(feature = "extensions"))]
+    #[test]
+    fn test_bestmove_offers_without_extensions() {
+        assert!(parse_strict("bestmove e2e4 draw\n").is_err());
+        assert!(parse_one("bestmove resign").is_unknown());
+    }
+
+    #[test]
+    fn test_position_uppercase_promotion() {
+        let ml = parse_strict("position startpos moves a7a8Q h2h1R\n").unwrap();
+        assert_eq!(ml.len(), 1);
+        assert_eq!(ml[0].serialize(), "position startpos moves a7a8q h2h1r");
     }
 
     #[test]
@@ -1929,6 +2788,78 @@ mod tests {
         assert_eq!(m, ml[0]);
     }
 
+    #[test]
+    fn test_parse_info_any_recovery() {
+        let ml = parse_strict("info foo 1 2 depth 8 nodes 5 bar baz string x\n").unwrap();
+
+        let m = UciMessage::Info(vec![
+            UciInfoAttribute::Any("foo".to_owned(), "1 2".to_owned()),
+            UciInfoAttribute::Depth(8),
+            UciInfoAttribute::Nodes(5),
+            UciInfoAttribute::Any("bar".to_owned(), "baz".to_owned()),
+            UciInfoAttribute::String("x".to_owned()),
+        ]);
+
+        assert_eq!(m, ml[0]);
+        assert_eq!(m.serialize(), "info foo 1 2 depth 8 nodes 5 bar baz string x");
+    }
+
+    #[test]
+    fn test_parse_info_any_without_value() {
+        let cases = [
+            ("info depth 5 foo nodes 10", vec![
+                UciInfoAttribute::Depth(5),
+                UciInfoAttribute::Any("foo".to_owned(), String::new()),
+                UciInfoAttribute::Nodes(10),
+            ]),
+            ("info depth 5 foo pv e2e4", vec![
+                UciInfoAttribute::Depth(5),
+                UciInfoAttribute::Any("foo".to_owned(), String::new()),
+                UciInfoAttribute::Pv(vec!["e2e4".parse().unwrap()]),
+            ]),
+            ("info foo string bar", vec![
+                UciInfoAttribute::Any("foo".to_owned(), String::new()),
+                UciInfoAttribute::String("bar".to_owned()),
+            ]),
+            ("info lowerbound depth 5", vec![
+                UciInfoAttribute::Any("lowerbound".to_owned(), String::new()),
+                UciInfoAttribute::Depth(5),
+            ]),
+            ("info depth 5 lowerbound", vec![
+                UciInfoAttribute::Depth(5),
+                UciInfoAttribute::Any("lowerbound".to_owned(), String::new()),
+            ]),
+        ];
+
+        for (line, attributes) in cases {
+            let m = UciMessage::Info(attributes);
+
+            assert_eq!(parse_strict(line).unwrap(), vec![m.clone()]);
+            assert_eq!(parse(line), vec![m.clone()]);
+            assert_eq!(parse_one(line), m);
+            assert_eq!(m.serialize(), line);
+        }
+    }
+
+    #[test]
+    fn test_parse_info_trailing_any_without_value() {
+        let text = "info depth 5 lowerbound \nreadyok\ninfo score cp 20 ebf\n";
+        let ml = vec![
+            UciMessage::Info(vec![
+                UciInfoAttribute::Depth(5),
+                UciInfoAttribute::Any("lowerbound".to_owned(), String::new()),
+            ]),
+            UciMessage::ReadyOk,
+            UciMessage::Info(vec![
+                UciInfoAttribute::from_centipawns(20),
+                UciInfoAttribute::Any("ebf".to_owned(), String::new()),
+            ]),
+        ];
+
+        assert_eq!(parse_strict(text).unwrap(), ml);
+        assert_eq!(parse(text), ml);
+    }
+
     #[test]
     fn test_parse_info_currmove() {
         let ml = parse_strict("info currmove a7a8q\n").unwrap();
@@ -2282,6 +3213,12 @@ mod tests {
         assert_eq!(msg, UciMessage::go())
     }
 
+    #[test]
+    fn test_parse_go_trailing_space() {
+        assert_eq!(parse_one("go "), UciMessage::go());
+        assert_eq!(parse_one(&UciMessage::go().serialize()), UciMessage::go());
+    }
+
     #[ignore]
     #[test]
     fn test_parse_stdin() {
@@ -2467,6 +3404,21 @@ mod tests {
         assert_eq!(msg, UciMessage::Uci);
     }
 
+    #[test]
+    fn test_parse_negative_clocks_strict() {
+        let ml = parse_strict("go wtime -134 btime 5000 winc -10 binc 0\ngo movetime -1\n").unwrap();
+
+        let tc = match &ml[0] {
+            UciMessage::Go { time_control: Some(tc), .. } => tc,
+            _ => unreachable!(),
+        };
+        assert_eq!(tc.white_time(), Some(Duration::milliseconds(-134)));
+        assert_eq!(tc.black_time(), Some(Duration::milliseconds(5000)));
+        assert_eq!(tc.white_increment(), Some(Duration::milliseconds(-10)));
+        assert_eq!(ml[0].serialize().trim_end(), "go wtime -134 btime 5000 winc -10 binc 0");
+        assert_eq!(ml[1], UciMessage::go_movetime(Duration::milliseconds(-1)));
+    }
+
     #[test]
     fn test_parse_negative_duration_wtime() {
         let parsed_msg = parse_one("go wtime -4061 btime 56826 movestogo 90\n");
@@ -2530,7 +3482,7 @@ mod tests {
     fn test_parse_signed_improperly_duration_wtime_strict() {
         let err = parse_strict("go wtime -15030 btime x56826 movestogo 90\n");
         assert!(err.is_err());
-        let e: pest::error::Error<_> = err.unwrap_err();
+        let e: pest::error::Error<_> = err.unwrap_err().into_pest_error();
         match e.variant {
             pest::error::ErrorVariant::ParsingError {
                 positives,
@@ -2541,4 +3493,432 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    fn assert_move_error(input: &str, message: &str, pos: (usize, usize)) {
+        let e = parse_strict(input).unwrap_err().into_pest_error();
+        match e.variant {
+            pest::error::ErrorVariant::CustomError { message: m } => assert_eq!(m, message),
+            _ => unreachable!(),
+        }
+        assert_eq!(e.line_col, pest::error::LineColLocation::Span(pos, (pos.0, pos.1 + 4)));
+    }
+
+    #[test]
+    fn test_strict_move_error_rank() {
+        assert_move_error("uci\nposition startpos moves e2e4 e9e4\n", "rank out of range 1–8: '9' in move 'e9e4'", (2, 30));
+    }
+
+    #[test]
+    fn test_strict_move_error_file() {
+        assert_move_error("go ponder searchmoves e2j4 d2d4\n", "file out of range a–h: 'j' in move 'e2j4'", (1, 23));
+    }
+
+    #[test]
+    fn test_strict_move_error_promotion() {
+        let e = parse_strict("bestmove e7e8x\n").unwrap_err().into_pest_error();
+        match e.variant {
+            pest::error::ErrorVariant::CustomError { message } => {
+                assert_eq!(message, "invalid promotion piece 'x' in move 'e7e8x'");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn assert_keyword_error(input: &str, message: &str, pos: (usize, usize), len: usize) {
+        let e = parse_strict(input).unwrap_err().into_pest_error();
+        match e.variant {
+            pest::error::ErrorVariant::CustomError { message: m } => assert_eq!(m, message),
+            _ => unreachable!(),
+        }
+        assert_eq!(e.line_col, pest::error::LineColLocation::Span(pos, (pos.0, pos.1 + len)));
+    }
+
+    #[test]
+    fn test_strict_keyword_error_command() {
+        assert_keyword_error("uci\nposiiton startpos\n", "unknown keyword `posiiton`, did you mean `position`?", (2, 1), 8);
+        assert_keyword_error("bestmov e2e4\n", "unknown keyword `bestmov`, did you mean `bestmove`?", (1, 1), 7);
+        assert_keyword_error("IsRaedy\n", "unknown keyword `IsRaedy`, did you mean `isready`?", (1, 1), 7);
+    }
+
+    #[test]
+    fn test_strict_keyword_error_argument() {
+        assert_keyword_error("go depht 5\n", "unknown keyword `depht`, did you mean `depth`?", (1, 4), 5);
+        assert_keyword_error("position startpos mvoes e2e4\n", "unknown keyword `mvoes`, did you mean `moves`?", (1, 19), 5);
+        assert_keyword_error("option name Hash tpye spin\n", "unknown keyword `tpye`, did you mean `type`?", (1, 18), 4);
+        assert_keyword_error("go currmovenun 3\n", "unknown keyword `currmovenun`, did you mean `currmovenum`?", (1, 4), 11);
+    }
+
+    #[test]
+    fn test_keyword_suggestion_parses() {
+        let message = parse_strict("go currmovenun 3\n").unwrap_err().to_string();
+        let suggestion = message.split('`').nth(3).unwrap();
+
+        assert_eq!(parse_strict(&format!("info {} 3\n", suggestion)).unwrap(),
+                   vec![UciMessage::Info(vec![UciInfoAttribute::CurrMoveNum(3)])]);
+    }
+
+    #[test]
+    fn test_strict_keyword_error_no_suggestion() {
+        for input in &["xyzzy\n", "go 42\n", "position e2e4\n"] {
+            match parse_strict(input).unwrap_err().into_pest_error().variant {
+                pest::error::ErrorVariant::ParsingError { .. } => {}
+                _ => panic!("unexpected suggestion for {}", input),
+            }
+        }
+
+        match parse_one("quti") {
+            UciMessage::Unknown(_, Some(e)) => assert!(e.to_string().contains("did you mean `quit`?")),
+            m => panic!("unexpected {:?}", m),
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let input = "uci\ndebug on\nisready\nregister later\nposition startpos\nsetoption name Hash value 1\nucinewgame\n\
+            stop\nponderhit\nquit\ngo infinite\nid name Vampirc\nuciok\nreadyok\nbestmove e2e4\ncopyprotection ok\n\
+            registration error\noption name Ponder type check\ninfo depth 1\n";
+
+        for line in input.lines() {
+            let kind = classify(line).unwrap();
+            assert_eq!(kind, parse_one(line).kind(), "{}", line);
+        }
+
+        assert_eq!(classify("READYOK"), Some(UciMessageKind::ReadyOk));
+        assert_eq!(classify("ucinewgamex"), None);
+        assert_eq!(classify("   "), None);
+    }
+
+    #[test]
+    fn test_parse_direction() {
+        let input = "uci\nid name Vampirc\nisready\nreadyok\nbestmove e2e4\ngo infinite\n";
+
+        assert_eq!(parse_engine_bound(input), vec![UciMessage::Uci, UciMessage::IsReady, UciMessage::go_infinite()]);
+        assert_eq!(parse_gui_bound(input).len(), 3);
+        assert_eq!(parse_with_options(input, &ParseOptions::default()).len(), 6);
+    }
+
+    #[test]
+    fn test_parse_strict_direction() {
+        let options = ParseOptions::new().direction(CommunicationDirection::GuiToEngine);
+        assert_eq!(parse_strict_with_options("uci\nisready\n", &options).unwrap().len(), 2);
+
+        let e = parse_strict_with_options("uci\nbestmove e2e4 ponder e7e5\n", &options).unwrap_err().into_pest_error();
+        match e.variant {
+            pest::error::ErrorVariant::CustomError { message } => assert_eq!(message, "`bestmove` is not an engine-bound message"),
+            _ => unreachable!(),
+        }
+        assert_eq!(e.line_col, pest::error::LineColLocation::Span((2, 1), (2, 26)));
+    }
+
+    #[test]
+    fn test_parse_one_direction() {
+        let options = ParseOptions::new().direction(CommunicationDirection::EngineToGui);
+        assert_eq!(parse_one_with_options("readyok", &options), UciMessage::ReadyOk);
+
+        match parse_one_with_options("ucinewgame\n", &options) {
+            UciMessage::Unknown(text, Some(e)) => {
+                assert_eq!(text, "ucinewgame");
+                assert!(e.to_string().contains("`ucinewgame` is not a GUI-bound message"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_unicode_whitespace_option() {
+        let input = "position\u{a0}startpos moves\u{2003}e2e4\n\u{c}\ngo\u{3000}depth\t3\n";
+        assert!(parse_strict(input).is_err());
+
+        let options = ParseOptions::new().unicode_whitespace(true);
+        assert!(options.get_unicode_whitespace());
+        let ml = parse_strict_with_options(input, &options).unwrap();
+        assert_eq!(ml.len(), 2);
+        assert_eq!(ml[1], UciMessage::go_depth(3));
+
+        let m = parse_one_with_options("setoption name Style value Very\u{a0}Solid", &options);
+        assert_eq!(m, UciMessage::SetOption { name: String::from("Style"), value: Some(String::from("Very Solid")) });
+    }
+
+    #[test]
+    fn test_skip_blank_lines_option() {
+        let input = "\n  \n\t\r\nisready\n\n\n";
+        assert!(parse_one(input).is_unknown());
+
+        let options = ParseOptions::new().skip_blank_lines(true);
+        assert!(options.get_skip_blank_lines());
+        assert_eq!(parse_one_with_options(input, &options), UciMessage::IsReady);
+        assert_eq!(parse_strict_with_options(input, &options).unwrap(), vec![UciMessage::IsReady]);
+
+        // The skipped lines still count in the position of an error
+        let err = parse_strict_with_options("isready\n \n\t\r\ngo depth x\n", &options).unwrap_err();
+        assert_eq!((err.line(), err.column(), err.text()), (4, 10, "go depth x"));
+
+        // Without `unicode_whitespace`, a line holding a no-break space is not blank
+        assert!(parse_one_with_options("\u{a0}\nuci", &options).is_unknown());
+        let options = options.unicode_whitespace(true);
+        assert_eq!(parse_one_with_options("\u{a0}\nuci", &options), UciMessage::Uci);
+    }
+
+    #[test]
+    fn test_keyword_case_any() {
+        let options = ParseOptions::new();
+        assert_eq!(options.get_keyword_case(), KeywordCase::Any);
+
+        let ml = parse_strict_with_options("UCI\nIsReady\nREGISTER LATER\nGo Depth 3\nPonderHit\n", &options).unwrap();
+        assert_eq!(ml, vec![UciMessage::Uci, UciMessage::IsReady, UciMessage::register_later(), UciMessage::go_depth(3),
+                            UciMessage::PonderHit]);
+    }
+
+    #[test]
+    fn test_keyword_case_lowercase() {
+        let options = ParseOptions::new().keyword_case(KeywordCase::Lowercase);
+
+        let input = "id name Stockfish GO\nregister name Matija Kejžar code ABC\ninfo depth 3 string Hello World\n\
+            position fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 moves e7e5\n\
+            option name Style type combo default Normal var Solid var Normal\nbestmove e7e8Q\n";
+        assert_eq!(parse_strict_with_options(input, &options).unwrap().len(), 6);
+
+        let err = parse_strict_with_options("isready\ngo ponder Wtime 100\n", &options).unwrap_err();
+        assert!(err.to_string().contains("keyword `Wtime` is not in lowercase"));
+        assert_eq!(err.pest_error().line_col, pest::error::LineColLocation::Span((2, 11), (2, 16)));
+
+        assert_eq!(parse_with_options("uci\nSetOption name Hash value 32\nisready\n", &options),
+                   vec![UciMessage::Uci, UciMessage::IsReady]);
+        match parse_one_with_options("info score cp 10 Lowerbound", &options) {
+            UciMessage::Unknown(text, Some(e)) => {
+                assert_eq!(text, "info score cp 10 Lowerbound");
+                assert!(e.to_string().contains("keyword `Lowerbound` is not in lowercase"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_option_default_with_keywords() {
+        let ml = parse_strict("option name Book File type string default my min max var.txt\n\
+            option name Style type combo default Min Depth var Max Depth var Min Depth\n\
+            option name Style type combo default minimal var minimal var maximal\n\
+            option name Path type string default \n\
+            option name Hash type spin default 16 min 1 max 128\n").unwrap();
+
+        assert_eq!(ml, vec![
+            UciMessage::Option(UciOptionConfig::String {
+                name: String::from("Book File"),
+                default: Some(String::from("my min max var.txt")),
+            }),
+            UciMessage::Option(UciOptionConfig::Combo {
+                name: String::from("Style"),
+                default: Some(String::from("Min Depth")),
+                var: vec![String::from("Max Depth"), String::from("Min Depth")],
+            }),
+            UciMessage::Option(UciOptionConfig::Combo {
+                name: String::from("Style"),
+                default: Some(String::from("minimal")),
+                var: vec![String::from("minimal"), String::from("maximal")],
+            }),
+            UciMessage::Option(UciOptionConfig::String {
+                name: String::from("Path"),
+                default: None,
+            }),
+            UciMessage::Option(UciOptionConfig::Spin {
+                name: String::from("Hash"),
+                default: Some(16),
+                min: Some(1),
+                max: Some(128),
+            }),
+        ]);
+    }
+
+    #[test]
+    fn test_decimal_spin_options() {
+        let ml = parse_strict("option name CPuct type spin default 1.745 min 0 max 100\n\
+            option name Temperature type spin default 0 min 0.0 max -0.5\n\
+            option name Odd type spin default 1.5e3 min 1 max 2.5\n\
+            option name Hash type spin default 16 min 1 max 128\n").unwrap();
+
+        assert_eq!(ml, vec![
+            UciMessage::Option(UciOptionConfig::Float {
+                name: String::from("CPuct"),
+                default: Some(UciFloat(1.745)),
+                min: Some(UciFloat(0.0)),
+                max: Some(UciFloat(100.0)),
+            }),
+            UciMessage::Option(UciOptionConfig::Float {
+                name: String::from("Temperature"),
+                default: Some(UciFloat(0.0)),
+                min: Some(UciFloat(0.0)),
+                max: Some(UciFloat(-0.5)),
+            }),
+            UciMessage::Option(UciOptionConfig::Float {
+                name: String::from("Odd"),
+                default: None,
+                min: Some(UciFloat(1.0)),
+                max: Some(UciFloat(2.5)),
+            }),
+            UciMessage::Option(UciOptionConfig::Spin {
+                name: String::from("Hash"),
+                default: Some(16),
+                min: Some(1),
+                max: Some(128),
+            }),
+        ]);
+
+        assert_eq!(ml[0].serialize(), "option name CPuct type spin default 1.745 min 0.0 max 100.0");
+        assert_eq!(parse_one(&ml[0].serialize()), ml[0]);
+    }
+
+    #[test]
+    fn test_duplicate_attributes() {
+        let line = "info depth 5 nodes 100 depth 6 string a\n";
+        let parse_with = |policy| parse_strict_with_options(line, &ParseOptions::new().duplicate_attributes(policy)).unwrap();
+
+        assert_eq!(parse_with(DuplicateAttributes::KeepAll), parse_strict(line).unwrap());
+        assert_eq!(parse_with(DuplicateAttributes::KeepFirst), vec![UciMessage::Info(vec![
+            UciInfoAttribute::Depth(5),
+            UciInfoAttribute::Nodes(100),
+            UciInfoAttribute::String(String::from("a")),
+        ])]);
+        assert_eq!(parse_with(DuplicateAttributes::KeepLast), vec![UciMessage::Info(vec![
+            UciInfoAttribute::Nodes(100),
+            UciInfoAttribute::Depth(6),
+            UciInfoAttribute::String(String::from("a")),
+        ])]);
+    }
+
+    #[test]
+    fn test_startup_banner() {
+        let options = ParseOptions::new().startup_banner(true);
+        let banner = |s: &str| UciMessage::Info(vec![UciInfoAttribute::String(s.to_owned())]);
+        let s = "Engine 1.0 (c) 2024\n  compiled with avx2\nid name Engine\nuciok\nno longer a banner\nreadyok\n";
+
+        assert!(parse_strict(s).is_err());
+        assert!(parse_strict_with_options(s, &options).is_err());
+        assert_eq!(parse_with_options(s, &options), vec![
+            banner("Engine 1.0 (c) 2024"),
+            banner("compiled with avx2"),
+            UciMessage::id_name("Engine"),
+            UciMessage::UciOk,
+            UciMessage::ReadyOk,
+        ]);
+
+        let s = "Engine 1.0\nuciok\nreadyok";
+        assert_eq!(parse_strict_with_options(s, &options).unwrap(),
+                   vec![banner("Engine 1.0"), UciMessage::UciOk, UciMessage::ReadyOk]);
+        assert_eq!(parse_one_with_options("Engine 1.0\r\n", &options), banner("Engine 1.0"));
+        assert_eq!(parse_one_with_options("readyok", &options), UciMessage::ReadyOk);
+    }
+
+    #[test]
+    fn test_bare_info() {
+        assert_eq!(parse_strict("info\ninfo  \n").unwrap(), vec![UciMessage::Info(vec![]), UciMessage::Info(vec![])]);
+        assert_eq!(parse_one("info"), UciMessage::Info(vec![]));
+        assert_eq!(UciMessage::Info(vec![]).serialize(), "info");
+    }
+
+    #[test]
+    fn test_info_with_junk() {
+        let junk = |token: &str| UciMessage::Info(vec![UciInfoAttribute::Any(token.to_owned(), String::new())]);
+
+        assert_eq!(parse_strict("info ???\n").unwrap(), vec![junk("???")]);
+        assert_eq!(parse_one("info ???"), junk("???"));
+        let ml = parse("readyok\n  info depth\nbestmove e2e4\n");
+        assert_eq!(ml.len(), 3);
+        assert_eq!(ml[1], junk("depth"));
+        assert_eq!(parse_with_unknown("info depth\ninformation\n"),
+                   vec![junk("depth"), UciMessage::Unknown(String::from("information"), None)]);
+    }
+
+    #[test]
+    fn test_info_with_junk_keeps_attributes() {
+        let m = UciMessage::Info(vec![
+            UciInfoAttribute::Depth(5),
+            UciInfoAttribute::Nodes(10),
+            UciInfoAttribute::Any("foo".to_owned(), String::new()),
+        ]);
+
+        assert_eq!(parse_strict("info depth 5 nodes 10 foo\n").unwrap(), vec![m.clone()]);
+        assert_eq!(parse_one("info depth 5 nodes 10 foo"), m);
+        assert_eq!(parse("info depth 5 nodes 10 foo\nreadyok\n"), vec![m.clone(), UciMessage::ReadyOk]);
+        assert_eq!(parse_with_unknown("info depth 5 nodes 10 foo"), vec![m]);
+    }
+
+    #[test]
+    fn test_long_pv() {
+        let moves = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let line: Vec<&str> = (0..2000).map(|i| moves[i % 4]).collect();
+        let line = line.join(" ");
+
+        let info = format!("info depth 245 seldepth 250 multipv 1 score mate 120 nodes 123456789012345 pv {}", line);
+        let m = parse_one(&info);
+        assert_eq!(m.pv().unwrap().len(), 2000);
+        assert_eq!(m.nodes(), Some(123456789012345));
+        assert_eq!(m.serialize(), info);
+        assert_eq!(parse(&format!("{}\n{}\n", info, info)).len(), 2);
+
+        let currline = format!("info currline 12 {}", line);
+        match parse_strict(&currline).unwrap().remove(0) {
+            UciMessage::Info(attributes) => match &attributes[0] {
+                UciInfoAttribute::CurrLine { cpu_nr, line } => {
+                    assert_eq!(*cpu_nr, Some(12));
+                    assert_eq!(line.len(), 2000);
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+
+        let position = format!("position startpos moves {}", line);
+        assert_eq!(parse_one(&position).serialize(), position);
+    }
+
+    #[test]
+    fn test_numbers_out_of_range() {
+        let m = parse_one("info depth 1000 seldepth 300 multipv 70000 score mate 200 nodes 99999999999999999999999");
+        assert_eq!(m, UciMessage::Info(vec![
+            UciInfoAttribute::Depth(u8::MAX),
+            UciInfoAttribute::SelDepth(u8::MAX),
+            UciInfoAttribute::MultiPv(u16::MAX),
+            UciInfoAttribute::from_mate(i8::MAX),
+            UciInfoAttribute::Nodes(u64::MAX),
+        ]));
+
+        assert_eq!(parse_one("info score cp -9999999999"), UciMessage::Info(vec![UciInfoAttribute::from_centipawns(i32::MIN)]));
+
+        match parse_one("go wtime -99999999999999999999999 movestogo 1000") {
+            UciMessage::Go { time_control: Some(tc), .. } => {
+                assert_eq!(tc.white_time(), Some(Duration::milliseconds(-i64::MAX)));
+                assert_eq!(tc.moves_to_go(), Some(u8::MAX));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_numbers_end_at_token() {
+        assert_eq!(parse_one("info depth 5 nodes 12.3M"), UciMessage::Info(vec![
+            UciInfoAttribute::Depth(5),
+            UciInfoAttribute::Any(String::from("nodes"), String::from("12.3M")),
+        ]));
+        assert!(parse_strict("go nodes 10k").is_err());
+    }
+
+    #[test]
+    fn test_numeric_suffixes() {
+        let options = ParseOptions::new().numeric_suffixes(true);
+        assert!(options.get_numeric_suffixes());
+
+        let ml = parse_strict_with_options("info depth 5 nodes 12.3M nps 1500k pv e2e4\nINFO NPS 1.5g\ninfo nodes 12.75\n\
+            info nodes 99999999999G\ninfo string nodes 5k\n", &options).unwrap();
+        assert_eq!(ml[0].nodes(), Some(12_300_000));
+        assert_eq!(ml[0].nps(), Some(1_500_000));
+        assert_eq!(ml[1].nps(), Some(1_500_000_000));
+        assert_eq!(ml[2].nodes(), Some(12));
+        assert_eq!(ml[3].nodes(), Some(u64::MAX));
+        assert_eq!(ml[4], UciMessage::info_string(String::from("nodes 5k")));
+
+        assert_eq!(parse_one_with_options("info nodes .5k", &options),
+                   UciMessage::Info(vec![UciInfoAttribute::Any(String::from("nodes"), String::from(".5k"))]));
+        assert_eq!(parse_scaled_count("1500"), None);
+        assert_eq!(parse_scaled_count("2.5k"), Some(2500));
+    }
 }
@@ -0,0 +1,267 @@
+//! The `pgn` module turns the games of a recorded `Session` into [PGN](https://en.wikipedia.org/wiki/Portable_Game_Notation),
+//! with the engine's evaluation of each move it searched in a `[%eval ...]` comment, as understood by most analysis
+//! tools. It is only available with the `chess` feature enabled.
+//!
+//! The moves of a game are taken from the `position` messages sent to the engine, so the moves of the opponent are
+//! included as well, even though the engine only searched its own. The evaluation of a move is the score of the last
+//! `info` message of the search that found it, converted to White's point of view: pawns with two decimals, as in
+//! `[%eval -0.35]`, or a mate, as in `[%eval #4]`.
+
+use chess::{Board, BoardStatus, ChessMove, Color};
+
+use crate::board::{move_to_san, start_board, PositionError};
+use crate::score::Score;
+use crate::session::{GameSegment, SearchEpisode, Session};
+use crate::uci::{UciFen, UciInfoAttribute, UciMessage};
+
+/// The tags every PGN game starts with, in the order they are written, with their values when unknown.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The maximal length of a line of movetext.
+const LINE_LENGTH: usize = 80;
+
+/// Converts a game to PGN. The `tags` (such as `("White", "Stockfish 16")`) override the values of the seven tag
+/// roster, which otherwise default to `?`, and any other tags are written after the roster. Unless given, the
+/// `Result` is derived from the final position: `1-0` or `0-1` for a checkmate, `1/2-1/2` for a stalemate and `*`
+/// otherwise. A game that does not start from the standard starting position gets the `SetUp` and `FEN` tags.
+///
+/// The moves are those of the longest `position` the engine searched, followed by the move it found. Fails if a
+/// position is invalid or a move is illegal.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::pgn::game_to_pgn;
+/// use vampirc_uci::session::Session;
+///
+/// let messages = parse("ucinewgame\nposition startpos\ngo depth 20\ninfo depth 20 score cp 31 pv e2e4 e7e5\n\
+///     bestmove e2e4\nposition startpos moves e2e4 c7c5\ngo depth 20\ninfo depth 20 score mate -3 pv g1f3\n\
+///     bestmove g1f3\n");
+/// let session = Session::from_messages(&messages);
+///
+/// let pgn = game_to_pgn(&session.games[0], &[("White", "Vampirc")]).unwrap();
+/// assert!(pgn.starts_with("[Event \"?\"]\n"));
+/// assert!(pgn.contains("[White \"Vampirc\"]\n"));
+/// assert!(pgn.ends_with("\n\n1. e4 {[%eval 0.31]} 1... c5 2. Nf3 {[%eval #-3]} *\n"));
+/// ```
+pub fn game_to_pgn(game: &GameSegment, tags: &[(&str, &str)]) -> Result<String, PositionError> {
+    let (startpos, fen, moves) = game_moves(game);
+    let start = start_board(startpos, fen.as_ref())?;
+    let mut fullmove = fen.as_ref().map_or(1, |f| f.fullmove_number());
+
+    let mut board = start;
+    let mut tokens: Vec<String> = Vec::new();
+    let mut interrupted = true;
+    for (ply, m) in moves.iter().enumerate() {
+        let san = move_to_san(&board, *m).map_err(|_| PositionError::IllegalMove { index: ply, chess_move: *m })?;
+
+        match board.side_to_move() {
+            Color::White => tokens.push(format!("{}.", fullmove)),
+            Color::Black if interrupted => tokens.push(format!("{}...", fullmove)),
+            Color::Black => {}
+        }
+        tokens.push(san);
+
+        interrupted = false;
+        if let Some(score) = move_score(game, startpos, fen.as_ref(), &moves[..ply], *m) {
+            tokens.push(format!("{{[%eval {}]}}", eval(score, board.side_to_move())));
+            interrupted = true;
+        }
+
+        if board.side_to_move() == Color::Black {
+            fullmove += 1;
+        }
+        board = board.make_move_new(*m);
+    }
+
+    let result = match board.status() {
+        BoardStatus::Checkmate if board.side_to_move() == Color::White => "0-1",
+        BoardStatus::Checkmate => "1-0",
+        BoardStatus::Stalemate => "1/2-1/2",
+        BoardStatus::Ongoing => "*",
+    };
+    let tag_value = |name: &str| tags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+    let result = tag_value("Result").unwrap_or(result);
+    tokens.push(result.to_string());
+
+    let mut pgn = String::new();
+    for (name, default) in SEVEN_TAG_ROSTER.iter() {
+        let value = if *name == "Result" { result } else { tag_value(name).unwrap_or(default) };
+        push_tag(&mut pgn, name, value);
+    }
+    if start != Board::default() {
+        push_tag(&mut pgn, "SetUp", "1");
+        push_tag(&mut pgn, "FEN", fen.map(|f| f.with_counters().0).unwrap_or_default().as_str());
+    }
+    for (name, value) in tags {
+        if !SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name) && *name != "SetUp" && *name != "FEN" {
+            push_tag(&mut pgn, name, value);
+        }
+    }
+    pgn.push('\n');
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+
+    Ok(pgn)
+}
+
+/// Converts the games of a session to PGN, separated by blank lines. Games without any moves are skipped. The `tags`
+/// apply to all of the games, see `game_to_pgn(..)`.
+pub fn session_to_pgn(session: &Session, tags: &[(&str, &str)]) -> Result<String, PositionError> {
+    let games: Vec<String> = session
+        .games
+        .iter()
+        .filter(|g| !game_moves(g).2.is_empty())
+        .map(|g| game_to_pgn(g, tags))
+        .collect::<Result<_, _>>()?;
+
+    Ok(games.join("\n"))
+}
+
+/// Returns the start of the game and its moves: those of the searched position with the most moves, followed by the
+/// move the engine found in it.
+fn game_moves(game: &GameSegment) -> (bool, Option<UciFen>, Vec<ChessMove>) {
+    let longest = game
+        .searches
+        .iter()
+        .filter_map(|s| match &s.position {
+            Some(UciMessage::Position { startpos, fen, moves }) => Some((s, *startpos, fen, moves)),
+            _ => None,
+        })
+        .max_by_key(|(s, _, _, moves)| moves.len() + usize::from(best_move(s).is_some()));
+
+    match longest {
+        Some((search, startpos, fen, moves)) => {
+            let mut moves = moves.clone();
+            moves.extend(best_move(search));
+            (startpos, fen.clone(), moves)
+        }
+        None => (true, None, Vec::new()),
+    }
+}
+
+/// Returns the score of the search that found the move `m` after the moves `before`, if the engine searched it.
+fn move_score(game: &GameSegment, startpos: bool, fen: Option<&UciFen>, before: &[ChessMove], m: ChessMove) -> Option<Score> {
+    game.searches.iter().rev().find_map(|s| match &s.position {
+        Some(UciMessage::Position { startpos: s_startpos, fen: s_fen, moves })
+            if *s_startpos == startpos && s_fen.as_ref() == fen && moves.as_slice() == before
+                && best_move(s) == Some(m) => final_score(s),
+        _ => None,
+    })
+}
+
+/// Returns the move of the search's `bestmove`.
+fn best_move(search: &SearchEpisode) -> Option<ChessMove> {
+    match &search.best_move {
+        Some(UciMessage::BestMove { best_move, .. }) => Some(*best_move),
+        _ => None,
+    }
+}
+
+/// Returns the score of the last `info` message of the main line (`multipv 1`) that has one.
+fn final_score(search: &SearchEpisode) -> Option<Score> {
+    search.infos.iter().rev().find_map(|m| match m {
+        UciMessage::Info(attributes) if !attributes.iter().any(|a| matches!(a, UciInfoAttribute::MultiPv(n) if *n != 1)) => {
+            Score::from_attributes(attributes).map(|(score, _)| score)
+        }
+        _ => None,
+    })
+}
+
+/// Formats the `score` of the side to move as the value of an `[%eval ...]` comment, from White's point of view.
+fn eval(score: Score, side_to_move: Color) -> String {
+    let sign = if side_to_move == Color::White { 1 } else { -1 };
+    match score {
+        Score::Mate(m) => format!("#{}", sign * i32::from(m)),
+        Score::Centipawns(c) => format!("{:.2}", f64::from(sign * c) / 100.0),
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_game_to_pgn() {
+        // The engine plays Black; White's moves arrive with the positions
+        let messages = parse("ucinewgame\nposition startpos moves e2e4\ngo wtime 1000 btime 1000\n\
+            info depth 12 score cp -20 pv e7e5 g1f3\ninfo depth 13 multipv 2 score cp -80 pv c7c5\n\
+            bestmove e7e5 ponder g1f3\nposition startpos moves e2e4 e7e5 f1c4\ngo wtime 900 btime 900\n\
+            bestmove b8c6\nposition startpos moves e2e4 e7e5 f1c4 b8c6 d1h5\ngo wtime 800 btime 800\n\
+            info depth 8 score cp 150\ninfo depth 9 score mate -1 pv g8f6 h5f7\nbestmove g8f6\n\
+            position startpos moves e2e4 e7e5 f1c4 b8c6 d1h5 g8f6 h5f7\n");
+        let session = Session::from_messages(&messages);
+
+        let pgn = game_to_pgn(&session.games[0], &[("Black", "Vampirc \"dev\""), ("Event", "Test"), ("TimeControl", "1+0")])
+            .unwrap();
+        assert_eq!(pgn, "[Event \"Test\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"?\"]\n\
+                         [Black \"Vampirc \\\"dev\\\"\"]\n[Result \"*\"]\n[TimeControl \"1+0\"]\n\n\
+                         1. e4 e5 {[%eval 0.20]} 2. Bc4 Nc6 3. Qh5 Nf6 {[%eval #1]} *\n");
+
+        // The unsearched final position is not part of a search, so the checkmate is not included
+        assert!(game_to_pgn(&session.games[0], &[("Result", "1-0")]).unwrap().ends_with(" 1-0\n"));
+    }
+
+    #[test]
+    fn test_game_to_pgn_from_fen() {
+        let messages = parse("position fen 7k/8/6K1/8/8/8/8/R7 w - - 0 60\ngo depth 5\ninfo depth 5 score mate 1 pv a1a8\n\
+                              bestmove a1a8\n");
+        let session = Session::from_messages(&messages);
+
+        let pgn = game_to_pgn(&session.games[0], &[]).unwrap();
+        assert!(pgn.contains("[Result \"1-0\"]\n[SetUp \"1\"]\n[FEN \"7k/8/6K1/8/8/8/8/R7 w - - 0 60\"]\n\n"));
+        assert!(pgn.ends_with("\n60. Ra8# {[%eval #1]} 1-0\n"));
+
+        let messages = parse("position fen 7k/8/6K1/8/8/8/8/R7 b - - 0 60\ngo depth 5\nbestmove h8g8\n");
+        let pgn = game_to_pgn(&Session::from_messages(&messages).games[0], &[]).unwrap();
+        assert!(pgn.ends_with("\n60... Kg8 *\n"));
+
+        let messages = parse("position startpos moves e2e5\ngo depth 5\nbestmove e7e5\n");
+        assert!(game_to_pgn(&Session::from_messages(&messages).games[0], &[]).is_err());
+    }
+
+    #[test]
+    fn test_session_to_pgn() {
+        let mut transcript = String::from("uci\nuciok\nucinewgame\nisready\nreadyok\nucinewgame\n");
+        let mut position = String::from("position startpos moves");
+        for m in ["g1f3", "g8f6", "f3g1", "f6g8"].iter().cycle().take(40) {
+            transcript.push_str(&format!("{}\ngo depth 1\ninfo score cp 0\nbestmove {}\n", position.trim_end_matches(" moves"), m));
+            position.push(' ');
+            position.push_str(m);
+        }
+        let session = Session::from_messages(&parse(&transcript));
+
+        let pgn = session_to_pgn(&session, &[]).unwrap();
+        assert_eq!(pgn.matches("[Event ").count(), 1);
+        assert!(pgn.lines().all(|l| l.len() <= LINE_LENGTH));
+        assert!(pgn.contains("\n1. Nf3 {[%eval 0.00]} 1... Nf6 {[%eval 0.00]} 2. Ng1"));
+        assert!(pgn.ends_with(" 20... Ng8\n{[%eval 0.00]} *\n"));
+    }
+}
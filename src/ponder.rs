@@ -0,0 +1,295 @@
+//! The `ponder` module contains the `PonderController`, which manages the pondering state machine on the GUI side:
+//! it starts pondering on the engine's expected reply, turns the opponent's move into a `ponderhit` or into a `stop`
+//! followed by a fresh search, and tells the engine's real `bestmove` apart from the one ending an abandoned ponder
+//! search.
+//!
+//! Like the `Watchdog`, the `PonderController` does not perform any I/O itself: it returns the messages to send to
+//! the engine, and the caller feeds it the messages received from the engine.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::uci::{UciMessage, UciTimeControl};
+
+/// The state of the `PonderController`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PonderState {
+    /// No search is in progress.
+    Idle,
+
+    /// The engine is pondering on the expected reply of the opponent.
+    Pondering,
+
+    /// The engine is searching for the move to play (after a `ponderhit` or a regular `go`).
+    Searching,
+
+    /// The ponder search was stopped because the opponent did not play the expected move; its `bestmove` is awaited
+    /// and will be discarded.
+    Stopping,
+}
+
+/// An error returned when a `PonderController` method is called in the wrong state.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PonderError {
+    /// A search was requested while another one was in progress.
+    NotIdle(PonderState),
+
+    /// The opponent's move was reported while the engine was not pondering.
+    NotPondering(PonderState),
+}
+
+impl Display for PonderError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            PonderError::NotIdle(state) => write!(f, "cannot start a search in the {:?} state", state),
+            PonderError::NotPondering(state) => write!(f, "the engine is not pondering, but in the {:?} state", state),
+        }
+    }
+}
+
+impl StdError for PonderError {}
+
+/// The result of a step of the `PonderController`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PonderStep {
+    /// The messages to send to the engine, in order.
+    pub send: Vec<UciMessage>,
+
+    /// The engine's `bestmove` for the move to play, once it is known.
+    pub best_move: Option<UciMessage>,
+}
+
+/// Manages the pondering state machine on the GUI side.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse_one, UciMessage};
+/// use vampirc_uci::ponder::{PonderController, PonderState};
+///
+/// let mut ponder = PonderController::new();
+///
+/// // The engine played e2e4 and expects e7e5, so ponder on it
+/// let step = ponder.ponder(parse_one("position startpos moves e2e4 e7e5")).unwrap();
+/// assert_eq!(step.send[1], UciMessage::go_ponder());
+///
+/// // The opponent played d7d5 instead: the ponder search is stopped and a new one started
+/// let step = ponder.opponent_moved(parse_one("position startpos moves e2e4 d7d5"), UciMessage::go()).unwrap();
+/// assert_eq!(step.send, vec![UciMessage::Stop]);
+///
+/// // The bestmove of the ponder search is discarded and the new search is started
+/// let step = ponder.on_engine_message(&parse_one("bestmove g1f3"));
+/// assert_eq!(step.best_move, None);
+/// assert_eq!(step.send.len(), 2);
+///
+/// let step = ponder.on_engine_message(&parse_one("bestmove e4d5"));
+/// assert_eq!(step.best_move, Some(parse_one("bestmove e4d5")));
+/// assert_eq!(ponder.state(), PonderState::Idle);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PonderController {
+    state: PonderState,
+    pondered_position: Option<UciMessage>,
+    early_best_move: Option<UciMessage>,
+    queued: Vec<UciMessage>,
+}
+
+impl Default for PonderController {
+    fn default() -> Self {
+        PonderController {
+            state: PonderState::Idle,
+            pondered_position: None,
+            early_best_move: None,
+            queued: Vec::new(),
+        }
+    }
+}
+
+impl PonderController {
+    /// Creates a `PonderController` in the `Idle` state.
+    pub fn new() -> PonderController {
+        PonderController::default()
+    }
+
+    /// The current state.
+    pub fn state(&self) -> PonderState {
+        self.state
+    }
+
+    /// Returns `true` while the `info` messages received from the engine belong to a search that will be discarded.
+    pub fn is_stale(&self) -> bool {
+        self.state == PonderState::Stopping
+    }
+
+    /// Starts a regular search with the `position` and `go` messages.
+    pub fn search(&mut self, position: UciMessage, go: UciMessage) -> Result<PonderStep, PonderError> {
+        self.check_idle()?;
+        self.state = PonderState::Searching;
+
+        Ok(PonderStep {
+            send: vec![position, go],
+            best_move: None,
+        })
+    }
+
+    /// Starts pondering. The `position` must end with the engine's expected reply of the opponent, that is, the
+    /// `ponder` move of the engine's last `bestmove`.
+    pub fn ponder(&mut self, position: UciMessage) -> Result<PonderStep, PonderError> {
+        self.check_idle()?;
+        self.state = PonderState::Pondering;
+        self.pondered_position = Some(position.clone());
+
+        Ok(PonderStep {
+            send: vec![position, UciMessage::go_ponder()],
+            best_move: None,
+        })
+    }
+
+    /// Reports the opponent's move, as the `position` after it. If it is the position the engine is pondering on,
+    /// `ponderhit` is sent; otherwise the ponder search is stopped, and once its `bestmove` arrives, a new search is
+    /// started with the `position` and `go` messages.
+    pub fn opponent_moved(&mut self, position: UciMessage, go: UciMessage) -> Result<PonderStep, PonderError> {
+        if self.state != PonderState::Pondering {
+            return Err(PonderError::NotPondering(self.state));
+        }
+
+        if self.pondered_position.take().as_ref() == Some(&position) {
+            self.state = PonderState::Searching;
+
+            // The engine may have finished the search while pondering and sent its bestmove too early
+            return Ok(PonderStep {
+                send: vec![UciMessage::PonderHit],
+                best_move: self.finish_early(),
+            });
+        }
+
+        self.state = PonderState::Stopping;
+        self.early_best_move = None;
+        self.queued = vec![position, go];
+
+        Ok(PonderStep {
+            send: vec![UciMessage::Stop],
+            best_move: None,
+        })
+    }
+
+    /// Abandons the search in progress, if any (for example, because the game is over). Its `bestmove` will be
+    /// discarded.
+    pub fn cancel(&mut self) -> PonderStep {
+        match self.state {
+            PonderState::Pondering | PonderState::Searching => {
+                self.state = PonderState::Stopping;
+                self.pondered_position = None;
+                self.early_best_move = None;
+                self.queued.clear();
+
+                PonderStep {
+                    send: vec![UciMessage::Stop],
+                    best_move: None,
+                }
+            }
+            PonderState::Idle | PonderState::Stopping => {
+                self.queued.clear();
+                PonderStep::default()
+            }
+        }
+    }
+
+    /// Feeds a message received from the engine. Only `bestmove` messages affect the state.
+    pub fn on_engine_message(&mut self, message: &UciMessage) -> PonderStep {
+        if let UciMessage::BestMove { .. } = message {
+            match self.state {
+                PonderState::Searching => {
+                    self.state = PonderState::Idle;
+                    return PonderStep {
+                        send: Vec::new(),
+                        best_move: Some(message.clone()),
+                    };
+                }
+                PonderState::Stopping => {
+                    let send = std::mem::take(&mut self.queued);
+                    self.state = if send.is_empty() { PonderState::Idle } else { PonderState::Searching };
+                    return PonderStep {
+                        send,
+                        best_move: None,
+                    };
+                }
+                PonderState::Pondering => self.early_best_move = Some(message.clone()),
+                PonderState::Idle => {}
+            }
+        }
+
+        PonderStep::default()
+    }
+
+    fn finish_early(&mut self) -> Option<UciMessage> {
+        let best_move = self.early_best_move.take();
+        if best_move.is_some() {
+            self.state = PonderState::Idle;
+        }
+
+        best_move
+    }
+
+    fn check_idle(&self) -> Result<(), PonderError> {
+        if self.state == PonderState::Idle {
+            Ok(())
+        } else {
+            Err(PonderError::NotIdle(self.state))
+        }
+    }
+}
+
+/// Returns `true` if the `go` message starts a ponder search.
+pub fn is_go_ponder(message: &UciMessage) -> bool {
+    matches!(message, UciMessage::Go { time_control: Some(UciTimeControl::Ponder), .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+
+    use super::*;
+
+    #[test]
+    fn test_ponder_hit() {
+        let mut p = PonderController::new();
+        let step = p.ponder(parse_one("position startpos moves e2e4 e7e5")).unwrap();
+        assert!(is_go_ponder(&step.send[1]));
+        assert_eq!(p.ponder(parse_one("position startpos")), Err(PonderError::NotIdle(PonderState::Pondering)));
+
+        assert_eq!(p.on_engine_message(&parse_one("info depth 5 pv g1f3")), PonderStep::default());
+        let step = p.opponent_moved(parse_one("position startpos moves e2e4 e7e5"), UciMessage::go()).unwrap();
+        assert_eq!(step, PonderStep { send: vec![UciMessage::PonderHit], best_move: None });
+        assert_eq!(p.state(), PonderState::Searching);
+
+        let step = p.on_engine_message(&parse_one("bestmove g1f3 ponder b8c6"));
+        assert_eq!(step.best_move, Some(parse_one("bestmove g1f3 ponder b8c6")));
+        assert_eq!(p.state(), PonderState::Idle);
+    }
+
+    #[test]
+    fn test_early_best_move() {
+        let mut p = PonderController::new();
+        p.ponder(parse_one("position startpos moves e2e4 e7e5")).unwrap();
+        p.on_engine_message(&parse_one("bestmove d1h5"));
+
+        let step = p.opponent_moved(parse_one("position startpos moves e2e4 e7e5"), UciMessage::go()).unwrap();
+        assert_eq!(step.send, vec![UciMessage::PonderHit]);
+        assert_eq!(step.best_move, Some(parse_one("bestmove d1h5")));
+        assert_eq!(p.state(), PonderState::Idle);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut p = PonderController::new();
+        assert_eq!(p.opponent_moved(parse_one("position startpos"), UciMessage::go()), Err(PonderError::NotPondering(PonderState::Idle)));
+
+        p.search(parse_one("position startpos"), UciMessage::go()).unwrap();
+        assert_eq!(p.cancel().send, vec![UciMessage::Stop]);
+        assert!(p.is_stale());
+
+        assert_eq!(p.on_engine_message(&parse_one("bestmove e2e4")), PonderStep::default());
+        assert_eq!(p.state(), PonderState::Idle);
+    }
+}
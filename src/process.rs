@@ -0,0 +1,313 @@
+//! The `process` module contains the `EngineProcess`, a blocking client for talking to a UCI engine running as a
+//! child process, built on `std::process::Command`. For an asynchronous client, see the `async_engine` module
+//! (available with the `tokio` feature). An `EngineProcess` can also talk to an engine over in-memory streams, such as
+//! a `MockEngine` from the `testing` module.
+
+use std::ffi::OsStr;
+use std::io::{copy, sink, BufRead, BufReader, Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::parser::parse_one;
+use crate::uci::{Serializable, UciMessage};
+
+/// A UCI engine running as a child process. Messages sent to the engine are flushed immediately, and messages
+/// received from it are read and parsed a line at a time.
+///
+/// If the `EngineProcess` is dropped while the engine is still running, the engine is killed. To give the engine a
+/// chance to exit cleanly first, call `shutdown(..)`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::{UciMessage, UciSearchControl};
+/// use vampirc_uci::process::EngineProcess;
+///
+/// let mut engine = EngineProcess::spawn("stockfish", &[] as &[&str]).unwrap();
+/// engine.handshake().unwrap();
+/// engine.send(&UciMessage::Position { startpos: true, fen: None, moves: vec![] }).unwrap();
+/// engine.send(&UciMessage::Go { time_control: None, search_control: Some(UciSearchControl::depth(10)) }).unwrap();
+///
+/// let messages = engine.recv_until(|m| matches!(m, UciMessage::BestMove { .. })).unwrap();
+/// println!("{}", messages.last().unwrap());
+/// engine.quit().unwrap();
+/// ```
+pub struct EngineProcess {
+    child: Option<KillOnDrop>,
+    stdin: Box<dyn Write + Send>,
+    stdout: BufReader<Box<dyn Read + Send>>,
+    searching: bool,
+}
+
+/// Kills the child process on drop if it is still running.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+impl EngineProcess {
+    /// Spawns the engine `program` with the specified `args`.
+    pub fn spawn<P: AsRef<OsStr>, A: AsRef<OsStr>>(program: P, args: &[A]) -> IoResult<EngineProcess> {
+        let mut command = Command::new(program);
+        command.args(args);
+        EngineProcess::spawn_command(command)
+    }
+
+    /// Spawns the engine with the prepared `command`. The command's standard input and output are replaced with
+    /// pipes.
+    pub fn spawn_command(mut command: Command) -> IoResult<EngineProcess> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| IoError::other("the engine's stdin is not piped"))?;
+        let stdout = child.stdout.take().ok_or_else(|| IoError::other("the engine's stdout is not piped"))?;
+
+        Ok(EngineProcess {
+            child: Some(KillOnDrop(child)),
+            stdin: Box::new(stdin),
+            stdout: BufReader::new(Box::new(stdout)),
+            searching: false,
+        })
+    }
+
+    /// Creates an `EngineProcess` that talks to an engine over the given streams instead of a child process: the
+    /// messages are written to `input` and read from `output`. The engine is considered to have exited once it closes
+    /// its `output`.
+    pub fn from_streams<W, R>(input: W, output: R) -> EngineProcess
+        where W: Write + Send + 'static, R: Read + Send + 'static {
+        EngineProcess {
+            child: None,
+            stdin: Box::new(input),
+            stdout: BufReader::new(Box::new(output)),
+            searching: false,
+        }
+    }
+
+    /// Sends the `message` to the engine and flushes its input.
+    pub fn send(&mut self, message: &UciMessage) -> IoResult<()> {
+        writeln!(self.stdin, "{}", message.serialize())?;
+        self.stdin.flush()?;
+
+        if let UciMessage::Go { .. } = message {
+            self.searching = true;
+        }
+
+        Ok(())
+    }
+
+    /// Blocks until the engine sends a message and returns it. Blank lines are skipped. If the engine closes its
+    /// output, an error of the `UnexpectedEof` kind is returned.
+    pub fn recv(&mut self) -> IoResult<UciMessage> {
+        let message = read_message(&mut self.stdout)?;
+        if message.ends_search() {
+            self.searching = false;
+        }
+
+        Ok(message)
+    }
+
+    /// Receives messages until one satisfies the `predicate`. All of the received messages are returned, the
+    /// matching one being the last.
+    pub fn recv_until<F: FnMut(&UciMessage) -> bool>(&mut self, mut predicate: F) -> IoResult<Vec<UciMessage>> {
+        let mut messages = Vec::new();
+        loop {
+            let m = self.recv()?;
+            let done = predicate(&m);
+            messages.push(m);
+
+            if done {
+                return Ok(messages);
+            }
+        }
+    }
+
+    /// Sends `uci` and waits for `uciok`, returning the messages the engine sent in the meantime (`id`, `option`
+    /// ...), including the `uciok`.
+    pub fn handshake(&mut self) -> IoResult<Vec<UciMessage>> {
+        self.send(&UciMessage::Uci)?;
+        self.recv_until(|m| *m == UciMessage::UciOk)
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    pub fn is_ready(&mut self) -> IoResult<()> {
+        self.send(&UciMessage::IsReady)?;
+        self.recv_until(|m| *m == UciMessage::ReadyOk)?;
+
+        Ok(())
+    }
+
+    /// Returns the underlying child process, or `None` if the engine was created with `from_streams(..)`.
+    pub fn child(&mut self) -> Option<&mut Child> {
+        self.child.as_mut().map(|c| &mut c.0)
+    }
+
+    /// Returns `true` if a `go` was sent and its `bestmove` has not been received yet.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Sends `quit` and waits for the engine process to exit. An engine created with `from_streams(..)` has exited
+    /// once it closes its output, and its exit status is always successful.
+    pub fn quit(mut self) -> IoResult<ExitStatus> {
+        self.send(&UciMessage::Quit)?;
+        match self.child {
+            Some(ref mut child) => child.0.wait(),
+            None => {
+                drop(self.stdin);
+                copy(&mut self.stdout, &mut sink())?;
+                Ok(ExitStatus::default())
+            }
+        }
+    }
+
+    /// Shuts the engine down gracefully. If a search is in progress, `stop` is sent and its `bestmove` awaited for at
+    /// most the `timeout`. Then `quit` is sent and the process is given another `timeout` to exit, after which it is
+    /// killed. Errors writing to an engine that already exited are ignored.
+    ///
+    /// Killing an engine outright may leave its caches (such as tablebase files) in an inconsistent state, so this
+    /// is preferable to simply dropping the `EngineProcess`.
+    pub fn shutdown(self, timeout: Duration) -> IoResult<ExitStatus> {
+        let EngineProcess { child, mut stdin, stdout, searching } = self;
+        let mut stdout = Some(stdout);
+
+        if searching && writeln!(stdin, "{}", UciMessage::Stop.serialize()).and_then(|_| stdin.flush()).is_ok() {
+            // Read on another thread, so the wait can be bounded; the thread ends once the engine's output is closed
+            let mut output = stdout.take().unwrap();
+            let (sender, receiver) = channel();
+            thread::spawn(move || {
+                while let Ok(message) = read_message(&mut output) {
+                    if message.ends_search() {
+                        break;
+                    }
+                }
+                let _ = sender.send(output);
+            });
+            stdout = receiver.recv_timeout(timeout).ok();
+        }
+
+        let _ = writeln!(stdin, "{}", UciMessage::Quit.serialize()).and_then(|_| stdin.flush());
+        drop(stdin);
+
+        let mut child = match child {
+            Some(child) => child,
+            None => {
+                // Without a process to wait for, the engine has exited once it closes its output
+                if let Some(mut output) = stdout {
+                    let (sender, receiver) = channel();
+                    thread::spawn(move || {
+                        let _ = copy(&mut output, &mut sink());
+                        let _ = sender.send(());
+                    });
+                    let _ = receiver.recv_timeout(timeout);
+                }
+                return Ok(ExitStatus::default());
+            }
+        };
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = child.0.try_wait()? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        child.0.kill()?;
+        child.0.wait()
+    }
+}
+
+fn read_message<R: BufRead>(stdout: &mut R) -> IoResult<UciMessage> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stdout.read_line(&mut line)? == 0 {
+            return Err(IoError::new(ErrorKind::UnexpectedEof, "the engine closed its output"));
+        }
+
+        if !line.trim().is_empty() {
+            return Ok(parse_one(&line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+    use crate::testing::{MockEngine, MockScript};
+    use crate::uci::{UciMessageKind, UciOptionConfig};
+
+    use super::*;
+
+    #[test]
+    fn test_engine_process() {
+        let mut engine = MockEngine::new("Fake", "Tester")
+            .option(UciOptionConfig::Spin { name: String::from("Hash"), default: Some(16), min: Some(1), max: Some(64) })
+            .on(UciMessageKind::IsReady, MockScript::new().send_line("").send(UciMessage::ReadyOk))
+            .on(UciMessageKind::Go, MockScript::new().send_all(["info depth 1 pv e2e4", "bestmove e2e4"].iter().map(|m| parse_one(m))))
+            .spawn();
+
+        let handshake = engine.handshake().unwrap();
+        assert_eq!(handshake.len(), 4);
+        assert_eq!(handshake[0], UciMessage::id_name("Fake"));
+        engine.is_ready().unwrap();
+
+        engine.send(&UciMessage::go()).unwrap();
+        assert!(matches!(engine.recv().unwrap(), UciMessage::Info(_)));
+        assert!(matches!(engine.recv().unwrap(), UciMessage::BestMove { .. }));
+
+        assert!(engine.quit().unwrap().success());
+    }
+
+    #[test]
+    fn test_engine_process_closed() {
+        let mut engine = MockEngine::new("Fake", "Tester")
+            .on(UciMessageKind::Uci, MockScript::new().send(UciMessage::id_name("Quitter")).exit())
+            .spawn();
+        let err = engine.handshake().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_unparsable_best_move() {
+        let mut engine = MockEngine::new("Fake", "Tester")
+            .on(UciMessageKind::Go, MockScript::new().send_line("bestmove e9e4"))
+            .spawn();
+
+        engine.send(&UciMessage::go()).unwrap();
+        assert!(engine.is_searching());
+        assert!(engine.recv().unwrap().is_unknown());
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        // Answers stop with a bestmove, then exits on quit
+        let mock = MockEngine::new("Fake", "Tester")
+            .on(UciMessageKind::Stop, MockScript::new().send(UciMessage::best_move("e2e4".parse().unwrap())));
+        let log = mock.log();
+        let mut engine = mock.spawn();
+        engine.send(&UciMessage::go_infinite()).unwrap();
+        assert!(engine.is_searching());
+        assert!(engine.shutdown(Duration::from_secs(5)).unwrap().success());
+        assert_eq!(log.messages(), vec![UciMessage::go_infinite(), UciMessage::Stop, UciMessage::Quit]);
+
+        // Never answers stop, so the wait for its bestmove is cut short
+        let mut engine = MockEngine::new("Fake", "Tester").spawn();
+        engine.send(&UciMessage::go_infinite()).unwrap();
+        let started = Instant::now();
+        assert!(engine.shutdown(Duration::from_millis(100)).unwrap().success());
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(100) && elapsed < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,283 @@
+//! The `protocol` module contains the `UciProtocolState`, a state machine that follows a conversation between a GUI
+//! and an engine and flags the messages that violate the order mandated by the UCI protocol.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::uci::{CommunicationDirection, UciMessage, UciTimeControl};
+
+/// A violation of the message order mandated by the UCI protocol.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum ProtocolViolation {
+    /// The GUI sent `setoption`, `ucinewgame`, `position` or `go` before the engine replied with `uciok`.
+    NotInitialized,
+
+    /// The GUI sent `go` while a search was in progress.
+    GoDuringSearch,
+
+    /// The GUI sent `go` without having sent a `position` first.
+    GoWithoutPosition,
+
+    /// The GUI sent `setoption` while a search was in progress.
+    SetOptionDuringSearch,
+
+    /// The GUI sent `position` while a search was in progress.
+    PositionDuringSearch,
+
+    /// The GUI sent `ucinewgame` while a search was in progress.
+    NewGameDuringSearch,
+
+    /// The GUI sent `ponderhit`, but the engine was not pondering.
+    PonderHitWithoutPonder,
+
+    /// A message was sent after `quit`.
+    MessageAfterQuit,
+
+    /// The engine sent `id` or `option` outside of the handshake (after `uci` and before `uciok`).
+    OutsideHandshake,
+
+    /// The engine sent `uciok` without having received `uci`.
+    UnsolicitedUciOk,
+
+    /// The engine sent `readyok` without having received `isready`.
+    UnsolicitedReadyOk,
+
+    /// The engine sent `bestmove` without a search in progress.
+    BestMoveWithoutGo,
+}
+
+impl Display for ProtocolViolation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let description = match self {
+            ProtocolViolation::NotInitialized => "message sent before the engine replied with `uciok`",
+            ProtocolViolation::GoDuringSearch => "`go` sent while a search was in progress",
+            ProtocolViolation::GoWithoutPosition => "`go` sent without a `position`",
+            ProtocolViolation::SetOptionDuringSearch => "`setoption` sent while a search was in progress",
+            ProtocolViolation::PositionDuringSearch => "`position` sent while a search was in progress",
+            ProtocolViolation::NewGameDuringSearch => "`ucinewgame` sent while a search was in progress",
+            ProtocolViolation::PonderHitWithoutPonder => "`ponderhit` sent while the engine was not pondering",
+            ProtocolViolation::MessageAfterQuit => "message sent after `quit`",
+            ProtocolViolation::OutsideHandshake => "`id` or `option` sent outside of the `uci` handshake",
+            ProtocolViolation::UnsolicitedUciOk => "`uciok` sent without a preceding `uci`",
+            ProtocolViolation::UnsolicitedReadyOk => "`readyok` sent without a preceding `isready`",
+            ProtocolViolation::BestMoveWithoutGo => "`bestmove` sent without a search in progress",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl StdError for ProtocolViolation {}
+
+/// Follows a conversation between a GUI and an engine, message by message, in both directions, and flags the
+/// messages that violate the order mandated by the UCI protocol. The direction of each message is determined by
+/// `UciMessage::direction()`; unrecognized messages are ignored.
+///
+/// A message that violates the protocol still updates the state, as if it had been accepted, so that a single
+/// violation does not cascade into many.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::protocol::{ProtocolViolation, UciProtocolState};
+///
+/// let messages = parse("uci\nuciok\nposition startpos\ngo infinite\nsetoption name Hash value 32\nstop\nbestmove e2e4\n");
+/// let violations = UciProtocolState::check_all(&messages);
+///
+/// assert_eq!(violations, vec![(4, ProtocolViolation::SetOptionDuringSearch)]);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct UciProtocolState {
+    uci_sent: bool,
+    initialized: bool,
+    pending_ready: u32,
+    has_position: bool,
+    searching: bool,
+    pondering: bool,
+    quit: bool,
+}
+
+impl UciProtocolState {
+    /// Creates the state at the start of a conversation.
+    pub fn new() -> UciProtocolState {
+        UciProtocolState::default()
+    }
+
+    /// Consumes the next message of the conversation.
+    pub fn consume(&mut self, message: &UciMessage) -> Result<(), ProtocolViolation> {
+        if message.is_unknown() {
+            return Ok(());
+        }
+
+        if self.quit {
+            return Err(ProtocolViolation::MessageAfterQuit);
+        }
+
+        match message.direction() {
+            CommunicationDirection::GuiToEngine => self.consume_gui_message(message),
+            CommunicationDirection::EngineToGui => self.consume_engine_message(message),
+        }
+    }
+
+    /// Consumes all of the `messages` and returns the violations, along with the indices of the offending messages.
+    pub fn check_all<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> Vec<(usize, ProtocolViolation)> {
+        let mut state = UciProtocolState::new();
+        messages
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, m)| state.consume(m).err().map(|v| (i, v)))
+            .collect()
+    }
+
+    /// Returns `true` once the engine has replied to `uci` with `uciok`.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Returns `true` while a search is in progress, that is, between `go` and `bestmove`.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Returns `true` while the engine is pondering, that is, between `go ponder` and `ponderhit` or `bestmove`.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering
+    }
+
+    /// Returns `true` once `quit` has been sent.
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn consume_gui_message(&mut self, message: &UciMessage) -> Result<(), ProtocolViolation> {
+        let mut result = Ok(());
+        let mut violation = |v| {
+            if result.is_ok() {
+                result = Err(v);
+            }
+        };
+
+        match message {
+            UciMessage::SetOption { .. } | UciMessage::UciNewGame | UciMessage::Position { .. } | UciMessage::Go { .. }
+            if !self.initialized => violation(ProtocolViolation::NotInitialized),
+            _ => {}
+        }
+
+        match message {
+            UciMessage::Uci => {
+                // A repeated `uci` starts the handshake over
+                self.uci_sent = true;
+                self.initialized = false;
+            }
+            UciMessage::IsReady => self.pending_ready += 1,
+            UciMessage::SetOption { .. } if self.searching => violation(ProtocolViolation::SetOptionDuringSearch),
+            UciMessage::UciNewGame => {
+                if self.searching {
+                    violation(ProtocolViolation::NewGameDuringSearch);
+                }
+                self.has_position = false;
+            }
+            UciMessage::Position { .. } => {
+                if self.searching {
+                    violation(ProtocolViolation::PositionDuringSearch);
+                }
+                self.has_position = true;
+            }
+            UciMessage::Go { time_control, .. } => {
+                if self.searching {
+                    violation(ProtocolViolation::GoDuringSearch);
+                } else if !self.has_position {
+                    violation(ProtocolViolation::GoWithoutPosition);
+                }
+                self.searching = true;
+                self.pondering = *time_control == Some(UciTimeControl::Ponder);
+            }
+            UciMessage::PonderHit => {
+                if !self.pondering {
+                    violation(ProtocolViolation::PonderHitWithoutPonder);
+                }
+                self.pondering = false;
+            }
+            UciMessage::Quit => self.quit = true,
+            _ => {}
+        }
+
+        result
+    }
+
+    fn consume_engine_message(&mut self, message: &UciMessage) -> Result<(), ProtocolViolation> {
+        match message {
+            UciMessage::Id { .. } | UciMessage::Option(..) if !self.uci_sent || self.initialized => {
+                Err(ProtocolViolation::OutsideHandshake)
+            }
+            UciMessage::UciOk => {
+                let result = if self.uci_sent && !self.initialized { Ok(()) } else { Err(ProtocolViolation::UnsolicitedUciOk) };
+                self.initialized = true;
+                result
+            }
+            UciMessage::ReadyOk => {
+                if self.pending_ready == 0 {
+                    return Err(ProtocolViolation::UnsolicitedReadyOk);
+                }
+                self.pending_ready -= 1;
+                Ok(())
+            }
+            UciMessage::BestMove { .. } => {
+                let result = if self.searching { Ok(()) } else { Err(ProtocolViolation::BestMoveWithoutGo) };
+                self.searching = false;
+                self.pondering = false;
+                result
+            }
+            _ => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_valid_conversation() {
+        let messages = parse("uci\nid name Fake\noption name Hash type spin default 16 min 1 max 64\nuciok\n\
+                              setoption name Hash value 32\nisready\nreadyok\nucinewgame\nposition startpos moves e2e4\n\
+                              go ponder\ninfo depth 1\nponderhit\nisready\nreadyok\nbestmove e7e5\nquit\n");
+        let mut state = UciProtocolState::new();
+        for m in &messages {
+            assert_eq!(state.consume(m), Ok(()), "{}", m);
+        }
+        assert!(state.is_initialized());
+        assert!(!state.is_searching());
+        assert!(state.is_quit());
+    }
+
+    #[test]
+    fn test_gui_violations() {
+        let messages = parse("go infinite\nbestmove a2a3\nuci\nuciok\nucinewgame\ngo depth 5\ngo depth 5\nposition startpos\n\
+                              ucinewgame\nponderhit\nbestmove e2e4\nquit\nisready\n");
+        assert_eq!(UciProtocolState::check_all(&messages), vec![
+            (0, ProtocolViolation::NotInitialized),
+            (5, ProtocolViolation::GoWithoutPosition),
+            (6, ProtocolViolation::GoDuringSearch),
+            (7, ProtocolViolation::PositionDuringSearch),
+            (8, ProtocolViolation::NewGameDuringSearch),
+            (9, ProtocolViolation::PonderHitWithoutPonder),
+            (12, ProtocolViolation::MessageAfterQuit),
+        ]);
+    }
+
+    #[test]
+    fn test_engine_violations() {
+        let messages = parse("id name Early\nuciok\nuci\nuciok\noption name Late type button\nreadyok\nbestmove e2e4\n");
+        assert_eq!(UciProtocolState::check_all(&messages), vec![
+            (0, ProtocolViolation::OutsideHandshake),
+            (1, ProtocolViolation::UnsolicitedUciOk),
+            (4, ProtocolViolation::OutsideHandshake),
+            (5, ProtocolViolation::UnsolicitedReadyOk),
+            (6, ProtocolViolation::BestMoveWithoutGo),
+        ]);
+    }
+}
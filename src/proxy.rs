@@ -0,0 +1,352 @@
+//! The `proxy` module contains the `UciProxy`, which sits between a GUI and an engine, parses the messages flowing
+//! in both directions and passes them through a chain of user-supplied `Middleware` before forwarding them. A
+//! middleware can observe, modify, drop or inject messages, which makes the proxy a base for adapters, loggers and
+//! adjudicators.
+
+use std::io::{BufRead, BufReader, Result as IoResult, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+use crate::parser::parse_one;
+use crate::uci::{CommunicationDirection, UciMessage, UciOptionConfig};
+
+/// The context a `Middleware` is invoked with: the direction of the message being processed and the means of
+/// injecting additional messages.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ProxyContext {
+    direction: CommunicationDirection,
+    to_engine: Vec<UciMessage>,
+    to_gui: Vec<UciMessage>,
+}
+
+impl ProxyContext {
+    fn new(direction: CommunicationDirection) -> ProxyContext {
+        ProxyContext {
+            direction,
+            to_engine: Vec::new(),
+            to_gui: Vec::new(),
+        }
+    }
+
+    /// The direction of the message being processed: `GuiToEngine` for messages sent by the GUI and `EngineToGui`
+    /// for messages sent by the engine.
+    pub fn direction(&self) -> CommunicationDirection {
+        self.direction
+    }
+
+    /// Injects a message to send to the engine after the message being processed. Injected messages do not pass
+    /// through the middleware.
+    pub fn send_to_engine(&mut self, message: UciMessage) {
+        self.to_engine.push(message);
+    }
+
+    /// Injects a message to send to the GUI after the message being processed. Injected messages do not pass through
+    /// the middleware.
+    pub fn send_to_gui(&mut self, message: UciMessage) {
+        self.to_gui.push(message);
+    }
+}
+
+/// A step in the `UciProxy`'s processing chain. It receives each message and returns the message to pass on to the
+/// next step (possibly modified), or `None` to drop it.
+///
+/// Closures of the form `FnMut(UciMessage, &mut ProxyContext) -> Option<UciMessage>` are middleware, too.
+pub trait Middleware {
+    /// Processes the `message`.
+    fn process(&mut self, message: UciMessage, context: &mut ProxyContext) -> Option<UciMessage>;
+}
+
+impl<F: FnMut(UciMessage, &mut ProxyContext) -> Option<UciMessage>> Middleware for F {
+    fn process(&mut self, message: UciMessage, context: &mut ProxyContext) -> Option<UciMessage> {
+        self(message, context)
+    }
+}
+
+/// A middleware that replaces the engine's name in `id name` with another one.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RenameEngine(pub String);
+
+impl Middleware for RenameEngine {
+    fn process(&mut self, message: UciMessage, _context: &mut ProxyContext) -> Option<UciMessage> {
+        match message {
+            UciMessage::Id { name: Some(_), author } => Some(UciMessage::Id {
+                name: Some(self.0.clone()),
+                author,
+            }),
+            m => Some(m)
+        }
+    }
+}
+
+/// A middleware that caps the `MultiPV` option: values above the cap set by the GUI are lowered to it, and the
+/// maximum the engine declares is lowered, too.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CapMultiPv(pub i64);
+
+impl Middleware for CapMultiPv {
+    fn process(&mut self, message: UciMessage, _context: &mut ProxyContext) -> Option<UciMessage> {
+        match message {
+            UciMessage::SetOption { name, value: Some(value) } if name.eq_ignore_ascii_case("MultiPV") => {
+                let value = match value.trim().parse::<i64>() {
+                    Ok(v) if v > self.0 => self.0.to_string(),
+                    _ => value,
+                };
+                Some(UciMessage::SetOption { name, value: Some(value) })
+            }
+            UciMessage::Option(UciOptionConfig::Spin { name, default, min, max }) if name.eq_ignore_ascii_case("MultiPV") => {
+                Some(UciMessage::Option(UciOptionConfig::Spin {
+                    name,
+                    default: default.map(|d| d.min(self.0)),
+                    min,
+                    max: Some(max.map_or(self.0, |m| m.min(self.0))),
+                }))
+            }
+            m => Some(m)
+        }
+    }
+}
+
+/// The messages to forward after processing a single message.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Forward {
+    /// The messages to send to the engine.
+    pub to_engine: Vec<UciMessage>,
+
+    /// The messages to send to the GUI.
+    pub to_gui: Vec<UciMessage>,
+}
+
+/// A proxy between a GUI and an engine that passes the messages through a chain of `Middleware`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{self, BufReader};
+/// use std::process::Command;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::proxy::{ProxyContext, RenameEngine, UciProxy};
+///
+/// let mut proxy = UciProxy::new()
+///     .with(RenameEngine(String::from("My Adapter")))
+///     .with(|m: UciMessage, ctx: &mut ProxyContext| {
+///         eprintln!("{:?}: {}", ctx.direction(), m);
+///         Some(m)
+///     });
+///
+/// proxy.run_process(Command::new("stockfish"), BufReader::new(io::stdin()), io::stdout()).unwrap();
+/// ```
+#[derive(Default)]
+pub struct UciProxy {
+    middleware: Vec<Box<dyn Middleware>>,
+}
+
+impl UciProxy {
+    /// Creates a proxy that forwards all messages unchanged.
+    pub fn new() -> UciProxy {
+        UciProxy::default()
+    }
+
+    /// Appends the `middleware` to the processing chain.
+    pub fn with<M: Middleware + 'static>(mut self, middleware: M) -> UciProxy {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Passes the `message`, flowing in the specified `direction`, through the middleware and returns the messages
+    /// to forward.
+    pub fn process(&mut self, message: UciMessage, direction: CommunicationDirection) -> Forward {
+        let mut context = ProxyContext::new(direction);
+        let mut current = Some(message);
+
+        for m in self.middleware.iter_mut() {
+            current = match current {
+                Some(msg) => m.process(msg, &mut context),
+                None => break,
+            };
+        }
+
+        let mut forward = Forward::default();
+        if let Some(msg) = current {
+            match direction {
+                CommunicationDirection::GuiToEngine => forward.to_engine.push(msg),
+                CommunicationDirection::EngineToGui => forward.to_gui.push(msg),
+            }
+        }
+        forward.to_engine.append(&mut context.to_engine);
+        forward.to_gui.append(&mut context.to_gui);
+
+        forward
+    }
+
+    /// Runs the proxy between the GUI (`gui_input` and `gui_output`) and the engine (`engine_input`, the engine's
+    /// standard input, and `engine_output`, its standard output), until the engine closes its output. Once the GUI
+    /// closes its output, the engine's input is closed, too.
+    ///
+    /// A message that passes through the middleware unchanged is forwarded as the line it was received in, so that
+    /// nothing the parser does not model is lost. Modified and injected messages are serialized canonically.
+    ///
+    /// The inputs are read on background threads, while the middleware is run on the calling thread.
+    pub fn run<GR, GW, EW, ER>(&mut self, gui_input: GR, mut gui_output: GW, engine_input: EW, engine_output: ER) -> IoResult<()>
+        where GR: BufRead + Send + 'static, GW: Write, EW: Write, ER: BufRead + Send + 'static {
+        let (sender, receiver) = channel();
+        let gui_sender = sender.clone();
+        thread::spawn(move || forward_lines(gui_input, CommunicationDirection::GuiToEngine, gui_sender));
+        thread::spawn(move || forward_lines(engine_output, CommunicationDirection::EngineToGui, sender));
+
+        let mut engine_input = Some(engine_input);
+        while let Ok((direction, line)) = receiver.recv() {
+            let line = match (direction, line) {
+                (_, Some(l)) => l,
+                (CommunicationDirection::GuiToEngine, None) => {
+                    engine_input = None;
+                    continue;
+                }
+                (CommunicationDirection::EngineToGui, None) => break,
+            };
+
+            let received = parse_one(&line);
+            let forward = self.process(received.clone(), direction);
+            if let Some(ei) = &mut engine_input {
+                write_messages(ei, &forward.to_engine, &received, &line)?;
+            }
+            write_messages(&mut gui_output, &forward.to_gui, &received, &line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Spawns the engine with the `command` and runs the proxy between it and the GUI, until the engine closes its
+    /// output.
+    pub fn run_process<GR, GW>(&mut self, mut command: Command, gui_input: GR, gui_output: GW) -> IoResult<()>
+        where GR: BufRead + Send + 'static, GW: Write {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| std::io::Error::other("the engine's stdin is not piped"))?;
+        let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("the engine's stdout is not piped"))?;
+
+        let result = self.run(gui_input, gui_output, stdin, BufReader::new(stdout));
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+        }
+        child.wait()?;
+
+        result
+    }
+}
+
+/// Reads the lines from the `input` and sends them on, followed by `None` once the input is exhausted.
+fn forward_lines<R: BufRead>(input: R, direction: CommunicationDirection, sender: Sender<(CommunicationDirection, Option<String>)>) {
+    for line in input.lines() {
+        match line {
+            Ok(l) if l.trim().is_empty() => continue,
+            Ok(l) => {
+                if sender.send((direction, Some(l))).is_err() {
+                    return;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    let _ = sender.send((direction, None));
+}
+
+/// Writes the `messages` to the `output`. Those equal to the `received` message are written as the `line` it was
+/// parsed from.
+fn write_messages<W: Write>(output: &mut W, messages: &[UciMessage], received: &UciMessage, line: &str) -> IoResult<()> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    for m in messages {
+        if m == received {
+            writeln!(output, "{}", line)?;
+        } else {
+            writeln!(output, "{}", m.serialize_canonical())?;
+        }
+    }
+
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::testing::MockEngine;
+    use crate::uci::Serializable;
+
+    use super::*;
+
+    #[test]
+    fn test_process_chain() {
+        let mut proxy = UciProxy::new()
+            .with(RenameEngine(String::from("Adapter")))
+            .with(CapMultiPv(4))
+            .with(|m: UciMessage, ctx: &mut ProxyContext| match m {
+                // Answer `isready` on behalf of the engine
+                UciMessage::IsReady => {
+                    ctx.send_to_gui(UciMessage::ReadyOk);
+                    None
+                }
+                m => Some(m)
+            });
+
+        let f = proxy.process(parse_one("id name Stockfish 16"), CommunicationDirection::EngineToGui);
+        assert_eq!(f, Forward { to_engine: vec![], to_gui: vec![UciMessage::id_name("Adapter")] });
+
+        let f = proxy.process(parse_one("setoption name multipv value 10"), CommunicationDirection::GuiToEngine);
+        assert_eq!(f.to_engine[0].serialize(), "setoption name multipv value 4");
+
+        let f = proxy.process(parse_one("option name MultiPV type spin default 1 min 1 max 500"), CommunicationDirection::EngineToGui);
+        assert_eq!(f.to_gui[0].serialize(), "option name MultiPV type spin default 1 min 1 max 4");
+
+        let f = proxy.process(UciMessage::IsReady, CommunicationDirection::GuiToEngine);
+        assert_eq!(f, Forward { to_engine: vec![], to_gui: vec![UciMessage::ReadyOk] });
+    }
+
+    #[test]
+    fn test_run() {
+        let gui_input = Cursor::new("uci\nisready\nfoo bar\n");
+        let engine_output = Cursor::new("id name Fake\nuciok\nreadyok\n");
+        let mut gui_output = Vec::new();
+        let mut engine_input = Vec::new();
+
+        UciProxy::new()
+            .with(RenameEngine(String::from("Adapter")))
+            .run(gui_input, &mut gui_output, &mut engine_input, engine_output)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(gui_output).unwrap(), "id name Adapter\nuciok\nreadyok\n");
+
+        // The engine's output may end before all of the GUI's messages have been forwarded
+        assert!("uci\nisready\nfoo bar\n".starts_with(&String::from_utf8(engine_input).unwrap()));
+    }
+
+    #[test]
+    fn test_run_forwards_lines_verbatim() {
+        let lines = "info depth 5 lowerbound\ninfo  depth 6 pv e2e4 vendorflag\nINFO depth 7\nbestmove e2e4 ponder\n";
+        let mut gui_output = Vec::new();
+
+        UciProxy::new()
+            .run(Cursor::new(""), &mut gui_output, Vec::new(), Cursor::new(lines))
+            .unwrap();
+
+        assert_eq!(String::from_utf8(gui_output).unwrap(), lines);
+    }
+
+    #[test]
+    fn test_run_mock_engine() {
+        let gui_input = Cursor::new("uci\nquit\n");
+        let mut gui_output = Vec::new();
+
+        let (input, output) = MockEngine::new("Fake", "Tester").spawn_pipes();
+
+        UciProxy::new()
+            .with(RenameEngine(String::from("Adapter")))
+            .run(gui_input, &mut gui_output, input, BufReader::new(output))
+            .unwrap();
+
+        assert_eq!(String::from_utf8(gui_output).unwrap(), "id name Adapter\nid author Tester\nuciok\n");
+    }
+}
@@ -0,0 +1,128 @@
+//! The `query` module contains the `MessageQuery` extension trait, which answers the common questions about a sequence
+//! of messages, such as a parsed transcript, without matching on each message by hand.
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+
+use crate::uci::{CommunicationDirection, UciInfoAttribute, UciMessage, UciOptionConfig};
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+
+/// Queries over a sequence of messages. It is implemented for everything that iterates over `&UciMessage`, so it can
+/// be used directly on a `MessageList`, a slice of messages or an iterator over them.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, MessageQuery};
+///
+/// let messages = parse("uci\nid name Vampirc\nuciok\ninfo depth 1 pv e2e4\nbestmove e2e4 ponder e7e5\n");
+///
+/// assert_eq!(messages.find_id(), (Some("Vampirc"), None));
+/// assert_eq!(messages.infos().len(), 1);
+/// assert_eq!(messages.first_best_move().unwrap().0.to_string(), "e2e4");
+/// ```
+pub trait MessageQuery<'a>: IntoIterator<Item=&'a UciMessage> + Sized {
+    /// Returns the best move and the ponder move (if any) of the first `bestmove` message.
+    #[cfg(not(feature = "chess"))]
+    fn first_best_move(self) -> Option<(&'a UciMove, Option<&'a UciMove>)> {
+        self.into_iter().find_map(|m| match m {
+            UciMessage::BestMove { best_move, ponder, .. } => Some((best_move, ponder.as_ref())),
+            _ => None
+        })
+    }
+
+    /// Returns the best move and the ponder move (if any) of the first `bestmove` message.
+    #[cfg(feature = "chess")]
+    fn first_best_move(self) -> Option<(&'a ChessMove, Option<&'a ChessMove>)> {
+        self.into_iter().find_map(|m| match m {
+            UciMessage::BestMove { best_move, ponder, .. } => Some((best_move, ponder.as_ref())),
+            _ => None
+        })
+    }
+
+    /// Returns the attributes of each `info` message, in order.
+    fn infos(self) -> Vec<&'a [UciInfoAttribute]> {
+        self.into_iter().filter_map(|m| match m {
+            UciMessage::Info(attributes) => Some(attributes.as_slice()),
+            _ => None
+        }).collect()
+    }
+
+    /// Returns the option definitions of the `option` messages, in order.
+    fn options(self) -> Vec<&'a UciOptionConfig> {
+        self.into_iter().filter_map(|m| match m {
+            UciMessage::Option(config) => Some(config),
+            _ => None
+        }).collect()
+    }
+
+    /// Returns the messages sent in the `direction`.
+    fn filter_direction(self, direction: CommunicationDirection) -> Vec<&'a UciMessage> {
+        self.into_iter().filter(|m| m.direction() == direction).collect()
+    }
+
+    /// Returns the engine's name and author, as found in the first `id name` and the first `id author` messages.
+    fn find_id(self) -> (Option<&'a str>, Option<&'a str>) {
+        let mut id = (None, None);
+
+        for m in self {
+            if let UciMessage::Id { name, author } = m {
+                id.0 = id.0.or(name.as_deref());
+                id.1 = id.1.or(author.as_deref());
+            }
+        }
+
+        id
+    }
+}
+
+impl<'a, I: IntoIterator<Item=&'a UciMessage>> MessageQuery<'a> for I {}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    const TRANSCRIPT: &str = "uci\nid name Vampirc\nid author Matija Kejžar\noption name Hash type spin default 16 min 1 max 1024\n\
+        option name Ponder type check default false\nuciok\nisready\nreadyok\ngo depth 2\ninfo depth 1 score cp 20 pv e2e4\n\
+        info depth 2 score cp 15 pv e2e4 e7e5\nbestmove e2e4 ponder e7e5\ngo depth 3\nbestmove d2d4\n";
+
+    #[test]
+    fn test_first_best_move() {
+        let messages = parse(TRANSCRIPT);
+        let (best_move, ponder) = messages.first_best_move().unwrap();
+
+        assert_eq!(best_move.to_string(), "e2e4");
+        assert_eq!(ponder.unwrap().to_string(), "e7e5");
+        assert_eq!(messages[..5].first_best_move(), None);
+    }
+
+    #[test]
+    fn test_infos_and_options() {
+        let messages = parse(TRANSCRIPT);
+
+        let infos = messages.infos();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[1][0], UciInfoAttribute::Depth(2));
+
+        let options: Vec<&str> = messages.options().iter().map(|o| o.get_name()).collect();
+        assert_eq!(options, vec!["Hash", "Ponder"]);
+    }
+
+    #[test]
+    fn test_filter_direction() {
+        let messages = parse(TRANSCRIPT);
+
+        let engine_bound = messages.iter().filter_direction(CommunicationDirection::GuiToEngine);
+        assert_eq!(engine_bound, vec![&UciMessage::Uci, &UciMessage::IsReady, &messages[8], &messages[12]]);
+        assert_eq!(messages.filter_direction(CommunicationDirection::EngineToGui).len(), messages.len() - 4);
+    }
+
+    #[test]
+    fn test_find_id() {
+        assert_eq!(parse(TRANSCRIPT).find_id(), (Some("Vampirc"), Some("Matija Kejžar")));
+        assert_eq!(parse("uci\nuciok\n").find_id(), (None, None));
+    }
+}
@@ -0,0 +1,95 @@
+//! The `reader` module contains functions that read UCI messages on a background thread and deliver them over an
+//! `std::sync::mpsc` channel. This lets a synchronous engine keep receiving messages (most importantly `stop`)
+//! while it is busy searching.
+
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use crate::parser::parse_one;
+use crate::uci::UciMessage;
+
+/// Starts a thread that reads the `input` line by line, parses each non-blank line and sends the message to the
+/// returned `Receiver`.
+///
+/// Once the input is exhausted (or fails to be read), the thread ends and the channel is closed, so the receiver's
+/// `recv()` returns an error after all of the messages have been received. If the receiver is dropped, the thread
+/// ends after the next line it reads.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::reader::spawn_reader;
+///
+/// let messages = spawn_reader(Cursor::new("isready\nstop\n"));
+///
+/// assert_eq!(messages.recv(), Ok(UciMessage::IsReady));
+/// assert_eq!(messages.recv(), Ok(UciMessage::Stop));
+/// assert!(messages.recv().is_err());
+/// ```
+pub fn spawn_reader<R: Read + Send + 'static>(input: R) -> Receiver<UciMessage> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || {
+        for line in BufReader::new(input).lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if sender.send(parse_one(&line)).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Starts a thread that reads the messages from the standard input. See `spawn_reader(..)`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::mpsc::TryRecvError;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::reader::spawn_stdin_reader;
+///
+/// let messages = spawn_stdin_reader();
+///
+/// // During the search, poll for `stop` between iterations
+/// loop {
+///     match messages.try_recv() {
+///         Ok(UciMessage::Stop) | Err(TryRecvError::Disconnected) => break,
+///         _ => { /* search some more */ }
+///     }
+/// }
+/// ```
+pub fn spawn_stdin_reader() -> Receiver<UciMessage> {
+    spawn_reader(io::stdin())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_spawn_reader() {
+        let receiver = spawn_reader(Cursor::new("uci\n\n  \nsetoption name Hash value 32\nfoo bar\ngo infinite"));
+        let messages: Vec<UciMessage> = receiver.iter().collect();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0], UciMessage::Uci);
+        assert_eq!(messages[1], UciMessage::SetOption { name: String::from("Hash"), value: Some(String::from("32")) });
+        assert!(messages[2].is_unknown());
+        assert_eq!(messages[3], UciMessage::go_infinite());
+    }
+}
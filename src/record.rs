@@ -0,0 +1,340 @@
+//! The `record` module contains the `Recorder`, which writes a transcript of the messages exchanged between a GUI and
+//! an engine, each with its direction and the time elapsed since the recording started, and the `load(..)` method,
+//! which reads such a transcript back.
+//!
+//! Two formats are supported. The plain text format has a line per message, with the elapsed time in seconds, `>`
+//! for the messages sent to the engine or `<` for the ones sent to the GUI, and the message:
+//!
+//! ```text
+//! 0.000 > uci
+//! 0.012 < id name Stockfish
+//! ```
+//!
+//! The JSONL format has a JSON object per line:
+//!
+//! ```text
+//! {"time_ms":0,"direction":"gui-to-engine","message":"uci"}
+//! {"time_ms":12,"direction":"engine-to-gui","message":"id name Stockfish"}
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Error as IoError, ErrorKind, Result as IoResult, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::parser::parse_one;
+use crate::proxy::{Middleware, ProxyContext};
+use crate::uci::{CommunicationDirection, UciMessage};
+
+/// The format of a recorded transcript.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum RecordFormat {
+    /// A line of plain text per message.
+    Text,
+
+    /// A JSON object per line.
+    Jsonl,
+}
+
+/// A message read back from a transcript.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RecordedMessage {
+    /// The time elapsed since the recording started.
+    pub elapsed: Duration,
+
+    /// The direction of the message.
+    pub direction: CommunicationDirection,
+
+    /// The message.
+    pub message: UciMessage,
+}
+
+/// Records the messages exchanged between a GUI and an engine. Each message is written on its own line and flushed
+/// immediately, so that the transcript survives a crash.
+///
+/// The `Recorder` is a `Middleware`, so it can be attached to a `UciProxy`; as a middleware, it passes all messages
+/// on unchanged and ignores write errors.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{CommunicationDirection, UciMessage};
+/// use vampirc_uci::record::{load, RecordFormat, Recorder};
+///
+/// let mut recorder = Recorder::new(Vec::new(), RecordFormat::Jsonl);
+/// recorder.record(CommunicationDirection::GuiToEngine, &UciMessage::Uci).unwrap();
+/// recorder.record(CommunicationDirection::EngineToGui, &UciMessage::UciOk).unwrap();
+///
+/// let transcript = recorder.into_inner();
+/// let messages = load(transcript.as_slice()).unwrap();
+/// assert_eq!(messages[1].message, UciMessage::UciOk);
+/// ```
+pub struct Recorder<W: Write> {
+    writer: W,
+    format: RecordFormat,
+    started: Instant,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Creates (or truncates) the file at `path` and records to it.
+    pub fn create<P: AsRef<Path>>(path: P, format: RecordFormat) -> IoResult<Recorder<BufWriter<File>>> {
+        Ok(Recorder::new(BufWriter::new(File::create(path)?), format))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Creates a `Recorder` writing to the `writer` in the specified `format`. The recording starts now.
+    pub fn new(writer: W, format: RecordFormat) -> Recorder<W> {
+        Recorder {
+            writer,
+            format,
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the `message`, flowing in the specified `direction`.
+    pub fn record(&mut self, direction: CommunicationDirection, message: &UciMessage) -> IoResult<()> {
+        self.record_line(direction, &message.serialize_canonical())
+    }
+
+    /// Records a raw line of text, flowing in the specified `direction`.
+    pub fn record_line(&mut self, direction: CommunicationDirection, line: &str) -> IoResult<()> {
+        self.record_at(self.started.elapsed(), direction, line)
+    }
+
+    /// Records a raw line of text, flowing in the specified `direction`, with the specified time `elapsed` since the
+    /// recording started.
+    pub fn record_at(&mut self, elapsed: Duration, direction: CommunicationDirection, line: &str) -> IoResult<()> {
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        match self.format {
+            RecordFormat::Text => {
+                let arrow = match direction {
+                    CommunicationDirection::GuiToEngine => '>',
+                    CommunicationDirection::EngineToGui => '<',
+                };
+                writeln!(self.writer, "{}.{:03} {} {}", elapsed.as_secs(), elapsed.subsec_millis(), arrow, line)?;
+            }
+            RecordFormat::Jsonl => {
+                writeln!(self.writer, "{{\"time_ms\":{},\"direction\":\"{}\",\"message\":\"{}\"}}", elapsed.as_millis(),
+                         direction_name(direction), escape_json(line))?;
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Middleware for Recorder<W> {
+    fn process(&mut self, message: UciMessage, context: &mut ProxyContext) -> Option<UciMessage> {
+        let _ = self.record(context.direction(), &message);
+        Some(message)
+    }
+}
+
+/// Reads a transcript written by the `Recorder`, in either format (the format is determined line by line). Blank lines
+/// are skipped. A malformed line results in an error of the `InvalidData` kind.
+pub fn load<R: BufRead>(input: R) -> IoResult<Vec<RecordedMessage>> {
+    let mut messages = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let parsed = if line.starts_with('{') { parse_jsonl_line(line) } else { parse_text_line(line) };
+        let (elapsed, direction, text) = parsed.ok_or_else(|| {
+            IoError::new(ErrorKind::InvalidData, format!("malformed record on line {}", index + 1))
+        })?;
+
+        messages.push(RecordedMessage {
+            elapsed,
+            direction,
+            message: parse_one(&text),
+        });
+    }
+
+    Ok(messages)
+}
+
+/// Reads a transcript from the file at `path`. See `load(..)`.
+pub fn load_file<P: AsRef<Path>>(path: P) -> IoResult<Vec<RecordedMessage>> {
+    load(std::io::BufReader::new(File::open(path)?))
+}
+
+pub(crate) fn direction_name(direction: CommunicationDirection) -> &'static str {
+    match direction {
+        CommunicationDirection::GuiToEngine => "gui-to-engine",
+        CommunicationDirection::EngineToGui => "engine-to-gui",
+    }
+}
+
+fn parse_text_line(line: &str) -> Option<(Duration, CommunicationDirection, String)> {
+    let (time, rest) = line.split_once(' ')?;
+    let (arrow, text) = rest.split_once(' ').unwrap_or((rest, ""));
+
+    let (secs, millis) = time.split_once('.')?;
+    let elapsed = Duration::from_secs(secs.parse().ok()?) + Duration::from_millis(millis.parse().ok()?);
+    let direction = match arrow {
+        ">" => CommunicationDirection::GuiToEngine,
+        "<" => CommunicationDirection::EngineToGui,
+        _ => return None,
+    };
+
+    Some((elapsed, direction, text.to_string()))
+}
+
+fn parse_jsonl_line(line: &str) -> Option<(Duration, CommunicationDirection, String)> {
+    let mut chars = line.trim().strip_prefix('{')?.strip_suffix('}')?.chars().peekable();
+    let (mut time, mut direction, mut message) = (None, None, None);
+
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let key = parse_json_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+
+        if chars.peek() == Some(&'"') {
+            let value = parse_json_string(&mut chars)?;
+            match key.as_str() {
+                "direction" => direction = Some(value),
+                "message" => message = Some(value),
+                _ => {}
+            }
+        } else {
+            let mut value = String::new();
+            while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                value.push(*c);
+                chars.next();
+            }
+            if key == "time_ms" {
+                time = Some(value.parse::<u64>().ok()?);
+            }
+        }
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => continue,
+            None => break,
+            _ => return None,
+        }
+    }
+
+    let direction = match direction?.as_str() {
+        "gui-to-engine" => CommunicationDirection::GuiToEngine,
+        "engine-to-gui" => CommunicationDirection::EngineToGui,
+        _ => return None,
+    };
+
+    Some((Duration::from_millis(time?), direction, message?))
+}
+
+fn skip_whitespace<I: Iterator<Item=char>>(chars: &mut std::iter::Peekable<I>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_json_string<I: Iterator<Item=char>>(chars: &mut std::iter::Peekable<I>) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                't' => s.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    s.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proxy::UciProxy;
+
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut recorder = Recorder::new(Vec::new(), RecordFormat::Text);
+        recorder.record_at(Duration::from_millis(1500), CommunicationDirection::GuiToEngine, "isready\n").unwrap();
+        recorder.record_at(Duration::from_millis(1512), CommunicationDirection::EngineToGui, "readyok").unwrap();
+
+        let transcript = recorder.into_inner();
+        assert_eq!(String::from_utf8(transcript.clone()).unwrap(), "1.500 > isready\n1.512 < readyok\n");
+
+        let messages = load(transcript.as_slice()).unwrap();
+        assert_eq!(messages[0], RecordedMessage {
+            elapsed: Duration::from_millis(1500),
+            direction: CommunicationDirection::GuiToEngine,
+            message: UciMessage::IsReady,
+        });
+        assert_eq!(messages[1].message, UciMessage::ReadyOk);
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let mut recorder = Recorder::new(Vec::new(), RecordFormat::Jsonl);
+        recorder.record_at(Duration::from_millis(7), CommunicationDirection::EngineToGui, "info string \"quoted\"\t\\").unwrap();
+
+        let transcript = recorder.into_inner();
+        assert_eq!(String::from_utf8(transcript.clone()).unwrap(),
+                   "{\"time_ms\":7,\"direction\":\"engine-to-gui\",\"message\":\"info string \\\"quoted\\\"\\t\\\\\"}\n");
+
+        let messages = load(transcript.as_slice()).unwrap();
+        assert_eq!(messages[0].elapsed, Duration::from_millis(7));
+        assert_eq!(messages[0].message, UciMessage::info_string(String::from("\"quoted\"\t\\")));
+    }
+
+    #[test]
+    fn test_load_errors_and_proxy() {
+        let err = load("0.000 > uci\n\nbogus\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "malformed record on line 3");
+
+        let mut proxy = UciProxy::new().with(Recorder::new(std::io::sink(), RecordFormat::Text));
+        assert_eq!(proxy.process(UciMessage::Uci, CommunicationDirection::GuiToEngine).to_engine, vec![UciMessage::Uci]);
+    }
+}
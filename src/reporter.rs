@@ -0,0 +1,163 @@
+//! The `reporter` module contains the `InfoReporter`, which lets an engine report its search progress as often as it
+//! likes, while only sending an `info` message to the GUI at a limited rate.
+
+use std::io::Result as IoResult;
+use std::time::{Duration, Instant};
+
+use crate::builder::InfoBuilder;
+use crate::engine::EngineOutput;
+use crate::uci::{UciInfoAttribute, UciMessage};
+
+/// Throttles the `info` messages sent by an engine during a search.
+///
+/// Each update is a complete snapshot of the search progress, built with the `InfoBuilder`. An update is sent
+/// immediately if it is significant compared to the last one sent (a different depth, score or principal variation,
+/// or a `string`) or if the interval has passed since the last one sent. Otherwise it is held back, replacing any
+/// update held back before it, until the next update or `flush()`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::engine::EngineOutput;
+/// use vampirc_uci::reporter::InfoReporter;
+///
+/// let mut reporter = InfoReporter::new(EngineOutput::new(std::io::sink()), Duration::from_millis(500));
+///
+/// for nodes in 1..=10000u64 {
+///     // Only the first update (and any change of depth, score or pv) is sent right away
+///     reporter.update(UciMessage::info_builder().depth(8).score_cp(20).nodes(nodes)).unwrap();
+/// }
+///
+/// // Send the latest progress before the `bestmove`
+/// reporter.flush().unwrap();
+/// ```
+pub struct InfoReporter {
+    output: EngineOutput,
+    interval: Duration,
+    last_sent: Option<(Instant, Vec<UciInfoAttribute>)>,
+    pending: Option<Vec<UciInfoAttribute>>,
+}
+
+impl InfoReporter {
+    /// Creates an `InfoReporter` that sends the `info` messages to the `output`, at most once per `interval` unless
+    /// they are significant.
+    pub fn new(output: EngineOutput, interval: Duration) -> InfoReporter {
+        InfoReporter {
+            output,
+            interval,
+            last_sent: None,
+            pending: None,
+        }
+    }
+
+    /// Reports the search progress. Returns `true` if an `info` message was sent.
+    pub fn update(&mut self, info: InfoBuilder) -> IoResult<bool> {
+        self.update_at(info, Instant::now())
+    }
+
+    /// Reports the search progress at the point in time `now`. Returns `true` if an `info` message was sent.
+    pub fn update_at(&mut self, info: InfoBuilder, now: Instant) -> IoResult<bool> {
+        let attributes = info.attributes();
+
+        let due = match &self.last_sent {
+            None => true,
+            Some((sent_at, last)) => now.duration_since(*sent_at) >= self.interval || is_significant(last, &attributes),
+        };
+
+        if due {
+            self.send(attributes, now)?;
+        } else {
+            self.pending = Some(attributes);
+        }
+
+        Ok(due)
+    }
+
+    /// Sends the update held back, if any. This should be called before sending `bestmove`, so that the GUI receives
+    /// the final search progress. Returns `true` if an `info` message was sent.
+    pub fn flush(&mut self) -> IoResult<bool> {
+        self.flush_at(Instant::now())
+    }
+
+    /// Sends the update held back, if any, at the point in time `now`. Returns `true` if an `info` message was sent.
+    pub fn flush_at(&mut self, now: Instant) -> IoResult<bool> {
+        match self.pending.take() {
+            Some(attributes) => {
+                self.send(attributes, now)?;
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Forgets the last `info` message sent, so that the first update of the next search is sent immediately. The
+    /// update held back, if any, is dropped.
+    pub fn reset(&mut self) {
+        self.last_sent = None;
+        self.pending = None;
+    }
+
+    /// Returns `true` if an update is being held back.
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn send(&mut self, attributes: Vec<UciInfoAttribute>, now: Instant) -> IoResult<()> {
+        self.output.send(&UciMessage::Info(attributes.clone()))?;
+        self.last_sent = Some((now, attributes));
+        self.pending = None;
+
+        Ok(())
+    }
+}
+
+fn is_significant(last: &[UciInfoAttribute], next: &[UciInfoAttribute]) -> bool {
+    let find = |attributes: &[UciInfoAttribute], name: &str| -> Option<UciInfoAttribute> {
+        attributes.iter().find(|a| a.get_name() == name).cloned()
+    };
+
+    next.iter().any(|a| matches!(a, UciInfoAttribute::String(..)))
+        || ["depth", "multipv", "score", "pv"].iter().any(|name| find(last, name) != find(next, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::SharedBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_throttling() {
+        let buffer = SharedBuffer::default();
+        let mut reporter = InfoReporter::new(EngineOutput::new(buffer.clone()), Duration::from_millis(100));
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let info = |depth: u8, nodes: u64| UciMessage::info_builder().depth(depth).score_cp(10).nodes(nodes);
+
+        assert!(reporter.update_at(info(5, 100), t0).unwrap());
+        assert!(!reporter.update_at(info(5, 200), t0 + ms(10)).unwrap());
+        assert!(!reporter.update_at(info(5, 300), t0 + ms(20)).unwrap());
+        assert!(reporter.has_pending());
+        assert!(reporter.update_at(info(6, 400), t0 + ms(30)).unwrap());
+        assert!(!reporter.has_pending());
+        assert!(!reporter.update_at(info(6, 500), t0 + ms(40)).unwrap());
+        assert!(reporter.update_at(info(6, 600), t0 + ms(130)).unwrap());
+        assert!(reporter.update_at(UciMessage::info_builder().string("hello"), t0 + ms(131)).unwrap());
+        assert!(reporter.update_at(info(6, 700), t0 + ms(132)).unwrap());
+        assert!(!reporter.update_at(info(6, 800), t0 + ms(140)).unwrap());
+        assert!(reporter.flush_at(t0 + ms(150)).unwrap());
+        assert!(!reporter.flush_at(t0 + ms(160)).unwrap());
+
+        let output = buffer.contents();
+        assert_eq!(output.lines().collect::<Vec<&str>>(), vec![
+            "info depth 5 score cp 10 nodes 100",
+            "info depth 6 score cp 10 nodes 400",
+            "info depth 6 score cp 10 nodes 600",
+            "info string hello",
+            "info depth 6 score cp 10 nodes 700",
+            "info depth 6 score cp 10 nodes 800",
+        ]);
+    }
+}
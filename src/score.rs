@@ -0,0 +1,293 @@
+//! The `score` module contains the `Score`, a typed view of the `info score` attribute.
+
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::uci::UciInfoAttribute;
+
+/// An engine's evaluation of a position, from the point of view of the side to move.
+///
+/// Scores are ordered from the worst to the best for the side to move: being mated (the sooner, the worse) is below
+/// every evaluation in centipawns, and mating (the sooner, the better) is above them. `Mate(0)`, the side to move
+/// being mated, is the worst score of all.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::score::Score;
+///
+/// let mut scores = vec![Score::Centipawns(30), Score::Mate(5), Score::Mate(-2), Score::Mate(1), Score::Centipawns(-900)];
+/// scores.sort_by(|a, b| b.cmp(a));
+/// assert_eq!(scores, vec![Score::Mate(1), Score::Mate(5), Score::Centipawns(30), Score::Centipawns(-900), Score::Mate(-2)]);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Score {
+    /// The evaluation in centipawns.
+    Centipawns(i32),
+
+    /// Mate in this many moves. A negative value means the side to move is getting mated.
+    Mate(i8),
+}
+
+/// Whether a `Score` is exact or only a bound of the real evaluation.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub enum ScoreBound {
+    /// The score is exact.
+    #[default]
+    Exact,
+
+    /// The real evaluation is at least the score (`lowerbound`, a fail-high).
+    Lower,
+
+    /// The real evaluation is at most the score (`upperbound`, a fail-low).
+    Upper,
+}
+
+impl Score {
+    /// Extracts the score and its bound from a `UciInfoAttribute::Score` attribute. If the attribute carries both
+    /// `cp` and `mate`, the mate takes precedence. Returns `None` for other attributes and for a score without a
+    /// value.
+    pub fn from_attribute(attribute: &UciInfoAttribute) -> Option<(Score, ScoreBound)> {
+        if let UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound } = attribute {
+            let score = match (mate, cp) {
+                (Some(m), _) => Score::Mate(*m),
+                (None, Some(c)) => Score::Centipawns(*c),
+                (None, None) => return None,
+            };
+
+            let bound = if lower_bound.unwrap_or(false) {
+                ScoreBound::Lower
+            } else if upper_bound.unwrap_or(false) {
+                ScoreBound::Upper
+            } else {
+                ScoreBound::Exact
+            };
+
+            return Some((score, bound));
+        }
+
+        None
+    }
+
+    /// Finds the score among the `attributes` of an `info` message.
+    pub fn from_attributes(attributes: &[UciInfoAttribute]) -> Option<(Score, ScoreBound)> {
+        attributes.iter().find_map(Score::from_attribute)
+    }
+
+    /// Converts the score, with the specified `bound`, to a `UciInfoAttribute::Score` attribute.
+    pub fn to_attribute(self, bound: ScoreBound) -> UciInfoAttribute {
+        let (cp, mate) = match self {
+            Score::Centipawns(c) => (Some(c), None),
+            Score::Mate(m) => (None, Some(m)),
+        };
+
+        UciInfoAttribute::Score {
+            cp,
+            mate,
+            lower_bound: if bound == ScoreBound::Lower { Some(true) } else { None },
+            upper_bound: if bound == ScoreBound::Upper { Some(true) } else { None },
+        }
+    }
+
+    /// Returns `true` for a mate score.
+    pub fn is_mate(&self) -> bool {
+        matches!(self, Score::Mate(..))
+    }
+
+    /// Returns the expected score of the side to move (the probability of a win, counting a draw as half a win),
+    /// between 0 and 1, according to the `model`. Mating scores are 1 and mated ones 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::score::{ExpectedScoreModel, Score};
+    ///
+    /// assert_eq!(Score::Centipawns(0).expected_score(ExpectedScoreModel::default()), 0.5);
+    /// assert!((Score::Centipawns(400).expected_score(ExpectedScoreModel::Elo { scale: 400.0 }) - 10.0 / 11.0).abs() < 1e-9);
+    /// assert_eq!(Score::Mate(-3).expected_score(ExpectedScoreModel::default()), 0.0);
+    /// ```
+    pub fn expected_score(self, model: ExpectedScoreModel) -> f64 {
+        match self {
+            Score::Mate(m) => if m > 0 { 1.0 } else { 0.0 },
+            Score::Centipawns(c) => model.expected_score(c),
+        }
+    }
+
+    /// Formats the score for display: mate scores as `#5` or `#-3`, and centipawns in pawns with a sign and two
+    /// decimals, as `+0.25` or `-1.50` (`0.00` when even).
+    pub fn to_short_string(self) -> String {
+        match self {
+            Score::Mate(m) => format!("#{}", m),
+            Score::Centipawns(0) => String::from("0.00"),
+            Score::Centipawns(c) => {
+                let sign = if c > 0 { '+' } else { '-' };
+                let c = (c as i64).abs();
+                format!("{}{}.{:02}", sign, c / 100, c % 100)
+            }
+        }
+    }
+
+    /// Limits a centipawn score to `-limit..=limit`. Mate scores are returned unchanged.
+    pub fn clamp_centipawns(self, limit: i32) -> Score {
+        match self {
+            Score::Centipawns(c) => Score::Centipawns(c.clamp(-limit.saturating_abs(), limit.saturating_abs())),
+            m => m,
+        }
+    }
+
+    /// Converts a centipawn score that encodes a mate into a mate score. Some engines report mates as centipawns,
+    /// as the value of a mate (`mate_value`) less the number of plies to it; a score within `max_ply` of
+    /// `mate_value` is taken to be such a mate. Stockfish, for one, uses 32000 and 246. Other scores are returned
+    /// unchanged.
+    pub fn normalize_mate(self, mate_value: i32, max_ply: i32) -> Score {
+        match self {
+            Score::Centipawns(c) if i64::from(c.unsigned_abs()) >= i64::from(mate_value) - i64::from(max_ply) => {
+                let plies = (i64::from(mate_value) - i64::from(c.unsigned_abs())).max(0);
+                let moves = if c > 0 { (plies + 1) / 2 } else { -(plies / 2) };
+                Score::Mate(moves.clamp(i8::MIN as i64, i8::MAX as i64) as i8)
+            }
+            s => s,
+        }
+    }
+}
+
+/// A model that converts an evaluation in centipawns to the expected score of the side to move.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ExpectedScoreModel {
+    /// The logistic curve Lichess uses for its winning chances, `1 / (1 + e^(-0.00368208 * cp))`.
+    Lichess,
+
+    /// The logistic curve of the Elo rating system, `1 / (1 + 10^(-cp / scale))`; a `scale` of 400 treats a pawn
+    /// as an advantage of 100 Elo points.
+    Elo {
+        /// The number of centipawns that multiply the odds by ten.
+        scale: f64,
+    },
+}
+
+impl Default for ExpectedScoreModel {
+    /// The `Lichess` model.
+    fn default() -> Self {
+        ExpectedScoreModel::Lichess
+    }
+}
+
+impl ExpectedScoreModel {
+    /// Returns the expected score of the side to move for an evaluation of `cp` centipawns.
+    pub fn expected_score(self, cp: i32) -> f64 {
+        let cp = cp as f64;
+        match self {
+            ExpectedScoreModel::Lichess => 1.0 / (1.0 + (-0.00368208 * cp).exp()),
+            ExpectedScoreModel::Elo { scale } => 1.0 / (1.0 + 10f64.powf(-cp / scale)),
+        }
+    }
+}
+
+impl Score {
+    /// The key of the ordering: the kind of the score (mated, centipawns, mating), then the value within the kind.
+    fn order_key(self) -> (u8, i32) {
+        match self {
+            Score::Mate(m) if m > 0 => (2, -(m as i32)),
+            Score::Mate(m) => (0, -(m as i32)),
+            Score::Centipawns(c) => (1, c),
+        }
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        self.order_key().cmp(&other.order_key())
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Score {
+    /// Formats the score as in the `info score` attribute, e.g. `cp 25` or `mate -3`.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Score::Centipawns(c) => write!(f, "cp {}", c),
+            Score::Mate(m) => write!(f, "mate {}", m),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_attribute() {
+        assert_eq!(Score::from_attribute(&UciInfoAttribute::from_centipawns(-40)), Some((Score::Centipawns(-40), ScoreBound::Exact)));
+        assert_eq!(Score::from_attribute(&UciInfoAttribute::Depth(3)), None);
+
+        let attr = UciInfoAttribute::Score { cp: Some(300), mate: Some(4), lower_bound: Some(true), upper_bound: None };
+        assert_eq!(Score::from_attribute(&attr), Some((Score::Mate(4), ScoreBound::Lower)));
+    }
+
+    #[test]
+    fn test_to_attribute() {
+        let attr = Score::Centipawns(15).to_attribute(ScoreBound::Upper);
+        assert_eq!(attr.to_string(), "score cp 15 upperbound");
+        assert_eq!(Score::from_attribute(&attr), Some((Score::Centipawns(15), ScoreBound::Upper)));
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Score::Mate(1) > Score::Mate(2));
+        assert!(Score::Mate(10) > Score::Centipawns(i32::MAX));
+        assert!(Score::Centipawns(15) > Score::Centipawns(-15));
+        assert!(Score::Centipawns(i32::MIN) > Score::Mate(-10));
+        assert!(Score::Mate(-10) > Score::Mate(-1));
+        assert!(Score::Mate(-1) > Score::Mate(0));
+        assert_eq!(Score::Mate(3).cmp(&Score::Mate(3)), Ordering::Equal);
+        assert_eq!([Score::Centipawns(5), Score::Mate(-3), Score::Mate(2)].iter().max(), Some(&Score::Mate(2)));
+    }
+
+    #[test]
+    fn test_expected_score() {
+        let lichess = ExpectedScoreModel::Lichess;
+        assert_eq!(Score::Centipawns(0).expected_score(lichess), 0.5);
+        assert!((Score::Centipawns(100).expected_score(lichess) - 0.591).abs() < 0.001);
+        assert!((Score::Centipawns(-100).expected_score(lichess) + Score::Centipawns(100).expected_score(lichess) - 1.0).abs() < 1e-9);
+        assert!(Score::Centipawns(300).expected_score(lichess) > Score::Centipawns(200).expected_score(lichess));
+        assert_eq!(Score::Mate(2).expected_score(lichess), 1.0);
+        assert_eq!(Score::Mate(0).expected_score(lichess), 0.0);
+
+        let elo = ExpectedScoreModel::Elo { scale: 400.0 };
+        assert!((Score::Centipawns(-400).expected_score(elo) - 1.0 / 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_short_string() {
+        assert_eq!(Score::Mate(5).to_short_string(), "#5");
+        assert_eq!(Score::Mate(-3).to_short_string(), "#-3");
+        assert_eq!(Score::Centipawns(25).to_short_string(), "+0.25");
+        assert_eq!(Score::Centipawns(-150).to_short_string(), "-1.50");
+        assert_eq!(Score::Centipawns(-5).to_short_string(), "-0.05");
+        assert_eq!(Score::Centipawns(0).to_short_string(), "0.00");
+        assert_eq!(Score::Centipawns(i32::MIN).to_short_string(), "-21474836.48");
+    }
+
+    #[test]
+    fn test_clamp_and_normalize() {
+        assert_eq!(Score::Centipawns(5000).clamp_centipawns(1000), Score::Centipawns(1000));
+        assert_eq!(Score::Centipawns(-5000).clamp_centipawns(1000), Score::Centipawns(-1000));
+        assert_eq!(Score::Mate(3).clamp_centipawns(1000), Score::Mate(3));
+
+        assert_eq!(Score::Centipawns(31999).normalize_mate(32000, 246), Score::Mate(1));
+        assert_eq!(Score::Centipawns(31995).normalize_mate(32000, 246), Score::Mate(3));
+        assert_eq!(Score::Centipawns(-31996).normalize_mate(32000, 246), Score::Mate(-2));
+        assert_eq!(Score::Centipawns(-32000).normalize_mate(32000, 246), Score::Mate(0));
+        assert_eq!(Score::Centipawns(31000).normalize_mate(32000, 246), Score::Centipawns(31000));
+        assert_eq!(Score::Centipawns(32000 - 246).normalize_mate(32000, 246), Score::Mate(123));
+
+        assert_eq!(Score::Centipawns(i32::MIN).clamp_centipawns(1000), Score::Centipawns(-1000));
+        assert_eq!(Score::Centipawns(i32::MIN).clamp_centipawns(i32::MIN), Score::Centipawns(-i32::MAX));
+        assert_eq!(Score::Centipawns(i32::MIN).normalize_mate(32000, 246), Score::Mate(0));
+    }
+}
@@ -0,0 +1,223 @@
+//! Helpers for the `serde` feature, used through the `#[serde(with = "..")]` attribute.
+//!
+//! Moves are represented by their UCI notation (as in, `"e2e4"` or `"a7a8q"`) and durations by their number of
+//! milliseconds, so that the serialized form is the same with or without the `chess` feature.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use chrono::Duration;
+use serde::de::{Error as DeError, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::options::OptionProfile;
+use crate::uci::UciFloat;
+
+/// (De)serializes a value through its `Display` and `FromStr` implementations.
+pub(crate) mod as_str {
+    use super::*;
+
+    pub(crate) fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub(crate) fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        T::from_str(&s).map_err(|_| D::Error::custom(format!("invalid value: {}", s)))
+    }
+}
+
+/// (De)serializes an optional value through its `Display` and `FromStr` implementations.
+#[cfg(feature = "chess")]
+pub(crate) mod option_as_str {
+    use super::*;
+
+    pub(crate) fn serialize<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(|v| v.to_string()).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => T::from_str(&s).map(Some).map_err(|_| D::Error::custom(format!("invalid value: {}", s))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// (De)serializes a list of values through their `Display` and `FromStr` implementations.
+#[cfg(feature = "chess")]
+pub(crate) mod vec_as_str {
+    use super::*;
+
+    pub(crate) fn serialize<T: Display, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|v| v.to_string()))
+    }
+
+    pub(crate) fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| T::from_str(s).map_err(|_| D::Error::custom(format!("invalid value: {}", s))))
+            .collect()
+    }
+}
+
+/// (De)serializes a `Duration` as a number of milliseconds.
+pub(crate) mod millis {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_milliseconds())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        i64::deserialize(deserializer).map(Duration::milliseconds)
+    }
+}
+
+/// (De)serializes an optional `Duration` as a number of milliseconds.
+pub(crate) mod option_millis {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        duration.map(|d| d.num_milliseconds()).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Option::<i64>::deserialize(deserializer).map(|ms| ms.map(Duration::milliseconds))
+    }
+}
+
+/// An `OptionProfile` is a map from the option names to the values, in order. When reading it, booleans and numbers
+/// are accepted in place of the strings.
+impl Serialize for OptionProfile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for OptionProfile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<OptionProfile, D::Error> {
+        deserializer.deserialize_map(OptionProfileVisitor)
+    }
+}
+
+struct OptionProfileVisitor;
+
+impl<'de> Visitor<'de> for OptionProfileVisitor {
+    type Value = OptionProfile;
+
+    fn expecting(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "a map of option names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OptionProfile, A::Error> {
+        let mut profile = OptionProfile::new();
+        while let Some((name, value)) = map.next_entry::<String, ProfileValue>()? {
+            let value = match value {
+                ProfileValue::Bool(b) => b.to_string(),
+                ProfileValue::Integer(i) => i.to_string(),
+                ProfileValue::Float(d) => UciFloat(d).to_string(),
+                ProfileValue::String(s) => s,
+            };
+            profile.set(&name, &value);
+        }
+
+        Ok(profile)
+    }
+}
+
+/// A value of an `OptionProfile`, as it may be written by hand.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProfileValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_one, parse_strict};
+    use crate::uci::{ByteVecUciMessage, UciMessage, UciOptionConfig};
+
+    fn round_trip(message: &UciMessage) -> UciMessage {
+        serde_json::from_str(&serde_json::to_string(message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = parse_strict("uci\n\
+            debug on\n\
+            register name Matija code 42\n\
+            position fen 8/8/8/8/8/8/4P3/4K2k w - - 0 1 moves e2e4 h1g2\n\
+            setoption name Hash value 128\n\
+            go wtime -500 btime 1000 winc 10 binc 10 movestogo 12 searchmoves e2e4 d2d4 depth 10\n\
+            go movetime 2500\n\
+            id name Vampirc 0.12\n\
+            bestmove a7a8q ponder h1g2\n\
+            copyprotection checking\n\
+            option name Style type combo default Normal var Solid var Normal var Risky\n\
+            info depth 12 seldepth 20 time 1523 nodes 2134223 score cp -31 lowerbound multipv 2 pv e2e4 e7e5 currmove g1f3 currline 1 e2e4 refutation d1h5 g6h5 string hello\n").unwrap();
+        assert_eq!(messages.len(), 12);
+
+        for message in &messages {
+            assert_eq!(&round_trip(message), message);
+        }
+    }
+
+    #[test]
+    fn test_representation() {
+        let json = serde_json::to_value(parse_one("bestmove e7e8q ponder d2d4")).unwrap();
+        assert_eq!(json, serde_json::json!({ "BestMove": { "best_move": "e7e8q", "ponder": "d2d4" } }));
+
+        let json = serde_json::to_value(parse_one("go movetime 2500")).unwrap();
+        assert_eq!(json, serde_json::json!({ "Go": { "time_control": { "MoveTime": 2500 }, "search_control": null } }));
+
+        assert_eq!(serde_json::to_value(UciMessage::Uci).unwrap(), serde_json::json!("Uci"));
+    }
+
+    #[test]
+    fn test_option_config() {
+        let option = UciOptionConfig::Spin { name: "Hash".to_string(), default: Some(16), min: Some(1), max: Some(1024) };
+        let json = serde_json::to_string(&option).unwrap();
+        assert_eq!(serde_json::from_str::<UciOptionConfig>(&json).unwrap(), option);
+    }
+
+    #[test]
+    fn test_unknown_drops_error() {
+        let message = parse_one("foo bar");
+        assert!(matches!(message, UciMessage::Unknown(_, Some(_))));
+        assert_eq!(round_trip(&message), UciMessage::Unknown("foo bar".to_string(), None));
+    }
+
+    #[test]
+    fn test_byte_vec_message() {
+        let message = ByteVecUciMessage::from(UciMessage::IsReady);
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, "\"IsReady\"");
+        assert_eq!(serde_json::from_str::<ByteVecUciMessage>(&json).unwrap(), message);
+    }
+
+    #[test]
+    #[cfg(feature = "schemars")]
+    fn test_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(UciMessage)).unwrap();
+        let definitions = &schema["$defs"];
+
+        let best_move = schema["oneOf"].as_array().unwrap().iter()
+            .find(|s| s["required"] == serde_json::json!(["BestMove"])).unwrap();
+        assert_eq!(best_move["properties"]["BestMove"]["properties"]["best_move"]["type"], "string");
+
+        let time_left = definitions["UciTimeControl"]["oneOf"].as_array().unwrap().iter()
+            .find(|s| s["required"] == serde_json::json!(["TimeLeft"])).unwrap();
+        assert_eq!(time_left["properties"]["TimeLeft"]["properties"]["white_time"]["type"], serde_json::json!(["integer", "null"]));
+        assert!(definitions.get("UciInfoAttribute").is_some());
+        assert!(definitions.get("UciOptionConfig").is_some());
+    }
+
+    #[test]
+    fn test_invalid_move() {
+        assert!(serde_json::from_str::<UciMessage>(r#"{"BestMove":{"best_move":"e9e4","ponder":null}}"#).is_err());
+    }
+}
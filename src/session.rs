@@ -0,0 +1,341 @@
+//! The `session` module contains the `Session`, a structured view of a transcript of the messages exchanged with an
+//! engine: the handshake, then the games (separated by `ucinewgame`), each with its searches (a `position`, a `go`,
+//! the `info` messages and the `bestmove`).
+
+use crate::discovery::EngineInfo;
+use crate::uci::{MessageList, UciMessage};
+
+/// A search: a `go`, the `position` it searched and the engine's `info` messages and `bestmove` in reply.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SearchEpisode {
+    /// The last `position` message before the `go`, if any.
+    pub position: Option<UciMessage>,
+
+    /// The `go` message.
+    pub go: UciMessage,
+
+    /// The `info` messages sent during the search.
+    pub infos: Vec<UciMessage>,
+
+    /// The other messages exchanged during the search, such as `stop` or `ponderhit`.
+    pub other: Vec<UciMessage>,
+
+    /// The `bestmove` message, or `None` if the transcript ends (or another search starts) before it.
+    pub best_move: Option<UciMessage>,
+}
+
+impl SearchEpisode {
+    fn new(position: Option<UciMessage>, go: UciMessage) -> SearchEpisode {
+        SearchEpisode {
+            position,
+            go,
+            infos: Vec::new(),
+            other: Vec::new(),
+            best_move: None,
+        }
+    }
+
+    /// Returns `true` if the search ended with a `bestmove`.
+    pub fn is_complete(&self) -> bool {
+        self.best_move.is_some()
+    }
+}
+
+/// A game: the messages from a `ucinewgame` to the next one.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GameSegment {
+    /// `true` if the game was started by a `ucinewgame`, rather than by a `position` or a `go` without one.
+    pub new_game: bool,
+
+    /// The searches of the game, in order.
+    pub searches: Vec<SearchEpisode>,
+
+    /// The other messages of the game that are not part of a search, such as `setoption` or `isready`, including the
+    /// `position` messages that were not followed by a `go`.
+    pub other: Vec<UciMessage>,
+}
+
+/// A transcript of the messages exchanged with an engine, structured into phases.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::session::Session;
+///
+/// let messages = parse("uci\nid name Vampirc\nuciok\nsetoption name Hash value 64\nucinewgame\n\
+///     position startpos\ngo depth 2\ninfo depth 1\ninfo depth 2\nbestmove e2e4\n\
+///     position startpos moves e2e4 e7e5\ngo depth 2\nbestmove g1f3\n");
+/// let session = Session::from_messages(&messages);
+///
+/// assert_eq!(session.engine.name.as_deref(), Some("Vampirc"));
+/// assert_eq!(session.setup.len(), 1);
+/// assert_eq!(session.games.len(), 1);
+/// assert_eq!(session.games[0].searches.len(), 2);
+/// assert_eq!(session.games[0].searches[0].infos.len(), 2);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Session {
+    /// The messages of the handshake, from `uci` to `uciok`.
+    pub handshake: Vec<UciMessage>,
+
+    /// The information the engine reported about itself during the handshake.
+    pub engine: EngineInfo,
+
+    /// The messages between the handshake and the first game, such as `setoption` or `isready`.
+    pub setup: Vec<UciMessage>,
+
+    /// The games, in order.
+    pub games: Vec<GameSegment>,
+}
+
+impl Session {
+    /// Structures the `messages` into a `Session`.
+    ///
+    /// The handshake lasts until `uciok` (if the messages start with `uci`). Each `ucinewgame` starts a game, as does
+    /// a `position` or a `go` outside of one. A search lasts from a `go` to its `bestmove`; the messages sent in the
+    /// meantime belong to it.
+    pub fn from_messages<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> Session {
+        let mut session = Session::default();
+        let mut messages = messages.into_iter().peekable();
+
+        if let Some(UciMessage::Uci) = messages.peek() {
+            for m in messages.by_ref() {
+                session.handshake.push(m.clone());
+                if *m == UciMessage::UciOk {
+                    break;
+                }
+            }
+            session.engine = EngineInfo::from_messages(&session.handshake);
+        }
+
+        let mut position: Option<UciMessage> = None;
+        let mut search: Option<SearchEpisode> = None;
+
+        for m in messages {
+            if let Some(episode) = search.as_mut() {
+                match m {
+                    UciMessage::Info(..) => {
+                        episode.infos.push(m.clone());
+                        continue;
+                    }
+                    UciMessage::BestMove { .. } => {
+                        episode.best_move = Some(m.clone());
+                        let episode = search.take().unwrap();
+                        session.games.last_mut().unwrap().searches.push(episode);
+                        continue;
+                    }
+                    UciMessage::Go { .. } | UciMessage::Position { .. } | UciMessage::UciNewGame => {
+                        // The search was never answered
+                        let episode = search.take().unwrap();
+                        session.games.last_mut().unwrap().searches.push(episode);
+                    }
+                    _ => {
+                        episode.other.push(m.clone());
+                        continue;
+                    }
+                }
+            }
+
+            match m {
+                UciMessage::UciNewGame => {
+                    if let Some(p) = position.take() {
+                        session.games.last_mut().unwrap().other.push(p);
+                    }
+                    session.games.push(GameSegment { new_game: true, ..GameSegment::default() });
+                }
+                UciMessage::Position { .. } => {
+                    if session.games.is_empty() {
+                        session.games.push(GameSegment::default());
+                    }
+                    if let Some(p) = position.replace(m.clone()) {
+                        session.games.last_mut().unwrap().other.push(p);
+                    }
+                }
+                UciMessage::Go { .. } => {
+                    if session.games.is_empty() {
+                        session.games.push(GameSegment::default());
+                    }
+                    search = Some(SearchEpisode::new(position.take(), m.clone()));
+                }
+                _ => match session.games.last_mut() {
+                    Some(game) => game.other.push(m.clone()),
+                    None => session.setup.push(m.clone()),
+                }
+            }
+        }
+
+        if let Some(episode) = search {
+            session.games.last_mut().unwrap().searches.push(episode);
+        }
+        if let Some(p) = position {
+            session.games.last_mut().unwrap().other.push(p);
+        }
+
+        session
+    }
+
+    /// Returns all of the searches, across the games.
+    pub fn searches(&self) -> impl Iterator<Item=&SearchEpisode> {
+        self.games.iter().flat_map(|g| g.searches.iter())
+    }
+}
+
+/// Splits a long transcript, such as the log of a tournament, into the messages of each game.
+///
+/// A game starts with a `ucinewgame`. As some GUIs omit it, a game also starts with a `position` that does not
+/// continue the previous one: one from another starting position, or with moves that do not extend the previous
+/// moves. The last move of the previous position may differ, as it may have been a ponder move the opponent did not
+/// play. A `ucinewgame` that follows another one before any `position` or `go` does not start another game.
+///
+/// The messages before the first game, such as the handshake, belong to no game and are left out.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::session::split_games;
+///
+/// let messages = parse("uci\nuciok\nucinewgame\nposition startpos\ngo depth 1\nbestmove e2e4\n\
+///     position startpos moves e2e4 e7e5\ngo depth 1\nbestmove g1f3\n\
+///     position startpos moves d2d4\ngo depth 1\nbestmove d7d5\n");
+/// let games = split_games(&messages);
+///
+/// assert_eq!(games.len(), 2);
+/// assert_eq!(games[0].len(), 7);
+/// assert_eq!(games[1][0], messages[9]);
+/// ```
+pub fn split_games<'a, I: IntoIterator<Item=&'a UciMessage>>(messages: I) -> Vec<MessageList> {
+    let mut games: Vec<MessageList> = Vec::new();
+    let mut in_game = false;
+    let mut started = false;
+    let mut previous: Option<&UciMessage> = None;
+
+    for m in messages {
+        let new_game = match m {
+            UciMessage::UciNewGame => !in_game || started,
+            UciMessage::Position { .. } => !in_game || previous.is_some_and(|p| !continues(p, m)),
+            UciMessage::Go { .. } => !in_game,
+            _ => false,
+        };
+
+        if new_game {
+            games.push(MessageList::new());
+            in_game = true;
+            started = false;
+            previous = None;
+        }
+
+        match m {
+            UciMessage::Position { .. } => {
+                previous = Some(m);
+                started = true;
+            }
+            UciMessage::Go { .. } => started = true,
+            _ => {}
+        }
+
+        if let Some(game) = games.last_mut() {
+            game.push(m.clone());
+        }
+    }
+
+    games
+}
+
+/// Returns `true` if the `next` position is a continuation of the `previous` one: the same starting position, with
+/// the moves of the previous one (but for its last move) followed by more.
+fn continues(previous: &UciMessage, next: &UciMessage) -> bool {
+    match (previous, next) {
+        (UciMessage::Position { startpos: s1, fen: f1, moves: m1 },
+            UciMessage::Position { startpos: s2, fen: f2, moves: m2 }) => {
+            let kept = m1.len().saturating_sub(1);
+            s1 == s2 && f1 == f2 && m2.len() >= m1.len() && m1[..kept] == m2[..kept]
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    const TRANSCRIPT: &str = "uci\nid name Vampirc\nid author Matija Kejžar\noption name Hash type spin default 16 min 1 max 64\n\
+        uciok\nsetoption name Hash value 32\nisready\nreadyok\n\
+        ucinewgame\nisready\nreadyok\nposition startpos\ngo wtime 1000 btime 1000\ninfo depth 1 pv e2e4\nbestmove e2e4\n\
+        position startpos moves e2e4 e7e5\ngo infinite\ninfo depth 1\nstop\ninfo depth 2\nbestmove g1f3\n\
+        ucinewgame\nposition startpos moves d2d4\nposition startpos moves d2d4 d7d5\ngo depth 3\ninfo depth 1\n";
+
+    #[test]
+    fn test_phases() {
+        let messages = parse(TRANSCRIPT);
+        let session = Session::from_messages(&messages);
+
+        assert_eq!(session.handshake.len(), 5);
+        assert_eq!(session.engine.options.len(), 1);
+        assert_eq!(session.setup.len(), 3);
+        assert_eq!(session.games.len(), 2);
+        assert!(session.games.iter().all(|g| g.new_game));
+
+        let first = &session.games[0];
+        assert_eq!(first.other, vec![UciMessage::IsReady, UciMessage::ReadyOk]);
+        assert_eq!(first.searches.len(), 2);
+        assert_eq!(first.searches[0].position, Some(messages[11].clone()));
+        assert_eq!(first.searches[1].infos.len(), 2);
+        assert_eq!(first.searches[1].other, vec![UciMessage::Stop]);
+        assert!(first.searches.iter().all(|s| s.is_complete()));
+
+        let second = &session.games[1];
+        assert_eq!(second.other, vec![messages[22].clone()]);
+        assert_eq!(second.searches[0].position, Some(messages[23].clone()));
+        assert_eq!(second.searches[0].infos.len(), 1);
+        assert!(!second.searches[0].is_complete());
+
+        assert_eq!(session.searches().count(), 3);
+    }
+
+    #[test]
+    fn test_without_handshake_or_new_game() {
+        let messages = parse("position startpos\ngo depth 1\ngo depth 2\nbestmove e2e4\nisready\nreadyok\n");
+        let session = Session::from_messages(&messages);
+
+        assert!(session.handshake.is_empty());
+        assert!(session.setup.is_empty());
+        assert_eq!(session.games.len(), 1);
+        assert!(!session.games[0].new_game);
+
+        let searches = &session.games[0].searches;
+        assert_eq!(searches.len(), 2);
+        assert!(searches[0].position.is_some() && !searches[0].is_complete());
+        assert!(searches[1].position.is_none() && searches[1].is_complete());
+        assert_eq!(session.games[0].other, vec![UciMessage::IsReady, UciMessage::ReadyOk]);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Session::from_messages(&[]), Session::default());
+    }
+
+    #[test]
+    fn test_split_games() {
+        let messages = parse("uci\nuciok\nisready\nreadyok\nucinewgame\nisready\nreadyok\nucinewgame\n\
+            position startpos\ngo ponder\nbestmove e2e4 ponder e7e5\nposition startpos moves e2e4 e7e5\n\
+            go ponder\nstop\nbestmove g1f3\nposition startpos moves e2e4 c7c5\ngo depth 1\nbestmove g1f3\n\
+            position startpos moves e2e4 c7c5 g1f3 d7d6\ngo depth 1\nbestmove d2d4\n\
+            position startpos\ngo depth 1\nbestmove d2d4\n\
+            position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1\ngo depth 1\nbestmove e1g1\n\
+            ucinewgame\nposition fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1\ngo depth 1\nbestmove h1h8\n");
+        let games = split_games(&messages);
+
+        let lengths: Vec<usize> = games.iter().map(|g| g.len()).collect();
+        assert_eq!(lengths, vec![17, 3, 3, 4]);
+        assert_eq!(&games[0][..3], &messages[4..7]);
+        assert_eq!(games[1][0], messages[21]);
+        assert_eq!(games[3][0], UciMessage::UciNewGame);
+
+        assert!(split_games(&parse("uci\nuciok\nisready\nreadyok\n")).is_empty());
+        assert_eq!(split_games(&parse("go depth 1\nbestmove e2e4\n")).len(), 1);
+    }
+}
@@ -0,0 +1,213 @@
+//! The `stats` module summarizes the searches of a recorded `Session`: the depth the engine reached on each move, its
+//! speed, the nodes it searched, the time it used against the time it was given and how full its hash table grew.
+//!
+//! The statistics of each search are taken from the last value of each `info` attribute the engine reported (the
+//! greatest, for the depth), so searches in which the engine did not report an attribute are left out of its
+//! aggregates, rather than counted as zero.
+
+use chrono::Duration;
+
+use crate::eval_graph::EvalPoint;
+use crate::session::{SearchEpisode, Session};
+use crate::uci::{UciInfoAttribute, UciMessage, UciTimeControl};
+
+/// The statistics of a search.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct SearchStats {
+    /// The greatest depth reported.
+    pub depth: Option<u8>,
+
+    /// The greatest selective depth reported.
+    pub sel_depth: Option<u8>,
+
+    /// The last node count reported.
+    pub nodes: Option<u64>,
+
+    /// The last speed reported, in nodes per second.
+    pub nps: Option<u64>,
+
+    /// The last search time reported.
+    pub time: Option<Duration>,
+
+    /// The last hash table usage reported, in permill.
+    pub hash_full: Option<u16>,
+
+    /// The time the engine was given for the move: the `movetime` of the `go`, or the clock of the side to move if
+    /// the `go` had one.
+    pub allocated: Option<Duration>,
+}
+
+impl SearchStats {
+    /// Collects the statistics of a `search`.
+    pub fn from_search(search: &SearchEpisode) -> SearchStats {
+        let mut stats = SearchStats::default();
+
+        for info in &search.infos {
+            let attributes = match info {
+                UciMessage::Info(attributes) => attributes,
+                _ => continue,
+            };
+
+            for a in attributes {
+                match a {
+                    UciInfoAttribute::Depth(d) => stats.depth = stats.depth.max(Some(*d)),
+                    UciInfoAttribute::SelDepth(d) => stats.sel_depth = stats.sel_depth.max(Some(*d)),
+                    UciInfoAttribute::Nodes(n) => stats.nodes = Some(*n),
+                    UciInfoAttribute::Nps(n) => stats.nps = Some(*n),
+                    UciInfoAttribute::Time(t) => stats.time = Some(*t),
+                    UciInfoAttribute::HashFull(h) => stats.hash_full = Some(*h),
+                    _ => {}
+                }
+            }
+        }
+
+        stats.allocated = match &search.go {
+            UciMessage::Go { time_control: Some(UciTimeControl::MoveTime(t)), .. } => Some(*t),
+            UciMessage::Go { time_control: Some(UciTimeControl::TimeLeft { white_time, black_time, .. }), .. } => {
+                match EvalPoint::from_search(search).map(|p| p.white_to_move) {
+                    Some(true) => *white_time,
+                    Some(false) => *black_time,
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+
+        stats
+    }
+
+    /// Returns the share of the allocated time the engine used, as reported in `info time`: `0.5` for half of it.
+    /// Returns `None` if either is unknown, or if no time was allocated.
+    pub fn time_usage(&self) -> Option<f64> {
+        let allocated = self.allocated?.num_milliseconds();
+        if allocated <= 0 {
+            return None;
+        }
+
+        Some(self.time?.num_milliseconds() as f64 / allocated as f64)
+    }
+}
+
+/// The statistics of the searches of a session.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::parse;
+/// use vampirc_uci::session::Session;
+/// use vampirc_uci::stats::SessionStats;
+///
+/// let messages = parse("position startpos\ngo movetime 1000\n\
+///     info depth 10 nodes 400000 nps 800000 time 500 hashfull 20\nbestmove e2e4\n\
+///     position startpos moves e2e4 e7e5\ngo movetime 1000\n\
+///     info depth 14 nodes 1000000 nps 1000000 time 1000 hashfull 60\nbestmove g1f3\n");
+/// let stats = SessionStats::from_session(&Session::from_messages(&messages));
+///
+/// assert_eq!(stats.average_depth(), Some(12.0));
+/// assert_eq!(stats.max_depth(), Some(14));
+/// assert_eq!(stats.average_nps(), Some(900000.0));
+/// assert_eq!(stats.total_nodes(), 1400000);
+/// assert_eq!(stats.average_time_usage(), Some(0.75));
+/// assert_eq!(stats.hash_full_trend(), vec![20, 60]);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct SessionStats {
+    /// The statistics of each search, in order, across the games.
+    pub searches: Vec<SearchStats>,
+}
+
+impl SessionStats {
+    /// Collects the statistics of the searches of a `session`.
+    pub fn from_session(session: &Session) -> SessionStats {
+        SessionStats {
+            searches: session.searches().map(SearchStats::from_search).collect(),
+        }
+    }
+
+    /// Returns the mean of the depths reached in the searches.
+    pub fn average_depth(&self) -> Option<f64> {
+        mean(self.searches.iter().filter_map(|s| s.depth).map(f64::from))
+    }
+
+    /// Returns the greatest depth reached in any search.
+    pub fn max_depth(&self) -> Option<u8> {
+        self.searches.iter().filter_map(|s| s.depth).max()
+    }
+
+    /// Returns the mean of the speeds of the searches, in nodes per second.
+    pub fn average_nps(&self) -> Option<f64> {
+        mean(self.searches.iter().filter_map(|s| s.nps).map(|n| n as f64))
+    }
+
+    /// Returns the sum of the nodes searched.
+    pub fn total_nodes(&self) -> u64 {
+        self.searches.iter().filter_map(|s| s.nodes).sum()
+    }
+
+    /// Returns the sum of the search times.
+    pub fn total_time(&self) -> Duration {
+        self.searches.iter().filter_map(|s| s.time).fold(Duration::zero(), |total, t| total + t)
+    }
+
+    /// Returns the mean share of the allocated time the engine used, see `SearchStats::time_usage()`.
+    pub fn average_time_usage(&self) -> Option<f64> {
+        mean(self.searches.iter().filter_map(SearchStats::time_usage))
+    }
+
+    /// Returns the hash table usage at the end of each search that reported it, in permill, in order.
+    pub fn hash_full_trend(&self) -> Vec<u16> {
+        self.searches.iter().filter_map(|s| s.hash_full).collect()
+    }
+}
+
+fn mean<I: Iterator<Item=f64>>(values: I) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / f64::from(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_session_stats() {
+        let messages = parse("ucinewgame\nposition startpos moves e2e4\ngo wtime 60000 btime 20000\n\
+            info depth 5 seldepth 9 nodes 1000 time 100\ninfo depth 4 seldepth 7 nodes 2000 nps 20000 time 100\n\
+            bestmove e7e5\nposition startpos moves e2e4 e7e5 g1f3\ngo wtime 59000 btime 19000\n\
+            info depth 6 nodes 3000 time 1900 hashfull 100\nbestmove b8c6\n\
+            go infinite\ninfo string no statistics\nstop\nbestmove d7d5\n");
+        let stats = SessionStats::from_session(&Session::from_messages(&messages));
+        assert_eq!(stats.searches.len(), 3);
+
+        assert_eq!(stats.searches[0], SearchStats {
+            depth: Some(5),
+            sel_depth: Some(9),
+            nodes: Some(2000),
+            nps: Some(20000),
+            time: Some(Duration::milliseconds(100)),
+            hash_full: None,
+            allocated: Some(Duration::milliseconds(20000)),
+        });
+        assert_eq!(stats.searches[0].time_usage(), Some(0.005));
+        assert_eq!(stats.searches[1].allocated, Some(Duration::milliseconds(19000)));
+        assert_eq!(stats.searches[2], SearchStats::default());
+
+        assert_eq!(stats.average_depth(), Some(5.5));
+        assert_eq!(stats.max_depth(), Some(6));
+        assert_eq!(stats.average_nps(), Some(20000.0));
+        assert_eq!(stats.total_nodes(), 5000);
+        assert_eq!(stats.total_time(), Duration::milliseconds(2000));
+        assert!((stats.average_time_usage().unwrap() - 0.0525).abs() < 1e-9);
+        assert_eq!(stats.hash_full_trend(), vec![100]);
+
+        let empty = SessionStats::default();
+        assert_eq!((empty.average_depth(), empty.max_depth(), empty.average_time_usage()), (None, None, None));
+        assert_eq!((empty.total_nodes(), empty.total_time()), (0, Duration::zero()));
+    }
+}
@@ -0,0 +1,285 @@
+//! The `strategies` module (available with the `proptest` feature) contains [proptest](https://crates.io/crates/proptest)
+//! strategies that generate valid messages, moves, FENs and option definitions, for property-testing code that handles
+//! the UCI protocol.
+//!
+//! The generated messages satisfy the round-trip property: serializing one and parsing it back with `parse_one(..)`
+//! yields the same message. Names and other free text are made of words that cannot be mistaken for protocol keywords,
+//! an `info string` or other unknown `info` attribute only comes last, and values stay within the ranges the grammar
+//! accepts. Neither `UciMessage::Unknown` nor the `cpunr` of an `info currline` are generated, as they do not survive the
+//! round trip.
+//!
+//! # Examples
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use vampirc_uci::{parse_one, Serializable};
+//! use vampirc_uci::strategies::message;
+//!
+//! proptest! {
+//!     // In a test module, mark this with #[test]
+//!     fn round_trip(m in message()) {
+//!         prop_assert_eq!(parse_one(&m.serialize()), m);
+//!     }
+//! }
+//! # round_trip();
+//! ```
+
+#[cfg(feature = "chess")]
+use chess::{ChessMove, Piece, ALL_SQUARES};
+use chrono::Duration;
+use proptest::collection::vec;
+use proptest::option::of;
+use proptest::prelude::*;
+#[cfg(feature = "chess")]
+use proptest::sample::select;
+
+use crate::uci::{ProtectionState, UciFen, UciFloat, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+#[cfg(not(feature = "chess"))]
+use crate::uci::{UciMove, UciPiece, UciSquare};
+
+/// The largest number the grammar accepts where it expects up to 12 digits.
+const MAX_DIGITS12: i64 = 999_999_999_999;
+
+/// No protocol keyword starts with one of the letters `fgjxyz`, and no move has a letter as its second character.
+const WORD: &str = "[fgjxyz][g-z][a-z0-9]{0,6}";
+const WORDS: &str = "[fgjxyz][g-z][a-z0-9]{0,6}( [fgjxyz][g-z][a-z0-9]{0,6}){0,2}";
+
+fn millis(min: i64) -> impl Strategy<Value=Duration> {
+    (min..=MAX_DIGITS12).prop_map(Duration::milliseconds)
+}
+
+fn digits12() -> impl Strategy<Value=u64> {
+    0..=MAX_DIGITS12 as u64
+}
+
+fn signed_digits12() -> impl Strategy<Value=i64> {
+    -MAX_DIGITS12..=MAX_DIGITS12
+}
+
+/// A decimal with three digits after the point, which survives serialization.
+fn decimal() -> impl Strategy<Value=UciFloat> {
+    signed_digits12().prop_map(|n| UciFloat(n as f64 / 1000.0))
+}
+
+/// A square on the board.
+#[cfg(not(feature = "chess"))]
+pub fn square() -> impl Strategy<Value=UciSquare> {
+    ((b'a'..=b'h'), (1u8..=8)).prop_map(|(file, rank)| UciSquare::from(file as char, rank))
+}
+
+/// A move between two squares on the board, possibly promoting to a knight, bishop, rook or queen.
+#[cfg(not(feature = "chess"))]
+pub fn a_move() -> impl Strategy<Value=UciMove> {
+    let promotion = of(prop_oneof![Just(UciPiece::Knight), Just(UciPiece::Bishop), Just(UciPiece::Rook), Just(UciPiece::Queen)]);
+    (square(), square(), promotion).prop_map(|(from, to, promotion)| UciMove { from, to, promotion })
+}
+
+/// A move between two squares on the board, possibly promoting to a knight, bishop, rook or queen.
+#[cfg(feature = "chess")]
+pub fn a_move() -> impl Strategy<Value=ChessMove> {
+    let promotion = of(select(vec![Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]));
+    (select(ALL_SQUARES.to_vec()), select(ALL_SQUARES.to_vec()), promotion)
+        .prop_map(|(from, to, promotion)| ChessMove::new(from, to, promotion))
+}
+
+/// A syntactically valid FEN. The position itself need not be legal.
+pub fn fen() -> impl Strategy<Value=UciFen> {
+    let rank = vec(of("[PNBRQKpnbrqk]"), 8).prop_map(|squares| {
+        let mut rank = String::new();
+        let mut empty = 0;
+        for square in squares {
+            match square {
+                None => empty += 1,
+                Some(piece) => {
+                    if empty > 0 {
+                        rank += &empty.to_string();
+                        empty = 0;
+                    }
+                    rank += &piece;
+                }
+            }
+        }
+        if empty > 0 {
+            rank += &empty.to_string();
+        }
+
+        rank
+    });
+
+    (vec(rank, 8), "[wb]", "K?Q?k?q?", "-|[a-h][36]", 0..100u16, 1..1000u16)
+        .prop_map(|(ranks, color, castling, en_passant, ply_clock, move_num)| {
+            let castling = if castling.is_empty() { "-".to_string() } else { castling };
+            UciFen(format!("{} {} {} {} {} {}", ranks.join("/"), color, castling, en_passant, ply_clock, move_num))
+        })
+}
+
+/// A time control; a `TimeLeft` always has the white time set.
+pub fn time_control() -> impl Strategy<Value=UciTimeControl> {
+    let time_left = (millis(-MAX_DIGITS12), of(millis(-MAX_DIGITS12)), of(millis(0)), of(millis(0)), of(any::<u8>()))
+        .prop_map(|(white_time, black_time, white_increment, black_increment, moves_to_go)| UciTimeControl::TimeLeft {
+            white_time: Some(white_time),
+            black_time,
+            white_increment,
+            black_increment,
+            moves_to_go,
+        });
+
+    prop_oneof![
+        Just(UciTimeControl::Ponder),
+        Just(UciTimeControl::Infinite),
+        millis(0).prop_map(UciTimeControl::MoveTime),
+        time_left,
+    ]
+}
+
+/// A search control that is never empty.
+pub fn search_control() -> impl Strategy<Value=UciSearchControl> {
+    (vec(a_move(), 0..8), of(any::<u8>()), of(any::<u8>()), of(digits12()))
+        .prop_map(|(search_moves, mate, depth, nodes)| {
+            let mut search_control = UciSearchControl { search_moves, mate, depth, nodes };
+            if search_control.is_empty() {
+                search_control.depth = Some(1);
+            }
+
+            search_control
+        })
+}
+
+/// An option definition of any type.
+pub fn option_config() -> impl Strategy<Value=UciOptionConfig> {
+    prop_oneof![
+        (WORDS, of(any::<bool>())).prop_map(|(name, default)| UciOptionConfig::Check { name, default }),
+        (WORDS, of(signed_digits12()), of(signed_digits12()), of(signed_digits12()))
+            .prop_map(|(name, default, min, max)| UciOptionConfig::Spin { name, default, min, max }),
+        (WORDS, of(WORDS), vec(WORDS, 0..5)).prop_map(|(name, default, var)| UciOptionConfig::Combo { name, default, var }),
+        // Without a default, a float option with integral bounds would be parsed back as a `spin` one
+        (WORDS, decimal(), of(decimal()), of(decimal()))
+            .prop_map(|(name, default, min, max)| UciOptionConfig::Float { name, default: Some(default), min, max }),
+        WORDS.prop_map(|name| UciOptionConfig::Button { name }),
+        (WORDS, of(WORDS)).prop_map(|(name, default)| UciOptionConfig::String { name, default }),
+    ]
+}
+
+/// An `info` attribute with a fixed number of values, which can be followed by more attributes. Neither
+/// `UciInfoAttribute::String` nor `UciInfoAttribute::Any` are generated, as they take the rest of the line.
+pub fn info_attribute() -> impl Strategy<Value=UciInfoAttribute> {
+    let score = (prop_oneof![any::<i32>().prop_map(|cp| (Some(cp), None)), any::<i8>().prop_map(|mate| (None, Some(mate)))],
+                 prop_oneof![Just((None, None)), Just((Some(true), None)), Just((None, Some(true)))])
+        .prop_map(|((cp, mate), (lower_bound, upper_bound))| UciInfoAttribute::Score { cp, mate, lower_bound, upper_bound });
+
+    prop_oneof![
+        any::<u8>().prop_map(UciInfoAttribute::Depth),
+        any::<u8>().prop_map(UciInfoAttribute::SelDepth),
+        millis(0).prop_map(UciInfoAttribute::Time),
+        digits12().prop_map(UciInfoAttribute::Nodes),
+        vec(a_move(), 1..8).prop_map(UciInfoAttribute::Pv),
+        any::<u16>().prop_map(UciInfoAttribute::MultiPv),
+        score,
+        a_move().prop_map(UciInfoAttribute::CurrMove),
+        any::<u16>().prop_map(UciInfoAttribute::CurrMoveNum),
+        any::<u16>().prop_map(UciInfoAttribute::HashFull),
+        digits12().prop_map(UciInfoAttribute::Nps),
+        digits12().prop_map(UciInfoAttribute::TbHits),
+        digits12().prop_map(UciInfoAttribute::SbHits),
+        any::<u16>().prop_map(UciInfoAttribute::CpuLoad),
+        vec(a_move(), 1..8).prop_map(UciInfoAttribute::Refutation),
+        vec(a_move(), 1..8).prop_map(|line| UciInfoAttribute::CurrLine { cpu_nr: None, line }),
+    ]
+}
+
+/// An `info` message, of up to six attributes from `info_attribute()`, possibly followed by an `info string` or an
+/// unknown attribute.
+pub fn info() -> impl Strategy<Value=UciMessage> {
+    let trailing = prop_oneof![
+        WORDS.prop_map(UciInfoAttribute::String),
+        (WORD, WORDS).prop_map(|(name, value)| UciInfoAttribute::Any(name, value)),
+    ];
+
+    (vec(info_attribute(), 0..=6), of(trailing)).prop_map(|(mut attributes, trailing)| {
+        attributes.extend(trailing);
+        UciMessage::Info(attributes)
+    })
+}
+
+/// An engine-bound message.
+pub fn engine_bound_message() -> impl Strategy<Value=UciMessage> {
+    prop_oneof![
+        Just(UciMessage::Uci),
+        any::<bool>().prop_map(UciMessage::Debug),
+        Just(UciMessage::IsReady),
+        Just(UciMessage::register_later()),
+        (WORDS, WORDS).prop_map(|(name, code)| UciMessage::register_code(&name, &code)),
+        WORDS.prop_map(|name| UciMessage::Register { later: false, name: Some(name), code: None }),
+        WORDS.prop_map(|code| UciMessage::Register { later: false, name: None, code: Some(code) }),
+        (of(fen()), vec(a_move(), 0..8)).prop_map(|(fen, moves)| UciMessage::Position { startpos: fen.is_none(), fen, moves }),
+        (WORDS, WORDS).prop_map(|(name, value)| UciMessage::SetOption { name, value: Some(value) }),
+        Just(UciMessage::UciNewGame),
+        Just(UciMessage::Stop),
+        Just(UciMessage::PonderHit),
+        Just(UciMessage::Quit),
+        (of(time_control()), of(search_control()))
+            .prop_map(|(time_control, search_control)| UciMessage::Go { time_control, search_control }),
+    ]
+}
+
+/// A GUI-bound message.
+pub fn gui_bound_message() -> impl Strategy<Value=UciMessage> {
+    let state = prop_oneof![Just(ProtectionState::Checking), Just(ProtectionState::Ok), Just(ProtectionState::Error)];
+
+    prop_oneof![
+        WORDS.prop_map(|name| UciMessage::Id { name: Some(name), author: None }),
+        WORDS.prop_map(|author| UciMessage::Id { name: None, author: Some(author) }),
+        Just(UciMessage::UciOk),
+        Just(UciMessage::ReadyOk),
+        (a_move(), of(a_move())).prop_map(|(best_move, ponder)| UciMessage::BestMove {
+            best_move,
+            ponder,
+            #[cfg(feature = "extensions")]
+            offer: None,
+        }),
+        state.clone().prop_map(UciMessage::CopyProtection),
+        state.prop_map(UciMessage::Registration),
+        option_config().prop_map(UciMessage::Option),
+        info(),
+    ]
+}
+
+/// A message in either direction.
+pub fn message() -> impl Strategy<Value=UciMessage> {
+    prop_oneof![engine_bound_message(), gui_bound_message()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+    use crate::uci::{CommunicationDirection, Serializable};
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_round_trip(m in message()) {
+            prop_assert_eq!(parse_one(&m.serialize()), m);
+        }
+
+        #[test]
+        fn test_canonical_round_trip(m in message()) {
+            let normalized = m.normalize();
+            prop_assert_eq!(parse_one(&m.serialize_canonical()), normalized.clone());
+            prop_assert_eq!(normalized.normalize(), normalized);
+        }
+
+        #[test]
+        fn test_directions(engine_bound in engine_bound_message(), gui_bound in gui_bound_message()) {
+            prop_assert_eq!(engine_bound.direction(), CommunicationDirection::GuiToEngine);
+            prop_assert_eq!(gui_bound.direction(), CommunicationDirection::EngineToGui);
+        }
+
+        #[test]
+        fn test_fen_shape(f in fen()) {
+            let fields: Vec<&str> = f.as_str().split(' ').collect();
+            prop_assert_eq!(fields.len(), 6);
+            prop_assert_eq!(fields[0].split('/').count(), 8);
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! The `tcp` module exposes UCI engines over TCP, for running an engine on another machine. It is available with the
+//! `tokio` feature.
+//!
+//! On the machine with the engine, `serve(..)` accepts connections and runs a fresh engine process for each,
+//! forwarding the messages between the connection and the process as they are. On the other end, `connect(..)` (or a
+//! `Connector`, which retries) connects to the server and returns an `EngineHandle`, just like the one for a local
+//! engine process. A `RemoteEngine` also restores a lost connection, setting the fresh engine up as the lost one was.
+//!
+//! The connection carries the plain UCI text, a message per line, so the server can also be used with any GUI that
+//! talks to an engine over TCP, and `connect(..)` with any server that relays an engine's standard input and output.
+
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use tokio::io::{copy, AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+use tokio::time::{sleep, timeout as with_timeout};
+
+use crate::async_engine::EngineHandle;
+use crate::uci::UciMessage;
+
+/// How long the engine is given to exit on its own once the client disconnects, before it is killed.
+const EXIT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How long `serve(..)` waits after failing to accept a connection, so that a lasting error does not spin the loop.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Connects to the engine served at `addr` (such as `"10.0.0.2:9000"`) and performs the `uci` handshake.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vampirc_uci::UciSearchControl;
+/// use vampirc_uci::tcp::connect;
+///
+/// # async fn analyse() -> std::io::Result<()> {
+/// let mut engine = connect("10.0.0.2:9000").await?;
+/// engine.position(None, &[]).await?;
+/// let best = engine.go(None, Some(UciSearchControl::depth(30))).await?.await?;
+/// println!("Best move: {}", best.best_move);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn connect(addr: &str) -> IoResult<EngineHandle> {
+    let stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    let (reader, writer) = stream.into_split();
+
+    EngineHandle::from_streams(writer, reader).await
+}
+
+/// Connects to a served engine, retrying if the server cannot be reached or the handshake fails.
+///
+/// A lost connection is not restored by the `EngineHandle`, because the server runs a fresh engine for each
+/// connection, which has to be set up again: use a `RemoteEngine` for that.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use vampirc_uci::tcp::Connector;
+///
+/// # async fn analyse() -> std::io::Result<()> {
+/// let connector = Connector::new("10.0.0.2:9000").attempts(10).delay(Duration::from_secs(2));
+/// let mut engine = connector.connect().await?;
+/// engine.set_option("Threads", Some("64")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Connector {
+    addr: String,
+    attempts: u32,
+    delay: Duration,
+}
+
+impl Connector {
+    /// Creates a `Connector` for the engine served at `addr`, which makes a single attempt.
+    pub fn new(addr: &str) -> Connector {
+        Connector {
+            addr: addr.to_owned(),
+            attempts: 1,
+            delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the number of attempts to connect before giving up (at least one).
+    pub fn attempts(mut self, attempts: u32) -> Connector {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets the time to wait between the attempts. The default is one second.
+    pub fn delay(mut self, delay: Duration) -> Connector {
+        self.delay = delay;
+        self
+    }
+
+    /// Returns the address of the served engine.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Connects to the engine and performs the `uci` handshake, making up to the set number of attempts. Returns the
+    /// error of the last attempt if none succeeds.
+    pub async fn connect(&self) -> IoResult<EngineHandle> {
+        let mut attempt = 1;
+        loop {
+            match connect(&self.addr).await {
+                Ok(handle) => return Ok(handle),
+                Err(e) if attempt >= self.attempts => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    sleep(self.delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// A served engine whose connection can be restored: it remembers the options and the position sent to the engine,
+/// and `reconnect()` connects again (retrying as its `Connector` is set to), performs the handshake and sends them to
+/// the fresh engine.
+///
+/// The options and the position are only remembered when they are sent through the `RemoteEngine`, not directly
+/// through its `handle()`. A `ucinewgame` forgets the position.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use vampirc_uci::{parse_one, UciSearchControl};
+/// use vampirc_uci::tcp::{Connector, RemoteEngine};
+///
+/// # async fn analyse() -> std::io::Result<()> {
+/// let connector = Connector::new("10.0.0.2:9000").attempts(10).delay(Duration::from_secs(2));
+/// let mut engine = RemoteEngine::connect(connector).await?;
+/// engine.set_option("Threads", Some("64")).await?;
+/// engine.send(&parse_one("position startpos moves e2e4")).await?;
+///
+/// let best = match engine.handle().go(None, Some(UciSearchControl::depth(30))).await?.await {
+///     Ok(best) => best,
+///     Err(_) => {
+///         // The connection was lost: restore it and search again
+///         engine.reconnect().await?;
+///         engine.handle().go(None, Some(UciSearchControl::depth(30))).await?.await?
+///     }
+/// };
+/// println!("Best move: {}", best.best_move);
+/// # Ok(())
+/// # }
+/// ```
+pub struct RemoteEngine {
+    connector: Connector,
+    handle: EngineHandle,
+    options: Vec<UciMessage>,
+    position: Option<UciMessage>,
+}
+
+impl RemoteEngine {
+    /// Connects to the engine with the `connector` and performs the `uci` handshake.
+    pub async fn connect(connector: Connector) -> IoResult<RemoteEngine> {
+        let handle = connector.connect().await?;
+
+        Ok(RemoteEngine {
+            connector,
+            handle,
+            options: Vec::new(),
+            position: None,
+        })
+    }
+
+    /// Returns the handle of the engine on the current connection.
+    pub fn handle(&mut self) -> &mut EngineHandle {
+        &mut self.handle
+    }
+
+    /// Sends a raw message to the engine, remembering it if it is a `setoption` or a `position`.
+    pub async fn send(&mut self, message: &UciMessage) -> IoResult<()> {
+        match message {
+            UciMessage::SetOption { name, .. } => {
+                self.options.retain(|o| !matches!(o, UciMessage::SetOption { name: n, .. } if n.eq_ignore_ascii_case(name)));
+                self.options.push(message.clone());
+            }
+            UciMessage::Position { .. } => self.position = Some(message.clone()),
+            UciMessage::UciNewGame => self.position = None,
+            _ => {}
+        }
+
+        self.handle.send(message).await
+    }
+
+    /// Sets the option `name` to the `value` (`None` for `button` options), remembering it.
+    pub async fn set_option(&mut self, name: &str, value: Option<&str>) -> IoResult<()> {
+        self.send(&UciMessage::set_option(name, value.map(String::from))).await
+    }
+
+    /// Connects to the engine again, performs the `uci` handshake, sends the remembered options and position and waits
+    /// for the engine to be ready. On success, the handle of the lost connection is replaced; on failure, it is kept.
+    pub async fn reconnect(&mut self) -> IoResult<()> {
+        let mut handle = self.connector.connect().await?;
+        for message in self.options.iter().chain(self.position.iter()) {
+            handle.send(message).await?;
+        }
+        handle.is_ready().await?;
+
+        self.handle = handle;
+        Ok(())
+    }
+
+    /// Sends `quit` and waits for the engine to close the connection.
+    pub async fn quit(self) -> IoResult<()> {
+        self.handle.quit().await
+    }
+}
+
+/// Serves an engine over TCP: accepts connections on the `listener` and, for each, spawns the engine with a
+/// `command` made by `make_command` and forwards the messages between them, until either side hangs up. Runs until it
+/// is cancelled: a connection that fails to be accepted (such as when the process runs out of file descriptors) is
+/// skipped after a short pause.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tokio::net::TcpListener;
+/// use tokio::process::Command;
+/// use vampirc_uci::tcp::serve;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let listener = TcpListener::bind("0.0.0.0:9000").await?;
+/// serve(listener, || Command::new("stockfish")).await
+/// # }
+/// ```
+pub async fn serve<F: Fn() -> Command>(listener: TcpListener, make_command: F) -> IoResult<()> {
+    accept_loop(listener, |stream| {
+        let command = make_command();
+        async move {
+            let _ = serve_connection(stream, command).await;
+        }
+    }).await
+}
+
+/// Accepts connections on the `listener` and spawns a task running `handle` for each, until it is cancelled.
+async fn accept_loop<F, H>(listener: TcpListener, handle: F) -> IoResult<()>
+    where F: Fn(TcpStream) -> H, H: Future<Output=()> + Send + 'static {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => {
+                sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        tokio::spawn(handle(stream));
+    }
+}
+
+/// Spawns the engine with the prepared `command` and forwards the messages between it and the `stream`, until
+/// either side hangs up. Once the client disconnects, the engine's input is closed and it is given a few seconds to
+/// exit before it is killed. Returns the engine's exit status.
+pub async fn serve_connection(stream: TcpStream, mut command: Command) -> IoResult<ExitStatus> {
+    stream.set_nodelay(true)?;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take().ok_or_else(|| std::io::Error::other("the engine's stdin is not piped"))?;
+    let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("the engine's stdout is not piped"))?;
+    relay(stream, stdin, stdout).await;
+
+    match with_timeout(EXIT_GRACE_PERIOD, child.wait()).await {
+        Ok(status) => status,
+        Err(_) => {
+            child.kill().await?;
+            child.wait().await
+        }
+    }
+}
+
+/// Forwards the messages between the `stream` and the engine's `input` and `output`, until either side hangs up. Once
+/// the client disconnects, the engine's input is closed and it is given a few seconds to close its output.
+async fn relay<W, R>(stream: TcpStream, mut input: W, mut output: R)
+    where W: AsyncWrite + Unpin, R: AsyncRead + Unpin {
+    let (mut socket_reader, mut socket_writer) = stream.into_split();
+
+    let to_engine = async move {
+        let _ = copy(&mut socket_reader, &mut input).await;
+        // Dropping the input closes it, which tells the engine to exit
+    };
+    let from_engine = async move {
+        let _ = copy(&mut output, &mut socket_writer).await;
+        let _ = socket_writer.shutdown().await;
+    };
+    tokio::pin!(from_engine);
+
+    tokio::select! {
+        _ = &mut from_engine => {}
+        _ = to_engine => {
+            let _ = with_timeout(EXIT_GRACE_PERIOD, from_engine).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener as StdTcpListener};
+
+    use crate::parser::parse_one;
+    use crate::testing::{MockEngine, MockScript};
+    use crate::uci::{UciInfoAttribute, UciMessageKind, UciSearchControl};
+
+    use super::*;
+
+    fn mock_engine() -> MockEngine {
+        MockEngine::new("Remote", "Tester").on(UciMessageKind::Go, MockScript::new().send_all(
+            ["info depth 1 score cp 12 pv e2e4", "bestmove e2e4"].iter().map(|m| parse_one(m))))
+    }
+
+    /// Serves the `mock` engine on a local port and returns its address.
+    fn serve_mock(mock: MockEngine) -> SocketAddr {
+        let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        mock.serve(listener);
+        addr
+    }
+
+    /// Relays the `stream` to the engine served at `engine_addr`, as `serve_connection(..)` does to a process.
+    async fn relay_to(stream: TcpStream, engine_addr: SocketAddr) {
+        let (output, input) = TcpStream::connect(engine_addr).await.unwrap().into_split();
+        relay(stream, input, output).await;
+    }
+
+    #[tokio::test]
+    async fn test_serve_and_connect() {
+        let mock = mock_engine();
+        let log = mock.log();
+        let engine_addr = serve_mock(mock);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(accept_loop(listener, move |stream| relay_to(stream, engine_addr)));
+
+        for _ in 0..2 {
+            let mut engine = connect(&addr).await.unwrap();
+            assert_eq!(engine.name(), Some("Remote"));
+            engine.new_game().await.unwrap();
+
+            let mut search = engine.go(None, Some(UciSearchControl::depth(1))).await.unwrap();
+            assert_eq!(search.next_info().await.unwrap()[0], UciInfoAttribute::Depth(1));
+            assert!(search.next_info().await.is_none());
+            assert!(search.await.is_ok());
+
+            engine.quit().await.unwrap();
+        }
+
+        assert_eq!(log.messages().iter().filter(|m| **m == UciMessage::Quit).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_relay_exit() {
+        let mock = mock_engine();
+        let log = mock.log();
+        let engine_addr = serve_mock(mock);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            relay_to(stream, engine_addr).await;
+
+            // The engine exits once the client hangs up
+            let (stream, _) = listener.accept().await.unwrap();
+            relay_to(stream, engine_addr).await;
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"quit\n").await.unwrap();
+        drop(TcpStream::connect(addr).await.unwrap());
+        server.await.unwrap();
+
+        assert_eq!(log.messages(), vec![UciMessage::Quit]);
+    }
+
+    #[tokio::test]
+    async fn test_remote_engine_reconnect() {
+        // Crashes on the first search, and answers the next one
+        let mock = MockEngine::new("Remote", "Tester")
+            .on(UciMessageKind::Go, MockScript::new().exit())
+            .on(UciMessageKind::Go, MockScript::new().send(parse_one("bestmove e2e4")));
+        let log = mock.log();
+        let addr = serve_mock(mock).to_string();
+
+        let mut engine = RemoteEngine::connect(Connector::new(&addr)).await.unwrap();
+        engine.set_option("Hash", Some("16")).await.unwrap();
+        engine.set_option("hash", Some("32")).await.unwrap();
+        engine.send(&UciMessage::UciNewGame).await.unwrap();
+        engine.send(&parse_one("position startpos moves e2e4")).await.unwrap();
+
+        assert!(engine.handle().go(None, Some(UciSearchControl::depth(9))).await.unwrap().await.is_err());
+
+        let lost = log.messages().len();
+        engine.reconnect().await.unwrap();
+        assert!(engine.handle().go(None, Some(UciSearchControl::depth(1))).await.unwrap().await.is_ok());
+        engine.quit().await.unwrap();
+
+        // The fresh engine is set up with the latest option value and the position
+        assert_eq!(log.messages()[lost..].to_vec(), vec![
+            UciMessage::Uci,
+            UciMessage::set_option("hash", Some(String::from("32"))),
+            parse_one("position startpos moves e2e4"),
+            UciMessage::IsReady,
+            parse_one("go depth 1"),
+            UciMessage::Quit,
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_connector_retries() {
+        // Find a free port, then only start serving on it after the first attempt has failed
+        let addr = StdTcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let connector = Connector::new(&addr.to_string()).attempts(20).delay(Duration::from_millis(50));
+        assert_eq!(connector.addr(), addr.to_string());
+        assert!(Connector::new(&addr.to_string()).connect().await.is_err());
+
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(200)).await;
+            mock_engine().serve(StdTcpListener::bind(addr).unwrap());
+        });
+
+        let engine = connector.connect().await.unwrap();
+        assert_eq!(engine.name(), Some("Remote"));
+        engine.quit().await.unwrap();
+    }
+}
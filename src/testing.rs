@@ -0,0 +1,813 @@
+//! The `testing` module contains the `MockEngine`, a scripted engine for testing GUIs and other clients without
+//! spawning a real engine, and `pipe()`, an in-memory pipe to connect it (or anything else) with.
+//!
+//! The `MockEngine` answers each message it receives according to a `MockScript`, a list of messages to send and
+//! delays to wait before sending them. It runs on its own thread and plugs into an `EngineProcess` in memory with
+//! `MockEngine::spawn()`, serves any pair of streams with `MockEngine::run(..)`, or serves TCP connections with
+//! `MockEngine::serve(..)`, for clients that need real streams.
+//!
+//! The `MockGui` is its counterpart for testing engines: it sends a scripted sequence of messages to an engine and
+//! checks that the expected replies arrive in time, as in "`readyok` within 100ms of `isready`" or "`bestmove` after
+//! `go`". It drives a `UciEngine` implementation in memory with `MockGui::run_engine(..)`, or any pair of streams with
+//! `MockGui::run(..)`.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::engine::{run as run_engine, UciEngine};
+use crate::parser::parse_one;
+use crate::process::EngineProcess;
+use crate::uci::{UciMessage, UciMessageKind, UciOptionConfig};
+
+/// The writing end of an in-memory pipe, created with `pipe()`. Writing to it fails with a `BrokenPipe` error once
+/// the `PipeReader` is dropped.
+#[derive(Debug)]
+pub struct PipeWriter {
+    sender: Sender<Vec<u8>>,
+}
+
+/// The reading end of an in-memory pipe, created with `pipe()`. Reading from it blocks until data is written, and
+/// reaches the end of the input once the `PipeWriter` is dropped.
+#[derive(Debug)]
+pub struct PipeReader {
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+/// Creates an in-memory pipe: the bytes written to the `PipeWriter` are read from the `PipeReader`. Both ends can be
+/// sent to other threads.
+pub fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = channel();
+    (PipeWriter { sender }, PipeReader { receiver, buffer: Vec::new(), position: 0 })
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.sender.send(buf.to_vec()).map_err(|_| IoError::new(ErrorKind::BrokenPipe, "the pipe is closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.position == self.buffer.len() {
+            match self.receiver.recv() {
+                Ok(data) => {
+                    self.buffer = data;
+                    self.position = 0;
+                }
+                Err(_) => return Ok(0)
+            }
+        }
+
+        let n = buf.len().min(self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+
+        Ok(n)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum MockStep {
+    Send(UciMessage),
+    SendLine(String),
+    Delay(Duration),
+    Exit,
+}
+
+/// The answer of a `MockEngine` to a message: the messages to send, in order, and the delays to wait between them.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::testing::MockScript;
+///
+/// let script = MockScript::new()
+///     .send(UciMessage::info_depth(1))
+///     .send(UciMessage::info_depth(2))
+///     .delay(Duration::from_millis(50))
+///     .send(UciMessage::best_move("e2e4".parse().unwrap()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockScript {
+    steps: Vec<MockStep>,
+}
+
+impl MockScript {
+    /// Creates an empty script, which sends nothing.
+    pub fn new() -> MockScript {
+        MockScript::default()
+    }
+
+    /// Sends the `message`.
+    pub fn send(mut self, message: UciMessage) -> MockScript {
+        self.steps.push(MockStep::Send(message));
+        self
+    }
+
+    /// Sends all of the `messages`, in order.
+    pub fn send_all<I: IntoIterator<Item=UciMessage>>(mut self, messages: I) -> MockScript {
+        self.steps.extend(messages.into_iter().map(MockStep::Send));
+        self
+    }
+
+    /// Sends the `line` as it is, such as a blank line or a malformed message.
+    pub fn send_line(mut self, line: &str) -> MockScript {
+        self.steps.push(MockStep::SendLine(line.to_string()));
+        self
+    }
+
+    /// Waits for the `duration` before going on with the script.
+    pub fn delay(mut self, duration: Duration) -> MockScript {
+        self.steps.push(MockStep::Delay(duration));
+        self
+    }
+
+    /// Stops the engine at once, as if it crashed: the rest of the script is not run and its output is closed.
+    pub fn exit(mut self) -> MockScript {
+        self.steps.push(MockStep::Exit);
+        self
+    }
+}
+
+/// The messages received by a `MockEngine`, shared with the test that drives it. Obtained with
+/// `MockEngine::log()`.
+#[derive(Clone, Debug, Default)]
+pub struct MockLog {
+    messages: Arc<Mutex<Vec<UciMessage>>>,
+}
+
+impl MockLog {
+    /// Returns a copy of the messages received so far, in order.
+    pub fn messages(&self) -> Vec<UciMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    fn push(&self, message: UciMessage) {
+        self.messages.lock().unwrap().push(message);
+    }
+}
+
+/// A scripted engine. Any message can be answered with a `MockScript`, registered for its kind with `on(..)`; if
+/// several scripts are registered for the same kind, they answer the messages of that kind in turn, the last one
+/// answering all the remaining ones.
+///
+/// Without a script, `uci` is answered with the `id` and `option` messages and `uciok`, `isready` with `readyok`, and
+/// the other messages are not answered. The engine stops on `quit` (after running its script, if any), when its
+/// input is closed or when a script exits. Every message it receives is added to its `log()`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use vampirc_uci::{UciMessage, UciMessageKind};
+/// use vampirc_uci::testing::{MockEngine, MockScript};
+///
+/// let mock = MockEngine::new("Mock", "Vampirc")
+///     .on(UciMessageKind::Go, MockScript::new()
+///         .send(UciMessage::info_depth(1))
+///         .delay(Duration::from_millis(50))
+///         .send(UciMessage::best_move("e2e4".parse().unwrap())));
+/// let log = mock.log();
+///
+/// let mut engine = mock.spawn();
+/// engine.handshake().unwrap();
+/// engine.send(&UciMessage::go()).unwrap();
+///
+/// let messages = engine.recv_until(|m| matches!(m, UciMessage::BestMove { .. })).unwrap();
+/// assert_eq!(messages.len(), 2);
+///
+/// engine.quit().unwrap();
+/// assert_eq!(log.messages(), vec![UciMessage::Uci, UciMessage::go(), UciMessage::Quit]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockEngine {
+    name: String,
+    author: String,
+    options: Vec<UciOptionConfig>,
+    scripts: HashMap<UciMessageKind, Vec<MockScript>>,
+    log: MockLog,
+}
+
+impl MockEngine {
+    /// Creates an engine that identifies itself with the `name` and the `author`.
+    pub fn new(name: &str, author: &str) -> MockEngine {
+        MockEngine {
+            name: name.to_string(),
+            author: author.to_string(),
+            options: Vec::new(),
+            scripts: HashMap::new(),
+            log: MockLog::default(),
+        }
+    }
+
+    /// Adds an option to the ones the engine advertises in its handshake.
+    pub fn option(mut self, config: UciOptionConfig) -> MockEngine {
+        self.options.push(config);
+        self
+    }
+
+    /// Answers the messages of the `kind` with the `script`, after the scripts already registered for the kind.
+    pub fn on(mut self, kind: UciMessageKind, script: MockScript) -> MockEngine {
+        self.scripts.entry(kind).or_default().push(script);
+        self
+    }
+
+    /// Returns the log of the messages the engine receives.
+    pub fn log(&self) -> MockLog {
+        self.log.clone()
+    }
+
+    /// Runs the engine on another thread, connected with in-memory pipes to the returned `EngineProcess`.
+    pub fn spawn(self) -> EngineProcess {
+        let (input, output) = self.spawn_pipes();
+        EngineProcess::from_streams(input, output)
+    }
+
+    /// Runs the engine on another thread and returns the in-memory pipes connected to it: the engine's input, to write
+    /// the messages to, and its output, to read its answers from. For clients that take streams rather than an
+    /// `EngineProcess`.
+    pub fn spawn_pipes(self) -> (PipeWriter, PipeReader) {
+        let (input, engine_input) = pipe();
+        let (engine_output, output) = pipe();
+
+        thread::spawn(move || {
+            let _ = self.run(BufReader::new(engine_input), engine_output);
+        });
+
+        (input, output)
+    }
+
+    /// Runs the engine on another thread, answering the connections accepted on the `listener` one after another, as
+    /// if each were to a freshly started engine, except that the scripts registered for a kind take their turns across
+    /// all the connections. This way, for example, an engine can crash on its first search and answer the next one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, BufReader, Write};
+    /// use std::net::{TcpListener, TcpStream};
+    /// use vampirc_uci::testing::MockEngine;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let addr = listener.local_addr().unwrap();
+    /// MockEngine::new("Mock", "Vampirc").serve(listener);
+    ///
+    /// let mut stream = TcpStream::connect(addr).unwrap();
+    /// stream.write_all(b"isready\n").unwrap();
+    /// let mut line = String::new();
+    /// BufReader::new(stream).read_line(&mut line).unwrap();
+    /// assert_eq!(line, "readyok\n");
+    /// ```
+    pub fn serve(self, listener: TcpListener) {
+        thread::spawn(move || {
+            let mut answered = HashMap::new();
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                if let Ok(input) = stream.try_clone() {
+                    let _ = self.run_with(BufReader::new(input), stream, &mut answered);
+                }
+            }
+        });
+    }
+
+    /// Reads messages from the `input` and answers them on the `output`, until `quit` is received, the `input` is
+    /// closed or a script exits.
+    pub fn run<R: BufRead, W: Write>(self, input: R, output: W) -> IoResult<()> {
+        self.run_with(input, output, &mut HashMap::new())
+    }
+
+    /// Like `run(..)`, but counts the messages of each kind answered so far in `answered`.
+    fn run_with<R: BufRead, W: Write>(&self, input: R, mut output: W, answered: &mut HashMap<UciMessageKind, usize>)
+                                      -> IoResult<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let message = parse_one(&line);
+            let kind = message.kind();
+            self.log.push(message);
+
+            let script = match self.scripts.get(&kind) {
+                Some(scripts) => {
+                    let count = answered.entry(kind).or_insert(0);
+                    let script = scripts[(*count).min(scripts.len() - 1)].clone();
+                    *count += 1;
+                    script
+                }
+                None => self.default_script(kind)
+            };
+
+            for step in script.steps {
+                match step {
+                    MockStep::Send(m) => {
+                        writeln!(output, "{}", m.serialize_canonical())?;
+                        output.flush()?;
+                    }
+                    MockStep::SendLine(line) => {
+                        writeln!(output, "{}", line)?;
+                        output.flush()?;
+                    }
+                    MockStep::Delay(d) => thread::sleep(d),
+                    MockStep::Exit => return Ok(()),
+                }
+            }
+
+            if kind == UciMessageKind::Quit {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn default_script(&self, kind: UciMessageKind) -> MockScript {
+        match kind {
+            UciMessageKind::Uci => MockScript::new()
+                .send(UciMessage::id_name(&self.name))
+                .send(UciMessage::id_author(&self.author))
+                .send_all(self.options.iter().cloned().map(UciMessage::Option))
+                .send(UciMessage::UciOk),
+            UciMessageKind::IsReady => MockScript::new().send(UciMessage::ReadyOk),
+            _ => MockScript::new()
+        }
+    }
+}
+
+enum GuiStep {
+    Send(UciMessage),
+    Expect {
+        description: String,
+        predicate: Box<dyn Fn(&UciMessage) -> bool + Send>,
+        timeout: Duration,
+    },
+    Delay(Duration),
+}
+
+/// An error describing why a `MockGui` script failed.
+#[derive(Debug)]
+pub enum MockGuiError {
+    /// The expected message did not arrive in time.
+    Timeout {
+        /// The description of the expected message.
+        expected: String,
+
+        /// The messages received while waiting for it.
+        received: Vec<UciMessage>,
+    },
+
+    /// The engine closed its output before the expected message arrived.
+    Closed {
+        /// The description of the expected message.
+        expected: String,
+
+        /// The messages received while waiting for it.
+        received: Vec<UciMessage>,
+    },
+
+    /// The messages could not be sent to the engine.
+    Io(IoError),
+}
+
+impl Display for MockGuiError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            MockGuiError::Timeout { expected, received } => {
+                write!(f, "timed out waiting for {} (received {} other messages)", expected, received.len())
+            }
+            MockGuiError::Closed { expected, .. } => {
+                write!(f, "the engine closed its output while waiting for {}", expected)
+            }
+            MockGuiError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl StdError for MockGuiError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            MockGuiError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<IoError> for MockGuiError {
+    fn from(e: IoError) -> Self {
+        MockGuiError::Io(e)
+    }
+}
+
+/// A scripted GUI, which sends messages to an engine and waits for its replies. Each expectation receives messages
+/// until one matches it, failing if none does within its timeout; the messages received in the meantime are
+/// skipped. All of the received messages are returned once the script completes.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Result as IoResult;
+/// use std::time::Duration;
+/// use vampirc_uci::{UciMessage, UciMessageKind, UciSearchControl, UciTimeControl};
+/// use vampirc_uci::engine::{EngineOutput, UciEngine};
+/// use vampirc_uci::testing::MockGui;
+///
+/// struct Engine;
+///
+/// impl UciEngine for Engine {
+///     fn name(&self) -> String {
+///         String::from("Engine")
+///     }
+///
+///     fn author(&self) -> String {
+///         String::from("Vampirc")
+///     }
+///
+///     fn on_go(&mut self, _: Option<&UciTimeControl>, _: Option<&UciSearchControl>, output: &EngineOutput) -> IoResult<()> {
+///         output.send(&UciMessage::best_move("e2e4".parse().unwrap()))
+///     }
+/// }
+///
+/// let timeout = Duration::from_millis(500);
+/// let received = MockGui::new()
+///     .send(UciMessage::Uci)
+///     .expect(UciMessageKind::UciOk, timeout)
+///     .send(UciMessage::IsReady)
+///     .expect(UciMessageKind::ReadyOk, timeout)
+///     .send(UciMessage::go())
+///     .expect(UciMessageKind::BestMove, timeout)
+///     .run_engine(Engine)
+///     .unwrap();
+///
+/// assert_eq!(received.len(), 5);
+/// ```
+#[derive(Default)]
+pub struct MockGui {
+    steps: Vec<GuiStep>,
+}
+
+impl MockGui {
+    /// Creates an empty script.
+    pub fn new() -> MockGui {
+        MockGui::default()
+    }
+
+    /// Sends the `message` to the engine.
+    pub fn send(mut self, message: UciMessage) -> MockGui {
+        self.steps.push(GuiStep::Send(message));
+        self
+    }
+
+    /// Waits for at most the `timeout` for a message of the `kind`.
+    pub fn expect(self, kind: UciMessageKind, timeout: Duration) -> MockGui {
+        self.expect_that(&format!("{:?}", kind), move |m| m.kind() == kind, timeout)
+    }
+
+    /// Waits for at most the `timeout` for a message that satisfies the `predicate`. The `description` of the
+    /// expected message is used in the error if it does not arrive.
+    pub fn expect_that<F>(mut self, description: &str, predicate: F, timeout: Duration) -> MockGui
+        where F: Fn(&UciMessage) -> bool + Send + 'static {
+        self.steps.push(GuiStep::Expect { description: description.to_string(), predicate: Box::new(predicate), timeout });
+        self
+    }
+
+    /// Waits for the `duration` before going on with the script.
+    pub fn delay(mut self, duration: Duration) -> MockGui {
+        self.steps.push(GuiStep::Delay(duration));
+        self
+    }
+
+    /// Runs the `engine` on another thread, connected with in-memory pipes, and runs the script against it. Once the
+    /// script completes, the engine's input is closed, which ends its `run` loop.
+    pub fn run_engine<E: UciEngine + Send + 'static>(self, mut engine: E) -> Result<Vec<UciMessage>, MockGuiError> {
+        let (input, engine_input) = pipe();
+        let (engine_output, output) = pipe();
+
+        thread::spawn(move || {
+            let _ = run_engine(&mut engine, BufReader::new(engine_input), engine_output);
+        });
+
+        self.run(input, output)
+    }
+
+    /// Runs the script, writing the messages to the engine's `input` and reading its replies from its `output`.
+    /// Returns all of the messages received from the engine by the time the script completes.
+    pub fn run<W: Write, R: Read + Send + 'static>(self, mut input: W, output: R) -> Result<Vec<UciMessage>, MockGuiError> {
+        // Read on another thread, so the waits can be bounded
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => if sender.send(parse_one(&line)).is_err() {
+                        break;
+                    },
+                    Err(_) => break
+                }
+            }
+        });
+
+        let mut received = Vec::new();
+        for step in self.steps {
+            match step {
+                GuiStep::Send(m) => {
+                    writeln!(input, "{}", m.serialize_canonical())?;
+                    input.flush()?;
+                }
+                GuiStep::Expect { description, predicate, timeout } => {
+                    let deadline = Instant::now() + timeout;
+                    let mut skipped = Vec::new();
+                    loop {
+                        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                            Ok(m) => {
+                                let done = predicate(&m);
+                                received.push(m.clone());
+                                if done {
+                                    break;
+                                }
+                                skipped.push(m);
+                            }
+                            Err(RecvTimeoutError::Timeout) => {
+                                return Err(MockGuiError::Timeout { expected: description, received: skipped });
+                            }
+                            Err(RecvTimeoutError::Disconnected) => {
+                                return Err(MockGuiError::Closed { expected: description, received: skipped });
+                            }
+                        }
+                    }
+                }
+                GuiStep::Delay(d) => thread::sleep(d)
+            }
+        }
+
+        received.extend(receiver.try_iter());
+        Ok(received)
+    }
+}
+
+/// A `Write` whose contents can be inspected after it has been moved into, say, an `EngineOutput`. Shared by the unit
+/// tests of the crate.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl SharedBuffer {
+    /// Returns what has been written so far.
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::EngineOutput;
+    use crate::uci::{UciSearchControl, UciTimeControl};
+
+    use super::*;
+
+    fn best_move(m: &str) -> UciMessage {
+        UciMessage::best_move(m.parse().unwrap())
+    }
+
+    #[test]
+    fn test_pipe() {
+        let (mut writer, reader) = pipe();
+        let handle = thread::spawn(move || {
+            let mut text = String::new();
+            BufReader::new(reader).read_to_string(&mut text).unwrap();
+            text
+        });
+
+        writer.write_all(b"uci\n").unwrap();
+        writer.write_all(b"isready\n").unwrap();
+        drop(writer);
+
+        assert_eq!(handle.join().unwrap(), "uci\nisready\n");
+
+        let (mut writer, reader) = pipe();
+        drop(reader);
+        assert_eq!(writer.write(b"uci\n").unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_mock_engine_defaults() {
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .option(UciOptionConfig::Check { name: String::from("Ponder"), default: Some(false) });
+        let mut engine = mock.spawn();
+
+        let handshake = engine.handshake().unwrap();
+        assert_eq!(handshake, vec![
+            UciMessage::id_name("Mock"),
+            UciMessage::id_author("Vampirc"),
+            UciMessage::Option(UciOptionConfig::Check { name: String::from("Ponder"), default: Some(false) }),
+            UciMessage::UciOk,
+        ]);
+        engine.is_ready().unwrap();
+
+        assert!(engine.child().is_none());
+        assert!(engine.quit().unwrap().success());
+    }
+
+    #[test]
+    fn test_mock_engine_scripts() {
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .on(UciMessageKind::Go, MockScript::new()
+                .send_all(vec![UciMessage::info_depth(1), UciMessage::info_depth(2)])
+                .delay(Duration::from_millis(50))
+                .send(best_move("e2e4")))
+            .on(UciMessageKind::Go, MockScript::new().send(best_move("d2d4")));
+        let log = mock.log();
+        let mut engine = mock.spawn();
+
+        let started = Instant::now();
+        engine.send(&UciMessage::go()).unwrap();
+        let messages = engine.recv_until(|m| matches!(m, UciMessage::BestMove { .. })).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(messages, vec![UciMessage::info_depth(1), UciMessage::info_depth(2), best_move("e2e4")]);
+
+        for _ in 0..2 {
+            engine.send(&UciMessage::go()).unwrap();
+            assert_eq!(engine.recv().unwrap(), best_move("d2d4"));
+        }
+
+        engine.shutdown(Duration::from_secs(1)).unwrap();
+        assert_eq!(log.messages().len(), 4);
+        assert_eq!(log.messages()[3], UciMessage::Quit);
+    }
+
+    #[test]
+    fn test_mock_engine_run() {
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .on(UciMessageKind::Stop, MockScript::new().send(best_move("e2e4")));
+
+        let mut output = Vec::new();
+        mock.run("isready\n\ngo infinite\nstop\nquit\nisready\n".as_bytes(), &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "readyok\nbestmove e2e4\n");
+
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .on(UciMessageKind::Go, MockScript::new().send_line("").send_line("bestmove e9e4").exit().send(best_move("e2e4")));
+
+        let mut output = Vec::new();
+        mock.run("go\nisready\n".as_bytes(), &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "\nbestmove e9e4\n");
+    }
+
+    #[test]
+    fn test_mock_engine_serve() {
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .on(UciMessageKind::Go, MockScript::new().exit())
+            .on(UciMessageKind::Go, MockScript::new().send(best_move("e2e4")));
+        let log = mock.log();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        mock.serve(listener);
+
+        let connect = || {
+            let stream = std::net::TcpStream::connect(addr).unwrap();
+            EngineProcess::from_streams(stream.try_clone().unwrap(), stream)
+        };
+
+        // The first connection crashes on go, and the next one answers it
+        let mut engine = connect();
+        engine.send(&UciMessage::go()).unwrap();
+        assert_eq!(engine.recv().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        let mut engine = connect();
+        assert_eq!(engine.handshake().unwrap().len(), 3);
+        engine.send(&UciMessage::go()).unwrap();
+        assert_eq!(engine.recv().unwrap(), best_move("e2e4"));
+        engine.quit().unwrap();
+
+        assert_eq!(log.messages(), vec![UciMessage::go(), UciMessage::Uci, UciMessage::go(), UciMessage::Quit]);
+    }
+
+    /// Answers `go` with a `bestmove` after the `delay`, or not at all if there is none.
+    struct SlowEngine {
+        delay: Option<Duration>,
+    }
+
+    impl UciEngine for SlowEngine {
+        fn name(&self) -> String {
+            String::from("Slow")
+        }
+
+        fn author(&self) -> String {
+            String::from("Vampirc")
+        }
+
+        fn on_go(&mut self, _: Option<&UciTimeControl>, _: Option<&UciSearchControl>, output: &EngineOutput) -> IoResult<()> {
+            if let Some(delay) = self.delay {
+                output.send(&UciMessage::info_depth(1))?;
+                thread::sleep(delay);
+                output.send(&best_move("e2e4"))?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mock_gui() {
+        let received = MockGui::new()
+            .send(UciMessage::Uci)
+            .expect_that("id name", |m| *m == UciMessage::id_name("Slow"), Duration::from_secs(1))
+            .expect(UciMessageKind::UciOk, Duration::from_secs(1))
+            .send(UciMessage::go())
+            .expect(UciMessageKind::BestMove, Duration::from_secs(1))
+            .run_engine(SlowEngine { delay: Some(Duration::from_millis(10)) })
+            .unwrap();
+
+        assert_eq!(received, vec![
+            UciMessage::id_name("Slow"),
+            UciMessage::id_author("Vampirc"),
+            UciMessage::UciOk,
+            UciMessage::info_depth(1),
+            best_move("e2e4"),
+        ]);
+    }
+
+    #[test]
+    fn test_mock_gui_timeout() {
+        let err = MockGui::new()
+            .send(UciMessage::go())
+            .expect(UciMessageKind::BestMove, Duration::from_millis(50))
+            .run_engine(SlowEngine { delay: Some(Duration::from_secs(1)) })
+            .unwrap_err();
+
+        match err {
+            MockGuiError::Timeout { expected, received } => {
+                assert_eq!(expected, "BestMove");
+                assert_eq!(received, vec![UciMessage::info_depth(1)]);
+            }
+            e => panic!("unexpected error: {}", e)
+        }
+    }
+
+    #[test]
+    fn test_mock_gui_closed() {
+        let err = MockGui::new()
+            .send(UciMessage::go())
+            .send(UciMessage::Quit)
+            .expect(UciMessageKind::BestMove, Duration::from_secs(1))
+            .run_engine(SlowEngine { delay: None })
+            .unwrap_err();
+
+        assert!(matches!(err, MockGuiError::Closed { .. }));
+        assert_eq!(err.to_string(), "the engine closed its output while waiting for BestMove");
+    }
+
+    #[test]
+    fn test_mock_gui_against_mock_engine() {
+        let (input, engine_input) = pipe();
+        let (engine_output, output) = pipe();
+        let mock = MockEngine::new("Mock", "Vampirc")
+            .on(UciMessageKind::Go, MockScript::new().delay(Duration::from_millis(20)).send(best_move("e2e4")));
+        thread::spawn(move || mock.run(BufReader::new(engine_input), engine_output));
+
+        let received = MockGui::new()
+            .send(UciMessage::IsReady)
+            .expect(UciMessageKind::ReadyOk, Duration::from_secs(1))
+            .send(UciMessage::go())
+            .expect(UciMessageKind::BestMove, Duration::from_secs(1))
+            .run(input, output)
+            .unwrap();
+
+        assert_eq!(received, vec![UciMessage::ReadyOk, best_move("e2e4")]);
+    }
+}
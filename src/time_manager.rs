@@ -0,0 +1,276 @@
+//! The `time_manager` module contains the `TimeManager`, which converts the time control of a `go` message into the
+//! time an engine should spend on the move.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::uci::UciTimeControl;
+
+/// The side to move, whose clock is used.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Side {
+    /// White to move.
+    White,
+
+    /// Black to move.
+    Black,
+}
+
+/// How the `TimeManager` divides the remaining time among the remaining moves. Whenever the `go` message specifies
+/// `movestogo`, it takes precedence over the number of moves estimated by the strategy.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum AllocationStrategy {
+    /// Assumes a fixed number of moves remain in the game and divides the remaining time evenly among them.
+    Classical {
+        /// The number of moves assumed to remain.
+        moves_to_go: u32,
+    },
+
+    /// Spends a fixed percentage of the remaining time on each move.
+    Percentage {
+        /// The percentage of the remaining time, between 1 and 100.
+        percent: u32,
+    },
+
+    /// Estimates the number of moves remaining from the move number: many at the start of the game, fewer later on,
+    /// but never fewer than `min_moves_to_go`.
+    MoveNumber {
+        /// The number of moves assumed to remain at the start of the game.
+        initial_moves_to_go: u32,
+
+        /// The number of moves assumed to remain, at least.
+        min_moves_to_go: u32,
+    },
+}
+
+impl Default for AllocationStrategy {
+    /// `Classical`, assuming 30 moves to go.
+    fn default() -> Self {
+        AllocationStrategy::Classical { moves_to_go: 30 }
+    }
+}
+
+/// The time to spend on a move.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct TimeBudget {
+    /// The time after which the engine should not start a new iteration of its search.
+    pub soft: Duration,
+
+    /// The time after which the engine must stop searching and send `bestmove`.
+    pub hard: Duration,
+}
+
+/// Converts a `UciTimeControl` into a `TimeBudget`.
+///
+/// The budget is computed from the side's remaining time, less the `move_overhead` that accounts for the latency
+/// between the engine and the GUI. Three quarters of the increment are added on top, unless the remaining time is
+/// less than the increment. When time is short, neither limit exceeds half of the remaining time (or nine tenths on
+/// the last move before the time control), and the soft limit is at least `min_time`, as long as there is that much
+/// time left.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use vampirc_uci::parse_one;
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::time_manager::{Side, TimeManager};
+///
+/// let tm = TimeManager::default();
+///
+/// if let UciMessage::Go { time_control: Some(tc), .. } = parse_one("go wtime 60050 btime 60000 winc 1000 binc 1000") {
+///     let budget = tm.budget(&tc, Side::White, 1).unwrap();
+///     assert_eq!(budget.soft, Duration::from_millis(2750));
+///     assert_eq!(budget.hard, Duration::from_millis(8250));
+/// }
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct TimeManager {
+    /// The allocation strategy.
+    pub strategy: AllocationStrategy,
+
+    /// The time reserved on every move for the communication with the GUI.
+    pub move_overhead: Duration,
+
+    /// The least time to spend on a move, time permitting.
+    pub min_time: Duration,
+
+    /// The hard limit, as a percentage of the soft limit.
+    pub hard_percent: u32,
+}
+
+impl Default for TimeManager {
+    /// The default strategy, a move overhead of 50 ms, a minimal time of 10 ms and a hard limit of three times the
+    /// soft limit.
+    fn default() -> Self {
+        TimeManager {
+            strategy: AllocationStrategy::default(),
+            move_overhead: Duration::from_millis(50),
+            min_time: Duration::from_millis(10),
+            hard_percent: 300,
+        }
+    }
+}
+
+impl TimeManager {
+    /// Creates a `TimeManager` with the specified strategy and the default settings otherwise.
+    pub fn new(strategy: AllocationStrategy) -> TimeManager {
+        TimeManager {
+            strategy,
+            ..TimeManager::default()
+        }
+    }
+
+    /// Returns the time to spend on the move for the `side` to move at the full move number `move_number` (starting
+    /// at 1). Returns `None` if the search is not limited by time (`infinite`, `ponder`) or if the side's time is not
+    /// specified.
+    pub fn budget(&self, time_control: &UciTimeControl, side: Side, move_number: u32) -> Option<TimeBudget> {
+        match time_control {
+            UciTimeControl::MoveTime(t) => {
+                let t = to_millis(t).saturating_sub(millis(self.move_overhead)).max(millis(self.min_time));
+                Some(TimeBudget {
+                    soft: Duration::from_millis(t),
+                    hard: Duration::from_millis(t),
+                })
+            }
+            UciTimeControl::TimeLeft { white_time, black_time, white_increment, black_increment, moves_to_go } => {
+                let (time, increment) = match side {
+                    Side::White => (white_time.as_ref()?, white_increment.as_ref()),
+                    Side::Black => (black_time.as_ref()?, black_increment.as_ref()),
+                };
+
+                let remaining = to_millis(time).saturating_sub(millis(self.move_overhead));
+                let increment = increment.map_or(0, to_millis);
+                let moves_to_go = moves_to_go.map(|m| u64::from(m.max(1)));
+
+                let mut soft = match self.strategy {
+                    AllocationStrategy::Classical { moves_to_go: expected } => {
+                        remaining / moves_to_go.unwrap_or(u64::from(expected.max(1)))
+                    }
+                    AllocationStrategy::Percentage { percent } => {
+                        let share = scale(remaining, u64::from(percent.clamp(1, 100)), 100);
+                        moves_to_go.map_or(share, |m| share.max(remaining / m))
+                    }
+                    AllocationStrategy::MoveNumber { initial_moves_to_go, min_moves_to_go } => {
+                        let expected = initial_moves_to_go.saturating_sub(move_number.saturating_sub(1) / 2)
+                            .max(min_moves_to_go)
+                            .max(1);
+                        remaining / moves_to_go.map_or(u64::from(expected), |m| m.min(u64::from(expected)))
+                    }
+                };
+
+                if remaining > increment {
+                    soft = soft.saturating_add(scale(increment, 3, 4));
+                }
+
+                let cap = if moves_to_go == Some(1) { scale(remaining, 9, 10) } else { remaining / 2 };
+                soft = soft.max(millis(self.min_time)).min(cap);
+                let hard = scale(soft, u64::from(self.hard_percent.max(100)), 100).min(cap).max(soft);
+
+                Some(TimeBudget {
+                    soft: Duration::from_millis(soft),
+                    hard: Duration::from_millis(hard),
+                })
+            }
+            UciTimeControl::Infinite | UciTimeControl::Ponder => None,
+        }
+    }
+}
+
+/// Returns `value * numerator / denominator`, computed without overflowing and saturating at `u64::MAX`.
+fn scale(value: u64, numerator: u64, denominator: u64) -> u64 {
+    u64::try_from(u128::from(value) * u128::from(numerator) / u128::from(denominator)).unwrap_or(u64::MAX)
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_millis() as u64
+}
+
+fn to_millis(d: &chrono::Duration) -> u64 {
+    d.num_milliseconds().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration as ChronoDuration;
+
+    use crate::parser::parse_one;
+    use crate::uci::UciMessage;
+
+    use super::*;
+
+    fn time_left(time: i64, inc: i64, moves_to_go: Option<u8>) -> UciTimeControl {
+        UciTimeControl::TimeLeft {
+            white_time: Some(ChronoDuration::milliseconds(time)),
+            black_time: Some(ChronoDuration::milliseconds(time)),
+            white_increment: Some(ChronoDuration::milliseconds(inc)),
+            black_increment: Some(ChronoDuration::milliseconds(inc)),
+            moves_to_go,
+        }
+    }
+
+    fn soft_hard(budget: Option<TimeBudget>) -> (u64, u64) {
+        let b = budget.unwrap();
+        (millis(b.soft), millis(b.hard))
+    }
+
+    #[test]
+    fn test_strategies() {
+        let tc = time_left(100_050, 0, None);
+
+        let classical = TimeManager::new(AllocationStrategy::Classical { moves_to_go: 40 });
+        assert_eq!(soft_hard(classical.budget(&tc, Side::Black, 10)), (2500, 7500));
+
+        let percentage = TimeManager::new(AllocationStrategy::Percentage { percent: 5 });
+        assert_eq!(soft_hard(percentage.budget(&tc, Side::Black, 10)), (5000, 15000));
+
+        let move_number = TimeManager::new(AllocationStrategy::MoveNumber { initial_moves_to_go: 50, min_moves_to_go: 20 });
+        assert_eq!(soft_hard(move_number.budget(&tc, Side::White, 1)), (2000, 6000));
+        assert_eq!(soft_hard(move_number.budget(&tc, Side::White, 81)), (5000, 15000));
+
+        // The explicit movestogo takes precedence
+        let tc = time_left(100_050, 0, Some(10));
+        assert_eq!(soft_hard(classical.budget(&tc, Side::Black, 10)), (10000, 30000));
+    }
+
+    #[test]
+    fn test_low_time() {
+        let tm = TimeManager::default();
+
+        // Increment-only: the increment is not relied on while the clock is below it
+        assert_eq!(soft_hard(tm.budget(&time_left(250, 1000, None), Side::White, 30)), (10, 30));
+
+        // The last move before the time control may use most of the remaining time
+        assert_eq!(soft_hard(tm.budget(&time_left(1050, 0, Some(1)), Side::White, 40)), (900, 900));
+
+        // Out of time
+        assert_eq!(soft_hard(tm.budget(&time_left(20, 0, None), Side::White, 40)), (0, 0));
+    }
+
+    #[test]
+    fn test_huge_clock() {
+        let tc = match parse_one("go wtime 9223372036854775807 btime 1000 winc 9223372036854775807\n") {
+            UciMessage::Go { time_control: Some(tc), .. } => tc,
+            m => panic!("unexpected message: {:?}", m),
+        };
+
+        for tm in [TimeManager::default(), TimeManager::new(AllocationStrategy::Percentage { percent: 100 })] {
+            let (soft, hard) = soft_hard(tm.budget(&tc, Side::White, 1));
+            assert!(soft > 0 && soft <= hard && hard <= i64::MAX as u64 / 2, "{} {}", soft, hard);
+            assert!(tm.budget(&tc, Side::Black, 1).unwrap().hard < Duration::from_millis(1000));
+        }
+
+        let (soft, hard) = soft_hard(TimeManager::default().budget(&time_left(i64::MAX, 0, Some(1)), Side::White, 1));
+        assert_eq!((soft, hard), (8301034833169298181, 8301034833169298181));
+    }
+
+    #[test]
+    fn test_other_time_controls() {
+        let tm = TimeManager::default();
+        let movetime = UciTimeControl::MoveTime(ChronoDuration::milliseconds(1000));
+
+        assert_eq!(soft_hard(tm.budget(&movetime, Side::White, 1)), (950, 950));
+        assert_eq!(tm.budget(&UciTimeControl::Infinite, Side::White, 1), None);
+        assert_eq!(tm.budget(&UciTimeControl::time_left(), Side::White, 1), None);
+    }
+}
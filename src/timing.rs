@@ -0,0 +1,314 @@
+//! The `timing` module contains the `TimingAnalyzer`, which measures the latencies of the exchanges between a GUI and
+//! an engine: from `go` to the first `info` and to the `bestmove`, from `isready` to `readyok` and from `uci` to
+//! `uciok`, along with the drift between the search time the engine reports in `info time` and the wall clock. The
+//! measurements are collected in a `TimingReport`.
+//!
+//! The analyzer works on a recorded transcript (see the `record` module) or on a live session, as a `Middleware` of a
+//! `UciProxy`. A stall in the protocol, such as an engine that is slow to answer `isready` while it loads its
+//! tablebases, shows up as an outlier in the report.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::Duration as ChronoDuration;
+
+use crate::proxy::{Middleware, ProxyContext};
+use crate::record::RecordedMessage;
+use crate::uci::{CommunicationDirection, UciInfoAttribute, UciMessage};
+
+/// The timing of a search, from the `go` that started it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SearchTiming {
+    /// When the `go` was sent, since the start of the session.
+    pub started: Duration,
+
+    /// The time from the `go` to the first `info`, or `None` if the engine sent none.
+    pub first_info: Option<Duration>,
+
+    /// The time from the `go` to the `bestmove`, or `None` if the search has not ended.
+    pub best_move: Option<Duration>,
+
+    /// The number of `info` messages the engine sent during the search.
+    pub infos: usize,
+
+    /// The largest difference between the wall-clock time elapsed since the `go` and the search time the engine
+    /// reported in an `info time` attribute, positive if the engine's clock lags behind the wall clock. `None` if the
+    /// engine did not report the search time.
+    pub time_drift: Option<ChronoDuration>,
+}
+
+/// The summary of a set of durations.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct TimingStats {
+    /// The number of durations.
+    pub count: usize,
+
+    /// The shortest duration.
+    pub min: Duration,
+
+    /// The median duration (the lower of the two middle ones, for an even count).
+    pub median: Duration,
+
+    /// The mean duration.
+    pub mean: Duration,
+
+    /// The longest duration.
+    pub max: Duration,
+}
+
+impl TimingStats {
+    /// Summarizes the `durations`, or returns `None` if there are none.
+    pub fn from_durations<I: IntoIterator<Item=Duration>>(durations: I) -> Option<TimingStats> {
+        let mut durations: Vec<Duration> = durations.into_iter().collect();
+        if durations.is_empty() {
+            return None;
+        }
+
+        durations.sort();
+        let count = durations.len();
+        let total: Duration = durations.iter().sum();
+
+        Some(TimingStats {
+            count,
+            min: durations[0],
+            median: durations[(count - 1) / 2],
+            mean: total / count as u32,
+            max: durations[count - 1],
+        })
+    }
+}
+
+/// The timings measured by a `TimingAnalyzer`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct TimingReport {
+    /// The time from `uci` to `uciok`, or `None` if the handshake was not seen to complete.
+    pub handshake: Option<Duration>,
+
+    /// The times from each `isready` to its `readyok`, in order.
+    pub ready: Vec<Duration>,
+
+    /// The searches, in order.
+    pub searches: Vec<SearchTiming>,
+}
+
+impl TimingReport {
+    /// Summarizes the times from `go` to the first `info`.
+    pub fn first_info_stats(&self) -> Option<TimingStats> {
+        TimingStats::from_durations(self.searches.iter().filter_map(|s| s.first_info))
+    }
+
+    /// Summarizes the times from `go` to `bestmove`, for the searches that ended.
+    pub fn best_move_stats(&self) -> Option<TimingStats> {
+        TimingStats::from_durations(self.searches.iter().filter_map(|s| s.best_move))
+    }
+
+    /// Summarizes the times from `isready` to `readyok`.
+    pub fn ready_stats(&self) -> Option<TimingStats> {
+        TimingStats::from_durations(self.ready.iter().copied())
+    }
+
+    /// Returns the largest drift (by magnitude) between the reported search time and the wall clock, over all of the
+    /// searches.
+    pub fn max_time_drift(&self) -> Option<ChronoDuration> {
+        self.searches.iter().filter_map(|s| s.time_drift).max_by_key(|d| d.abs())
+    }
+}
+
+/// Measures the latencies of the exchanges between a GUI and an engine, see the `timing` module.
+///
+/// Feed it the messages in the order they were exchanged, with `record_at(..)` for a transcript (or `analyze(..)`
+/// for a whole one) or with `record(..)` as they happen, and take the `report()` at any time.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use vampirc_uci::{parse_one, CommunicationDirection};
+/// use vampirc_uci::timing::TimingAnalyzer;
+///
+/// let mut analyzer = TimingAnalyzer::new();
+/// for (ms, direction, line) in &[
+///     (0, CommunicationDirection::GuiToEngine, "isready"),
+///     (40, CommunicationDirection::EngineToGui, "readyok"),
+///     (50, CommunicationDirection::GuiToEngine, "go movetime 1000"),
+///     (55, CommunicationDirection::EngineToGui, "info depth 1 time 2"),
+///     (1060, CommunicationDirection::EngineToGui, "bestmove e2e4"),
+/// ] {
+///     analyzer.record_at(Duration::from_millis(*ms), *direction, &parse_one(line));
+/// }
+///
+/// let report = analyzer.report();
+/// assert_eq!(report.ready, vec![Duration::from_millis(40)]);
+/// assert_eq!(report.searches[0].first_info, Some(Duration::from_millis(5)));
+/// assert_eq!(report.searches[0].best_move, Some(Duration::from_millis(1010)));
+/// assert_eq!(report.max_time_drift(), Some(vampirc_uci::Duration::milliseconds(3)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimingAnalyzer {
+    started: Instant,
+    uci_at: Option<Duration>,
+    ready_at: VecDeque<Duration>,
+    searching: bool,
+    report: TimingReport,
+}
+
+impl Default for TimingAnalyzer {
+    fn default() -> Self {
+        TimingAnalyzer::new()
+    }
+}
+
+impl TimingAnalyzer {
+    /// Creates an analyzer with nothing measured. The clock used by `record(..)` starts now.
+    pub fn new() -> TimingAnalyzer {
+        TimingAnalyzer {
+            started: Instant::now(),
+            uci_at: None,
+            ready_at: VecDeque::new(),
+            searching: false,
+            report: TimingReport::default(),
+        }
+    }
+
+    /// Measures all of the messages of a recorded transcript and returns the report.
+    pub fn analyze<'a, I: IntoIterator<Item=&'a RecordedMessage>>(transcript: I) -> TimingReport {
+        let mut analyzer = TimingAnalyzer::new();
+        for m in transcript {
+            analyzer.record_at(m.elapsed, m.direction, &m.message);
+        }
+
+        analyzer.report
+    }
+
+    /// Records a `message` flowing in the `direction` now.
+    pub fn record(&mut self, direction: CommunicationDirection, message: &UciMessage) {
+        self.record_at(self.started.elapsed(), direction, message);
+    }
+
+    /// Records a `message` flowing in the `direction`, with the specified time `elapsed` since the start of the
+    /// session. The times must not decrease from one message to the next.
+    pub fn record_at(&mut self, elapsed: Duration, direction: CommunicationDirection, message: &UciMessage) {
+        match (direction, message) {
+            (CommunicationDirection::GuiToEngine, UciMessage::Uci) => self.uci_at = Some(elapsed),
+            (CommunicationDirection::GuiToEngine, UciMessage::IsReady) => self.ready_at.push_back(elapsed),
+            (CommunicationDirection::GuiToEngine, UciMessage::Go { .. }) => {
+                self.searching = true;
+                self.report.searches.push(SearchTiming {
+                    started: elapsed,
+                    first_info: None,
+                    best_move: None,
+                    infos: 0,
+                    time_drift: None,
+                });
+            }
+            (CommunicationDirection::EngineToGui, UciMessage::UciOk) => {
+                if let Some(at) = self.uci_at.take() {
+                    self.report.handshake = Some(elapsed.saturating_sub(at));
+                }
+            }
+            (CommunicationDirection::EngineToGui, UciMessage::ReadyOk) => {
+                if let Some(at) = self.ready_at.pop_front() {
+                    self.report.ready.push(elapsed.saturating_sub(at));
+                }
+            }
+            (CommunicationDirection::EngineToGui, UciMessage::Info(attributes)) if self.searching => {
+                let search = self.report.searches.last_mut().unwrap();
+                let since_go = elapsed.saturating_sub(search.started);
+                search.infos += 1;
+                search.first_info.get_or_insert(since_go);
+
+                for a in attributes {
+                    if let UciInfoAttribute::Time(reported) = a {
+                        let drift = ChronoDuration::milliseconds(since_go.as_millis() as i64) - *reported;
+                        if !matches!(search.time_drift, Some(d) if d.abs() >= drift.abs()) {
+                            search.time_drift = Some(drift);
+                        }
+                    }
+                }
+            }
+            (CommunicationDirection::EngineToGui, UciMessage::BestMove { .. }) if self.searching => {
+                self.searching = false;
+                let search = self.report.searches.last_mut().unwrap();
+                search.best_move = Some(elapsed.saturating_sub(search.started));
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the timings measured so far. A search in progress is included, without its `best_move`.
+    pub fn report(&self) -> TimingReport {
+        self.report.clone()
+    }
+
+    /// Forgets all of the measurements and restarts the clock.
+    pub fn reset(&mut self) {
+        *self = TimingAnalyzer::new();
+    }
+}
+
+impl Middleware for TimingAnalyzer {
+    fn process(&mut self, message: UciMessage, context: &mut ProxyContext) -> Option<UciMessage> {
+        self.record(context.direction(), &message);
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::record::load;
+
+    use super::*;
+
+    #[test]
+    fn test_analyze_transcript() {
+        let transcript = "0.000 > uci\n0.020 < id name Fake\n0.150 < uciok\n0.200 > isready\n0.210 < readyok\n\
+                          0.300 > isready\n0.301 > isready\n0.400 < readyok\n0.900 < readyok\n\
+                          1.000 > go depth 3\n1.010 < info depth 1 time 5\n1.100 < info depth 2 time 130\n\
+                          1.200 < bestmove e2e4\n1.250 < info depth 9\n\
+                          2.000 > go infinite\n2.500 > stop\n2.600 < bestmove d2d4\n3.000 > go infinite\n";
+        let report = TimingAnalyzer::analyze(&load(transcript.as_bytes()).unwrap());
+
+        assert_eq!(report.handshake, Some(Duration::from_millis(150)));
+        assert_eq!(report.ready, vec![Duration::from_millis(10), Duration::from_millis(100), Duration::from_millis(599)]);
+        assert_eq!(report.searches.len(), 3);
+
+        let search = &report.searches[0];
+        assert_eq!(search.started, Duration::from_secs(1));
+        assert_eq!(search.first_info, Some(Duration::from_millis(10)));
+        assert_eq!(search.best_move, Some(Duration::from_millis(200)));
+        assert_eq!(search.infos, 2);
+        assert_eq!(search.time_drift, Some(ChronoDuration::milliseconds(-30)));
+
+        assert_eq!(report.searches[1].first_info, None);
+        assert_eq!(report.searches[1].best_move, Some(Duration::from_millis(600)));
+        assert_eq!(report.searches[1].time_drift, None);
+        assert_eq!(report.searches[2].best_move, None);
+
+        let ready = report.ready_stats().unwrap();
+        assert_eq!((ready.count, ready.min, ready.median, ready.max),
+                   (3, Duration::from_millis(10), Duration::from_millis(100), Duration::from_millis(599)));
+        assert_eq!(ready.mean, Duration::from_millis(236) + Duration::from_micros(333) + Duration::from_nanos(333));
+        assert_eq!(report.best_move_stats().unwrap().count, 2);
+        assert_eq!(report.first_info_stats().unwrap().max, Duration::from_millis(10));
+        assert_eq!(report.max_time_drift(), Some(ChronoDuration::milliseconds(-30)));
+    }
+
+    #[test]
+    fn test_empty() {
+        let report = TimingAnalyzer::new().report();
+        assert_eq!(report, TimingReport::default());
+        assert_eq!(report.best_move_stats(), None);
+        assert_eq!(report.max_time_drift(), None);
+    }
+
+    #[test]
+    fn test_live() {
+        let mut analyzer = TimingAnalyzer::new();
+        analyzer.record(CommunicationDirection::GuiToEngine, &UciMessage::IsReady);
+        analyzer.record(CommunicationDirection::EngineToGui, &UciMessage::ReadyOk);
+        assert_eq!(analyzer.report().ready.len(), 1);
+
+        analyzer.reset();
+        assert!(analyzer.report().ready.is_empty());
+    }
+}
@@ -5,6 +5,8 @@
 
 
 use std::fmt::{Display, Error as FmtError, Formatter, Result as FmtResult};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 #[cfg(not(feature = "chess"))]
 use std::str::FromStr;
 
@@ -13,10 +15,16 @@ use chess::ChessMove;
 use chrono::Duration;
 use pest::error::Error as PestError;
 
+#[cfg(feature = "chess")]
+use crate::board::{validate_position, PositionError};
+use crate::builder::{GoBuilder, InfoBuilder};
 use crate::parser::Rule;
+use crate::score::{Score, ScoreBound};
 
 /// Specifies whether a message is engine- or GUI-bound.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum CommunicationDirection {
     /// An engine-bound message.
     GuiToEngine,
@@ -25,11 +33,135 @@ pub enum CommunicationDirection {
     EngineToGui,
 }
 
+/// The kind of a `UciMessage`, that is, the variant without its fields.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum UciMessageKind {
+    /// The `uci` engine-bound message.
+    Uci,
+
+    /// The `debug` engine-bound message.
+    Debug,
+
+    /// The `isready` engine-bound message.
+    IsReady,
+
+    /// The `register` engine-bound message.
+    Register,
+
+    /// The `position` engine-bound message.
+    Position,
+
+    /// The `setoption` engine-bound message.
+    SetOption,
+
+    /// The `ucinewgame` engine-bound message.
+    UciNewGame,
+
+    /// The `stop` engine-bound message.
+    Stop,
+
+    /// The `ponderhit` engine-bound message.
+    PonderHit,
+
+    /// The `quit` engine-bound message.
+    Quit,
+
+    /// The `go` engine-bound message.
+    Go,
+
+    /// The `id` GUI-bound message.
+    Id,
+
+    /// The `uciok` GUI-bound message.
+    UciOk,
+
+    /// The `readyok` GUI-bound message.
+    ReadyOk,
+
+    /// The `bestmove` GUI-bound message.
+    BestMove,
+
+    /// The `copyprotection` GUI-bound message.
+    CopyProtection,
+
+    /// The `registration` GUI-bound message.
+    Registration,
+
+    /// The `option` GUI-bound message.
+    Option,
+
+    /// The `info` GUI-bound message.
+    Info,
+
+    /// An unknown message.
+    Unknown,
+}
+
+impl UciMessageKind {
+    /// Returns the kind of the messages that start with the `keyword` (in any case), or `None` if no message does.
+    pub fn from_keyword(keyword: &str) -> Option<UciMessageKind> {
+        let kind = match keyword.to_ascii_lowercase().as_str() {
+            "uci" => UciMessageKind::Uci,
+            "debug" => UciMessageKind::Debug,
+            "isready" => UciMessageKind::IsReady,
+            "register" => UciMessageKind::Register,
+            "position" => UciMessageKind::Position,
+            "setoption" => UciMessageKind::SetOption,
+            "ucinewgame" => UciMessageKind::UciNewGame,
+            "stop" => UciMessageKind::Stop,
+            "ponderhit" => UciMessageKind::PonderHit,
+            "quit" => UciMessageKind::Quit,
+            "go" => UciMessageKind::Go,
+            "id" => UciMessageKind::Id,
+            "uciok" => UciMessageKind::UciOk,
+            "readyok" => UciMessageKind::ReadyOk,
+            "bestmove" => UciMessageKind::BestMove,
+            "copyprotection" => UciMessageKind::CopyProtection,
+            "registration" => UciMessageKind::Registration,
+            "option" => UciMessageKind::Option,
+            "info" => UciMessageKind::Info,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Returns the keyword the messages of this kind start with, or `"unknown"` for the unknown messages.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UciMessageKind::Uci => "uci",
+            UciMessageKind::Debug => "debug",
+            UciMessageKind::IsReady => "isready",
+            UciMessageKind::Register => "register",
+            UciMessageKind::Position => "position",
+            UciMessageKind::SetOption => "setoption",
+            UciMessageKind::UciNewGame => "ucinewgame",
+            UciMessageKind::Stop => "stop",
+            UciMessageKind::PonderHit => "ponderhit",
+            UciMessageKind::Quit => "quit",
+            UciMessageKind::Go => "go",
+            UciMessageKind::Id => "id",
+            UciMessageKind::UciOk => "uciok",
+            UciMessageKind::ReadyOk => "readyok",
+            UciMessageKind::BestMove => "bestmove",
+            UciMessageKind::CopyProtection => "copyprotection",
+            UciMessageKind::Registration => "registration",
+            UciMessageKind::Option => "option",
+            UciMessageKind::Info => "info",
+            UciMessageKind::Unknown => "unknown",
+        }
+    }
+}
+
 pub trait Serializable: Display {
     fn serialize(&self) -> String;
 }
 
 /// An enumeration type containing representations for all messages supported by the UCI protocol.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum UciMessage {
     /// The `uci` engine-bound message.
@@ -42,15 +174,16 @@ pub enum UciMessage {
     /// The `isready` engine-bound message.
     IsReady,
 
-    /// The `register` engine-bound message.
+    /// The `register` engine-bound message. Either the name or the code may be missing, as in `register code 12345`,
+    /// since some GUIs only send part of the registration data.
     Register {
         /// The `register later` engine-bound message.
         later: bool,
 
-        /// The name part of the `register <code> <name>` engine-bound message.
+        /// The name part of the `register name <name> code <code>` engine-bound message.
         name: Option<String>,
 
-        /// The code part of the `register <code> <name>` engine-bound message.
+        /// The code part of the `register name <name> code <code>` engine-bound message.
         code: Option<String>,
     },
 
@@ -64,16 +197,21 @@ pub enum UciMessage {
         /// position.
         fen: Option<UciFen>,
 
-        /// A list of moves to apply to the position.
+        /// A list of moves to apply to the position, which may include the null move (see `UciMove::null()`).
         #[cfg(not(feature = "chess"))]
         moves: Vec<UciMove>,
 
-        /// A list of moves to apply to the position.
+        /// A list of moves to apply to the position. The null move (`0000`) is represented by `ChessMove::default()`,
+        /// the impossible move from `a1` to `a1`, which is serialized as `0000`.
         #[cfg(feature = "chess")]
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::vec_as_str"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
         moves: Vec<ChessMove>,
     },
 
-    /// The `setoption` engine-bound message.
+    /// The `setoption` engine-bound message. When parsing, the name and the value are separated by the last standalone
+    /// `value` token, so the name may contain the word `value` (as in `setoption name value value 1`), but the value
+    /// may not.
     SetOption {
         /// The name of the option to set.
         name: String,
@@ -128,6 +266,8 @@ pub enum UciMessage {
 
         /// The move the engine thinks is the best one in the position.
         #[cfg(feature = "chess")]
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::as_str"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         best_move: ChessMove,
 
         /// The move the engine would like to ponder on.
@@ -136,7 +276,15 @@ pub enum UciMessage {
 
         /// The move the engine would like to ponder on.
         #[cfg(feature = "chess")]
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::option_as_str"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
         ponder: Option<ChessMove>,
+
+        /// The draw offer or resignation of the extended `bestmove e2e4 draw` and `bestmove resign` notation, if any.
+        /// On a resignation, the `best_move` is the null move (`0000`).
+        #[cfg(feature = "extensions")]
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        offer: Option<Offer>,
     },
 
     /// The `copyprotection` GUI-bound message.
@@ -152,7 +300,7 @@ pub enum UciMessage {
     Info(Vec<UciInfoAttribute>),
 
     /// Indicating unknown message.
-    Unknown(String, Option<PestError<Rule>>)
+    Unknown(String, #[cfg_attr(feature = "serde", serde(skip))] Option<PestError<Rule>>)
 }
 
 impl UciMessage {
@@ -174,6 +322,25 @@ impl UciMessage {
         }
     }
 
+    /// Constructs a `setoption name <name> [value <value>]` message. The `value` is either a `String` or an
+    /// `Option<String>`, `None` being used for the `button` options.
+    pub fn set_option<V: Into<Option<String>>>(name: &str, value: V) -> UciMessage {
+        UciMessage::SetOption {
+            name: name.to_string(),
+            value: value.into(),
+        }
+    }
+
+    /// Constructs a `setoption name <name> value <value>` message for a `spin` option.
+    pub fn set_option_i64(name: &str, value: i64) -> UciMessage {
+        UciMessage::set_option(name, value.to_string())
+    }
+
+    /// Constructs a `setoption name <name> value <true|false>` message for a `check` option.
+    pub fn set_option_bool(name: &str, value: bool) -> UciMessage {
+        UciMessage::set_option(name, value.to_string())
+    }
+
     /// Constructs an empty [UciMessage::Register](enum.UciMessage.html#variant.Go) message.
     pub fn go() -> UciMessage {
         UciMessage::Go {
@@ -207,6 +374,84 @@ impl UciMessage {
         }
     }
 
+    /// Constructs a `go depth <depth>` [UciMessage::Go](enum.UciMessage.html#variant.Go) message.
+    pub fn go_depth(depth: u8) -> UciMessage {
+        UciMessage::Go {
+            search_control: Some(UciSearchControl::depth(depth)),
+            time_control: None,
+        }
+    }
+
+    /// Constructs a `go nodes <nodes>` [UciMessage::Go](enum.UciMessage.html#variant.Go) message.
+    pub fn go_nodes(nodes: u64) -> UciMessage {
+        UciMessage::Go {
+            search_control: Some(UciSearchControl::nodes(nodes)),
+            time_control: None,
+        }
+    }
+
+    /// Constructs a `go mate <moves>` [UciMessage::Go](enum.UciMessage.html#variant.Go) message.
+    pub fn go_mate(moves: u8) -> UciMessage {
+        UciMessage::Go {
+            search_control: Some(UciSearchControl::mate(moves)),
+            time_control: None,
+        }
+    }
+
+    /// Constructs a `go searchmoves <moves>` [UciMessage::Go](enum.UciMessage.html#variant.Go) message, which
+    /// limits the search to the `moves`.
+    #[cfg(not(feature = "chess"))]
+    pub fn go_searchmoves(moves: Vec<UciMove>) -> UciMessage {
+        UciMessage::Go {
+            search_control: Some(UciSearchControl { search_moves: moves, mate: None, depth: None, nodes: None }),
+            time_control: None,
+        }
+    }
+
+    /// Constructs a `go searchmoves <moves>` [UciMessage::Go](enum.UciMessage.html#variant.Go) message, which
+    /// limits the search to the `moves`.
+    #[cfg(feature = "chess")]
+    pub fn go_searchmoves(moves: Vec<ChessMove>) -> UciMessage {
+        UciMessage::Go {
+            search_control: Some(UciSearchControl { search_moves: moves, mate: None, depth: None, nodes: None }),
+            time_control: None,
+        }
+    }
+
+    /// Returns a [GoBuilder](../builder/struct.GoBuilder.html) for fluently constructing a
+    /// [UciMessage::Go](enum.UciMessage.html#variant.Go) message.
+    pub fn go_builder() -> GoBuilder {
+        GoBuilder::new()
+    }
+
+    /// Constructs a `position` message from a starting FEN and a list of moves. If the FEN describes the standard
+    /// starting position, the canonical `position startpos moves ...` form is produced instead of `position fen ...`.
+    #[cfg(not(feature = "chess"))]
+    pub fn position_from_fen(fen: &str, moves: Vec<UciMove>) -> UciMessage {
+        let uci_fen = UciFen::from(fen);
+        let startpos = uci_fen.is_startpos();
+
+        UciMessage::Position {
+            startpos,
+            fen: if startpos { None } else { Some(uci_fen) },
+            moves,
+        }
+    }
+
+    /// Constructs a `position` message from a starting FEN and a list of moves. If the FEN describes the standard
+    /// starting position, the canonical `position startpos moves ...` form is produced instead of `position fen ...`.
+    #[cfg(feature = "chess")]
+    pub fn position_from_fen(fen: &str, moves: Vec<ChessMove>) -> UciMessage {
+        let uci_fen = UciFen::from(fen);
+        let startpos = uci_fen.is_startpos();
+
+        UciMessage::Position {
+            startpos,
+            fen: if startpos { None } else { Some(uci_fen) },
+            moves,
+        }
+    }
+
     /// Constructs an `id <name>` GUI-bound message.
     pub fn id_name(name: &str) -> UciMessage {
         UciMessage::Id {
@@ -229,6 +474,8 @@ impl UciMessage {
         UciMessage::BestMove {
             best_move,
             ponder: None,
+            #[cfg(feature = "extensions")]
+            offer: None,
         }
     }
 
@@ -238,6 +485,8 @@ impl UciMessage {
         UciMessage::BestMove {
             best_move,
             ponder: Some(ponder),
+            #[cfg(feature = "extensions")]
+            offer: None,
         }
     }
 
@@ -247,6 +496,8 @@ impl UciMessage {
         UciMessage::BestMove {
             best_move,
             ponder: None,
+            #[cfg(feature = "extensions")]
+            offer: None,
         }
     }
 
@@ -256,6 +507,8 @@ impl UciMessage {
         UciMessage::BestMove {
             best_move,
             ponder: Some(ponder),
+            #[cfg(feature = "extensions")]
+            offer: None,
         }
     }
 
@@ -264,6 +517,188 @@ impl UciMessage {
         UciMessage::Info(vec![UciInfoAttribute::String(s)])
     }
 
+    /// Constructs an `info depth <depth>` message.
+    pub fn info_depth(depth: u8) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Depth(depth)])
+    }
+
+    /// Constructs an `info score cp <cp>` message.
+    pub fn info_score_cp(cp: i32) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::from_centipawns(cp)])
+    }
+
+    /// Constructs an `info score mate <mate>` message.
+    pub fn info_score_mate(mate: i8) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::from_mate(mate)])
+    }
+
+    /// Constructs an `info time <milliseconds>` message.
+    pub fn info_time(milliseconds: Duration) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Time(milliseconds)])
+    }
+
+    /// Constructs an `info nodes <nodes>` message.
+    pub fn info_nodes(nodes: u64) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Nodes(nodes)])
+    }
+
+    /// Constructs an `info nps <nps>` message.
+    pub fn info_nps(nps: u64) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Nps(nps)])
+    }
+
+    /// Constructs an `info hashfull <permill>` message.
+    pub fn info_hashfull(permill: u16) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::HashFull(permill)])
+    }
+
+    /// Constructs an `info pv ...` message.
+    #[cfg(not(feature = "chess"))]
+    pub fn info_pv(moves: Vec<UciMove>) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Pv(moves)])
+    }
+
+    /// Constructs an `info pv ...` message.
+    #[cfg(feature = "chess")]
+    pub fn info_pv(moves: Vec<ChessMove>) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::Pv(moves)])
+    }
+
+    /// Constructs an `info currmove <move> currmovenum <number>` message.
+    #[cfg(not(feature = "chess"))]
+    pub fn info_currmove(curr_move: UciMove, number: u16) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::CurrMove(curr_move), UciInfoAttribute::CurrMoveNum(number)])
+    }
+
+    /// Constructs an `info currmove <move> currmovenum <number>` message.
+    #[cfg(feature = "chess")]
+    pub fn info_currmove(curr_move: ChessMove, number: u16) -> UciMessage {
+        UciMessage::Info(vec![UciInfoAttribute::CurrMove(curr_move), UciInfoAttribute::CurrMoveNum(number)])
+    }
+
+    /// Returns an [InfoBuilder](../builder/struct.InfoBuilder.html) for fluently constructing a
+    /// [UciMessage::Info](enum.UciMessage.html#variant.Info) message.
+    pub fn info_builder() -> InfoBuilder {
+        InfoBuilder::new()
+    }
+
+    /// Returns the kind of the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{parse_one, UciMessageKind};
+    ///
+    /// assert_eq!(parse_one("go infinite").kind(), UciMessageKind::Go);
+    /// ```
+    pub fn kind(&self) -> UciMessageKind {
+        match self {
+            UciMessage::Uci => UciMessageKind::Uci,
+            UciMessage::Debug(..) => UciMessageKind::Debug,
+            UciMessage::IsReady => UciMessageKind::IsReady,
+            UciMessage::Register { .. } => UciMessageKind::Register,
+            UciMessage::Position { .. } => UciMessageKind::Position,
+            UciMessage::SetOption { .. } => UciMessageKind::SetOption,
+            UciMessage::UciNewGame => UciMessageKind::UciNewGame,
+            UciMessage::Stop => UciMessageKind::Stop,
+            UciMessage::PonderHit => UciMessageKind::PonderHit,
+            UciMessage::Quit => UciMessageKind::Quit,
+            UciMessage::Go { .. } => UciMessageKind::Go,
+            UciMessage::Id { .. } => UciMessageKind::Id,
+            UciMessage::UciOk => UciMessageKind::UciOk,
+            UciMessage::ReadyOk => UciMessageKind::ReadyOk,
+            UciMessage::BestMove { .. } => UciMessageKind::BestMove,
+            UciMessage::CopyProtection(..) => UciMessageKind::CopyProtection,
+            UciMessage::Registration(..) => UciMessageKind::Registration,
+            UciMessage::Option(..) => UciMessageKind::Option,
+            UciMessage::Info(..) => UciMessageKind::Info,
+            UciMessage::Unknown(..) => UciMessageKind::Unknown,
+        }
+    }
+
+    /// Returns the score of an `info` message and whether it is exact or a bound, or `None` if this is not an `info`
+    /// message or it has no score.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::parse_one;
+    /// use vampirc_uci::score::{Score, ScoreBound};
+    ///
+    /// let info = parse_one("info depth 18 multipv 2 score cp -35 nodes 240000 nps 1200000 pv e7e5 g1f3");
+    /// assert_eq!(info.depth(), Some(18));
+    /// assert_eq!(info.multipv(), Some(2));
+    /// assert_eq!(info.score(), Some((Score::Centipawns(-35), ScoreBound::Exact)));
+    /// assert_eq!(info.pv().map(|pv| pv.len()), Some(2));
+    /// assert_eq!(parse_one("bestmove e2e4").depth(), None);
+    /// ```
+    pub fn score(&self) -> Option<(Score, ScoreBound)> {
+        match self {
+            UciMessage::Info(attributes) => Score::from_attributes(attributes),
+            _ => None
+        }
+    }
+
+    /// Returns the principal variation of an `info` message, or `None` if this is not an `info` message or it has
+    /// no `pv` attribute.
+    #[cfg(not(feature = "chess"))]
+    pub fn pv(&self) -> Option<&[UciMove]> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::Pv(moves) => Some(moves.as_slice()),
+            _ => None
+        })
+    }
+
+    /// Returns the principal variation of an `info` message, or `None` if this is not an `info` message or it has
+    /// no `pv` attribute.
+    #[cfg(feature = "chess")]
+    pub fn pv(&self) -> Option<&[ChessMove]> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::Pv(moves) => Some(moves.as_slice()),
+            _ => None
+        })
+    }
+
+    /// Returns the depth of an `info` message, if any.
+    pub fn depth(&self) -> Option<u8> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::Depth(d) => Some(*d),
+            _ => None
+        })
+    }
+
+    /// Returns the node count of an `info` message, if any.
+    pub fn nodes(&self) -> Option<u64> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::Nodes(n) => Some(*n),
+            _ => None
+        })
+    }
+
+    /// Returns the nodes per second of an `info` message, if any.
+    pub fn nps(&self) -> Option<u64> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::Nps(n) => Some(*n),
+            _ => None
+        })
+    }
+
+    /// Returns the `multipv` line number of an `info` message, if any.
+    pub fn multipv(&self) -> Option<u16> {
+        self.find_info_attribute(|a| match a {
+            UciInfoAttribute::MultiPv(n) => Some(*n),
+            _ => None
+        })
+    }
+
+    /// Returns the first value the `f` extracts from the attributes of an `info` message.
+    fn find_info_attribute<'a, T, F: Fn(&'a UciInfoAttribute) -> Option<T>>(&'a self, f: F) -> Option<T> {
+        match self {
+            UciMessage::Info(attributes) => attributes.iter().find_map(f),
+            _ => None
+        }
+    }
+
     /// Returns whether the command was meant for the engine or for the GUI.
     pub fn direction(&self) -> CommunicationDirection {
         match self {
@@ -318,6 +753,54 @@ impl UciMessage {
         }
     }
 
+    /// If this `UciMessage` is a `UciMessage::Position`, this method applies its `moves` to the starting position or
+    /// FEN and returns the first problem encountered, such as an illegal move together with its index in the list.
+    /// Any other message results in a `PositionError::NotAPosition` error.
+    #[cfg(feature = "chess")]
+    pub fn validate(&self) -> Result<(), PositionError> {
+        validate_position(self)
+    }
+
+    /// If this `UciMessage` is a `UciMessage::Position`, returns a copy in which the starting position is always
+    /// expressed as a FEN: `startpos` is replaced with the FEN of the standard starting position, and the whitespace
+    /// of an explicit FEN is normalized to single spaces. Any other message is returned unchanged.
+    ///
+    /// This lets downstream code treat positions uniformly, such as when using them as cache keys.
+    pub fn to_fen_position(&self) -> UciMessage {
+        match self {
+            UciMessage::Position { startpos, fen, moves } => {
+                let uci_fen = if *startpos {
+                    UciFen::startpos()
+                } else {
+                    fen.as_ref().map(|f| UciFen(f.0.split_whitespace().collect::<Vec<&str>>().join(" ")))
+                        .unwrap_or_else(UciFen::startpos)
+                };
+
+                UciMessage::Position {
+                    startpos: false,
+                    fen: Some(uci_fen),
+                    moves: moves.clone(),
+                }
+            }
+            _ => self.clone()
+        }
+    }
+
+    /// If this `UciMessage` is a `UciMessage::Position` whose FEN describes the standard starting position, returns a
+    /// copy that uses `startpos` instead. Any other message is returned unchanged.
+    pub fn to_startpos_position(&self) -> UciMessage {
+        match self {
+            UciMessage::Position { fen: Some(uci_fen), moves, .. } if uci_fen.is_startpos() => {
+                UciMessage::Position {
+                    startpos: true,
+                    fen: None,
+                    moves: moves.clone(),
+                }
+            }
+            _ => self.clone()
+        }
+    }
+
     /// Return `true` if this `UciMessage` is of variant `UnknownMessage`.
     pub fn is_unknown(&self) -> bool {
         match self {
@@ -325,11 +808,27 @@ impl UciMessage {
             _ => false
         }
     }
+
+    /// Returns `true` if this `UciMessage` ends a search: a `UciMessage::BestMove`, or a `bestmove` line that could not
+    /// be parsed and so is a `UciMessage::Unknown`.
+    pub fn ends_search(&self) -> bool {
+        match self {
+            UciMessage::BestMove { .. } => true,
+            UciMessage::Unknown(text, _) => text.trim_start().starts_with("bestmove"),
+            _ => false
+        }
+    }
 }
 
 impl Display for UciMessage {
+    /// Formats the message in the wire format or, with the alternate flag (`{:#}`), as the plain English explanation
+    /// returned by `explain()`.
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "{}", self.serialize())
+        if f.alternate() {
+            write!(f, "{}", self.explain())
+        } else {
+            write!(f, "{}", self.serialize())
+        }
     }
 }
 
@@ -375,7 +874,7 @@ impl Serializable for UciMessage {
                     s += String::from(" moves").as_str();
 
                     for m in moves {
-                        s += format!(" {}", *m).as_str();
+                        s += format!(" {}", list_move(m)).as_str();
                     }
                 }
 
@@ -446,7 +945,7 @@ impl Serializable for UciMessage {
                     if !sc.search_moves.is_empty() {
                         s += " searchmoves ";
                         for m in &sc.search_moves {
-                            s += format!("{} ", m).as_str();
+                            s += format!("{} ", list_move(m)).as_str();
                         }
                     }
                 }
@@ -477,13 +976,23 @@ impl Serializable for UciMessage {
             },
             UciMessage::UciOk => String::from("uciok"),
             UciMessage::ReadyOk => String::from("readyok"),
-            UciMessage::BestMove { best_move, ponder } => {
+            UciMessage::BestMove { best_move, ponder, .. } => {
+                #[cfg(feature = "extensions")]
+                if let UciMessage::BestMove { offer: Some(Offer::Resign), .. } = self {
+                    return String::from("bestmove resign");
+                }
+
                 let mut s = String::from(format!("bestmove {}", *best_move));
 
                 if let Some(p) = ponder {
                     s += format!(" ponder {}", *p).as_str();
                 }
 
+                #[cfg(feature = "extensions")]
+                if let UciMessage::BestMove { offer: Some(Offer::Draw), .. } = self {
+                    s += " draw";
+                }
+
                 s
             },
             UciMessage::CopyProtection(cp_state) | UciMessage::Registration(cp_state) => {
@@ -523,6 +1032,8 @@ impl Serializable for UciMessage {
 
 /// This enum represents the possible variants of the `go` UCI message that deal with the chess game's time controls
 /// and the engine's thinking time.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum UciTimeControl {
     /// The `go ponder` message.
@@ -533,16 +1044,26 @@ pub enum UciTimeControl {
 
     /// The information about the game's time controls.
     TimeLeft {
-        /// White's time on the clock, in milliseconds.
+        /// White's time on the clock, in milliseconds. Negative if the flag has fallen, as some GUIs send it (such as
+        /// `go wtime -134`).
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::option_millis"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<i64>"))]
         white_time: Option<Duration>,
 
-        /// Black's time on the clock, in milliseconds.
+        /// Black's time on the clock, in milliseconds. Negative if the flag has fallen, as some GUIs send it (such as
+        /// `go wtime -134`).
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::option_millis"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<i64>"))]
         black_time: Option<Duration>,
 
         /// White's increment per move, in milliseconds.
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::option_millis"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<i64>"))]
         white_increment: Option<Duration>,
 
         /// Black's increment per move, in milliseconds.
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::option_millis"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<i64>"))]
         black_increment: Option<Duration>,
 
         /// The number of moves to go to the next time control.
@@ -550,7 +1071,7 @@ pub enum UciTimeControl {
     },
 
     /// Specifies how much time the engine should think about the move, in milliseconds.
-    MoveTime(Duration)
+    MoveTime(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::millis"))] #[cfg_attr(feature = "schemars", schemars(with = "i64"))] Duration)
 }
 
 impl UciTimeControl {
@@ -564,17 +1085,122 @@ impl UciTimeControl {
             moves_to_go: None
         }
     }
+
+    /// Returns a `UciTimeControl::TimeLeft` with both clocks set and no increments or moves to go. Those can be added
+    /// with `with_increments(..)` and `with_moves_to_go(..)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{Duration, UciTimeControl};
+    ///
+    /// let tc = UciTimeControl::clock(Duration::seconds(60), Duration::seconds(55))
+    ///     .with_increments(Duration::seconds(1), Duration::seconds(1))
+    ///     .with_moves_to_go(20);
+    ///
+    /// assert_eq!(tc.white_time(), Some(Duration::seconds(60)));
+    /// assert_eq!(tc.moves_to_go(), Some(20));
+    /// ```
+    pub fn clock(white_time: Duration, black_time: Duration) -> UciTimeControl {
+        UciTimeControl::TimeLeft {
+            white_time: Some(white_time),
+            black_time: Some(black_time),
+            white_increment: None,
+            black_increment: None,
+            moves_to_go: None
+        }
+    }
+
+    /// Sets the increments of a `UciTimeControl::TimeLeft`. Other time controls are returned unchanged.
+    pub fn with_increments(mut self, white: Duration, black: Duration) -> UciTimeControl {
+        if let UciTimeControl::TimeLeft { white_increment, black_increment, .. } = &mut self {
+            *white_increment = Some(white);
+            *black_increment = Some(black);
+        }
+        self
+    }
+
+    /// Sets the number of moves to go of a `UciTimeControl::TimeLeft`. Other time controls are returned unchanged.
+    pub fn with_moves_to_go(mut self, moves: u8) -> UciTimeControl {
+        if let UciTimeControl::TimeLeft { moves_to_go, .. } = &mut self {
+            *moves_to_go = Some(moves);
+        }
+        self
+    }
+
+    /// Returns `true` for `UciTimeControl::Infinite`.
+    pub fn is_infinite(&self) -> bool {
+        matches!(self, UciTimeControl::Infinite)
+    }
+
+    /// Returns `true` for `UciTimeControl::Ponder`.
+    pub fn is_ponder(&self) -> bool {
+        matches!(self, UciTimeControl::Ponder)
+    }
+
+    /// Returns the time to search of a `UciTimeControl::MoveTime`.
+    pub fn move_time(&self) -> Option<Duration> {
+        match self {
+            UciTimeControl::MoveTime(t) => Some(*t),
+            _ => None
+        }
+    }
+
+    /// Returns white's time on the clock of a `UciTimeControl::TimeLeft`, which is negative if the flag has fallen.
+    pub fn white_time(&self) -> Option<Duration> {
+        match self {
+            UciTimeControl::TimeLeft { white_time, .. } => *white_time,
+            _ => None
+        }
+    }
+
+    /// Returns black's time on the clock of a `UciTimeControl::TimeLeft`, which is negative if the flag has fallen.
+    pub fn black_time(&self) -> Option<Duration> {
+        match self {
+            UciTimeControl::TimeLeft { black_time, .. } => *black_time,
+            _ => None
+        }
+    }
+
+    /// Returns white's increment of a `UciTimeControl::TimeLeft`.
+    pub fn white_increment(&self) -> Option<Duration> {
+        match self {
+            UciTimeControl::TimeLeft { white_increment, .. } => *white_increment,
+            _ => None
+        }
+    }
+
+    /// Returns black's increment of a `UciTimeControl::TimeLeft`.
+    pub fn black_increment(&self) -> Option<Duration> {
+        match self {
+            UciTimeControl::TimeLeft { black_increment, .. } => *black_increment,
+            _ => None
+        }
+    }
+
+    /// Returns the number of moves to go of a `UciTimeControl::TimeLeft`.
+    pub fn moves_to_go(&self) -> Option<u8> {
+        match self {
+            UciTimeControl::TimeLeft { moves_to_go, .. } => *moves_to_go,
+            _ => None
+        }
+    }
 }
 
 /// A struct that controls the engine's (non-time-related) search settings.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct UciSearchControl {
-    /// Limits the search to these moves.
+    /// Limits the search to these moves, which may include the null move (see `UciMove::null()`).
     #[cfg(not(feature = "chess"))]
     pub search_moves: Vec<UciMove>,
 
-    /// Limits the search to these moves.
+    /// Limits the search to these moves. The null move (`0000`) is represented by `ChessMove::default()`, as in the
+    /// `moves` of `UciMessage::Position`.
     #[cfg(feature = "chess")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::vec_as_str"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
     pub search_moves: Vec<ChessMove>,
 
     /// Search for mate in this many moves.
@@ -636,7 +1262,23 @@ impl Default for UciSearchControl {
     }
 }
 
+/// The offer that follows the move of a `bestmove` message in the extended notation of some adjudication setups (with
+/// the `extensions` feature).
+#[cfg(feature = "extensions")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Offer {
+    /// The engine offers (or accepts) a draw, as in `bestmove e2e4 draw`.
+    Draw,
+
+    /// The engine resigns, as in `bestmove resign`.
+    Resign,
+}
+
 /// Represents the copy protection or registration state.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum ProtectionState {
     /// Signifies the engine is checking the copy protection or registration.
@@ -650,6 +1292,8 @@ pub enum ProtectionState {
 }
 
 /// Represents a UCI option definition.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum UciOptionConfig {
     /// The option of type `check` (a boolean).
@@ -676,15 +1320,34 @@ pub enum UciOptionConfig {
         max: Option<i64>,
     },
 
+    /// An option of type `spin` declared with decimal values, such as lc0's `option name CPuct type spin default 1.745
+    /// min 0 max 100`. The protocol only allows for integers, so the parser falls back to this variant whenever the
+    /// default, the minimum or the maximum has a decimal point. It is serialized as a `spin` option, with each value
+    /// written with a decimal point.
+    Float {
+        /// The name of the option.
+        name: String,
+
+        /// The default value of this decimal property.
+        default: Option<UciFloat>,
+
+        /// The minimal value of this decimal property.
+        min: Option<UciFloat>,
+
+        /// The maximal value of this decimal property.
+        max: Option<UciFloat>,
+    },
+
     /// The option of type `combo` (a list of strings).
     Combo {
         /// The name of the option.
         name: String,
 
-        /// The default value for this list of strings.
+        /// The default value for this list of strings. When parsing, it runs to the next standalone `var` token, so it
+        /// may contain the words `min` or `max`.
         default: Option<String>,
 
-        /// The list of acceptable strings.
+        /// The list of acceptable strings. When parsing, each runs to the next standalone `var` token.
         var: Vec<String>,
     },
 
@@ -699,7 +1362,8 @@ pub enum UciOptionConfig {
         /// The name of the option.
         name: String,
 
-        /// The default value of this string option.
+        /// The default value of this string option. When parsing, it runs to the end of the line, so it may contain the
+        /// words `min`, `max` or `var`.
         default: Option<String>,
     },
 }
@@ -708,8 +1372,8 @@ impl UciOptionConfig {
     /// Returns the name of the option.
     pub fn get_name(&self) -> &str {
         match self {
-            UciOptionConfig::Check { name, .. } | UciOptionConfig::Spin { name, .. } | UciOptionConfig::Combo { name, .. } | UciOptionConfig::Button { name } |
-            UciOptionConfig::String { name, .. } => name.as_str()
+            UciOptionConfig::Check { name, .. } | UciOptionConfig::Spin { name, .. } | UciOptionConfig::Float { name, .. } |
+            UciOptionConfig::Combo { name, .. } | UciOptionConfig::Button { name } | UciOptionConfig::String { name, .. } => name.as_str()
         }
     }
 
@@ -717,7 +1381,7 @@ impl UciOptionConfig {
     pub fn get_type_str(&self) -> &'static str {
         match self {
             UciOptionConfig::Check { .. } => "check",
-            UciOptionConfig::Spin { .. } => "spin",
+            UciOptionConfig::Spin { .. } | UciOptionConfig::Float { .. } => "spin",
             UciOptionConfig::Combo { .. } => "combo",
             UciOptionConfig::Button { .. } => "button",
             UciOptionConfig::String { .. } => "string"
@@ -761,6 +1425,19 @@ impl Serializable for UciOptionConfig {
                     s += format!(" max {}", *m).as_str();
                 }
             }
+            UciOptionConfig::Float { default, min, max, .. } => {
+                if let Some(def) = default {
+                    s += format!(" default {}", *def).as_str();
+                }
+
+                if let Some(m) = min {
+                    s += format!(" min {}", *m).as_str();
+                }
+
+                if let Some(m) = max {
+                    s += format!(" max {}", *m).as_str();
+                }
+            }
             UciOptionConfig::Combo { default, var, .. } => {
                 if let Some(def) = default {
                     s += format!(" default {}", *def).as_str();
@@ -792,6 +1469,8 @@ impl Display for UciOptionConfig {
 
 /// The representation of various info messages. For an info attribute that is not listed in the protocol specification,
 /// the `UciInfoAttribute::Any(name, value)` variant can be used.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum UciInfoAttribute {
     /// The `info depth` message.
@@ -801,7 +1480,7 @@ pub enum UciInfoAttribute {
     SelDepth(u8),
 
     /// The `info time` message.
-    Time(Duration),
+    Time(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::millis"))] #[cfg_attr(feature = "schemars", schemars(with = "i64"))] Duration),
 
     /// The `info nodes` message.
     Nodes(u64),
@@ -812,7 +1491,7 @@ pub enum UciInfoAttribute {
 
     /// The `info pv` message (best line move sequence).
     #[cfg(feature = "chess")]
-    Pv(Vec<ChessMove>),
+    Pv(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::vec_as_str"))] #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))] Vec<ChessMove>),
 
     /// The `info pv ... multipv` message (the pv line number in a multi pv sequence).
     MultiPv(u16),
@@ -838,7 +1517,7 @@ pub enum UciInfoAttribute {
 
     /// The `info currmove` message (current move).
     #[cfg(feature = "chess")]
-    CurrMove(ChessMove),
+    CurrMove(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::as_str"))] #[cfg_attr(feature = "schemars", schemars(with = "String"))] ChessMove),
 
     /// The `info currmovenum` message (current move number).
     CurrMoveNum(u16),
@@ -868,7 +1547,7 @@ pub enum UciInfoAttribute {
 
     /// The `info refutation` message (the first move is the move being refuted).
     #[cfg(feature = "chess")]
-    Refutation(Vec<ChessMove>),
+    Refutation(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::vec_as_str"))] #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))] Vec<ChessMove>),
 
     /// The `info currline` message (current line being calculated on a CPU).
     CurrLine {
@@ -881,10 +1560,13 @@ pub enum UciInfoAttribute {
 
         /// The line being calculated.
         #[cfg(feature = "chess")]
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::vec_as_str"))]
+        #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
         line: Vec<ChessMove>,
     },
 
-    /// Any other info line in the format `(name, value)`.
+    /// Any other info line in the format `(name, value)`. When parsing, the value runs up to the next attribute of
+    /// another variant.
     Any(String, String),
 }
 
@@ -987,7 +1669,9 @@ impl Serializable for UciInfoAttribute {
                 }
             },
             UciInfoAttribute::Any(_, value) => {
-                s += &format!(" {}", value);
+                if !value.is_empty() {
+                    s += &format!(" {}", value);
+                }
             }
         }
 
@@ -1001,7 +1685,61 @@ impl Display for UciInfoAttribute {
     }
 }
 
+/// What to do with an attribute that occurs more than once in an `info` message, as in the `info depth 5 depth 6` some
+/// buggy engines send. Two attributes are the same if they have the same name (see `UciInfoAttribute::get_name()`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub enum DuplicateAttributes {
+    /// Keeps all the attributes. This is the default.
+    #[default]
+    KeepAll,
+
+    /// Keeps the first of the same attributes.
+    KeepFirst,
+
+    /// Keeps the last of the same attributes.
+    KeepLast,
+}
+
+/// Helpers for the attributes of an `info` message.
+pub trait InfoAttributes {
+    /// Removes the attributes that occur more than once, keeping the one the `policy` says. The remaining attributes
+    /// stay in their order. Unlike `Vec::dedup()`, this considers all the attributes with the same name as duplicates,
+    /// whether they are next to each other and have the same value or not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vampirc_uci::{DuplicateAttributes, InfoAttributes, UciInfoAttribute};
+    ///
+    /// let mut attributes = vec![UciInfoAttribute::Depth(5), UciInfoAttribute::Nodes(100), UciInfoAttribute::Depth(6)];
+    /// attributes.dedup_attributes(DuplicateAttributes::KeepLast);
+    /// assert_eq!(attributes, vec![UciInfoAttribute::Nodes(100), UciInfoAttribute::Depth(6)]);
+    /// ```
+    fn dedup_attributes(&mut self, policy: DuplicateAttributes);
+}
+
+impl InfoAttributes for Vec<UciInfoAttribute> {
+    fn dedup_attributes(&mut self, policy: DuplicateAttributes) {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut first_seen = |a: &UciInfoAttribute| seen.insert(a.get_name().to_string());
+        let keep: Vec<bool> = match policy {
+            DuplicateAttributes::KeepAll => return,
+            DuplicateAttributes::KeepFirst => self.iter().map(&mut first_seen).collect(),
+            DuplicateAttributes::KeepLast => {
+                let mut keep: Vec<bool> = self.iter().rev().map(&mut first_seen).collect();
+                keep.reverse();
+                keep
+            }
+        };
+
+        let mut keep = keep.into_iter();
+        self.retain(|_| keep.next().unwrap());
+    }
+}
+
 /// An enum representing the chess piece types.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 #[cfg(not(feature = "chess"))]
 pub enum UciPiece {
@@ -1064,6 +1802,8 @@ impl FromStr for UciPiece {
 
 /// A representation of a chessboard square.
 #[cfg(not(feature = "chess"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub struct UciSquare {
     /// The file. A character in the range of `a..h`.
@@ -1127,6 +1867,91 @@ impl UciMove {
             promotion: None,
         }
     }
+
+    /// Creates the null move, written as `0000`, which passes the turn to the other side. Some analysis tools insert
+    /// it into the `moves` of a `position` message or the `searchmoves` of a `go` message. Both of its squares are the
+    /// off-board square with the file `'0'` and the rank `0`.
+    pub fn null() -> UciMove {
+        UciMove::from_to(UciSquare::from('0', 0), UciSquare::from('0', 0))
+    }
+
+    /// Returns `true` if this is the null move (`0000`).
+    pub fn is_null(&self) -> bool {
+        *self == UciMove::null()
+    }
+}
+
+#[cfg(not(feature = "chess"))]
+impl FromStr for UciMove {
+    type Err = FmtError;
+
+    /// Creates a `UciMove` from its UCI move notation (as in, `e2e4` or `a7a8q`, or `0000` for the null move). The
+    /// promotion piece and the files may be uppercase.
+    fn from_str(s: &str) -> Result<UciMove, FmtError> {
+        if s == "0000" {
+            return Ok(UciMove::null());
+        }
+
+        let chars: Vec<char> = s.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if chars.len() != 4 && chars.len() != 5 {
+            return Err(FmtError);
+        }
+
+        let square = |file: char, rank: char| -> Result<UciSquare, FmtError> {
+            if ('a'..='h').contains(&file) && ('1'..='8').contains(&rank) {
+                Ok(UciSquare::from(file, rank as u8 - b'0'))
+            } else {
+                Err(FmtError)
+            }
+        };
+
+        let promotion = match chars.get(4) {
+            Some(&p) if "qrnb".contains(p) => Some(UciPiece::from_str(&p.to_string())?),
+            Some(_) => return Err(FmtError),
+            None => None,
+        };
+
+        Ok(UciMove {
+            from: square(chars[0], chars[1])?,
+            to: square(chars[2], chars[3])?,
+            promotion,
+        })
+    }
+}
+
+#[cfg(all(feature = "serde", not(feature = "chess")))]
+impl serde::Serialize for UciMove {
+    /// Serializes the move in the UCI move notation.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde_support::as_str::serialize(self, serializer)
+    }
+}
+
+#[cfg(all(feature = "serde", not(feature = "chess")))]
+impl<'de> serde::Deserialize<'de> for UciMove {
+    /// Deserializes the move from the UCI move notation.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<UciMove, D::Error> {
+        crate::serde_support::as_str::deserialize(deserializer)
+    }
+}
+
+#[cfg(all(feature = "schemars", not(feature = "chess")))]
+impl schemars::JsonSchema for UciMove {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "UciMove".into()
+    }
+
+    /// A string in the UCI move notation.
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "pattern": "^([a-hA-H][1-8][a-hA-H][1-8][qrnbQRNB]?|0000)$"
+        })
+    }
 }
 
 #[cfg(not(feature = "chess"))]
@@ -1148,16 +1973,58 @@ impl Display for UciMove {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 /// A representation of the notation in the [FEN notation](https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation).
+/// The FEN is kept as it was sent, which may be without the halfmove clock and the fullmove number (see `has_counters()`).
 pub struct UciFen(pub String);
 
+/// The FEN of the standard chess starting position.
+pub const STARTPOS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 impl UciFen {
     /// Returns the FEN string.
     #[inline]
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// Returns the FEN of the standard chess starting position.
+    pub fn startpos() -> UciFen {
+        UciFen::from(STARTPOS_FEN)
+    }
+
+    /// Returns `true` if this FEN describes the standard chess starting position, disregarding any extra whitespace
+    /// between the FEN fields and the missing counters.
+    pub fn is_startpos(&self) -> bool {
+        self.with_counters().0.split_whitespace().eq(STARTPOS_FEN.split_whitespace())
+    }
+
+    /// Returns `true` if this FEN includes the halfmove clock and the fullmove number, rather than stopping after the
+    /// en passant square.
+    pub fn has_counters(&self) -> bool {
+        self.0.split_whitespace().nth(5).is_some()
+    }
+
+    /// Returns the halfmove clock, or `0` if the FEN does not include it.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.0.split_whitespace().nth(4).and_then(|c| c.parse().ok()).unwrap_or(0)
+    }
+
+    /// Returns the fullmove number, or `1` if the FEN does not include it.
+    pub fn fullmove_number(&self) -> u32 {
+        self.0.split_whitespace().nth(5).and_then(|c| c.parse().ok()).unwrap_or(1)
+    }
+
+    /// Returns this FEN with the halfmove clock and the fullmove number, filling in `0` and `1` if they are missing.
+    pub fn with_counters(&self) -> UciFen {
+        if self.has_counters() {
+            self.clone()
+        } else {
+            UciFen(format!("{} 0 1", self.0.trim_end()))
+        }
+    }
 }
 
 impl From<&str> for UciFen {
@@ -1175,6 +2042,68 @@ impl Display for UciFen {
     }
 }
 
+/// A decimal number, as found in the `spin` options some engines declare with decimal values (see
+/// `UciOptionConfig::Float`). Two `UciFloat`s are equal if their bits are, which makes the type `Eq` and `Hash`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema), schemars(transparent))]
+#[derive(Copy, Clone, Debug)]
+pub struct UciFloat(pub f64);
+
+impl PartialEq for UciFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for UciFloat {}
+
+impl Hash for UciFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl From<f64> for UciFloat {
+    fn from(f: f64) -> Self {
+        UciFloat(f)
+    }
+}
+
+impl From<UciFloat> for f64 {
+    fn from(f: UciFloat) -> Self {
+        f.0
+    }
+}
+
+impl Display for UciFloat {
+    /// Outputs the number, always with a decimal point if it is integral (`100.0`, not `100`), so that it is read back
+    /// as a decimal.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        if self.0.is_finite() && self.0.fract() == 0.0 {
+            write!(f, "{:.1}", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// Formats a move of the `moves` of a `position` message or of the `searchmoves` of a `go` message, where `0000` stands
+/// for the null move.
+#[cfg(not(feature = "chess"))]
+fn list_move(m: &UciMove) -> String {
+    m.to_string()
+}
+
+/// Formats a move of the `moves` of a `position` message or of the `searchmoves` of a `go` message, where `0000` stands
+/// for the null move, represented by `ChessMove::default()`.
+#[cfg(feature = "chess")]
+fn list_move(m: &ChessMove) -> String {
+    if *m == ChessMove::default() {
+        String::from("0000")
+    } else {
+        m.to_string()
+    }
+}
 
 /// A vector containing several `UciMessage`s.
 pub type MessageList = Vec<UciMessage>;
@@ -1182,6 +2111,9 @@ pub type MessageList = Vec<UciMessage>;
 /// A wrapper that keeps the serialized form in a byte vector. Mostly useful to provide an `AsRef<[u8]>` implementation for
 /// quick conversion to an array of bytes. Use the `::from(m: UciMessage)` to construct it. It will add the newline
 /// character `\n` to the serialized message.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(from = "UciMessage", into = "UciMessage"))]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct ByteVecUciMessage {
     pub message: UciMessage,
@@ -1224,9 +2156,13 @@ impl AsRef<[u8]> for ByteVecUciMessage {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     #[cfg(feature = "chess")]
     use chess::Square;
 
+    use crate::parser::parse_one;
+
     use super::*;
 
     #[test]
@@ -1239,6 +2175,61 @@ mod tests {
         assert_eq!(UciMessage::UciOk.direction(), CommunicationDirection::EngineToGui);
     }
 
+    #[test]
+    fn test_time_control_accessors() {
+        let tc = UciTimeControl::clock(Duration::seconds(60), Duration::seconds(30)).with_increments(Duration::seconds(2), Duration::seconds(1));
+        assert_eq!(tc.white_time(), Some(Duration::seconds(60)));
+        assert_eq!(tc.black_time(), Some(Duration::seconds(30)));
+        assert_eq!(tc.white_increment(), Some(Duration::seconds(2)));
+        assert_eq!(tc.black_increment(), Some(Duration::seconds(1)));
+        assert_eq!(tc.moves_to_go(), None);
+        assert_eq!(tc.move_time(), None);
+        assert!(!tc.is_infinite() && !tc.is_ponder());
+        assert_eq!(UciMessage::Go { time_control: Some(tc), search_control: None }.serialize_canonical(),
+                   "go wtime 60000 btime 30000 winc 2000 binc 1000");
+
+        let tc = UciTimeControl::MoveTime(Duration::milliseconds(500)).with_moves_to_go(10);
+        assert_eq!(tc, UciTimeControl::MoveTime(Duration::milliseconds(500)));
+        assert_eq!(tc.move_time(), Some(Duration::milliseconds(500)));
+        assert_eq!(tc.white_time(), None);
+
+        assert!(UciTimeControl::Infinite.is_infinite());
+        assert!(UciTimeControl::Ponder.is_ponder());
+        assert_eq!(UciTimeControl::time_left().with_moves_to_go(5).moves_to_go(), Some(5));
+    }
+
+    #[test]
+    fn test_info_lookup() {
+        let info = parse_one("info depth 12 seldepth 20 multipv 1 score mate 3 lowerbound nodes 5000 nps 250000 pv e2e4 e7e5 g1f3");
+        assert_eq!(info.depth(), Some(12));
+        assert_eq!(info.multipv(), Some(1));
+        assert_eq!(info.score(), Some((Score::Mate(3), ScoreBound::Lower)));
+        assert_eq!(info.nodes(), Some(5000));
+        assert_eq!(info.nps(), Some(250000));
+        assert_eq!(info.pv().unwrap().iter().map(|m| m.to_string()).collect::<Vec<_>>(), vec!["e2e4", "e7e5", "g1f3"]);
+
+        let info = UciMessage::info_nodes(10);
+        assert_eq!((info.depth(), info.score(), info.pv(), info.nps(), info.multipv()), (None, None, None, None, None));
+
+        let go = UciMessage::go_depth(5);
+        assert_eq!((go.depth(), go.nodes()), (None, None));
+    }
+
+    #[test]
+    fn test_kind() {
+        let mut counts: HashMap<UciMessageKind, usize> = HashMap::new();
+        for m in &[UciMessage::Uci, UciMessage::info_depth(3), UciMessage::info_nps(100), UciMessage::go()] {
+            *counts.entry(m.kind()).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts[&UciMessageKind::Info], 2);
+        assert_eq!(counts[&UciMessageKind::Go], 1);
+        assert_eq!(UciMessage::Unknown(String::from("?"), None).kind(), UciMessageKind::Unknown);
+        assert_eq!(UciMessageKind::BestMove.as_str(), "bestmove");
+        assert_eq!(UciMessageKind::from_keyword(UciMessageKind::UciNewGame.as_str()), Some(UciMessageKind::UciNewGame));
+        assert_eq!(UciMessageKind::from_keyword(UciMessageKind::Unknown.as_str()), None);
+    }
+
     #[test]
     fn test_serialize_id_name() {
         assert_eq!(UciMessage::id_name("Vampirc 0.5.0").serialize().as_str(), "id name Vampirc 0.5.0");
@@ -1249,6 +2240,37 @@ mod tests {
         assert_eq!(UciMessage::id_author("Matija Kejžar").serialize().as_str(), "id author Matija Kejžar");
     }
 
+    #[test]
+    fn test_serialize_info_constructors() {
+        assert_eq!(UciMessage::info_depth(12).serialize().as_str(), "info depth 12");
+        assert_eq!(UciMessage::info_score_cp(-35).serialize().as_str(), "info score cp -35");
+        assert_eq!(UciMessage::info_score_mate(3).serialize().as_str(), "info score mate 3");
+        assert_eq!(UciMessage::info_time(Duration::milliseconds(1500)).serialize().as_str(), "info time 1500");
+        assert_eq!(UciMessage::info_nodes(100000).serialize().as_str(), "info nodes 100000");
+        assert_eq!(UciMessage::info_nps(2500000).serialize().as_str(), "info nps 2500000");
+        assert_eq!(UciMessage::info_hashfull(512).serialize().as_str(), "info hashfull 512");
+    }
+
+    #[cfg(not(feature = "chess"))]
+    #[test]
+    fn test_serialize_info_pv_and_currmove() {
+        let e2e4 = UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4));
+        let e7e5 = UciMove::from_to(UciSquare::from('e', 7), UciSquare::from('e', 5));
+
+        assert_eq!(UciMessage::info_pv(vec![e2e4, e7e5]).serialize().as_str(), "info pv e2e4 e7e5");
+        assert_eq!(UciMessage::info_currmove(e2e4, 1).serialize().as_str(), "info currmove e2e4 currmovenum 1");
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_serialize_info_pv_and_currmove() {
+        let e2e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let e7e5 = ChessMove::new(Square::E7, Square::E5, None);
+
+        assert_eq!(UciMessage::info_pv(vec![e2e4, e7e5]).serialize().as_str(), "info pv e2e4 e7e5");
+        assert_eq!(UciMessage::info_currmove(e2e4, 1).serialize().as_str(), "info currmove e2e4 currmovenum 1");
+    }
+
     #[test]
     fn test_serialize_uciok() {
         assert_eq!(UciMessage::UciOk.serialize().as_str(), "uciok");
@@ -1655,6 +2677,15 @@ mod tests {
         }.serialize(), "setoption name ABC value <empty>")
     }
 
+    #[test]
+    fn test_serialize_set_option_constructors() {
+        assert_eq!(UciMessage::set_option("Style", String::from("Risky")).serialize(), "setoption name Style value Risky");
+        assert_eq!(UciMessage::set_option("Clear Hash", None).serialize(),
+                   UciMessage::SetOption { name: String::from("Clear Hash"), value: None }.serialize());
+        assert_eq!(UciMessage::set_option_i64("Hash", 128).serialize(), "setoption name Hash value 128");
+        assert_eq!(UciMessage::set_option_bool("Ponder", false).serialize(), "setoption name Ponder value false");
+    }
+
     #[test]
     fn test_is_unknown_false() {
         assert_eq!(UciMessage::Uci.is_unknown(), false);
@@ -1697,6 +2728,96 @@ mod tests {
         assert_eq!(empty_go, UciMessage::Go { time_control: None, search_control: None });
     }
 
+    #[test]
+    fn test_go_shortcuts() {
+        assert_eq!(UciMessage::go_depth(12).serialize_canonical(), "go depth 12");
+        assert_eq!(UciMessage::go_nodes(100000).serialize_canonical(), "go nodes 100000");
+        assert_eq!(UciMessage::go_mate(3).serialize_canonical(), "go mate 3");
+
+        let moves = vec!["e2e4".parse().unwrap(), "d2d4".parse().unwrap()];
+        let go = UciMessage::go_searchmoves(moves);
+        assert_eq!(go.serialize_canonical(), "go searchmoves e2e4 d2d4");
+        assert_eq!(parse_one(&go.serialize_canonical()), go);
+    }
+
+    #[test]
+    fn test_fen_is_startpos() {
+        assert!(UciFen::startpos().is_startpos());
+        assert!(UciFen::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  w KQkq -   0 1").is_startpos());
+        assert!(!UciFen::from("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").is_startpos());
+        assert!(UciFen::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -").is_startpos());
+    }
+
+    #[test]
+    fn test_fen_counters() {
+        let full = UciFen::from("2k5/6PR/8/8/2b4P/8/6K1/8 w - - 3 53");
+        assert!(full.has_counters());
+        assert_eq!((full.halfmove_clock(), full.fullmove_number()), (3, 53));
+        assert_eq!(full.with_counters(), full);
+
+        let short = UciFen::from("2k5/6PR/8/8/2b4P/8/6K1/8 w - -");
+        assert!(!short.has_counters());
+        assert_eq!((short.halfmove_clock(), short.fullmove_number()), (0, 1));
+        assert_eq!(short.with_counters().as_str(), "2k5/6PR/8/8/2b4P/8/6K1/8 w - - 0 1");
+    }
+
+    #[cfg(not(feature = "chess"))]
+    #[test]
+    fn test_position_from_fen() {
+        let moves = vec![UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4))];
+
+        assert_eq!(UciMessage::position_from_fen(STARTPOS_FEN, moves.clone()).serialize(),
+                   "position startpos moves e2e4");
+        assert_eq!(UciMessage::position_from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", moves).serialize(),
+                   "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4");
+    }
+
+    #[cfg(feature = "chess")]
+    #[test]
+    fn test_position_from_fen() {
+        let moves = vec![ChessMove::new(Square::E2, Square::E4, None)];
+
+        assert_eq!(UciMessage::position_from_fen(STARTPOS_FEN, moves.clone()).serialize(),
+                   "position startpos moves e2e4");
+        assert_eq!(UciMessage::position_from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", moves).serialize(),
+                   "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4");
+    }
+
+    #[test]
+    fn test_to_fen_position() {
+        let m = UciMessage::Position { startpos: true, fen: None, moves: vec![] };
+        assert_eq!(m.to_fen_position().serialize(), format!("position fen {}", STARTPOS_FEN));
+
+        let m = UciMessage::Position { startpos: false, fen: Some(UciFen::from("8/8/8/8/8/8/8/K6k  w - -  0 1")), moves: vec![] };
+        assert_eq!(m.to_fen_position().serialize(), "position fen 8/8/8/8/8/8/8/K6k w - - 0 1");
+
+        assert_eq!(UciMessage::Uci.to_fen_position(), UciMessage::Uci);
+    }
+
+    #[test]
+    fn test_to_startpos_position() {
+        let m = UciMessage::Position { startpos: false, fen: Some(UciFen::startpos()), moves: vec![] };
+        assert_eq!(m.to_startpos_position(), UciMessage::Position { startpos: true, fen: None, moves: vec![] });
+        assert_eq!(m.to_startpos_position().to_fen_position(), m);
+
+        let m = UciMessage::Position { startpos: false, fen: Some(UciFen::from("8/8/8/8/8/8/8/K6k w - - 0 1")), moves: vec![] };
+        assert_eq!(m.to_startpos_position(), m);
+    }
+
+    #[test]
+    #[cfg(not(feature = "chess"))]
+    fn test_move_from_str() {
+        assert_eq!(UciMove::from_str("e2e4"), Ok(UciMove::from_to(UciSquare::from('e', 2), UciSquare::from('e', 4))));
+        assert_eq!(UciMove::from_str("A7A8Q"), Ok(UciMove {
+            from: UciSquare::from('a', 7),
+            to: UciSquare::from('a', 8),
+            promotion: Some(UciPiece::Queen),
+        }));
+        assert!(UciMove::from_str("e9e4").is_err());
+        assert!(UciMove::from_str("e7e8k").is_err());
+        assert!(UciMove::from_str("e2").is_err());
+    }
+
     #[test]
     fn test_negative_duration() {
         let time_control = UciTimeControl::TimeLeft {
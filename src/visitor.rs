@@ -0,0 +1,211 @@
+//! The `visitor` module contains the `UciMessageVisitor` trait, an alternative to matching on `UciMessage` for the
+//! consumers that handle the messages in several places.
+
+#[cfg(feature = "chess")]
+use chess::ChessMove;
+use pest::error::Error as PestError;
+
+use crate::parser::Rule;
+use crate::uci::{ProtectionState, UciFen, UciInfoAttribute, UciMessage, UciOptionConfig, UciSearchControl, UciTimeControl};
+#[cfg(not(feature = "chess"))]
+use crate::uci::UciMove;
+
+/// A visitor of messages, with a method per message kind that receives the message's fields. Every method does
+/// nothing by default, so an implementation only overrides the ones for the messages it cares about. A message is
+/// passed to the visitor through `UciMessage::accept(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use vampirc_uci::{parse, UciInfoAttribute, UciMessageVisitor};
+///
+/// #[derive(Default)]
+/// struct DepthCounter {
+///     infos: usize,
+///     max_depth: u8,
+/// }
+///
+/// impl UciMessageVisitor for DepthCounter {
+///     fn visit_info(&mut self, attributes: &[UciInfoAttribute]) {
+///         self.infos += 1;
+///         for a in attributes {
+///             if let UciInfoAttribute::Depth(d) = a {
+///                 self.max_depth = self.max_depth.max(*d);
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut counter = DepthCounter::default();
+/// for m in parse("info depth 1\ninfo depth 2 nodes 40\nbestmove e2e4\n") {
+///     m.accept(&mut counter);
+/// }
+///
+/// assert_eq!((counter.infos, counter.max_depth), (2, 2));
+/// ```
+pub trait UciMessageVisitor {
+    /// Visits the `uci` message.
+    fn visit_uci(&mut self) {}
+
+    /// Visits the `debug` message.
+    fn visit_debug(&mut self, _on: bool) {}
+
+    /// Visits the `isready` message.
+    fn visit_is_ready(&mut self) {}
+
+    /// Visits the `register` message.
+    fn visit_register(&mut self, _later: bool, _name: Option<&str>, _code: Option<&str>) {}
+
+    /// Visits the `position` message.
+    #[cfg(not(feature = "chess"))]
+    fn visit_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, _moves: &[UciMove]) {}
+
+    /// Visits the `position` message.
+    #[cfg(feature = "chess")]
+    fn visit_position(&mut self, _startpos: bool, _fen: Option<&UciFen>, _moves: &[ChessMove]) {}
+
+    /// Visits the `setoption` message.
+    fn visit_set_option(&mut self, _name: &str, _value: Option<&str>) {}
+
+    /// Visits the `ucinewgame` message.
+    fn visit_uci_new_game(&mut self) {}
+
+    /// Visits the `stop` message.
+    fn visit_stop(&mut self) {}
+
+    /// Visits the `ponderhit` message.
+    fn visit_ponder_hit(&mut self) {}
+
+    /// Visits the `quit` message.
+    fn visit_quit(&mut self) {}
+
+    /// Visits the `go` message.
+    fn visit_go(&mut self, _time_control: Option<&UciTimeControl>, _search_control: Option<&UciSearchControl>) {}
+
+    /// Visits the `id` message.
+    fn visit_id(&mut self, _name: Option<&str>, _author: Option<&str>) {}
+
+    /// Visits the `uciok` message.
+    fn visit_uci_ok(&mut self) {}
+
+    /// Visits the `readyok` message.
+    fn visit_ready_ok(&mut self) {}
+
+    /// Visits the `bestmove` message.
+    #[cfg(not(feature = "chess"))]
+    fn visit_best_move(&mut self, _best_move: &UciMove, _ponder: Option<&UciMove>) {}
+
+    /// Visits the `bestmove` message.
+    #[cfg(feature = "chess")]
+    fn visit_best_move(&mut self, _best_move: &ChessMove, _ponder: Option<&ChessMove>) {}
+
+    /// Visits the `copyprotection` message.
+    fn visit_copy_protection(&mut self, _state: ProtectionState) {}
+
+    /// Visits the `registration` message.
+    fn visit_registration(&mut self, _state: ProtectionState) {}
+
+    /// Visits the `option` message.
+    fn visit_option(&mut self, _config: &UciOptionConfig) {}
+
+    /// Visits the `info` message.
+    fn visit_info(&mut self, _attributes: &[UciInfoAttribute]) {}
+
+    /// Visits an unrecognized message, along with the error that occurred while parsing it, if any.
+    fn visit_unknown(&mut self, _text: &str, _error: Option<&PestError<Rule>>) {}
+}
+
+impl UciMessage {
+    /// Calls the method of the `visitor` for this message's kind with the message's fields.
+    pub fn accept<V: UciMessageVisitor + ?Sized>(&self, visitor: &mut V) {
+        match self {
+            UciMessage::Uci => visitor.visit_uci(),
+            UciMessage::Debug(on) => visitor.visit_debug(*on),
+            UciMessage::IsReady => visitor.visit_is_ready(),
+            UciMessage::Register { later, name, code } => visitor.visit_register(*later, name.as_deref(), code.as_deref()),
+            UciMessage::Position { startpos, fen, moves } => visitor.visit_position(*startpos, fen.as_ref(), moves),
+            UciMessage::SetOption { name, value } => visitor.visit_set_option(name, value.as_deref()),
+            UciMessage::UciNewGame => visitor.visit_uci_new_game(),
+            UciMessage::Stop => visitor.visit_stop(),
+            UciMessage::PonderHit => visitor.visit_ponder_hit(),
+            UciMessage::Quit => visitor.visit_quit(),
+            UciMessage::Go { time_control, search_control } => visitor.visit_go(time_control.as_ref(), search_control.as_ref()),
+            UciMessage::Id { name, author } => visitor.visit_id(name.as_deref(), author.as_deref()),
+            UciMessage::UciOk => visitor.visit_uci_ok(),
+            UciMessage::ReadyOk => visitor.visit_ready_ok(),
+            UciMessage::BestMove { best_move, ponder, .. } => visitor.visit_best_move(best_move, ponder.as_ref()),
+            UciMessage::CopyProtection(state) => visitor.visit_copy_protection(*state),
+            UciMessage::Registration(state) => visitor.visit_registration(*state),
+            UciMessage::Option(config) => visitor.visit_option(config),
+            UciMessage::Info(attributes) => visitor.visit_info(attributes),
+            UciMessage::Unknown(text, error) => visitor.visit_unknown(text, error.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, parse_one};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        visited: Vec<String>,
+    }
+
+    impl UciMessageVisitor for Recorder {
+        fn visit_uci(&mut self) {
+            self.visited.push(String::from("uci"));
+        }
+
+        fn visit_set_option(&mut self, name: &str, value: Option<&str>) {
+            self.visited.push(format!("setoption {}={:?}", name, value));
+        }
+
+        fn visit_go(&mut self, time_control: Option<&UciTimeControl>, search_control: Option<&UciSearchControl>) {
+            self.visited.push(format!("go {:?} {:?}", time_control, search_control.and_then(|sc| sc.depth)));
+        }
+
+        #[cfg(not(feature = "chess"))]
+        fn visit_best_move(&mut self, best_move: &UciMove, ponder: Option<&UciMove>) {
+            self.visited.push(format!("bestmove {} {:?}", best_move, ponder.map(|p| p.to_string())));
+        }
+
+        #[cfg(feature = "chess")]
+        fn visit_best_move(&mut self, best_move: &ChessMove, ponder: Option<&ChessMove>) {
+            self.visited.push(format!("bestmove {} {:?}", best_move, ponder.map(|p| p.to_string())));
+        }
+
+        fn visit_unknown(&mut self, text: &str, error: Option<&PestError<Rule>>) {
+            self.visited.push(format!("unknown {} {}", text, error.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_accept() {
+        let mut recorder = Recorder::default();
+        for m in parse("uci\nid name Vampirc\nsetoption name Clear Hash\ngo depth 3\nbestmove e2e4 ponder e7e5\n") {
+            m.accept(&mut recorder);
+        }
+        parse_one("what").accept(&mut recorder);
+
+        assert_eq!(recorder.visited, vec![
+            "uci",
+            "setoption Clear Hash=None",
+            "go None Some(3)",
+            "bestmove e2e4 Some(\"e7e5\")",
+            "unknown what true",
+        ]);
+    }
+
+    #[test]
+    fn test_accept_dyn() {
+        let mut recorder = Recorder::default();
+        let visitor: &mut dyn UciMessageVisitor = &mut recorder;
+        UciMessage::Uci.accept(visitor);
+        UciMessage::ReadyOk.accept(visitor);
+
+        assert_eq!(recorder.visited, vec!["uci"]);
+    }
+}
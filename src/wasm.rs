@@ -0,0 +1,47 @@
+//! The `wasm` module (available with the `wasm` feature) exposes the parser and the serializer to JavaScript through
+//! [wasm-bindgen](https://crates.io/crates/wasm-bindgen), for browser GUIs that run engines in web workers.
+//!
+//! The messages are plain JavaScript objects with the same shape as the representation derived by the `serde` feature:
+//! a message without fields is a string with the name of its variant (as in, `"UciOk"`), and any other message is an
+//! object with a single key, the name of its variant, holding the fields (as in,
+//! `{ BestMove: { best_move: "e2e4", ponder: null } }`). Moves are strings in the UCI move notation and durations are
+//! numbers of milliseconds.
+//!
+//! ```js
+//! import { parseOne, serialize } from "vampirc_uci";
+//!
+//! const message = parseOne("bestmove e2e4 ponder e7e5");
+//! console.log(message.BestMove.best_move); // e2e4
+//! console.log(serialize({ Go: { time_control: "Infinite", search_control: null } })); // go infinite
+//! ```
+
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+use crate::parser;
+use crate::uci::{Serializable, UciMessage};
+
+fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsError> {
+    value.serialize(&Serializer::json_compatible()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Parses a single line into a message object. Unrecognized input is returned as an `Unknown` message.
+#[wasm_bindgen(js_name = parseOne)]
+pub fn parse_one(line: &str) -> Result<JsValue, JsError> {
+    to_js(&parser::parse_one(line))
+}
+
+/// Parses any number of lines into an array of message objects. Unrecognized lines are returned as `Unknown`
+/// messages.
+#[wasm_bindgen(js_name = parse)]
+pub fn parse(text: &str) -> Result<JsValue, JsError> {
+    to_js(&parser::parse_with_unknown(text))
+}
+
+/// Serializes a message object into a line of the protocol, without the terminating newline.
+#[wasm_bindgen(js_name = serialize)]
+pub fn serialize(message: JsValue) -> Result<String, JsError> {
+    let message: UciMessage = serde_wasm_bindgen::from_value(message).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(Serializable::serialize(&message))
+}
@@ -0,0 +1,269 @@
+//! The `watchdog` module contains the `Watchdog`, which tracks the replies a GUI expects from an engine and reports
+//! the ones that fail to arrive in time, so that a dead or hung engine does not stall the GUI forever.
+//!
+//! The `Watchdog` does not perform any I/O itself: the caller feeds it the messages sent to and received from the
+//! engine, and periodically calls `check(..)`. `next_deadline()` tells the caller how long it may sleep for.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{Duration, Instant};
+
+use crate::uci::{UciMessage, UciTimeControl};
+
+/// A reply the GUI expects from the engine.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum ExpectedReply {
+    /// `uciok`, in reply to `uci`.
+    UciOk,
+
+    /// `readyok`, in reply to `isready`.
+    ReadyOk,
+
+    /// `bestmove`, in reply to `go`.
+    BestMove,
+}
+
+impl Display for ExpectedReply {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ExpectedReply::UciOk => write!(f, "uciok"),
+            ExpectedReply::ReadyOk => write!(f, "readyok"),
+            ExpectedReply::BestMove => write!(f, "bestmove"),
+        }
+    }
+}
+
+/// The timeouts used by the `Watchdog`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct WatchdogTimeouts {
+    /// How long to wait for `uciok` after `uci`.
+    pub uci: Duration,
+
+    /// How long to wait for `readyok` after `isready`.
+    pub is_ready: Duration,
+
+    /// How long to wait for `bestmove` after `stop` (or after the time of a `go movetime` search has run out).
+    pub stop: Duration,
+
+    /// How long to wait for `bestmove` beyond the larger of the clocks of a `go wtime .. btime ..` search.
+    pub clock_margin: Duration,
+}
+
+impl Default for WatchdogTimeouts {
+    /// Five seconds for `uciok` and `readyok`, and one second for `bestmove` after `stop` or beyond the clock.
+    fn default() -> Self {
+        WatchdogTimeouts {
+            uci: Duration::from_secs(5),
+            is_ready: Duration::from_secs(5),
+            stop: Duration::from_secs(1),
+            clock_margin: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The event reported by the `Watchdog` when an expected reply fails to arrive in time.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Timeout {
+    /// The reply that did not arrive.
+    pub expected: ExpectedReply,
+
+    /// When the message the reply was expected to was sent.
+    pub sent_at: Instant,
+
+    /// When the reply was due.
+    pub deadline: Instant,
+}
+
+impl Display for Timeout {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "the engine did not reply with `{}` within {:?}", self.expected, self.deadline - self.sent_at)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct Pending {
+    expected: ExpectedReply,
+    sent_at: Instant,
+    deadline: Option<Instant>,
+}
+
+/// Tracks the replies expected from an engine and reports the ones that are overdue.
+///
+/// A `go` without time limits (`infinite`, `ponder`, `depth` ...) has no deadline until `stop` is sent.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use vampirc_uci::UciMessage;
+/// use vampirc_uci::watchdog::{ExpectedReply, Watchdog};
+///
+/// let mut watchdog = Watchdog::default();
+/// let start = Instant::now();
+///
+/// watchdog.on_sent(&UciMessage::IsReady, start);
+/// assert!(watchdog.check(start + Duration::from_secs(1)).is_empty());
+///
+/// let timeouts = watchdog.check(start + Duration::from_secs(10));
+/// assert_eq!(timeouts[0].expected, ExpectedReply::ReadyOk);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Watchdog {
+    timeouts: WatchdogTimeouts,
+    pending: Vec<Pending>,
+}
+
+impl Watchdog {
+    /// Creates a `Watchdog` with the specified timeouts.
+    pub fn new(timeouts: WatchdogTimeouts) -> Watchdog {
+        Watchdog {
+            timeouts,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Records a message sent to the engine at `now`.
+    pub fn on_sent(&mut self, message: &UciMessage, now: Instant) {
+        match message {
+            UciMessage::Uci => self.expect(ExpectedReply::UciOk, now, Some(now + self.timeouts.uci)),
+            UciMessage::IsReady => self.expect(ExpectedReply::ReadyOk, now, Some(now + self.timeouts.is_ready)),
+            UciMessage::Go { time_control, .. } => {
+                let deadline = self.go_deadline(time_control.as_ref(), now);
+                self.expect(ExpectedReply::BestMove, now, deadline);
+            }
+            UciMessage::Stop => self.tighten_bestmove(now + self.timeouts.stop),
+            _ => {}
+        }
+    }
+
+    /// Records a message received from the engine.
+    pub fn on_received(&mut self, message: &UciMessage) {
+        let expected = match message {
+            UciMessage::UciOk => ExpectedReply::UciOk,
+            UciMessage::ReadyOk => ExpectedReply::ReadyOk,
+            UciMessage::BestMove { .. } => ExpectedReply::BestMove,
+            _ => return,
+        };
+
+        if let Some(index) = self.pending.iter().position(|p| p.expected == expected) {
+            self.pending.remove(index);
+        }
+    }
+
+    /// Returns the expected replies that are overdue at `now`. Each is reported only once and is no longer tracked
+    /// afterwards.
+    pub fn check(&mut self, now: Instant) -> Vec<Timeout> {
+        let mut timeouts = Vec::new();
+        self.pending.retain(|p| match p.deadline {
+            Some(deadline) if deadline <= now => {
+                timeouts.push(Timeout {
+                    expected: p.expected,
+                    sent_at: p.sent_at,
+                    deadline,
+                });
+                false
+            }
+            _ => true,
+        });
+
+        timeouts
+    }
+
+    /// Returns the earliest deadline of the expected replies, if any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.iter().filter_map(|p| p.deadline).min()
+    }
+
+    /// Returns `true` if any reply is expected.
+    pub fn is_waiting(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Returns `true` if the `expected` reply is awaited.
+    pub fn is_waiting_for(&self, expected: ExpectedReply) -> bool {
+        self.pending.iter().any(|p| p.expected == expected)
+    }
+
+    fn expect(&mut self, expected: ExpectedReply, sent_at: Instant, deadline: Option<Instant>) {
+        self.pending.push(Pending {
+            expected,
+            sent_at,
+            deadline,
+        });
+    }
+
+    fn go_deadline(&self, time_control: Option<&UciTimeControl>, now: Instant) -> Option<Instant> {
+        match time_control {
+            Some(UciTimeControl::MoveTime(t)) => Some(now + t.to_std().unwrap_or_default() + self.timeouts.stop),
+            Some(UciTimeControl::TimeLeft { white_time, black_time, .. }) => {
+                let clock = white_time.iter().chain(black_time.iter()).filter_map(|t| t.to_std().ok()).max()?;
+                Some(now + clock + self.timeouts.clock_margin)
+            }
+            _ => None,
+        }
+    }
+
+    fn tighten_bestmove(&mut self, deadline: Instant) {
+        for p in self.pending.iter_mut().filter(|p| p.expected == ExpectedReply::BestMove) {
+            p.deadline = Some(p.deadline.map_or(deadline, |d| d.min(deadline)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_one;
+
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_handshake_timeouts() {
+        let mut w = Watchdog::default();
+        let t0 = Instant::now();
+
+        w.on_sent(&UciMessage::Uci, t0);
+        w.on_sent(&UciMessage::IsReady, t0 + secs(1));
+        assert_eq!(w.next_deadline(), Some(t0 + secs(5)));
+
+        w.on_received(&UciMessage::UciOk);
+        assert!(!w.is_waiting_for(ExpectedReply::UciOk));
+        assert!(w.check(t0 + secs(5)).is_empty());
+
+        let timeouts = w.check(t0 + secs(6));
+        assert_eq!(timeouts, vec![Timeout { expected: ExpectedReply::ReadyOk, sent_at: t0 + secs(1), deadline: t0 + secs(6) }]);
+        assert!(!w.is_waiting());
+        assert!(w.check(t0 + secs(100)).is_empty());
+    }
+
+    #[test]
+    fn test_infinite_go_waits_for_stop() {
+        let mut w = Watchdog::new(WatchdogTimeouts { stop: secs(2), ..WatchdogTimeouts::default() });
+        let t0 = Instant::now();
+
+        w.on_sent(&UciMessage::go_infinite(), t0);
+        assert_eq!(w.next_deadline(), None);
+        assert!(w.check(t0 + secs(3600)).is_empty());
+
+        w.on_sent(&UciMessage::Stop, t0 + secs(3600));
+        assert_eq!(w.check(t0 + secs(3602)).len(), 1);
+    }
+
+    #[test]
+    fn test_timed_go() {
+        let mut w = Watchdog::default();
+        let t0 = Instant::now();
+
+        w.on_sent(&parse_one("go movetime 3000"), t0);
+        assert_eq!(w.next_deadline(), Some(t0 + secs(4)));
+        w.on_received(&parse_one("bestmove e2e4"));
+        assert!(!w.is_waiting());
+
+        w.on_sent(&parse_one("go wtime 10000 btime 20000"), t0);
+        assert_eq!(w.next_deadline(), Some(t0 + secs(21)));
+        w.on_sent(&UciMessage::Stop, t0 + secs(2));
+        assert_eq!(w.next_deadline(), Some(t0 + secs(3)));
+    }
+}